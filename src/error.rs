@@ -0,0 +1,132 @@
+use std::{fmt, sync::Arc};
+
+/// The errors raised by husky.
+///
+/// The methods of husky return a [Result] with a [HuskyError], so that the kind of error can be matched on.
+/// Closures passed to husky return [anyhow::Result], so that they can fail with any error,
+/// and the [HuskyError] carried by an [anyhow::Error] is recovered with [HuskyError::from].
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, HuskyError};
+/// # let db = husky::open_temp().unwrap();
+/// let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// tree.insert(1u32, 10u32).unwrap();
+/// // Overwrites the value with bytes that aren't a valid u32
+/// let (key, _) = tree.to_inner().first().unwrap().unwrap();
+/// tree.to_inner().insert(key, Vec::new()).unwrap();
+///
+/// let err = tree.get(1u32).unwrap_err();
+/// assert!(matches!(err, HuskyError::Serialization(_)));
+/// ```
+#[derive(Debug)]
+pub enum HuskyError {
+	/// An error from the underlying [sled] database.
+	Sled(sled::Error),
+	/// A key or value that couldn't be serialized or deserialized.
+	Serialization(anyhow::Error),
+	/// An error raised by a background thread of a pipeline, such as a failing map.
+	Pipeline(Arc<anyhow::Error>),
+	/// Any other error.
+	Other(anyhow::Error),
+}
+
+/// The result of the methods of husky.
+pub type Result<T> = std::result::Result<T, HuskyError>;
+
+impl HuskyError {
+	/// Wraps an error from a serializer, keeping it as is if it already is a more specific [HuskyError].
+	pub(crate) fn serialization(e: impl Into<anyhow::Error>) -> Self {
+		match e.into().downcast::<HuskyError>() {
+			Ok(HuskyError::Other(e)) | Err(e) => HuskyError::Serialization(e),
+			Ok(e) => e,
+		}
+	}
+}
+
+impl fmt::Display for HuskyError {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		match self {
+			HuskyError::Sled(e) => write!(f, "Database error: {}", e),
+			HuskyError::Serialization(e) => write!(f, "Serialization error: {:#}", e),
+			HuskyError::Pipeline(e) => write!(f, "Error in Husky thread: {:#}", e),
+			HuskyError::Other(e) => write!(f, "{:#}", e),
+		}
+	}
+}
+
+impl std::error::Error for HuskyError {
+	fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+		match self {
+			HuskyError::Sled(e) => Some(e),
+			HuskyError::Serialization(e) | HuskyError::Other(e) => Some(e.as_ref()),
+			HuskyError::Pipeline(e) => Some(e.as_ref().as_ref()),
+		}
+	}
+}
+
+impl From<sled::Error> for HuskyError {
+	fn from(e: sled::Error) -> Self {
+		HuskyError::Sled(e)
+	}
+}
+
+impl From<std::io::Error> for HuskyError {
+	fn from(e: std::io::Error) -> Self {
+		HuskyError::Other(e.into())
+	}
+}
+
+#[cfg(feature = "serde_json")]
+impl From<serde_json::Error> for HuskyError {
+	fn from(e: serde_json::Error) -> Self {
+		HuskyError::Serialization(e.into())
+	}
+}
+
+#[cfg(feature = "csv")]
+impl From<csv::Error> for HuskyError {
+	fn from(e: csv::Error) -> Self {
+		HuskyError::Serialization(e.into())
+	}
+}
+
+impl From<sled::transaction::UnabortableTransactionError> for HuskyError {
+	fn from(e: sled::transaction::UnabortableTransactionError) -> Self {
+		match e {
+			sled::transaction::UnabortableTransactionError::Storage(e) => HuskyError::Sled(e),
+			e => HuskyError::Other(e.into()),
+		}
+	}
+}
+
+impl From<sled::transaction::TransactionError<sled::Error>> for HuskyError {
+	fn from(e: sled::transaction::TransactionError<sled::Error>) -> Self {
+		match e {
+			sled::transaction::TransactionError::Abort(e) => HuskyError::Sled(e),
+			sled::transaction::TransactionError::Storage(e) => HuskyError::Sled(e),
+		}
+	}
+}
+
+impl From<sled::transaction::TransactionError<HuskyError>> for HuskyError {
+	fn from(e: sled::transaction::TransactionError<HuskyError>) -> Self {
+		match e {
+			sled::transaction::TransactionError::Abort(e) => e,
+			sled::transaction::TransactionError::Storage(e) => HuskyError::Sled(e),
+		}
+	}
+}
+
+impl From<anyhow::Error> for HuskyError {
+	/// Recovers the [HuskyError] carried by an error, falling back to [HuskyError::Other].
+	fn from(e: anyhow::Error) -> Self {
+		let e = match e.downcast::<HuskyError>() {
+			Ok(e) => return e,
+			Err(e) => e,
+		};
+		match e.downcast::<sled::Error>() {
+			Ok(e) => HuskyError::Sled(e),
+			Err(e) => HuskyError::Other(e),
+		}
+	}
+}
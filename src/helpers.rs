@@ -1,6 +1,12 @@
-use anyhow::Result;
+use std::time::{SystemTime, UNIX_EPOCH};
+#[cfg(feature = "rayon")]
+use std::{collections::HashMap, hash::Hash};
 
-use crate::{macros::unwrap_or_return, traits::serial::Serial};
+use crate::{
+	error::{HuskyError, Result},
+	macros::unwrap_or_return,
+	traits::serial::{deserialize_value, serialize_value, Serial},
+};
 
 pub fn deserialize_tuple<K, V>(input: Option<(Vec<u8>, Vec<u8>)>) -> Result<Option<(K, V)>>
 where
@@ -8,17 +14,26 @@ where
 	V: Serial,
 {
 	let (key, value) = unwrap_or_return!(input);
-	let key = Serial::deserialize(key)?;
-	let value = Serial::deserialize(value)?;
+	let key = Serial::deserialize(key).map_err(HuskyError::serialization)?;
+	let value = deserialize_value(value)?;
 	Ok(Some((key, value)))
 }
 
+pub fn deserialize_key<K>(key: Option<Vec<u8>>) -> Result<Option<K>>
+where
+	K: Serial,
+{
+	let key = unwrap_or_return!(key);
+	let key = Serial::deserialize(key).map_err(HuskyError::serialization)?;
+	Ok(Some(key))
+}
+
 pub fn deserialize_option<V>(value: Option<Vec<u8>>) -> Result<Option<V>>
 where
 	V: Serial,
 {
 	let value = unwrap_or_return!(value);
-	let value = Serial::deserialize(value)?;
+	let value = deserialize_value(value)?;
 	Ok(Some(value))
 }
 
@@ -27,6 +42,49 @@ where
 	V: Serial,
 {
 	let value = unwrap_or_return!(value);
-	let value = Serial::serialize(value)?;
+	let value = serialize_value(value)?;
 	Ok(Some(value))
 }
+
+/// The most entries of the source a parallel rebuild holds in memory at once
+#[cfg(feature = "rayon")]
+pub const REBUILD_CHUNK: usize = 1 << 14;
+
+/// Groups what each entry of a chunk maps to, in parallel, along with the position of the entry.
+/// The partial groups are merged in order, so each group lists its items in the order of the chunk.
+#[cfg(feature = "rayon")]
+pub fn par_group<E, G, T, F>(chunk: &[E], f: F) -> HashMap<G, Vec<(usize, T)>>
+where
+	E: Sync,
+	G: Hash + Eq + Send,
+	T: Send,
+	F: Fn(&E) -> Vec<(G, T)> + Sync,
+{
+	use rayon::iter::{IndexedParallelIterator, IntoParallelRefIterator, ParallelIterator};
+	chunk
+		.par_iter()
+		.enumerate()
+		.fold(
+			HashMap::new,
+			|mut groups: HashMap<G, Vec<(usize, T)>>, (position, entry)| {
+				for (group, item) in f(entry) {
+					groups.entry(group).or_default().push((position, item));
+				}
+				groups
+			},
+		)
+		.reduce(HashMap::new, |mut left, right| {
+			for (group, items) in right {
+				left.entry(group).or_default().extend(items);
+			}
+			left
+		})
+}
+
+/// The current time, in milliseconds since the epoch
+pub fn now_millis() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|d| d.as_millis() as u64)
+		.unwrap_or(0)
+}
@@ -10,9 +10,10 @@
 //!
 //! Take a look at [traits] for a list of available traits.
 
-use anyhow::Result;
 use std::path::Path;
 
+/// The error type of husky
+pub mod error;
 mod helpers;
 mod macros;
 /// Various operations for transforming trees
@@ -25,29 +26,47 @@ pub mod traits;
 pub mod wrappers;
 
 pub use {
-	ops::Operate,
-	structs::{material::Material, single::Single},
+	error::HuskyError,
+	ops::{ingest::ingest, merge_sorted::merge_sorted, Operate},
+	structs::{
+		iter::{Chunks, Windows},
+		material::Material,
+		single::Single,
+	},
 	traits::{
-		auto_inc::AutoInc, change::Change, load::Load, store::Store, view::View, watch::Watch,
+		auto_inc::{AutoInc, Sequence},
+		change::Change,
+		load::{Load, LoadUnordered},
+		store::Store,
+		view::View,
+		watch::Watch,
+	},
+  threads::{merge_watch, wait_all, wait_all_timeout},
+	wrappers::{
+		batch::Batch,
+		tree::{Tree, TreeStats},
 	},
-  threads::wait_all,
-	wrappers::{batch::Batch, tree::Tree},
 };
 
+#[cfg(feature = "metrics")]
+pub use threads::SyncStats;
+#[cfg(feature = "uuid")]
+pub use traits::serial::UuidKey;
+#[cfg(feature = "chrono")]
+pub use traits::serial::Timestamp;
 pub use database::Db;
-pub use sled::Config;
+pub use config::{Config, Mode};
+use error::Result;
 use wrappers::*;
 
 /// Opens a database at the given path
 pub fn open(path: impl AsRef<Path>) -> Result<Db> {
-	let db = sled::open(path)?;
-	Ok(Db::from(db))
+	Config::new().path(path).open()
 }
 
 /// Opens a database in memory
 pub fn open_temp() -> Result<Db> {
-	let db = sled::Config::new().temporary(true).open()?;
-	Ok(Db::from(db))
+	Config::new().temporary(true).open()
 }
 
 #[cfg(test)]
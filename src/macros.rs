@@ -11,10 +11,20 @@ macro_rules! unwrap_or_error {
 	( $e: expr ) => {
 		match $e {
 			Ok(x) => x,
-			Err(err) => return Err(anyhow::anyhow!("{}", err)),
+			Err(err) => {
+				return Err($crate::error::HuskyError::serialization(anyhow::anyhow!(
+					"{}", err
+				)))
+			}
 		}
 	};
 }
+/// Like [anyhow::bail], but for the [Result](crate::error::Result) of husky
+macro_rules! bail {
+	( $($arg:tt)* ) => {
+		return Err(anyhow::anyhow!($($arg)*).into())
+	};
+}
 macro_rules! hash {
   ( $( $x:expr ),* ) => {
     {
@@ -37,6 +47,23 @@ macro_rules! cloned {
     $f
   };
 }
+macro_rules! into_iter {
+	( impl<$($g:ident),*> for $t:ty where $($w:tt)* ) => {
+		impl<'a, $($g),*> IntoIterator for &'a $t
+		where
+			$($w)*
+		{
+			type Item = $crate::error::Result<(
+				<$t as $crate::traits::view::View>::Key,
+				<$t as $crate::traits::view::View>::Value,
+			)>;
+			type IntoIter = <$t as $crate::traits::view::View>::Iter;
+			fn into_iter(self) -> Self::IntoIter {
+				$crate::traits::view::View::iter(self)
+			}
+		}
+	};
+}
 #[cfg(feature = "bytecheck")]
 pub(crate) use unwrap_or_error;
-pub(crate) use {cloned, hash, unwrap_or_return};
+pub(crate) use {bail, cloned, hash, into_iter, unwrap_or_return};
@@ -0,0 +1,415 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{
+	hash::Hash,
+	ops::{Add, Sub},
+	sync::Arc,
+};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	structs::single::Single,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+/// Computes the next aggregate from the current one, the old value and the new value of an entry.
+/// Returns [None] when the aggregate can't be updated in place, and must be computed again.
+type StepFn<V, A> = dyn Fn(Option<A>, Option<&V>, Option<&V>) -> Option<Option<A>> + Send + Sync;
+
+/// A struct that aggregates all values of a view into a single scalar.
+/// You can create an [Aggregate] with [sum](crate::Operate::sum), [count](crate::Operate::count),
+/// [min](crate::Operate::min) or [max](crate::Operate::max).
+///
+/// Each change is applied as a delta, the new value is added and the old value is taken back out.
+/// A minimum or a maximum can't take a value back out, so when the current extreme
+/// is removed or replaced by a worse value, the aggregate is computed again
+/// by walking every entry, which is O(n).
+///
+/// [Aggregate] doesn't implement [View] or [Watch], you must store it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let sum = tree.sum().load().unwrap();
+/// let max = tree.max().load().unwrap();
+///
+/// tree.insert(1u32, 10u32).unwrap();
+/// tree.insert(2u32, 5u32).unwrap();
+/// assert_eq!(sum.get().unwrap(), Some(15));
+/// assert_eq!(max.get().unwrap(), Some(10));
+///
+/// tree.remove(1u32).unwrap();
+/// assert_eq!(sum.get().unwrap(), Some(5));
+/// assert_eq!(max.get().unwrap(), Some(5));
+/// ```
+pub struct Aggregate<Previous, Acc>
+where
+	Previous: View,
+{
+	from: Previous,
+	init: Option<Acc>,
+	step: Arc<StepFn<Previous::Value, Acc>>,
+}
+impl<P, A> Clone for Aggregate<P, A>
+where
+	P: View,
+	A: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			init: self.init.clone(),
+			step: Arc::clone(&self.step),
+		}
+	}
+}
+
+impl<P, A> Aggregate<P, A>
+where
+	P: View + Watch,
+{
+	fn new<S>(from: P, init: Option<A>, step: S) -> Self
+	where
+		S: 'static
+			+ Fn(Option<A>, Option<&P::Value>, Option<&P::Value>) -> Option<Option<A>>
+			+ Send
+			+ Sync,
+	{
+		Aggregate {
+			from,
+			init,
+			step: Arc::new(step),
+		}
+	}
+	/// Folds every value, starting from the initial aggregate.
+	fn fold<I>(init: Option<A>, step: &StepFn<P::Value, A>, values: I) -> Result<Option<A>>
+	where
+		I: Iterator<Item = Result<P::Value>>,
+	{
+		let mut acc = init;
+		for value in values {
+			let value = value?;
+			// Inserting a value never requires the aggregate to be computed again
+			acc = step(acc, None, Some(&value)).unwrap_or(None);
+		}
+		Ok(acc)
+	}
+}
+
+impl<P> Aggregate<P, P::Value>
+where
+	P: View + Watch,
+{
+	pub(crate) fn sum(from: P) -> Self
+	where
+		P::Value: Default + Add<Output = P::Value> + Sub<Output = P::Value>,
+	{
+		Self::new(from, Some(Default::default()), |acc, old, new| {
+			let mut acc = acc.unwrap_or_default();
+			if let Some(new) = new {
+				acc = acc + new.clone();
+			}
+			if let Some(old) = old {
+				acc = acc - old.clone();
+			}
+			Some(Some(acc))
+		})
+	}
+	pub(crate) fn min(from: P) -> Self
+	where
+		P::Value: Ord,
+	{
+		Self::new(from, None, |acc, old, new| {
+			if let (Some(acc), Some(old)) = (&acc, old) {
+				// The minimum left, and the new value doesn't replace it
+				if old == acc && !matches!(new, Some(new) if new <= old) {
+					return None;
+				}
+			}
+			Some(match (acc, new) {
+				(Some(acc), Some(new)) => Some(acc.min(new.clone())),
+				(acc, new) => acc.or_else(|| new.cloned()),
+			})
+		})
+	}
+	pub(crate) fn max(from: P) -> Self
+	where
+		P::Value: Ord,
+	{
+		Self::new(from, None, |acc, old, new| {
+			if let (Some(acc), Some(old)) = (&acc, old) {
+				// The maximum left, and the new value doesn't replace it
+				if old == acc && !matches!(new, Some(new) if new >= old) {
+					return None;
+				}
+			}
+			Some(match (acc, new) {
+				(Some(acc), Some(new)) => Some(acc.max(new.clone())),
+				(acc, new) => acc.or_else(|| new.cloned()),
+			})
+		})
+	}
+}
+
+impl<P> Aggregate<P, u64>
+where
+	P: View + Watch,
+{
+	pub(crate) fn count(from: P) -> Self {
+		Self::new(from, Some(0), |acc, old, new| {
+			let acc = acc.unwrap_or(0);
+			Some(Some(match (old, new) {
+				(None, Some(_)) => acc + 1,
+				(Some(_), None) => acc - 1,
+				_ => acc,
+			}))
+		})
+	}
+}
+
+/// A stored or loaded [Aggregate].
+///
+/// The scalar holds the aggregate, under the `()` key.
+/// The backward map holds the value each source entry contributed,
+/// so the contribution can be taken back out after the source has changed.
+pub struct MaterialAggregate<P, A, S, B>
+where
+	P: View,
+	S: Clone,
+	B: Clone,
+{
+	from: Aggregate<P, A>,
+	scalar: S,
+	bwd: B,
+	watcher: Watcher<(), A>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, A, S, B> Clone for MaterialAggregate<P, A, S, B>
+where
+	P: View,
+	A: Clone,
+	S: Clone,
+	B: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			scalar: self.scalar.clone(),
+			bwd: self.bwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, A, S, B> MaterialAggregate<P, A, S, B>
+where
+	P: Watch + Sync + Send,
+	A: 'static + Clone + Send + Sync,
+	S: Clone + View<Key = (), Value = A> + Change<Key = (), Value = A, Insert = A> + Send + Sync,
+	B: Clone
+		+ View<Key = P::Key, Value = P::Value>
+		+ Change<Key = P::Key, Value = P::Value, Insert = P::Value>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: Aggregate<P, A>, scalar: S, bwd: B) -> Self {
+		let reader = from.from.watch();
+		let init = from.init.clone();
+		let step = Arc::clone(&from.step);
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(scalar, bwd, move |event| {
+				let (key, value) = match &event {
+					Event::Insert { key, value } => (key, Some(&**value)),
+					Event::Remove { key } => (key, None),
+				};
+				let old = match value {
+					Some(value) => bwd.insert_ref(key, value)?,
+					None => bwd.remove_ref(key)?,
+				};
+				let acc = scalar.get_ref(&())?;
+				let acc = match step(acc, old.as_ref(), value) {
+					Some(acc) => acc,
+					// The slow path, every value is walked again
+					None => {
+						let values = bwd.iter().map(|r| r.map(|(_, v)| v));
+						Aggregate::<P, A>::fold(init.clone(), &*step, values)?
+					}
+				};
+				let key = Arc::new(());
+				let event = match acc {
+					Some(acc) => {
+						scalar.insert_ref(&(), &acc)?;
+						let value = Arc::new(acc);
+						Event::Insert { key, value }
+					}
+					None => {
+						scalar.remove_ref(&())?;
+						Event::Remove { key }
+					}
+				};
+				Ok(vec![event])
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			scalar,
+			bwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the aggregate from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.bwd.clear()?;
+		let mut values = Vec::new();
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			self.bwd.insert_ref(&k, &v)?;
+			values.push(Ok(v));
+		}
+		let init = self.from.init.clone();
+		let acc = Aggregate::<P, A>::fold(init, &*self.from.step, values.into_iter())?;
+		match acc {
+			Some(acc) => self.scalar.insert_owned((), acc)?,
+			None => self.scalar.remove_owned(())?,
+		};
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+	/// Gets the aggregate
+	pub fn get(&self) -> Result<Option<A>> {
+		self.sync.wait_checked()?;
+		self.scalar.get_ref(&())
+	}
+}
+
+impl<P, A, S, B> View for MaterialAggregate<P, A, S, B>
+where
+	P: View,
+	A: 'static + Clone + Send + Sync,
+	S: Clone + View<Key = (), Value = A>,
+	B: 'static + Clone,
+{
+	type Key = ();
+	type Value = A;
+	type Iter = S::Iter;
+	fn get_ref(&self, key: &()) -> Result<Option<A>> {
+		self.sync.wait_checked()?;
+		self.scalar.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.scalar.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.scalar.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		self.sync.wait_checked()?;
+		self.scalar.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		self.sync.wait_checked()?;
+		self.scalar.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		self.sync.wait_checked()?;
+		self.scalar.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		self.sync.wait_checked()?;
+		self.scalar.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.scalar.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.scalar.range(range)
+	}
+}
+into_iter!(
+	impl<P, A, S, B> for MaterialAggregate<P, A, S, B>
+	where
+		P: View,
+		A: 'static + Clone + Send + Sync,
+		S: Clone + View<Key = (), Value = A>,
+		B: 'static + Clone,
+);
+impl<P, A, S, B> Watch for MaterialAggregate<P, A, S, B>
+where
+	P: Watch,
+	A: 'static + Clone + Send + Sync,
+	S: Clone + View<Key = (), Value = A>,
+	B: 'static + Clone,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, A> Store for Aggregate<P, A>
+where
+	P: Watch + Sync + Send,
+	A: Serial,
+	<P as View>::Key: Serial,
+	<P as View>::Value: Serial,
+{
+	type Stored = MaterialAggregate<P, A, Single<A>, Tree<<P as View>::Key, <P as View>::Value>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let scalar = db.open_store_single(&name, "scalar")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
+		Ok(MaterialAggregate::new(self.clone(), scalar, bwd))
+	}
+}
+
+impl<P, A> Load for Aggregate<P, A>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	A: 'static + Clone + Send + Sync,
+{
+	type Loaded =
+		MaterialAggregate<P, A, Loaded<(), A>, Loaded<<P as View>::Key, <P as View>::Value>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let scalar = Loaded::new();
+		let bwd = Loaded::new();
+		let res = MaterialAggregate::new(self.clone(), scalar, bwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
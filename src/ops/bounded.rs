@@ -0,0 +1,338 @@
+use bus::Bus;
+use parking_lot::RwLock;
+use std::{
+	iter::{empty as empty_iter, once},
+	ops::{Bound, RangeBounds},
+	sync::Arc,
+};
+
+use crate::{
+	error::Result,
+	macros::{bail, cloned, into_iter, unwrap_or_return},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::database::Db,
+};
+
+type Bounds<K> = (Bound<K>, Bound<K>);
+
+/// A struct that restricts a view to a range of keys.
+/// You can create a [Bounded] from a [View] struct.
+///
+/// Keys outside the range are hidden, and only their events are filtered out.
+/// Inserting or updating a key outside the range fails, removing it does nothing.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let shard = tree.bounded(10..20);
+///
+/// tree.insert(5u32, 5u32).unwrap();
+/// shard.insert(10u32, 10u32).unwrap();
+///
+/// assert_eq!(shard.get(5u32).unwrap(), None);
+/// assert_eq!(shard.first().unwrap(), Some((10, 10)));
+/// assert!(shard.insert(20u32, 20u32).is_err());
+/// ```
+pub struct Bounded<Previous>
+where
+	Previous: View,
+{
+	bounds: Arc<Bounds<Previous::Key>>,
+	from: Previous,
+	watcher: Watcher<Previous::Key, Previous::Value>,
+	sync: Arc<Synchronizer>,
+}
+impl<P: View> Clone for Bounded<P> {
+	fn clone(&self) -> Self {
+		Self {
+			bounds: Arc::clone(&self.bounds),
+			from: self.from.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P> Bounded<P>
+where
+	P: View + Watch,
+	P::Key: Ord,
+{
+	pub(crate) fn new(from: P, range: impl RangeBounds<P::Key>) -> Self
+	where
+		P: 'static + Sync + Send,
+	{
+		let bounds = (range.start_bound().cloned(), range.end_bound().cloned());
+		let bounds = Arc::new(bounds);
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		let watcher = Watcher::new(cloned!(sync, from, bounds, move || {
+			let bus = Arc::new(RwLock::new(Bus::new(128)));
+			let previous = from.watch();
+			spawn_watcher(
+				sync,
+				previous,
+				Arc::clone(&bus),
+				cloned!(bounds, move |event| {
+					let key = match &event {
+						Event::Insert { key, .. } => key,
+						Event::Remove { key } => key,
+					};
+					if bounds.contains(&**key) {
+						Ok(vec![event])
+					} else {
+						Ok(vec![])
+					}
+				}),
+			);
+			bus
+		}));
+		Bounded {
+			bounds,
+			from,
+			watcher,
+			sync,
+		}
+	}
+}
+
+impl<P> Bounded<P>
+where
+	P: View,
+	P::Key: Ord,
+{
+	/// Whether a key is past the end of the range
+	fn is_after(&self, key: &P::Key) -> bool {
+		match &self.bounds.1 {
+			Bound::Included(end) => key > end,
+			Bound::Excluded(end) => key >= end,
+			Bound::Unbounded => false,
+		}
+	}
+	/// Whether a key is before the start of the range
+	fn is_before(&self, key: &P::Key) -> bool {
+		match &self.bounds.0 {
+			Bound::Included(start) => key < start,
+			Bound::Excluded(start) => key <= start,
+			Bound::Unbounded => false,
+		}
+	}
+	/// Keeps an entry only if it is inside the range
+	fn inside<V>(&self, entry: Option<(P::Key, V)>) -> Option<(P::Key, V)> {
+		entry.filter(|(k, _)| self.bounds.contains(k))
+	}
+	/// Intersects a range of keys with the bounds
+	fn clamp(&self, range: impl RangeBounds<P::Key>) -> Bounds<P::Key> {
+		let (start, end) = &*self.bounds;
+		let start = match (range.start_bound(), start) {
+			(Bound::Unbounded, b) => b.clone(),
+			(a, Bound::Unbounded) => a.cloned(),
+			(Bound::Included(a), Bound::Included(b)) => Bound::Included(a.max(b).clone()),
+			(Bound::Excluded(a), Bound::Included(b)) | (Bound::Included(b), Bound::Excluded(a))
+				if a >= b =>
+			{
+				Bound::Excluded(a.clone())
+			}
+			(Bound::Excluded(_), Bound::Included(b)) | (Bound::Included(b), Bound::Excluded(_)) => {
+				Bound::Included(b.clone())
+			}
+			(Bound::Excluded(a), Bound::Excluded(b)) => Bound::Excluded(a.max(b).clone()),
+		};
+		let end = match (range.end_bound(), end) {
+			(Bound::Unbounded, b) => b.clone(),
+			(a, Bound::Unbounded) => a.cloned(),
+			(Bound::Included(a), Bound::Included(b)) => Bound::Included(a.min(b).clone()),
+			(Bound::Excluded(a), Bound::Included(b)) | (Bound::Included(b), Bound::Excluded(a))
+				if a <= b =>
+			{
+				Bound::Excluded(a.clone())
+			}
+			(Bound::Excluded(_), Bound::Included(b)) | (Bound::Included(b), Bound::Excluded(_)) => {
+				Bound::Included(b.clone())
+			}
+			(Bound::Excluded(a), Bound::Excluded(b)) => Bound::Excluded(a.min(b).clone()),
+		};
+		(start, end)
+	}
+	/// Fails if a key is outside the range
+	fn check(&self, key: &P::Key) -> Result<()> {
+		if !self.bounds.contains(key) {
+			bail!("The key is outside the bounds of the view");
+		}
+		Ok(())
+	}
+}
+
+impl<P> View for Bounded<P>
+where
+	P: View,
+	P::Key: Ord,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		if !self.bounds.contains(key) {
+			return Ok(None);
+		}
+		self.from.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		match self.range(..) {
+			Ok(iter) => iter,
+			Err(e) => Box::new(once(Err(e))),
+		}
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		if !self.bounds.contains(key) {
+			return Ok(false);
+		}
+		self.from.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		if self.is_after(key) {
+			return self.last();
+		}
+		let entry = self.from.get_lt_ref(key)?;
+		Ok(self.inside(entry))
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		if self.is_before(key) {
+			return self.first();
+		}
+		let entry = self.from.get_gt_ref(key)?;
+		Ok(self.inside(entry))
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let entry = self.iter().next();
+		let entry = unwrap_or_return!(entry);
+		entry.map(Some)
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let entry = match &self.bounds.1 {
+			Bound::Included(end) => match self.from.get_ref(end)? {
+				Some(value) => Some((end.clone(), value)),
+				None => self.from.get_lt_ref(end)?,
+			},
+			Bound::Excluded(end) => self.from.get_lt_ref(end)?,
+			Bound::Unbounded => self.from.last()?,
+		};
+		Ok(self.inside(entry))
+	}
+	fn is_empty(&self) -> Option<bool> {
+		match self.iter().next() {
+			Some(Ok(_)) => Some(false),
+			Some(Err(_)) => None,
+			None => Some(true),
+		}
+	}
+	fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		let bounds = Arc::clone(&self.bounds);
+		let (start, end) = self.clamp(range);
+		// An inverted range would make some views panic
+		let empty = match (&start, &end) {
+			(Bound::Included(s), Bound::Included(e)) => s > e,
+			(Bound::Included(s), Bound::Excluded(e))
+			| (Bound::Excluded(s), Bound::Included(e))
+			| (Bound::Excluded(s), Bound::Excluded(e)) => s >= e,
+			_ => false,
+		};
+		if empty {
+			return Ok(Box::new(empty_iter()));
+		}
+		let iter = self.from.range((start, end))?;
+		Ok(Box::new(iter.filter(move |r| match r {
+			Ok((k, _)) => bounds.contains(k),
+			Err(_) => true,
+		})))
+	}
+}
+into_iter!(
+	impl<P> for Bounded<P>
+	where
+		P: View,
+		P::Key: Ord,
+);
+impl<P> Change for Bounded<P>
+where
+	P: View + Change<Key = <P as View>::Key>,
+	<P as View>::Key: Ord,
+{
+	type Key = <P as View>::Key;
+	type Value = <P as Change>::Value;
+	type Insert = <P as Change>::Insert;
+	/// Inserts into the source, failing if the key is outside the range
+	fn insert_owned(
+		&self,
+		key: Self::Key,
+		value: Self::Insert,
+	) -> Result<Option<<Self as Change>::Value>> {
+		self.check(&key)?;
+		self.from.insert_owned(key, value)
+	}
+	/// Inserts into the source, failing if the key is outside the range
+	fn insert_ref(
+		&self,
+		key: &Self::Key,
+		value: &Self::Insert,
+	) -> Result<Option<<Self as Change>::Value>> {
+		self.check(key)?;
+		self.from.insert_ref(key, value)
+	}
+	/// Removes from the source, doing nothing if the key is outside the range
+	fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>> {
+		if !self.bounds.contains(key) {
+			return Ok(None);
+		}
+		self.from.remove_ref(key)
+	}
+	/// Removes every key in the range, leaving the rest of the view as it is.
+	fn clear(&self) -> Result<()> {
+		self.from.remove_range(self.clamp(..))?;
+		Ok(())
+	}
+	/// Updates the source, failing if the key is outside the range
+	fn fetch_and_update(
+		&self,
+		key: &Self::Key,
+		f: impl FnMut(Option<<Self as Change>::Value>) -> Option<Self::Insert>,
+	) -> Result<Option<<Self as Change>::Value>> {
+		self.check(key)?;
+		self.from.fetch_and_update(key, f)
+	}
+}
+impl<P> Watch for Bounded<P>
+where
+	P: View + Watch,
+	P::Key: Ord,
+{
+	fn watch(&self) -> bus::BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.from.wait()
+	}
+}
@@ -0,0 +1,178 @@
+use bus::BusReader;
+use delegate::delegate;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	threads::{spawn_listener, Synchronizer},
+	traits::{
+		change::Change,
+		load::LoadedLru,
+		view::View,
+		watch::{Event, Watch},
+	},
+	wrappers::{batch::Batch, database::Db},
+};
+
+/// A struct that keeps the most recently read entries of a view in memory.
+/// You can create a [Cache] from a [View] struct with hashable keys.
+///
+/// Reads go through a [LoadedLru], and misses are loaded from the source view.
+/// Changes in the source update or evict the entries in memory, everything else goes to the source.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Watch};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let cache = tree.cache(2);
+///
+/// tree.insert(1u32, 1u32).unwrap();
+/// assert_eq!(cache.get(1u32).unwrap(), Some(1));
+/// assert!(cache.is_cached(&1));
+///
+/// tree.insert(1u32, 2u32).unwrap();
+/// assert_eq!(cache.get(1u32).unwrap(), Some(2));
+/// ```
+pub struct Cache<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	lru: LoadedLru<Previous::Key, Previous::Value>,
+	sync: Arc<Synchronizer>,
+}
+impl<P: View> Clone for Cache<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			lru: self.lru.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P> Cache<P>
+where
+	P: View + Watch,
+	P::Key: Hash + Eq,
+{
+	pub(crate) fn new(from: P, capacity: usize) -> Self
+	where
+		P: 'static + Sync + Send,
+	{
+		let lru = LoadedLru::new(
+			capacity,
+			cloned!(from, move |key: &P::Key| Ok(from.get_ref(key)?)),
+		);
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		spawn_listener(
+			Arc::clone(&sync),
+			from.watch(),
+			cloned!(lru, move |event| {
+				match event {
+					// Only keys in memory are updated, the others are loaded when read
+					Event::Insert { key, value } => {
+						if lru.is_cached(&key) {
+							lru.insert_ref(&*key, &*value)?;
+						}
+					}
+					Event::Remove { key } => {
+						lru.remove_ref(&*key)?;
+					}
+				}
+				Ok(1)
+			}),
+		);
+		Self { from, lru, sync }
+	}
+	/// Checks if a key is in memory, without loading it or marking it as used.
+	pub fn is_cached(&self, key: &P::Key) -> bool {
+		self.sync.wait();
+		self.lru.is_cached(key)
+	}
+}
+
+impl<P> View for Cache<P>
+where
+	P: View,
+	P::Key: Hash + Eq,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = P::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.lru.get_ref(key)
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.get_ref(key)?.is_some())
+	}
+	#[rustfmt::skip]
+	delegate! {
+		to self.from {
+			fn iter(&self) -> Self::Iter;
+			fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn first(&self) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn last(&self) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn is_empty(&self) -> Option<bool>;
+			fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter>;
+		}
+	}
+}
+into_iter!(
+	impl<P> for Cache<P>
+	where
+		P: View,
+		P::Key: Hash + Eq,
+);
+impl<P> Change for Cache<P>
+where
+	P: View + Change,
+	<P as View>::Key: Hash + Eq,
+{
+	type Key = <P as Change>::Key;
+	type Value = <P as Change>::Value;
+	type Insert = <P as Change>::Insert;
+	#[rustfmt::skip]
+	delegate! {
+		to self.from {
+			fn insert_owned(&self, key: Self::Key, value: Self::Insert) -> Result<Option<<Self as Change>::Value>>;
+			fn insert_ref(&self, key: &Self::Key, value: &Self::Insert) -> Result<Option<<Self as Change>::Value>>;
+			fn remove_owned(&self, key: <Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+			fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+			fn clear(&self) -> Result<()>;
+			fn apply_batch(&self, batch: Batch<Self::Key, Self::Insert>) -> Result<()>;
+			fn fetch_and_update(
+				&self,
+				key: &Self::Key,
+				f: impl FnMut(Option<<Self as Change>::Value>) -> Option<Self::Insert>,
+			) -> Result<Option<<Self as Change>::Value>>;
+			fn update_and_fetch(
+				&self,
+				key: &Self::Key,
+				f: impl FnMut(Option<<Self as Change>::Value>) -> Option<Self::Insert>,
+			) -> Result<Option<Self::Insert>>;
+		}
+	}
+}
+impl<P> Watch for Cache<P>
+where
+	P: View + Watch,
+	P::Key: Hash + Eq,
+{
+	#[rustfmt::skip]
+	delegate! {
+		to self.from {
+			fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>>;
+			fn db(&self) -> Db;
+		}
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
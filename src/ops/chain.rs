@@ -1,10 +1,17 @@
-use anyhow::Result;
 use bus::{Bus, BusReader};
 use parking_lot::RwLock;
-use std::{cmp::Ordering, hash::Hash, sync::Arc};
+use std::{
+	cmp::Ordering,
+	collections::{BTreeMap, HashMap, VecDeque},
+	fmt,
+	hash::Hash,
+	sync::Arc,
+};
 
 use crate::{
-	macros::cloned,
+	error::Result,
+	macros::{cloned, into_iter},
+	structs::merge::Merge,
 	threads::{spawn_watcher, Synchronizer},
 	traits::{
 		view::View,
@@ -12,9 +19,13 @@ use crate::{
 	},
 };
 
+type Resolver<K, V> = dyn Fn(&K, &V, &V) -> V + Send + Sync;
+
 /// A struct that chains two trees.
 /// You can create a [Chain] from two [View] structs, as long as they have the same keys and values.
-/// It gives preference to the first tree.
+/// It gives preference to the first tree,
+/// unless it is created with [chain_with](crate::Operate::chain_with),
+/// in which case the resolver picks the value of keys present in both trees.
 /// # Examples
 /// ```
 /// # use husky::{Tree, Change, View, Operate};
@@ -31,6 +42,10 @@ use crate::{
 /// a_tree.insert("key", "a").unwrap();
 /// let result = chain.get("key").unwrap();
 /// assert_eq!(result, Some("a".to_string()));
+///
+/// let merged = a_tree.chain_with(&b_tree, |_, a, b| format!("{a}{b}"));
+/// let result = merged.get("key").unwrap();
+/// assert_eq!(result, Some("ab".to_string()));
 /// ```
 pub struct Chain<A, B>
 where
@@ -39,6 +54,7 @@ where
 {
 	a: A,
 	b: B,
+	resolver: Arc<Resolver<A::Key, A::Value>>,
 	watcher: Watcher<A::Key, A::Value>,
 	sync: Arc<Synchronizer>,
 }
@@ -51,20 +67,34 @@ where
 		Self {
 			a: self.a.clone(),
 			b: self.b.clone(),
+			resolver: Arc::clone(&self.resolver),
 			watcher: self.watcher.clone(),
 			sync: Arc::clone(&self.sync),
 		}
 	}
 }
+impl<A, B> fmt::Debug for Chain<A, B>
+where
+	A: View + fmt::Debug,
+	B: View<Key = A::Key, Value = A::Value> + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Chain").field(&self.a).field(&self.b).finish()
+	}
+}
 
 impl<A, B> Chain<A, B>
 where
 	A: View + Watch + Sync + Send,
 	B: View<Key = <A as View>::Key, Value = <A as View>::Value> + Watch + Sync + Send,
 {
-	pub(crate) fn new(a: A, b: B) -> Self {
+	pub(crate) fn new<R>(a: A, b: B, resolver: R) -> Self
+	where
+		R: 'static + Fn(&A::Key, &A::Value, &A::Value) -> A::Value + Sync + Send,
+	{
+		let resolver: Arc<Resolver<A::Key, A::Value>> = Arc::new(resolver);
 		let sync = Arc::new(Synchronizer::from(vec![a.sync(), b.sync()]));
-		let watcher = Watcher::new(cloned!(sync, a, b, move || {
+		let watcher = Watcher::new(cloned!(sync, a, b, resolver, move || {
 			let bus = Arc::new(RwLock::new(Bus::new(128)));
 			let a_reader = a.watch();
 			let b_reader = b.watch();
@@ -72,33 +102,18 @@ where
 				Arc::clone(&sync),
 				a_reader,
 				Arc::clone(&bus),
-				cloned!(move |event| {
-					let (key, value) = match event {
-						Event::Insert { key, value } => {
-							(Arc::clone(&key), Some(Arc::clone(&value)))
-						}
-						Event::Remove { key } => {
-							(Arc::clone(&key), b.get_ref(&*key)?.map(Arc::new))
-						}
-					};
-					let event = match value {
-						Some(value) => Event::Insert { key, value },
-						None => Event::Remove { key },
-					};
-					Ok(vec![event])
-				}),
-			);
-			spawn_watcher(
-				sync,
-				b_reader,
-				Arc::clone(&bus),
-				cloned!(move |event| {
+				cloned!(b, resolver, move |event| {
 					let (key, value) = match event {
 						Event::Insert { key, value } => {
-							(Arc::clone(&key), Some(Arc::clone(&value)))
+							let value = match b.get_ref(&key)? {
+								Some(b) => Arc::new(resolver(&key, &value, &b)),
+								None => value,
+							};
+							(key, Some(value))
 						}
 						Event::Remove { key } => {
-							(Arc::clone(&key), a.get_ref(&*key)?.map(Arc::new))
+							let value = b.get_ref(&key)?.map(Arc::new);
+							(key, value)
 						}
 					};
 					let event = match value {
@@ -108,37 +123,76 @@ where
 					Ok(vec![event])
 				}),
 			);
+			spawn_watcher(sync, b_reader, Arc::clone(&bus), move |event| {
+				let (key, value) = match event {
+					Event::Insert { key, value } => {
+						let value = match a.get_ref(&key)? {
+							Some(a) => Arc::new(resolver(&key, &a, &value)),
+							None => value,
+						};
+						(key, Some(value))
+					}
+					Event::Remove { key } => {
+						let value = a.get_ref(&key)?.map(Arc::new);
+						(key, value)
+					}
+				};
+				let event = match value {
+					Some(value) => Event::Insert { key, value },
+					None => Event::Remove { key },
+				};
+				Ok(vec![event])
+			});
 			bus
 		}));
 		Chain {
 			a,
 			b,
+			resolver,
 			watcher,
 			sync,
 		}
 	}
 }
 
+impl<A, B> Chain<A, B>
+where
+	A: View,
+	B: View<Key = A::Key, Value = A::Value>,
+{
+	/// Picks the value of a key, calling the resolver when both trees hold it
+	fn resolve(&self, key: &A::Key, a: Option<A::Value>, b: Option<A::Value>) -> Option<A::Value> {
+		match (a, b) {
+			(Some(a), Some(b)) => Some((self.resolver)(key, &a, &b)),
+			(a, b) => a.or(b),
+		}
+	}
+	fn resolve_entry(&self, a: (A::Key, A::Value), b: (A::Key, A::Value)) -> (A::Key, A::Value) {
+		let value = (self.resolver)(&a.0, &a.1, &b.1);
+		(a.0, value)
+	}
+}
+
 impl<A, B> View for Chain<A, B>
 where
 	A: View,
 	B: View<Key = A::Key, Value = A::Value>,
+	A::Key: Ord,
 {
 	type Key = A::Key;
 	type Value = A::Value;
-	type Iter = std::iter::Chain<A::Iter, B::Iter>;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
 	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
 		let a = self.a.get_ref(key)?;
-		if let Some(a) = a {
-			return Ok(Some(a));
-		}
 		let b = self.b.get_ref(key)?;
-		Ok(b)
+		Ok(self.resolve(key, a, b))
 	}
+	/// Iterates the first tree, then the second one.
+	/// Keys in both trees are yielded twice, use [range](View::range) for sorted and unique keys.
 	fn iter(&self) -> Self::Iter {
 		let a = self.a.iter();
 		let b = self.b.iter();
-		a.chain(b)
+		Box::new(a.chain(b))
 	}
 	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
 		Ok(self.a.contains_key_ref(key)? || self.b.contains_key_ref(key)?)
@@ -155,7 +209,7 @@ where
 			(None, Some(b)) => Ok(Some(b)),
 			(Some(a), Some(b)) => match a.0.cmp(&b.0) {
 				Ordering::Less => Ok(Some(b)),
-				Ordering::Equal => Ok(Some(a)),
+				Ordering::Equal => Ok(Some(self.resolve_entry(a, b))),
 				Ordering::Greater => Ok(Some(a)),
 			},
 		}
@@ -172,7 +226,7 @@ where
 			(None, Some(b)) => Ok(Some(b)),
 			(Some(a), Some(b)) => match a.0.cmp(&b.0) {
 				Ordering::Less => Ok(Some(a)),
-				Ordering::Equal => Ok(Some(a)),
+				Ordering::Equal => Ok(Some(self.resolve_entry(a, b))),
 				Ordering::Greater => Ok(Some(b)),
 			},
 		}
@@ -189,7 +243,7 @@ where
 			(None, Some(b)) => Ok(Some(b)),
 			(Some(a), Some(b)) => match a.0.cmp(&b.0) {
 				Ordering::Less => Ok(Some(a)),
-				Ordering::Equal => Ok(Some(a)),
+				Ordering::Equal => Ok(Some(self.resolve_entry(a, b))),
 				Ordering::Greater => Ok(Some(b)),
 			},
 		}
@@ -206,7 +260,7 @@ where
 			(None, Some(b)) => Ok(Some(b)),
 			(Some(a), Some(b)) => match a.0.cmp(&b.0) {
 				Ordering::Less => Ok(Some(b)),
-				Ordering::Equal => Ok(Some(a)),
+				Ordering::Equal => Ok(Some(self.resolve_entry(a, b))),
 				Ordering::Greater => Ok(Some(a)),
 			},
 		}
@@ -236,17 +290,80 @@ where
 	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
 		let a = (range.start_bound(), range.end_bound());
 		let b = (range.start_bound(), range.end_bound());
-		let a = self.a.range(a)?;
-		let b = self.b.range(b)?;
-		Ok(a.chain(b))
+		let merge = Merge::new(self.a.range(a)?, self.b.range(b)?);
+		let resolver = Arc::clone(&self.resolver);
+		Ok(Box::new(merge.map(move |r| {
+			r.map(|(k, a, b)| match (a, b) {
+				(Some(a), Some(b)) => {
+					let value = resolver(&k, &a, &b);
+					(k, value)
+				}
+				(a, b) => (k, a.or(b).expect("merge yields a value on either side")),
+			})
+		})))
+	}
+	/// Counts along the merged keys, since [iter](View::iter) yields keys in both trees twice.
+	fn nth(&self, n: usize) -> Result<Option<(Self::Key, Self::Value)>> {
+		let mut range = self.range(..)?;
+		for _ in 0..n {
+			if range.next().transpose()?.is_none() {
+				return Ok(None);
+			}
+		}
+		range.next().transpose()
+	}
+	/// Reads the merged keys, so the first keys of both trees are taken in order.
+	fn first_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.range(..)?.take(n).collect()
+	}
+	/// Reads the merged keys, keeping the last entries.
+	fn last_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		if n == 0 {
+			return Ok(Vec::new());
+		}
+		let mut last = VecDeque::with_capacity(n);
+		for res in self.range(..)? {
+			if last.len() == n {
+				last.pop_front();
+			}
+			last.push_back(res?);
+		}
+		Ok(last.into())
+	}
+	/// Reads the merged keys, so keys in both trees get the resolved value.
+	fn collect_btree(&self) -> Result<BTreeMap<Self::Key, Self::Value>>
+	where
+		Self::Key: Ord,
+	{
+		self.range(..)?.collect()
+	}
+	/// Reads the merged keys, so keys in both trees get the resolved value.
+	fn collect_hash(&self) -> Result<HashMap<Self::Key, Self::Value>>
+	where
+		Self::Key: Hash + Eq,
+	{
+		self.range(..)?.collect()
 	}
 }
+into_iter!(
+	impl<A, B> for Chain<A, B>
+	where
+		A: View,
+		B: View<Key = A::Key, Value = A::Value>,
+		A::Key: Ord,
+);
 
 impl<A, B> Watch for Chain<A, B>
 where
 	A: View + Watch,
 	B: View<Key = A::Key, Value = A::Value> + Watch,
-	<A as View>::Key: Hash + Eq,
+	<A as View>::Key: Hash + Ord,
 {
 	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
 		self.watcher.new_reader()
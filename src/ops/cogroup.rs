@@ -0,0 +1,182 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	ops::zip::Zip,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::database::Db,
+};
+
+type CogroupItem<A, B> = (<A as View>::Value, <B as View>::Value);
+
+/// A struct that groups the values of two views under their shared keys.
+/// You can create a [Cogroup] from two [View] structs, as long as they have the same key type.
+///
+/// It is meant for views whose values are lists of rows, as indexes.
+/// Each key gets the rows of both sides, a side without the key getting an empty list.
+/// It works as a [Zip], so a key is updated when either side changes, and iterating merges both views in key order.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let users: Tree<u32, String> = db.open_tree("users").unwrap();
+/// # let orders: Tree<u32, (u32, String)> = db.open_tree("orders").unwrap();
+/// let users_by_id = users.index(|id, _| vec![*id]).load().unwrap();
+/// let orders_by_user = orders.index(|_, (user, _)| vec![*user]).load().unwrap();
+/// let cogroup = users_by_id.cogroup(&orders_by_user);
+///
+/// users.insert(1u32, "alice").unwrap();
+/// orders.insert(10u32, (1u32, "book".to_string())).unwrap();
+/// orders.insert(11u32, (1u32, "pen".to_string())).unwrap();
+///
+/// let (users, orders) = cogroup.get(1u32).unwrap().unwrap();
+/// assert_eq!(users, vec!["alice".to_string()]);
+/// assert_eq!(orders.len(), 2);
+/// ```
+pub struct Cogroup<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+{
+	zip: Zip<A, B>,
+	watcher: Watcher<A::Key, CogroupItem<A, B>>,
+	sync: Arc<Synchronizer>,
+}
+impl<A, B> Clone for Cogroup<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+{
+	fn clone(&self) -> Self {
+		Self {
+			zip: self.zip.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+/// Fills the missing side of a zipped entry with an empty list
+fn group<A: Default, B: Default>((a, b): (Option<A>, Option<B>)) -> (A, B) {
+	(a.unwrap_or_default(), b.unwrap_or_default())
+}
+
+impl<A, B> Cogroup<A, B>
+where
+	A: View + Watch + Sync + Send,
+	B: View<Key = <A as View>::Key> + Watch + Sync + Send,
+	<A as View>::Key: Hash + Ord,
+	A::Value: Default,
+	B::Value: Default,
+{
+	pub(crate) fn new(a: A, b: B) -> Self {
+		let zip = Zip::new(a, b);
+		let sync = Arc::new(Synchronizer::from(vec![zip.sync()]));
+		let watcher = Watcher::new(cloned!(sync, zip, move || {
+			let bus = Arc::new(RwLock::new(Bus::new(128)));
+			spawn_watcher(sync, zip.watch(), Arc::clone(&bus), move |event| {
+				let event = match event {
+					Event::Insert { key, value } => Event::Insert {
+						key,
+						value: Arc::new(group((*value).clone())),
+					},
+					Event::Remove { key } => Event::Remove { key },
+				};
+				Ok(vec![event])
+			});
+			bus
+		}));
+		Cogroup { zip, watcher, sync }
+	}
+}
+
+impl<A, B> View for Cogroup<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+	A::Key: Ord,
+	A::Value: Default,
+	B::Value: Default,
+{
+	type Key = A::Key;
+	type Value = CogroupItem<A, B>;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		Ok(self.zip.get_ref(key)?.map(group))
+	}
+	fn iter(&self) -> Self::Iter {
+		Box::new(self.zip.iter().map(|r| r.map(|(k, v)| (k, group(v)))))
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.zip.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		Ok(self.zip.get_lt_ref(key)?.map(|(k, v)| (k, group(v))))
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		Ok(self.zip.get_gt_ref(key)?.map(|(k, v)| (k, group(v))))
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		Ok(self.zip.first()?.map(|(k, v)| (k, group(v))))
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		Ok(self.zip.last()?.map(|(k, v)| (k, group(v))))
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.zip.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		let iter = self.zip.range(range)?;
+		Ok(Box::new(iter.map(|r| r.map(|(k, v)| (k, group(v))))))
+	}
+}
+into_iter!(
+	impl<A, B> for Cogroup<A, B>
+	where
+		A: View,
+		B: View<Key = A::Key>,
+		A::Key: Ord,
+		A::Value: Default,
+		B::Value: Default,
+);
+
+impl<A, B> Watch for Cogroup<A, B>
+where
+	A: View + Watch,
+	B: View<Key = A::Key> + Watch,
+	<A as View>::Key: Hash + Ord,
+	A::Value: Default,
+	B::Value: Default,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.zip.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
@@ -0,0 +1,150 @@
+use bus::{Bus, BusReader};
+use delegate::delegate;
+use parking_lot::RwLock;
+use std::{hash::Hash, ops::RangeBounds, sync::Arc, time::Duration};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	threads::{spawn_debouncer, Synchronizer},
+	traits::{
+		change::Change,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::database::Db,
+};
+
+/// A struct that coalesces bursts of events.
+/// You can create a [Debounce] from a [View] struct with hashable keys.
+///
+/// Once an event arrives, the events within the window are collected,
+/// and only the latest event of each key is sent, so views that store it skip the intermediate values.
+/// Reads and changes go straight to the source, only the events are delayed.
+/// With the `sync` feature every event is sent as it comes.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Store};
+/// # use std::time::Duration;
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let stored = tree
+///   .debounce(Duration::from_millis(10))
+///   .map(|_, v| v * 2)
+///   .store("stored")
+///   .unwrap();
+///
+/// for i in 0..100u32 {
+///   tree.insert(0u32, i).unwrap();
+/// }
+///
+/// assert_eq!(stored.get(0u32).unwrap(), Some(198));
+/// ```
+pub struct Debounce<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	watcher: Watcher<Previous::Key, Previous::Value>,
+	sync: Arc<Synchronizer>,
+}
+impl<P: View> Clone for Debounce<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P> Debounce<P>
+where
+	P: View + Watch,
+	P::Key: Hash + Eq,
+{
+	pub(crate) fn new(from: P, window: Duration) -> Self
+	where
+		P: 'static + Sync + Send,
+	{
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		let watcher = Watcher::new(cloned!(sync, from, move || {
+			let bus = Arc::new(RwLock::new(Bus::new(128)));
+			spawn_debouncer(sync, from.watch(), Arc::clone(&bus), window);
+			bus
+		}));
+		Debounce {
+			from,
+			watcher,
+			sync,
+		}
+	}
+}
+
+impl<P> View for Debounce<P>
+where
+	P: View,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = P::Iter;
+	#[rustfmt::skip]
+	delegate! {
+		to self.from {
+			fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>>;
+			fn iter(&self) -> Self::Iter;
+			fn contains_key_ref(&self, key: &Self::Key) -> Result<bool>;
+			fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn first(&self) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn last(&self) -> Result<Option<(Self::Key, Self::Value)>> where Self::Key: Ord;
+			fn is_empty(&self) -> Option<bool>;
+			fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter>;
+		}
+	}
+}
+into_iter!(
+	impl<P> for Debounce<P>
+	where
+		P: View,
+);
+impl<P> Change for Debounce<P>
+where
+	P: View + Change,
+{
+	type Key = <P as Change>::Key;
+	type Value = <P as Change>::Value;
+	type Insert = <P as Change>::Insert;
+	#[rustfmt::skip]
+	delegate! {
+		to self.from {
+			fn insert_owned(&self, key: Self::Key, value: Self::Insert) -> Result<Option<<Self as Change>::Value>>;
+			fn insert_ref(&self, key: &Self::Key, value: &Self::Insert) -> Result<Option<<Self as Change>::Value>>;
+			fn remove_owned(&self, key: <Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+			fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+			fn clear(&self) -> Result<()>;
+			fn fetch_and_update(
+				&self,
+				key: &Self::Key,
+				f: impl FnMut(Option<<Self as Change>::Value>) -> Option<Self::Insert>,
+			) -> Result<Option<<Self as Change>::Value>>;
+		}
+	}
+}
+impl<P> Watch for Debounce<P>
+where
+	P: View + Watch,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.from.wait()
+	}
+}
@@ -0,0 +1,76 @@
+use crate::{error::Result, macros::bail, traits::serial::Serial};
+
+/// The difference between two views on a key.
+/// You can create a view of these with [diff](crate::Operate::diff),
+/// which is a [ZipWith](crate::ops::zip::ZipWith) that skips the keys where both values are equal.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, ops::diff::Diff};
+/// # let db = husky::open_temp().unwrap();
+/// # let source: Tree<String, u32> = db.open_tree("source").unwrap();
+/// # let target: Tree<String, u32> = db.open_tree("target").unwrap();
+/// let diff = source.diff(&target);
+///
+/// source.insert("same", 1u32).unwrap();
+/// target.insert("same", 1u32).unwrap();
+/// source.insert("changed", 1u32).unwrap();
+/// target.insert("changed", 2u32).unwrap();
+/// source.insert("added", 1u32).unwrap();
+///
+/// assert_eq!(diff.get("same").unwrap(), None);
+/// assert_eq!(diff.get("changed").unwrap(), Some(Diff::Changed(1, 2)));
+/// assert_eq!(diff.get("added").unwrap(), Some(Diff::OnlyLeft(1)));
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Diff<V> {
+	/// The key is only in the first view
+	OnlyLeft(V),
+	/// The key is only in the second view
+	OnlyRight(V),
+	/// The key is in both views, with different values
+	Changed(V, V),
+}
+
+impl<V> Diff<V>
+where
+	V: Clone + PartialEq,
+{
+	pub(crate) fn new(left: Option<&V>, right: Option<&V>) -> Option<Self> {
+		match (left, right) {
+			(None, None) => None,
+			(Some(l), None) => Some(Diff::OnlyLeft(l.clone())),
+			(None, Some(r)) => Some(Diff::OnlyRight(r.clone())),
+			(Some(l), Some(r)) if l == r => None,
+			(Some(l), Some(r)) => Some(Diff::Changed(l.clone(), r.clone())),
+		}
+	}
+}
+
+impl<V> Serial for Diff<V>
+where
+	V: Serial,
+{
+	fn serialize(&self) -> Result<Vec<u8>> {
+		let parts = match self {
+			Diff::OnlyLeft(l) => vec![vec![0], l.serialize()?],
+			Diff::OnlyRight(r) => vec![vec![1], r.serialize()?],
+			Diff::Changed(l, r) => vec![vec![2], l.serialize()?, r.serialize()?],
+		};
+		Serial::serialize(&parts)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		let parts: Vec<Vec<u8>> = Serial::deserialize(bytes)?;
+		let mut parts = parts.into_iter();
+		let tag = parts.next().unwrap_or_default();
+		let mut next = || match parts.next() {
+			Some(part) => V::deserialize(part),
+			None => bail!("Missing value in a serialized diff"),
+		};
+		match tag.as_slice() {
+			[0] => Ok(Diff::OnlyLeft(next()?)),
+			[1] => Ok(Diff::OnlyRight(next()?)),
+			[2] => Ok(Diff::Changed(next()?, next()?)),
+			_ => bail!("Unknown tag in a serialized diff"),
+		}
+	}
+}
@@ -0,0 +1,362 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{collections::HashMap, hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded, LoadedUnordered},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+/// A struct that keeps a single key for each distinct value.
+/// You can create a [Distinct] from a [View] struct with hashable values.
+///
+/// The first key to get a value represents it, and later keys with the same value are left out.
+/// Once the representative is removed or changes its value, the next key with that value takes its place.
+/// When loaded, the first key in key order represents each value.
+///
+/// [Distinct] doesn't implement [View] or [Watch], you must store it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let distinct = tree.distinct_by_value().load().unwrap();
+///
+/// tree.insert("a", 1u32).unwrap();
+/// tree.insert("b", 1u32).unwrap();
+/// assert_eq!(distinct.get("a").unwrap(), Some(1));
+/// assert_eq!(distinct.get("b").unwrap(), None);
+///
+/// tree.remove("a").unwrap();
+/// assert_eq!(distinct.get("b").unwrap(), Some(1));
+/// ```
+pub struct Distinct<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+}
+impl<P: View> Clone for Distinct<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+		}
+	}
+}
+
+impl<P> Distinct<P>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new(from: P) -> Self {
+		Distinct { from }
+	}
+}
+
+/// A stored or loaded [Distinct].
+///
+/// The forward map holds the representative keys along with their values.
+/// The groups map holds the keys of each value, the first being the representative.
+/// The backward map holds the value of every key, to find its group once it changes.
+pub struct MaterialDistinct<P, F, G, B>
+where
+	P: View,
+	F: Clone,
+	G: Clone,
+	B: Clone,
+{
+	from: Distinct<P>,
+	fwd: F,
+	groups: G,
+	bwd: B,
+	watcher: Watcher<P::Key, P::Value>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, F, G, B> Clone for MaterialDistinct<P, F, G, B>
+where
+	P: View,
+	F: Clone,
+	G: Clone,
+	B: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			groups: self.groups.clone(),
+			bwd: self.bwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, F, G, B> MaterialDistinct<P, F, G, B>
+where
+	P: Watch + Sync + Send,
+	P::Key: PartialEq,
+	P::Value: Hash + Eq,
+	F: Clone
+		+ View<Key = P::Key, Value = P::Value>
+		+ Change<Key = P::Key, Value = P::Value, Insert = P::Value>
+		+ Send
+		+ Sync,
+	G: Clone
+		+ View<Key = P::Value, Value = Vec<P::Key>>
+		+ Change<Key = P::Value, Value = Vec<P::Key>, Insert = Vec<P::Key>>
+		+ Send
+		+ Sync,
+	B: Clone
+		+ View<Key = P::Key, Value = P::Value>
+		+ Change<Key = P::Key, Value = P::Value, Insert = P::Value>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: Distinct<P>, fwd: F, groups: G, bwd: B) -> Self {
+		let reader = from.from.watch();
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, groups, bwd, move |event| {
+				let (key, value) = match event {
+					Event::Insert { key, value } => (key, Some(value)),
+					Event::Remove { key } => (key, None),
+				};
+				let old = bwd.get_ref(&key)?;
+				if old.as_ref() == value.as_deref() {
+					return Ok(vec![]);
+				}
+				let mut events = Vec::new();
+
+				// Leave the old group, promoting the next key if this one represented it
+				if let Some(old) = old {
+					let mut keys = groups.get_ref(&old)?.unwrap_or_default();
+					let position = keys.iter().position(|k| *k == *key);
+					if let Some(position) = position {
+						keys.remove(position);
+					}
+					if position == Some(0) {
+						fwd.remove_ref(&key)?;
+						events.push(Event::Remove {
+							key: Arc::clone(&key),
+						});
+						if let Some(next) = keys.first() {
+							fwd.insert_ref(next, &old)?;
+							events.push(Event::Insert {
+								key: Arc::new(next.clone()),
+								value: Arc::new(old.clone()),
+							});
+						}
+					}
+					match keys.is_empty() {
+						true => groups.remove_ref(&old)?,
+						false => groups.insert_owned(old, keys)?,
+					};
+				}
+
+				// Join the new group, representing it if it is the first
+				match value {
+					Some(value) => {
+						bwd.insert_ref(&key, &value)?;
+						let mut keys = groups.get_ref(&value)?.unwrap_or_default();
+						keys.push((*key).clone());
+						let first = keys.len() == 1;
+						groups.insert_ref(&value, &keys)?;
+						if first {
+							fwd.insert_ref(&key, &value)?;
+							events.push(Event::Insert { key, value });
+						}
+					}
+					None => {
+						bwd.remove_ref(&key)?;
+					}
+				}
+				Ok(events)
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			groups,
+			bwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the groups from the source view, the first key of each value in key order representing it
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.groups.clear()?;
+		self.bwd.clear()?;
+		let mut groups: HashMap<P::Value, Vec<P::Key>> = HashMap::new();
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			self.bwd.insert_ref(&k, &v)?;
+			let keys = groups.entry(v.clone()).or_default();
+			if keys.is_empty() {
+				self.fwd.insert_ref(&k, &v)?;
+			}
+			keys.push(k);
+		}
+		for (v, keys) in groups {
+			self.groups.insert_owned(v, keys)?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+	/// Gets the keys that have a value, the first one representing it
+	pub fn keys_of(&self, value: &P::Value) -> Result<Vec<P::Key>> {
+		self.sync.wait_checked()?;
+		Ok(self.groups.get_ref(value)?.unwrap_or_default())
+	}
+}
+
+impl<P, F, G, B> View for MaterialDistinct<P, F, G, B>
+where
+	P: View,
+	F: Clone + View<Key = P::Key, Value = P::Value>,
+	G: 'static + Clone,
+	B: 'static + Clone,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, F, G, B> for MaterialDistinct<P, F, G, B>
+	where
+		P: View,
+		F: Clone + View<Key = P::Key, Value = P::Value>,
+		G: 'static + Clone,
+		B: 'static + Clone,
+);
+impl<P, F, G, B> Watch for MaterialDistinct<P, F, G, B>
+where
+	P: Watch,
+	F: Clone + View<Key = P::Key, Value = P::Value>,
+	G: 'static + Clone,
+	B: 'static + Clone,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P> Store for Distinct<P>
+where
+	P: Watch + Sync + Send,
+	<P as View>::Key: Serial + PartialEq,
+	<P as View>::Value: Serial + Hash + Eq,
+	Vec<<P as View>::Key>: Serial,
+{
+	type Stored = MaterialDistinct<
+		P,
+		Tree<<P as View>::Key, P::Value>,
+		Tree<<P as View>::Value, Vec<P::Key>>,
+		Tree<<P as View>::Key, P::Value>,
+	>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let groups = db.open_store_tree(&name, "groups")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
+		Ok(MaterialDistinct::new(self.clone(), fwd, groups, bwd))
+	}
+}
+
+impl<P> Load for Distinct<P>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	<P as View>::Value: Hash + Eq,
+{
+	type Loaded = MaterialDistinct<
+		P,
+		Loaded<<P as View>::Key, P::Value>,
+		LoadedUnordered<<P as View>::Value, Vec<P::Key>>,
+		Loaded<<P as View>::Key, P::Value>,
+	>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let fwd = Loaded::new();
+		let groups = LoadedUnordered::new();
+		let bwd = Loaded::new();
+		let res = MaterialDistinct::new(self.clone(), fwd, groups, bwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
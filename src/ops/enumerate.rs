@@ -0,0 +1,300 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	structs::single::Single,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+/// A struct that tags entries with a sequence number, in the order they are inserted.
+/// You can create an [Enumerate] from a [View] struct.
+///
+/// Each new key gets the next number of a counter, and the value becomes `(sequence, value)`.
+/// Replacing a key keeps its number, unless [bump_on_replace](Enumerate::bump_on_replace) is set,
+/// in which case it gets the next one. Removing a key doesn't give its number back.
+///
+/// [Enumerate] doesn't implement [View] or [Watch], you must store it first.
+/// A stored enumerate keeps its counter in the database, so it carries on after a restart.
+/// Loading it numbers the entries already in the view, in key order.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let enumerated = tree.enumerate().load().unwrap();
+///
+/// tree.insert("b", 1u32).unwrap();
+/// tree.insert("a", 2u32).unwrap();
+/// tree.insert("b", 3u32).unwrap();
+///
+/// assert_eq!(enumerated.get("a").unwrap(), Some((1, 2)));
+/// assert_eq!(enumerated.get("b").unwrap(), Some((0, 3)));
+/// assert_eq!(enumerated.sequence().unwrap(), 2);
+/// ```
+pub struct Enumerate<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	bump: bool,
+}
+impl<P: View> Clone for Enumerate<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			bump: self.bump,
+		}
+	}
+}
+
+impl<P> Enumerate<P>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new(from: P) -> Self {
+		Enumerate { from, bump: false }
+	}
+	/// Gives a replaced key the next sequence number, instead of keeping its own.
+	pub fn bump_on_replace(mut self) -> Self {
+		self.bump = true;
+		self
+	}
+}
+
+/// A stored or loaded [Enumerate].
+///
+/// The counter holds the next sequence number, under the `()` key.
+/// The forward map holds each key along with its number.
+pub struct MaterialEnumerate<P, C, F>
+where
+	P: View,
+	C: Clone,
+	F: Clone,
+{
+	from: Enumerate<P>,
+	counter: C,
+	fwd: F,
+	watcher: Watcher<P::Key, (u64, P::Value)>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, C, F> Clone for MaterialEnumerate<P, C, F>
+where
+	P: View,
+	C: Clone,
+	F: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			counter: self.counter.clone(),
+			fwd: self.fwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, C, F> MaterialEnumerate<P, C, F>
+where
+	P: Watch + Sync + Send,
+	C: Clone
+		+ View<Key = (), Value = u64>
+		+ Change<Key = (), Value = u64, Insert = u64>
+		+ Send
+		+ Sync,
+	F: Clone
+		+ View<Key = P::Key, Value = (u64, P::Value)>
+		+ Change<Key = P::Key, Value = (u64, P::Value), Insert = (u64, P::Value)>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: Enumerate<P>, counter: C, fwd: F) -> Self {
+		let reader = from.from.watch();
+		let bump = from.bump;
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		// A single watcher thread, so the numbers follow the order of the events
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(counter, fwd, move |event| match event {
+				Event::Insert { key, value } => {
+					let seq = match fwd.get_ref(&key)? {
+						Some((seq, _)) if !bump => seq,
+						_ => {
+							let seq = counter.get_ref(&())?.unwrap_or(0);
+							counter.insert_owned((), seq + 1)?;
+							seq
+						}
+					};
+					let value = (seq, (*value).clone());
+					fwd.insert_ref(&key, &value)?;
+					let value = Arc::new(value);
+					Ok(vec![Event::Insert { key, value }])
+				}
+				Event::Remove { key } => {
+					fwd.remove_ref(&key)?;
+					Ok(vec![Event::Remove { key }])
+				}
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			counter,
+			fwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the numbers from the source view, in key order
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		let mut seq = 0;
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			self.fwd.insert_owned(k, (seq, v))?;
+			seq += 1;
+		}
+		self.counter.insert_owned((), seq)?;
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+	/// Gets the sequence number the next insert will get
+	pub fn sequence(&self) -> Result<u64> {
+		self.sync.wait_checked()?;
+		Ok(self.counter.get_ref(&())?.unwrap_or(0))
+	}
+}
+
+impl<P, C, F> View for MaterialEnumerate<P, C, F>
+where
+	P: View,
+	C: 'static + Clone,
+	F: Clone + View<Key = P::Key, Value = (u64, P::Value)>,
+{
+	type Key = P::Key;
+	type Value = (u64, P::Value);
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, C, F> for MaterialEnumerate<P, C, F>
+	where
+		P: View,
+		C: 'static + Clone,
+		F: Clone + View<Key = P::Key, Value = (u64, P::Value)>,
+);
+impl<P, C, F> Watch for MaterialEnumerate<P, C, F>
+where
+	P: Watch,
+	C: 'static + Clone,
+	F: Clone + View<Key = P::Key, Value = (u64, P::Value)>,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P> Store for Enumerate<P>
+where
+	P: Watch + Sync + Send,
+	<P as View>::Key: Serial,
+	(u64, <P as View>::Value): Serial,
+{
+	type Stored = MaterialEnumerate<P, Single<u64>, Tree<<P as View>::Key, (u64, P::Value)>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let counter = db.open_store_single(&name, "counter")?;
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		Ok(MaterialEnumerate::new(self.clone(), counter, fwd))
+	}
+}
+
+impl<P> Load for Enumerate<P>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+{
+	type Loaded = MaterialEnumerate<P, Loaded<(), u64>, Loaded<<P as View>::Key, (u64, P::Value)>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let counter = Loaded::new();
+		let fwd = Loaded::new();
+		let res = MaterialEnumerate::new(self.clone(), counter, fwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
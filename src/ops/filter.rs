@@ -1,12 +1,13 @@
-use anyhow::Result;
 use bus::Bus;
 use delegate::delegate;
+use once_cell::sync::OnceCell;
 use parking_lot::RwLock;
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use crate::{
-	macros::{cloned, unwrap_or_return},
-	threads::{spawn_watcher, Synchronizer},
+	error::Result,
+	macros::{cloned, into_iter, unwrap_or_return},
+	threads::{broadcast, spawn_listener, spawn_watcher, Synchronizer},
 	traits::{
 		change::Change,
 		view::View,
@@ -16,6 +17,9 @@ use crate::{
 };
 
 type FilterOp<K, V> = dyn Fn(&K, &V) -> bool + Send + Sync;
+type Shared<K, V> = Arc<RwLock<Bus<Event<K, V>>>>;
+/// The buses of a filter and its complement, created once either is watched.
+type Buses<K, V> = Arc<OnceCell<(Shared<K, V>, Shared<K, V>)>>;
 
 /// A struct that filters values.
 /// You can create a [Filter] from a [View] struct.
@@ -50,6 +54,14 @@ impl<P: View> Clone for Filter<P> {
 		}
 	}
 }
+impl<P> fmt::Debug for Filter<P>
+where
+	P: View + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Filter").field(&self.from).finish()
+	}
+}
 
 impl<P> Filter<P>
 where
@@ -96,6 +108,70 @@ where
 	}
 }
 
+impl<P> Filter<P>
+where
+	P: View + Watch,
+{
+	/// Creates a filter along with its complement, sharing a single thread for the events of the source.
+	/// The thread starts once either of them is watched.
+	pub(crate) fn partition<F>(from: P, filter: F) -> (Self, Self)
+	where
+		F: 'static + Fn(&P::Key, &P::Value) -> bool + Sync + Send,
+		P: 'static + Sync + Send,
+	{
+		let filter: Arc<FilterOp<P::Key, P::Value>> = Arc::new(filter);
+		let complement: Arc<FilterOp<P::Key, P::Value>> = Arc::new(cloned!(filter, move |k, v| !filter(k, v)));
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		let complement_sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		let buses: Buses<P::Key, P::Value> = Arc::default();
+		let init = Arc::new(cloned!(from, filter, sync, complement_sync, buses, move || {
+			buses
+				.get_or_init(|| {
+					let bus: Shared<_, _> = Arc::new(RwLock::new(Bus::new(128)));
+					let complement_bus: Shared<_, _> = Arc::new(RwLock::new(Bus::new(128)));
+					spawn_listener(
+						Arc::clone(&sync),
+						from.watch(),
+						cloned!(filter, sync, complement_sync, bus, complement_bus, move |event| {
+							let (matched, unmatched) = match &event {
+								Event::Insert { key, value } if filter(key, value) => {
+									(event.clone(), Event::Remove { key: Arc::clone(key) })
+								}
+								Event::Insert { key, .. } => (Event::Remove { key: Arc::clone(key) }, event.clone()),
+								Event::Remove { .. } => (event.clone(), event),
+							};
+							sync.outgoing(1);
+							broadcast(&bus, matched);
+							complement_sync.outgoing(1);
+							broadcast(&complement_bus, unmatched);
+							complement_sync.received();
+							// Already counted, as the complement is only received once sent
+							Ok(0)
+						}),
+					);
+					(bus, complement_bus)
+				})
+				.clone()
+		}));
+		let watcher = Watcher::new(cloned!(init, move || init().0));
+		let complement_watcher = Watcher::new(move || init().1);
+		(
+			Filter {
+				from: from.clone(),
+				filter,
+				sync,
+				watcher,
+			},
+			Filter {
+				from,
+				filter: complement,
+				sync: complement_sync,
+				watcher: complement_watcher,
+			},
+		)
+	}
+}
+
 impl<Previous> View for Filter<Previous>
 where
 	Previous: View,
@@ -211,6 +287,11 @@ where
 		})))
 	}
 }
+into_iter!(
+	impl<Previous> for Filter<Previous>
+	where
+		Previous: View,
+);
 impl<Previous> Change for Filter<Previous>
 where
 	Previous: View + Change,
@@ -231,6 +312,11 @@ where
         key: &Self::Key,
         f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
       ) -> Result<Option<Self::Value>>;
+      fn update_and_fetch(
+        &self,
+        key: &Self::Key,
+        f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+      ) -> Result<Option<Self::Insert>>;
 	  }
 	}
 }
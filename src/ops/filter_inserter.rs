@@ -1,9 +1,9 @@
-use anyhow::Result;
-
 use delegate::delegate;
 use std::sync::Arc;
 
 use crate::{
+	error::Result,
+	macros::into_iter,
 	threads::Synchronizer,
 	traits::{
 		change::Change,
@@ -96,6 +96,12 @@ where
     }
   );
 }
+into_iter!(
+	impl<Previous, Merge> for FilterInserter<Previous, Merge>
+	where
+		Previous: View + Change,
+		Merge: 'static + Clone + Send + Sync,
+);
 impl<Previous, Merge> Change for FilterInserter<Previous, Merge>
 where
 	Previous: Change,
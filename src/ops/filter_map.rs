@@ -1,11 +1,11 @@
-use anyhow::Result;
 use bus::Bus;
 use delegate::delegate;
 use parking_lot::RwLock;
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use crate::{
-	macros::{cloned, unwrap_or_return},
+	error::Result,
+	macros::{cloned, into_iter, unwrap_or_return},
 	threads::{spawn_watcher, Synchronizer},
 	traits::{
 		change::Change,
@@ -51,6 +51,14 @@ impl<P: View, M> Clone for FilterMap<P, M> {
 		}
 	}
 }
+impl<P, M> fmt::Debug for FilterMap<P, M>
+where
+	P: View + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("FilterMap").field(&self.from).finish()
+	}
+}
 
 impl<P, Mapped> FilterMap<P, Mapped>
 where
@@ -195,6 +203,12 @@ where
 		))
 	}
 }
+into_iter!(
+	impl<Previous, Mapped> for FilterMap<Previous, Mapped>
+	where
+		Previous: View,
+		Mapped: 'static + Clone + Send + Sync,
+);
 impl<Previous, Mapped> Change for FilterMap<Previous, Mapped>
 where
 	Previous: View + Change,
@@ -1,9 +1,9 @@
-use anyhow::Result;
-
 use delegate::delegate;
 use std::sync::Arc;
 
 use crate::{
+	error::Result,
+	macros::into_iter,
 	threads::Synchronizer,
 	traits::{
 		change::Change,
@@ -18,8 +18,14 @@ type ReduceFn<P, M> =
 
 /// A struct that reduces values on insert.
 /// You can create a [FilterReducer] from a [Change] struct.
-/// # Important
-/// If you perform an insert that bypasses the [FilterReducer] struct, be in on the tree or in another reduce, you may experience data races.
+/// # Atomicity
+/// Each insert is a single [fetch_and_update](Change::fetch_and_update) on the underlying struct,
+/// so the read, the reduce and the write happen atomically on a [Tree](crate::Tree),
+/// where a compare and swap is retried until no other write got in between,
+/// and on a [Loaded](crate::traits::load::Loaded), which holds its lock for the whole update.
+/// As it may be retried, the reducer should not have side effects.
+///
+/// Any other struct is only as atomic as its own [fetch_and_update](Change::fetch_and_update).
 ///
 /// # Examples
 /// ```
@@ -110,6 +116,12 @@ where
     }
   );
 }
+into_iter!(
+	impl<Previous, Merge> for FilterReducer<Previous, Merge>
+	where
+		Previous: View + Change,
+		Merge: 'static + Clone + Send + Sync,
+);
 impl<Previous, Merge> Change for FilterReducer<Previous, Merge>
 where
 	Previous: View + Change<Key = <Previous as View>::Key>,
@@ -0,0 +1,312 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+/// A struct that turns each element of a list value into its own entry.
+/// You can create a [Flatten] from a [View] struct with [Vec] values.
+///
+/// Each element is keyed by the source key and its position in the list, as in `(key, position)`.
+/// When a list is replaced, only the positions that changed get an event,
+/// and the positions past the end of the new list are removed.
+/// Flattening undoes a grouping, such as one made by [index](crate::Operate::index) or [group_by](crate::Operate::group_by).
+///
+/// [Flatten] doesn't implement [View] or [Watch], you must store or load it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, Vec<u32>> = db.open_tree("tree").unwrap();
+/// let flat = tree.flatten().load().unwrap();
+///
+/// tree.insert("a", vec![1u32, 2]).unwrap();
+/// tree.insert("b", vec![3u32]).unwrap();
+///
+/// assert_eq!(flat.get(("a".to_string(), 1)).unwrap(), Some(2));
+/// assert_eq!(flat.iter().count(), 3);
+/// ```
+pub struct Flatten<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+}
+impl<P: View> Clone for Flatten<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+		}
+	}
+}
+
+impl<P> Flatten<P>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new(from: P) -> Self {
+		Flatten { from }
+	}
+}
+
+/// A stored or loaded [Flatten].
+///
+/// The forward map holds each element by its source key and position.
+/// The backward map holds the last list of each source key, to tell which positions changed.
+pub struct MaterialFlatten<P, E, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	from: Flatten<P>,
+	fwd: F,
+	bwd: B,
+	watcher: Watcher<(P::Key, u64), E>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, E, F, B> Clone for MaterialFlatten<P, E, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			bwd: self.bwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, E, F, B> MaterialFlatten<P, E, F, B>
+where
+	P: Watch<Value = Vec<E>> + Sync + Send,
+	E: 'static + Clone + Send + Sync + PartialEq,
+	F: Clone
+		+ View<Key = (P::Key, u64), Value = E>
+		+ Change<Key = (P::Key, u64), Value = E, Insert = E>
+		+ Send
+		+ Sync,
+	B: Clone
+		+ View<Key = P::Key, Value = Vec<E>>
+		+ Change<Key = P::Key, Value = Vec<E>, Insert = Vec<E>>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: Flatten<P>, fwd: F, bwd: B) -> Self {
+		let reader = from.from.watch();
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, bwd, move |event| {
+				let (key, value) = match &event {
+					Event::Insert { key, value } => (key, Some(value)),
+					Event::Remove { key } => (key, None),
+				};
+				let new = value.map(|v| &v[..]).unwrap_or_default();
+				let old = bwd.get_ref(key)?.unwrap_or_default();
+				let mut events = Vec::new();
+
+				// Only the positions that changed are written
+				for (i, element) in new.iter().enumerate() {
+					if old.get(i) == Some(element) {
+						continue;
+					}
+					let position = ((**key).clone(), i as u64);
+					fwd.insert_ref(&position, element)?;
+					events.push(Event::Insert {
+						key: Arc::new(position),
+						value: Arc::new(element.clone()),
+					});
+				}
+				for i in new.len()..old.len() {
+					let position = ((**key).clone(), i as u64);
+					fwd.remove_ref(&position)?;
+					events.push(Event::Remove {
+						key: Arc::new(position),
+					});
+				}
+
+				if new.is_empty() {
+					bwd.remove_ref(key)?;
+				} else {
+					bwd.insert_ref(key, &new.to_vec())?;
+				}
+				Ok(events)
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			bwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the elements from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.bwd.clear()?;
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			if v.is_empty() {
+				continue;
+			}
+			for (i, element) in v.iter().enumerate() {
+				self.fwd
+					.insert_owned((k.clone(), i as u64), element.clone())?;
+			}
+			self.bwd.insert_owned(k, v)?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+}
+
+impl<P, E, F, B> View for MaterialFlatten<P, E, F, B>
+where
+	P: View<Value = Vec<E>>,
+	E: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = (P::Key, u64), Value = E>,
+	B: 'static + Clone,
+{
+	type Key = (P::Key, u64);
+	type Value = E;
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, E, F, B> for MaterialFlatten<P, E, F, B>
+	where
+		P: View<Value = Vec<E>>,
+		E: 'static + Clone + Send + Sync,
+		F: Clone + View<Key = (P::Key, u64), Value = E>,
+		B: 'static + Clone,
+);
+impl<P, E, F, B> Watch for MaterialFlatten<P, E, F, B>
+where
+	P: Watch<Value = Vec<E>>,
+	E: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = (P::Key, u64), Value = E>,
+	B: 'static + Clone,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, E> Store for Flatten<P>
+where
+	P: Watch<Value = Vec<E>> + Sync + Send,
+	E: 'static + Clone + Send + Sync + PartialEq + Serial,
+	<P as View>::Key: Serial,
+	(<P as View>::Key, u64): Serial,
+	Vec<E>: Serial,
+{
+	type Stored =
+		MaterialFlatten<P, E, Tree<(<P as View>::Key, u64), E>, Tree<<P as View>::Key, Vec<E>>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
+		Ok(MaterialFlatten::new(self.clone(), fwd, bwd))
+	}
+}
+
+impl<P, E> Load for Flatten<P>
+where
+	P: Watch<Value = Vec<E>> + View + Sync + Send,
+	E: 'static + Clone + Send + Sync + PartialEq,
+	<P as View>::Key: Ord,
+{
+	type Loaded =
+		MaterialFlatten<P, E, Loaded<(<P as View>::Key, u64), E>, Loaded<<P as View>::Key, Vec<E>>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let fwd = Loaded::new();
+		let bwd = Loaded::new();
+		let res = MaterialFlatten::new(self.clone(), fwd, bwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
@@ -0,0 +1,465 @@
+use bus::{Bus, BusReader};
+use delegate::delegate;
+use parking_lot::RwLock;
+use std::{collections::HashMap, hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter, unwrap_or_return},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+type KeyFn<K, V, G> = dyn Fn(&K, &V) -> G + Send + Sync;
+type FoldFn<K, V, A> = dyn Fn(Option<A>, &K, &V) -> A + Send + Sync;
+type UnfoldFn<K, V, A> = dyn Fn(A, &K, &V) -> A + Send + Sync;
+
+/// A struct that groups entries and aggregates each group.
+/// You can create a [GroupBy] from a [View] struct.
+///
+/// The key function assigns each entry to a group.
+/// The fold function adds an entry to its group's aggregate,
+/// and the unfold function takes it back out when the entry is removed or replaced.
+/// A group is removed once its last entry leaves it.
+///
+/// [GroupBy] doesn't implement [View] or [Watch], you must store it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, (String, u32)> = db.open_tree("tree").unwrap();
+/// let totals = tree
+///   .group_by(
+///     |_, (customer, _)| customer.clone(),
+///     |total, _, (_, price)| total.unwrap_or(0) + price,
+///     |total, _, (_, price)| total - price,
+///   )
+///   .load()
+///   .unwrap();
+///
+/// tree.insert(1u32, ("alice".to_string(), 10u32)).unwrap();
+/// tree.insert(2u32, ("alice".to_string(), 5u32)).unwrap();
+///
+/// let result = totals.get("alice").unwrap();
+/// assert_eq!(result, Some(15));
+/// ```
+pub struct GroupBy<Previous, GroupKey, Acc>
+where
+	Previous: View,
+{
+	key: Arc<KeyFn<Previous::Key, Previous::Value, GroupKey>>,
+	fold: Arc<FoldFn<Previous::Key, Previous::Value, Acc>>,
+	unfold: Arc<UnfoldFn<Previous::Key, Previous::Value, Acc>>,
+	from: Previous,
+}
+impl<P, G, A> Clone for GroupBy<P, G, A>
+where
+	P: View,
+{
+	fn clone(&self) -> Self {
+		Self {
+			key: Arc::clone(&self.key),
+			fold: Arc::clone(&self.fold),
+			unfold: Arc::clone(&self.unfold),
+			from: self.from.clone(),
+		}
+	}
+}
+
+impl<P, G, A> GroupBy<P, G, A>
+where
+	P: View + Watch,
+	G: Serial,
+	A: Serial,
+{
+	pub(crate) fn new<KeyFn, FoldFn, UnfoldFn>(
+		from: P,
+		key: KeyFn,
+		fold: FoldFn,
+		unfold: UnfoldFn,
+	) -> Self
+	where
+		KeyFn: 'static + Fn(&P::Key, &P::Value) -> G + Sync + Send,
+		FoldFn: 'static + Fn(Option<A>, &P::Key, &P::Value) -> A + Sync + Send,
+		UnfoldFn: 'static + Fn(A, &P::Key, &P::Value) -> A + Sync + Send,
+	{
+		GroupBy {
+			from,
+			key: Arc::new(key),
+			fold: Arc::new(fold),
+			unfold: Arc::new(unfold),
+		}
+	}
+}
+
+impl<P, G> GroupBy<P, G, u64>
+where
+	P: View + Watch,
+	G: Serial,
+{
+	pub(crate) fn count_by<KeyFn>(from: P, key: KeyFn) -> Self
+	where
+		KeyFn: 'static + Fn(&P::Key, &P::Value) -> G + Sync + Send,
+	{
+		Self::new(from, key, |count, _, _| count.unwrap_or(0) + 1, |count, _, _| count - 1)
+	}
+}
+
+impl<P, G, A> Change for GroupBy<P, G, A>
+where
+	P: View + Change,
+	G: Serial,
+	A: Serial,
+{
+	type Key = <P as Change>::Key;
+	type Value = <P as Change>::Value;
+	type Insert = <P as Change>::Insert;
+  #[rustfmt::skip]
+	delegate! {
+	  to self.from {
+      fn insert_owned(&self, key: Self::Key, value: Self::Insert) -> Result<Option<<Self as Change>::Value>>;
+      fn insert_ref(&self, key: &<Self as Change>::Key, value: &<Self as Change>::Insert) -> Result<Option<<Self as Change>::Value>>;
+      fn remove_owned(&self, key: <Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+      fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+      fn clear(&self) -> Result<()>;
+      fn fetch_and_update(
+        &self,
+        key: &Self::Key,
+        f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+      ) -> Result<Option<Self::Value>>;
+	  }
+	}
+}
+
+/// A stored or loaded [GroupBy].
+///
+/// The forward map holds the aggregate of each group, along with its entry count.
+/// The backward map holds the group and value each source entry contributed,
+/// so the contribution can be taken back out after the source has changed.
+pub struct MaterialGroupBy<P, G, A, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	from: GroupBy<P, G, A>,
+	fwd: F,
+	bwd: B,
+	watcher: Watcher<G, A>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, G, A, F, B> Clone for MaterialGroupBy<P, G, A, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			bwd: self.bwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, G, A, F, B> MaterialGroupBy<P, G, A, F, B>
+where
+	P: Watch + Sync + Send,
+	G: 'static + Clone + Send + Sync + Hash + Eq,
+	A: 'static + Clone + Send + Sync,
+	F: Clone
+		+ View<Key = G, Value = (u64, A)>
+		+ Change<Key = G, Value = (u64, A), Insert = (u64, A)>
+		+ Send
+		+ Sync,
+	B: Clone
+		+ View<Key = P::Key, Value = (G, P::Value)>
+		+ Change<Key = P::Key, Value = (G, P::Value), Insert = (G, P::Value)>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: GroupBy<P, G, A>, fwd: F, bwd: B) -> Self {
+		let reader = from.from.watch();
+		let key_fn = Arc::clone(&from.key);
+		let fold = Arc::clone(&from.fold);
+		let unfold = Arc::clone(&from.unfold);
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, bwd, move |event| {
+				let mut changed: HashMap<G, Option<(u64, A)>> = HashMap::new();
+				let (key, value) = match &event {
+					Event::Insert { key, value } => (key, Some(value)),
+					Event::Remove { key } => (key, None),
+				};
+
+				// Take the old contribution out of its group
+				if let Some((group, old)) = bwd.remove_ref(key)? {
+					let prev = match changed.remove(&group) {
+						Some(prev) => prev,
+						None => fwd.get_ref(&group)?,
+					};
+					let next = match prev {
+						Some((count, acc)) if count > 1 => Some((count - 1, unfold(acc, key, &old))),
+						_ => None,
+					};
+					changed.insert(group, next);
+				}
+
+				// Add the new contribution to its group
+				if let Some(value) = value {
+					let group = key_fn(key, value);
+					let prev = match changed.remove(&group) {
+						Some(prev) => prev,
+						None => fwd.get_ref(&group)?,
+					};
+					let next = match prev {
+						Some((count, acc)) => (count + 1, fold(Some(acc), key, value)),
+						None => (1, fold(None, key, value)),
+					};
+					changed.insert(group.clone(), Some(next));
+					bwd.insert_owned((**key).clone(), (group, (**value).clone()))?;
+				}
+
+				// Synchronize and create events
+				let mut events = Vec::with_capacity(changed.len());
+				for (group, entry) in changed.into_iter() {
+					match entry {
+						Some(entry) => {
+							fwd.insert_ref(&group, &entry)?;
+							let key = Arc::new(group);
+							let value = Arc::new(entry.1);
+							events.push(Event::Insert { key, value });
+						}
+						None => {
+							fwd.remove_ref(&group)?;
+							let key = Arc::new(group);
+							events.push(Event::Remove { key });
+						}
+					}
+				}
+
+				Ok(events)
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			bwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the groups from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.bwd.clear()?;
+		let mut groups: HashMap<G, (u64, A)> = HashMap::new();
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			let group = (self.from.key)(&k, &v);
+			let next = match groups.remove(&group) {
+				Some((count, acc)) => (count + 1, (self.from.fold)(Some(acc), &k, &v)),
+				None => (1, (self.from.fold)(None, &k, &v)),
+			};
+			groups.insert(group.clone(), next);
+			self.bwd.insert_owned(k, (group, v))?;
+		}
+		for (group, entry) in groups.into_iter() {
+			self.fwd.insert_owned(group, entry)?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+}
+
+impl<P, G, A, F, B> View for MaterialGroupBy<P, G, A, F, B>
+where
+	P: View,
+	G: 'static + Clone + Send + Sync,
+	A: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = G, Value = (u64, A)>,
+	B: View,
+{
+	type Key = G;
+	type Value = A;
+	type Iter = Box<dyn Iterator<Item = Result<(G, A)>>>;
+	fn get_ref(&self, key: &G) -> Result<Option<A>> {
+		self.sync.wait_checked()?;
+		let v = self.fwd.get_ref(key)?;
+		let (_, acc) = unwrap_or_return!(v);
+		Ok(Some(acc))
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		Box::new(self.fwd.iter().map(|r| r.map(|(k, (_, acc))| (k, acc))))
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.get_lt_ref(key)?;
+		let (k, (_, acc)) = unwrap_or_return!(e);
+		Ok(Some((k, acc)))
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.get_gt_ref(key)?;
+		let (k, (_, acc)) = unwrap_or_return!(e);
+		Ok(Some((k, acc)))
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.first()?;
+		let (k, (_, acc)) = unwrap_or_return!(e);
+		Ok(Some((k, acc)))
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.last()?;
+		let (k, (_, acc)) = unwrap_or_return!(e);
+		Ok(Some((k, acc)))
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		let iter = self.fwd.range(range)?;
+		Ok(Box::new(iter.map(|r| r.map(|(k, (_, acc))| (k, acc)))))
+	}
+}
+into_iter!(
+	impl<P, G, A, F, B> for MaterialGroupBy<P, G, A, F, B>
+	where
+		P: View,
+		G: 'static + Clone + Send + Sync,
+		A: 'static + Clone + Send + Sync,
+		F: Clone + View<Key = G, Value = (u64, A)>,
+		B: View,
+);
+impl<P, G, A, F, B> Change for MaterialGroupBy<P, G, A, F, B>
+where
+	P: View + Change,
+	G: 'static + Clone + Send + Sync,
+	A: 'static + Clone + Send + Sync,
+	F: 'static + Clone,
+	B: 'static + Clone,
+{
+	type Key = <P as Change>::Key;
+	type Value = <P as Change>::Value;
+	type Insert = <P as Change>::Insert;
+  #[rustfmt::skip]
+	delegate! {
+    to self.from.from {
+      fn insert_owned(&self, key: Self::Key, value: Self::Insert) -> Result<Option<<Self as Change>::Value>>;
+      fn insert_ref(&self, key: &<Self as Change>::Key, value: &<Self as Change>::Insert) -> Result<Option<<Self as Change>::Value>>;
+      fn remove_owned(&self, key: <Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+      fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
+      fn clear(&self) -> Result<()>;
+      fn fetch_and_update(
+        &self,
+        key: &Self::Key,
+        f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+      ) -> Result<Option<Self::Value>>;
+    }
+  }
+}
+impl<P, G, A, F, B> Watch for MaterialGroupBy<P, G, A, F, B>
+where
+	P: Watch,
+	G: 'static + Clone + Send + Sync,
+	A: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = G, Value = (u64, A)>,
+	B: View,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, G, A> Store for GroupBy<P, G, A>
+where
+	P: Watch + Sync + Send,
+	G: Serial + Hash + Eq,
+	A: Serial,
+	<P as View>::Key: Serial,
+	<P as View>::Value: Serial,
+	(u64, A): Serial,
+	(G, <P as View>::Value): Serial,
+{
+	type Stored =
+		MaterialGroupBy<P, G, A, Tree<G, (u64, A)>, Tree<<P as View>::Key, (G, <P as View>::Value)>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
+		Ok(MaterialGroupBy::new(self.clone(), fwd, bwd))
+	}
+}
+
+impl<P, G, A> Load for GroupBy<P, G, A>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	G: 'static + Clone + Send + Sync + Hash + Ord,
+	A: 'static + Clone + Send + Sync,
+{
+	type Loaded = MaterialGroupBy<
+		P,
+		G,
+		A,
+		Loaded<G, (u64, A)>,
+		Loaded<<P as View>::Key, (G, <P as View>::Value)>,
+	>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let fwd = Loaded::new();
+		let bwd = Loaded::new();
+		let res = MaterialGroupBy::new(self.clone(), fwd, bwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
@@ -1,10 +1,15 @@
 mod store;
+mod unique;
 
-use anyhow::Result;
 use delegate::delegate;
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-use crate::traits::{change::Change, serial::Serial, view::View, watch::Watch};
+use crate::{
+	error::Result,
+	traits::{change::Change, serial::Serial, view::View, watch::Watch},
+};
+
+pub use self::unique::{MaterialUniqueIndex, UniqueIndex};
 
 type Indexer<K, V, I> = dyn Fn(&K, &V) -> Vec<I> + Send + Sync;
 
@@ -46,6 +51,14 @@ where
 		}
 	}
 }
+impl<P, I> fmt::Debug for Index<P, I>
+where
+	P: View + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Index").field(&self.from).finish()
+	}
+}
 
 impl<P, I> Index<P, I>
 where
@@ -1,13 +1,13 @@
-use anyhow::Result;
 use bus::{Bus, BusReader};
 use delegate::delegate;
 use parking_lot::RwLock;
 use std::{collections::HashMap, hash::Hash, sync::Arc};
 
 use crate::{
-	macros::{cloned, hash, unwrap_or_return},
+	error::Result,
+	macros::{cloned, into_iter, unwrap_or_return},
 	structs::stable_vec::StableVec,
-	threads::{spawn_watcher, Synchronizer},
+	threads::{spawn_batch_watcher, Synchronizer},
 	traits::{serial::Serial, watch::Watcher},
 	wrappers::{database::Db, tree::Tree},
 };
@@ -20,8 +20,24 @@ use crate::traits::{
 	watch::{Event, Watch},
 };
 
+#[cfg(feature = "rayon")]
+use crate::helpers::{par_group, REBUILD_CHUNK};
+
 use super::Index;
 
+macro_rules! values_from_keys {
+	($s:expr, $k:expr) => {{
+		let mut values = Vec::with_capacity($k.len());
+		for i in $k {
+			let value = $s.get_ref(&i)?;
+			if let Some(value) = value {
+				values.push(value);
+			}
+		}
+		values
+	}};
+}
+
 pub struct MaterialIndex<P, I, F, B>
 where
 	P: View,
@@ -76,42 +92,45 @@ where
 		let indexer = Arc::clone(&from.indexer);
 		let bus = Arc::new(RwLock::new(Bus::new(128)));
 		let sync = Arc::new(Synchronizer::from(vec![source.sync()]));
-		spawn_watcher(
+		// Batched, so a bulk load writes and sends each index once
+		spawn_batch_watcher(
 			Arc::clone(&sync),
 			reader,
 			Arc::clone(&bus),
-			cloned!(fwd, bwd, move |event| {
+			cloned!(fwd, bwd, move |batch| {
 				let mut changed: HashMap<I, StableVec<P::Key>> = HashMap::new();
-				let (key, value) = match &event {
-					Event::Insert { key, value } => (&*key, Some(&*value)),
-					Event::Remove { key } => (&*key, None),
-				};
+				for event in batch {
+					let (key, value) = match &event {
+						Event::Insert { key, value } => (&*key, Some(&*value)),
+						Event::Remove { key } => (&*key, None),
+					};
 
-				//Remove old entries
-				let bwd_keys = bwd.get_ref(key)?.unwrap_or_default();
-				bwd.remove_ref(key)?;
+					//Remove old entries
+					let bwd_keys = bwd.get_ref(key)?.unwrap_or_default();
+					bwd.remove_ref(key)?;
 
-				for (k, position) in bwd_keys.into_vec() {
-					let entry = changed
-						.entry(k.clone())
-						.or_insert_with(|| fwd.get_ref(&k).ok().flatten().unwrap_or_default());
-					entry.remove(position);
-				}
+					for (k, position) in bwd_keys.into_vec() {
+						let entry = changed
+							.entry(k.clone())
+							.or_insert_with(|| fwd.get_ref(&k).ok().flatten().unwrap_or_default());
+						entry.remove(position);
+					}
 
-				// Add new entries
-				if let Some(value) = value {
-					let mut bwd_keys = bwd.entry((**key).clone())?;
-					let bwd_keys = bwd_keys.or_insert_with(StableVec::new);
-					let new_entries = indexer(key, value);
-					for i in new_entries {
-						let entry = changed.entry(i.clone()).or_insert_with(|| {
-							fwd.get_ref(&i)
-								.ok()
-								.flatten()
-								.unwrap_or_else(StableVec::new)
-						});
-						let position = entry.push((**key).clone());
-						bwd_keys.push((i, position));
+					// Add new entries
+					if let Some(value) = value {
+						let mut bwd_keys = bwd.entry((**key).clone())?;
+						let bwd_keys = bwd_keys.or_insert_with(StableVec::new);
+						let new_entries = indexer(key, value);
+						for i in new_entries {
+							let entry = changed.entry(i.clone()).or_insert_with(|| {
+								fwd.get_ref(&i)
+									.ok()
+									.flatten()
+									.unwrap_or_else(StableVec::new)
+							});
+							let position = entry.push((**key).clone());
+							bwd_keys.push((i, position));
+						}
 					}
 				}
 
@@ -153,6 +172,9 @@ where
 	pub fn rebuild(&self) -> Result<()> {
 		self.fwd.clear()?;
 		self.bwd.clear()?;
+		#[cfg(feature = "rayon")]
+		self.rebuild_chunks()?;
+		#[cfg(not(feature = "rayon"))]
 		for res in self.from.from.iter() {
 			let (k, v) = res?;
 			let entries = (self.from.indexer)(&k, &v);
@@ -178,19 +200,200 @@ where
 		self.sync.reset();
 		Ok(())
 	}
+	/// Indexes the source a chunk at a time, with the `rayon` feature.
+	/// The entries of a chunk are grouped by index key in parallel, then each index key is written once.
+	/// Only [REBUILD_CHUNK] entries of the source are held in memory at once.
+	#[cfg(feature = "rayon")]
+	fn rebuild_chunks(&self) -> Result<()> {
+		let indexer = &self.from.indexer;
+		let mut iter = self.from.from.iter();
+		loop {
+			let chunk = iter.by_ref().take(REBUILD_CHUNK).collect::<Result<Vec<_>>>()?;
+			if chunk.is_empty() {
+				return Ok(());
+			}
+			let groups = par_group(&chunk, |(k, v)| indexer(k, v).into_iter().map(|i| (i, ())).collect());
+			// The index keys of each entry of the chunk, along with its position under them
+			let mut links = vec![Vec::new(); chunk.len()];
+			for (i, members) in groups {
+				let positions = members.into_iter().map(|(p, _)| p).collect::<Vec<_>>();
+				let mut entry = self.fwd.entry_ref(&i)?;
+				let values = entry.or_insert_with(StableVec::new);
+				let indexes = values.extend(positions.iter().map(|p| chunk[*p].0.clone()));
+				for (p, index) in positions.into_iter().zip(indexes) {
+					links[p].push((i.clone(), index));
+				}
+			}
+			for ((k, _), links) in chunk.iter().zip(links) {
+				let mut entry = self.bwd.entry_ref(k)?;
+				entry.or_insert_with(StableVec::new).extend(links.into_iter());
+			}
+		}
+	}
+	/// Gets the source keys under an index key, to push or remove them one by one.
+	/// Please refer to [IndexEntry]
+	pub fn bucket(&self, index: I) -> Result<IndexEntry<'_, P, I, F, B>> {
+		self.sync.wait_checked()?;
+		let keys = self.fwd.get_ref(&index)?.unwrap_or_default();
+		Ok(IndexEntry {
+			index,
+			keys,
+			pushed: Vec::new(),
+			removed: Vec::new(),
+			from: self,
+		})
+	}
 }
 
-macro_rules! values_from_keys {
-	($s:expr, $k:expr) => {{
-		let mut values = Vec::with_capacity($k.len());
-		for i in $k {
-			let value = $s.get_ref(&i)?;
-			if let Some(value) = value {
-				values.push(value);
+/// The source keys of a [MaterialIndex] under a single index key.
+///
+/// Changes are written back when the entry is dropped, and sent as a single event.
+/// Pushed keys are linked to the index key until removed, or until their entry in the source changes.
+///
+/// The source should not change while an entry is held, or one of the changes may be lost.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let index = tree.index(|_, v| vec![v % 2]).load().unwrap();
+/// tree.insert(1u32, 10u32).unwrap();
+/// tree.insert(2u32, 20u32).unwrap();
+/// tree.insert(3u32, 31u32).unwrap();
+///
+/// let mut bucket = index.bucket(0).unwrap();
+/// bucket.remove(0);
+/// bucket.push(3);
+/// drop(bucket);
+///
+/// assert_eq!(index.get(0u32).unwrap(), Some(vec![31, 20]));
+/// ```
+pub struct IndexEntry<'a, P, I, F, B>
+where
+	P: View,
+	I: 'static + Clone + Send + Sync + Hash + Ord,
+	F: Clone
+		+ View<Key = I, Value = StableVec<P::Key>>
+		+ Change<Key = I, Value = StableVec<P::Key>, Insert = StableVec<P::Key>>,
+	B: Clone
+		+ View<Key = <P as View>::Key, Value = StableVec<(I, usize)>>
+		+ Change<
+			Key = <P as View>::Key,
+			Value = StableVec<(I, usize)>,
+			Insert = StableVec<(I, usize)>,
+		>,
+{
+	index: I,
+	keys: StableVec<P::Key>,
+	pushed: Vec<usize>,
+	removed: Vec<(usize, P::Key)>,
+	from: &'a MaterialIndex<P, I, F, B>,
+}
+impl<'a, P, I, F, B> IndexEntry<'a, P, I, F, B>
+where
+	P: View,
+	I: 'static + Clone + Send + Sync + Hash + Ord,
+	F: Clone
+		+ View<Key = I, Value = StableVec<P::Key>>
+		+ Change<Key = I, Value = StableVec<P::Key>, Insert = StableVec<P::Key>>,
+	B: Clone
+		+ View<Key = <P as View>::Key, Value = StableVec<(I, usize)>>
+		+ Change<
+			Key = <P as View>::Key,
+			Value = StableVec<(I, usize)>,
+			Insert = StableVec<(I, usize)>,
+		>,
+{
+	/// The current source keys under the index key
+	pub fn keys(&self) -> Vec<&P::Key> {
+		self.keys.to_vec()
+	}
+	/// Links a source key to the index key
+	pub fn push(&mut self, key: P::Key) {
+		let position = self.keys.push(key);
+		self.pushed.push(position);
+	}
+	/// Unlinks the source key at an index of [keys](IndexEntry::keys)
+	pub fn remove(&mut self, nth: usize) -> Option<P::Key> {
+		let position = self.keys.position(nth)?;
+		let key = self.keys.take(position)?;
+		self.removed.push((position, key.clone()));
+		Some(key)
+	}
+	/// Unlinks every source key that doesn't match the predicate
+	pub fn retain(&mut self, f: impl Fn(&P::Key) -> bool) {
+		let positions = self
+			.keys
+			.slots()
+			.filter(|(_, k)| !f(k))
+			.map(|(i, _)| i)
+			.collect::<Vec<_>>();
+		for position in positions {
+			if let Some(key) = self.keys.take(position) {
+				self.removed.push((position, key));
 			}
 		}
-		values
-	}};
+	}
+	/// Writes the keys back, along with the links from each source key
+	fn write(&mut self) -> Result<()> {
+		let from = self.from;
+		for (position, key) in self.removed.drain(..) {
+			let mut links = from.bwd.get_ref(&key)?.unwrap_or_default();
+			let unlinked = links
+				.slots()
+				.filter(|(_, (i, p))| *i == self.index && *p == position)
+				.map(|(i, _)| i)
+				.collect::<Vec<_>>();
+			for i in unlinked {
+				links.take(i);
+			}
+			from.bwd.insert_owned(key, links)?;
+		}
+		self.pushed.sort_unstable();
+		self.pushed.dedup();
+		for position in self.pushed.drain(..) {
+			let key = match self.keys.slots().find(|(i, _)| *i == position) {
+				Some((_, key)) => key.clone(),
+				None => continue,
+			};
+			let mut links = from.bwd.get_ref(&key)?.unwrap_or_default();
+			links.push((self.index.clone(), position));
+			from.bwd.insert_owned(key, links)?;
+		}
+		let key = Arc::new(self.index.clone());
+		from.sync.outgoing(1);
+		if self.keys.is_empty() {
+			from.fwd.remove_ref(&self.index)?;
+			from.watcher.send(Event::Remove { key });
+		} else {
+			from.fwd.insert_ref(&self.index, &self.keys)?;
+			let source = &from.from.from;
+			let value = Arc::new(values_from_keys!(source, self.keys.to_vec()));
+			from.watcher.send(Event::Insert { key, value });
+		}
+		Ok(())
+	}
+}
+impl<'a, P, I, F, B> Drop for IndexEntry<'a, P, I, F, B>
+where
+	P: View,
+	I: 'static + Clone + Send + Sync + Hash + Ord,
+	F: Clone
+		+ View<Key = I, Value = StableVec<P::Key>>
+		+ Change<Key = I, Value = StableVec<P::Key>, Insert = StableVec<P::Key>>,
+	B: Clone
+		+ View<Key = <P as View>::Key, Value = StableVec<(I, usize)>>
+		+ Change<
+			Key = <P as View>::Key,
+			Value = StableVec<(I, usize)>,
+			Insert = StableVec<(I, usize)>,
+		>,
+{
+	fn drop(&mut self) {
+		if !self.pushed.is_empty() || !self.removed.is_empty() {
+			self.write().unwrap();
+		}
+	}
 }
 
 impl<P, I, F, B> View for MaterialIndex<P, I, F, B>
@@ -204,7 +407,7 @@ where
 	type Value = Vec<P::Value>;
 	type Iter = Box<dyn Iterator<Item = Result<(I, Vec<P::Value>)>>>;
 	fn get_ref(&self, key: &I) -> Result<Option<Vec<P::Value>>> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let v = self.fwd.get_ref(key)?;
 		let keys = unwrap_or_return!(v).into_vec();
 		let source = &self.from.from;
@@ -232,14 +435,14 @@ where
 		}))
 	}
 	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.fwd.contains_key_ref(key)
 	}
 	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.get_lt_ref(key)?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -255,7 +458,7 @@ where
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.get_gt_ref(key)?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -267,7 +470,7 @@ where
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.first()?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -279,7 +482,7 @@ where
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.last()?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -307,6 +510,14 @@ where
 		})))
 	}
 }
+into_iter!(
+	impl<P, I, F, B> for MaterialIndex<P, I, F, B>
+	where
+		P: View,
+		I: 'static + Clone + Send + Sync,
+		F: Clone + View<Key = I, Value = StableVec<P::Key>>,
+		B: View,
+);
 impl<P, I, F, B> Change for MaterialIndex<P, I, F, B>
 where
 	P: View + Change,
@@ -354,8 +565,6 @@ where
 	}
 }
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
 
 impl<P, I> Store for Index<P, I>
 where
@@ -372,10 +581,8 @@ where
 	>;
 	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
 		let db = self.from.db();
-		let fwd = hash!(name, "fwd");
-		let bwd = hash!(name, "bwd");
-		let fwd = db.open_tree(fwd)?;
-		let bwd = db.open_tree(bwd)?;
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
 		Ok(MaterialIndex::new(self.clone(), fwd, bwd))
 	}
 }
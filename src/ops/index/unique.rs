@@ -0,0 +1,426 @@
+use bus::{Bus, BusReader};
+use parking_lot::{Mutex, RwLock};
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{bail, cloned, into_iter, unwrap_or_return},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+type Indexer<K, V, I> = dyn Fn(&K, &V) -> Vec<I> + Send + Sync;
+
+/// A struct that reindexes entries, where each index key belongs to a single entry.
+/// You can create an [UniqueIndex] from a [View] struct.
+///
+/// Inserting through the stored index fails when another entry already holds one of its index keys.
+/// Inserts through the index take turns, so two entries can't claim the same index key at once.
+/// If a conflicting entry gets in by bypassing the index, the entry that held the key first keeps it.
+///
+/// [UniqueIndex] doesn't implement [View] or [Watch], you must store it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, String> = db.open_tree("tree").unwrap();
+/// let by_email = tree
+///   .unique_index(|_, email| vec![email.clone()])
+///   .load()
+///   .unwrap();
+///
+/// by_email.insert(1u32, "alice@example.com").unwrap();
+/// assert!(by_email.insert(2u32, "alice@example.com").is_err());
+///
+/// let result = by_email.get_by_index("alice@example.com").unwrap();
+/// assert_eq!(result, Some((1, "alice@example.com".to_string())));
+/// ```
+pub struct UniqueIndex<Previous, IndexKey>
+where
+	Previous: View,
+{
+	indexer: Arc<Indexer<Previous::Key, Previous::Value, IndexKey>>,
+	from: Previous,
+	/// Held by inserts from the check of their index keys until the source has the entry
+	inserting: Arc<Mutex<()>>,
+}
+impl<P, I> Clone for UniqueIndex<P, I>
+where
+	P: View,
+{
+	fn clone(&self) -> Self {
+		Self {
+			indexer: self.indexer.clone(),
+			from: self.from.clone(),
+			inserting: Arc::clone(&self.inserting),
+		}
+	}
+}
+
+impl<P, I> UniqueIndex<P, I>
+where
+	P: View + Watch,
+	I: Serial,
+{
+	pub(crate) fn new<Indexer>(from: P, indexer: Indexer) -> Self
+	where
+		Indexer: 'static + Fn(&P::Key, &P::Value) -> Vec<I> + Sync + Send,
+	{
+		let indexer = Arc::new(indexer);
+		let inserting = Arc::default();
+		UniqueIndex {
+			from,
+			indexer,
+			inserting,
+		}
+	}
+}
+
+/// A stored or loaded [UniqueIndex].
+///
+/// The forward map holds the source key of each index key.
+/// The backward map holds the index keys each source entry got.
+pub struct MaterialUniqueIndex<P, I, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	from: UniqueIndex<P, I>,
+	fwd: F,
+	bwd: B,
+	watcher: Watcher<I, P::Value>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, I, F, B> Clone for MaterialUniqueIndex<P, I, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			bwd: self.bwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, I, F, B> MaterialUniqueIndex<P, I, F, B>
+where
+	P: Watch + Sync + Send,
+	P::Key: PartialEq,
+	I: 'static + Clone + Send + Sync + PartialEq,
+	F: Clone
+		+ View<Key = I, Value = P::Key>
+		+ Change<Key = I, Value = P::Key, Insert = P::Key>
+		+ Send
+		+ Sync,
+	B: Clone
+		+ View<Key = P::Key, Value = Vec<I>>
+		+ Change<Key = P::Key, Value = Vec<I>, Insert = Vec<I>>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: UniqueIndex<P, I>, fwd: F, bwd: B) -> Self {
+		let reader = from.from.watch();
+		let indexer = Arc::clone(&from.indexer);
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, bwd, move |event| {
+				let (key, value) = match &event {
+					Event::Insert { key, value } => (key, Some(value)),
+					Event::Remove { key } => (key, None),
+				};
+				let new = match value {
+					Some(value) => indexer(key, value),
+					None => Vec::new(),
+				};
+				let mut events = Vec::new();
+
+				// Remove the index keys the entry no longer has
+				let old = bwd.remove_ref(key)?.unwrap_or_default();
+				for i in old.into_iter().filter(|i| !new.contains(i)) {
+					if fwd.get_ref(&i)?.as_ref() == Some(&**key) {
+						fwd.remove_ref(&i)?;
+						events.push(Event::Remove { key: Arc::new(i) });
+					}
+				}
+
+				// Claim the new ones, unless another entry holds them
+				let mut claimed = Vec::with_capacity(new.len());
+				if let Some(value) = value {
+					for i in new {
+						match fwd.get_ref(&i)? {
+							Some(owner) if owner != **key => continue,
+							_ => fwd.insert_ref(&i, key)?,
+						};
+						claimed.push(i.clone());
+						let value = Arc::clone(value);
+						events.push(Event::Insert {
+							key: Arc::new(i),
+							value,
+						});
+					}
+				}
+				if !claimed.is_empty() {
+					bwd.insert_ref(key, &claimed)?;
+				}
+
+				Ok(events)
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			bwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the index from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.bwd.clear()?;
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			let mut claimed = Vec::new();
+			for i in (self.from.indexer)(&k, &v) {
+				if self.fwd.contains_key_ref(&i)? {
+					continue;
+				}
+				self.fwd.insert_ref(&i, &k)?;
+				claimed.push(i);
+			}
+			if !claimed.is_empty() {
+				self.bwd.insert_owned(k, claimed)?;
+			}
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+}
+
+impl<P, I, F, B> MaterialUniqueIndex<P, I, F, B>
+where
+	P: View,
+	I: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = I, Value = P::Key>,
+	B: Clone,
+{
+	/// Gets the source entry that holds an index key
+	pub fn get_by_index<IK: Into<I>>(&self, index_key: IK) -> Result<Option<(P::Key, P::Value)>> {
+		self.sync.wait_checked()?;
+		let key = self.fwd.get_ref(&index_key.into())?;
+		let key = unwrap_or_return!(key);
+		let value = self.from.from.get_ref(&key)?;
+		Ok(value.map(|value| (key, value)))
+	}
+}
+
+impl<P, I, F, B> View for MaterialUniqueIndex<P, I, F, B>
+where
+	P: View,
+	I: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = I, Value = P::Key>,
+	B: 'static + Clone,
+{
+	type Key = I;
+	type Value = P::Value;
+	type Iter = Box<dyn Iterator<Item = Result<(I, P::Value)>>>;
+	fn get_ref(&self, key: &I) -> Result<Option<P::Value>> {
+		self.sync.wait_checked()?;
+		let key = self.fwd.get_ref(key)?;
+		let key = unwrap_or_return!(key);
+		self.from.from.get_ref(&key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		let source = self.from.from.clone();
+		Box::new(self.fwd.iter().filter_map(move |r| {
+			let (i, k) = match r {
+				Ok(entry) => entry,
+				Err(e) => return Some(Err(e)),
+			};
+			source.get_ref(&k).transpose().map(|v| v.map(|v| (i, v)))
+		}))
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.get_lt_ref(key)?;
+		let (i, k) = unwrap_or_return!(e);
+		Ok(self.from.from.get_ref(&k)?.map(|v| (i, v)))
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.get_gt_ref(key)?;
+		let (i, k) = unwrap_or_return!(e);
+		Ok(self.from.from.get_ref(&k)?.map(|v| (i, v)))
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.first()?;
+		let (i, k) = unwrap_or_return!(e);
+		Ok(self.from.from.get_ref(&k)?.map(|v| (i, v)))
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		let e = self.fwd.last()?;
+		let (i, k) = unwrap_or_return!(e);
+		Ok(self.from.from.get_ref(&k)?.map(|v| (i, v)))
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		let source = self.from.from.clone();
+		Ok(Box::new(self.fwd.range(range)?.filter_map(move |r| {
+			let (i, k) = match r {
+				Ok(entry) => entry,
+				Err(e) => return Some(Err(e)),
+			};
+			source.get_ref(&k).transpose().map(|v| v.map(|v| (i, v)))
+		})))
+	}
+}
+into_iter!(
+	impl<P, I, F, B> for MaterialUniqueIndex<P, I, F, B>
+	where
+		P: View,
+		I: 'static + Clone + Send + Sync,
+		F: Clone + View<Key = I, Value = P::Key>,
+		B: 'static + Clone,
+);
+impl<P, I, F, B> Change for MaterialUniqueIndex<P, I, F, B>
+where
+	P: View
+		+ Change<Key = <P as View>::Key, Value = <P as View>::Value, Insert = <P as View>::Value>,
+	<P as View>::Key: PartialEq,
+	I: 'static + Clone + Send + Sync,
+	F: 'static + Clone + View<Key = I, Value = <P as View>::Key>,
+	B: 'static + Clone,
+{
+	type Key = <P as View>::Key;
+	type Value = <P as View>::Value;
+	type Insert = <P as View>::Value;
+	/// Inserts into the source, failing if another entry holds one of the index keys
+	fn insert_owned(&self, key: Self::Key, value: Self::Insert) -> Result<Option<Self::Value>> {
+		// Waited on once the lock is held, so the index has the keys of the previous insert
+		let _inserting = self.from.inserting.lock();
+		self.sync.wait_checked()?;
+		for i in (self.from.indexer)(&key, &value) {
+			match self.fwd.get_ref(&i)? {
+				Some(owner) if owner != key => {
+					bail!("The index key is already taken by another entry")
+				}
+				_ => continue,
+			}
+		}
+		self.from.from.insert_owned(key, value)
+	}
+	fn remove_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.from.from.remove_ref(key)
+	}
+	fn clear(&self) -> Result<()> {
+		self.from.from.clear()
+	}
+	/// Updates the source, the index keys of the update are not checked
+	fn fetch_and_update(
+		&self,
+		key: &Self::Key,
+		f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+	) -> Result<Option<Self::Value>> {
+		self.from.from.fetch_and_update(key, f)
+	}
+}
+impl<P, I, F, B> Watch for MaterialUniqueIndex<P, I, F, B>
+where
+	P: Watch,
+	I: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = I, Value = P::Key>,
+	B: 'static + Clone,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, I> Store for UniqueIndex<P, I>
+where
+	P: Watch + Sync + Send,
+	P::Key: PartialEq,
+	I: Serial + PartialEq,
+	<P as View>::Key: Serial,
+	Vec<I>: Serial,
+{
+	type Stored = MaterialUniqueIndex<P, I, Tree<I, P::Key>, Tree<<P as View>::Key, Vec<I>>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
+		Ok(MaterialUniqueIndex::new(self.clone(), fwd, bwd))
+	}
+}
+
+impl<P, I> Load for UniqueIndex<P, I>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	I: 'static + Clone + Send + Sync + Ord,
+{
+	type Loaded = MaterialUniqueIndex<P, I, Loaded<I, P::Key>, Loaded<P::Key, Vec<I>>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let fwd = Loaded::new();
+		let bwd = Loaded::new();
+		let res = MaterialUniqueIndex::new(self.clone(), fwd, bwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
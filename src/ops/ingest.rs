@@ -0,0 +1,65 @@
+use anyhow::anyhow;
+use std::thread::JoinHandle;
+
+use crate::{error::Result, traits::change::Change};
+
+/// A handle to a thread that feeds a source into a tree.
+/// You can create an [Ingest] with [ingest].
+pub struct Ingest {
+	handle: JoinHandle<Result<()>>,
+}
+
+impl Ingest {
+	/// Waits until the source runs out, returning the error that stopped it, if any.
+	pub fn join(self) -> Result<()> {
+		self.handle
+			.join()
+			.map_err(|_| anyhow!("Ingest thread panicked"))?
+	}
+	/// Checks if the source ran out or the thread stopped.
+	pub fn is_finished(&self) -> bool {
+		self.handle.is_finished()
+	}
+}
+
+/// Feeds the changes of an iterator or channel into a tree, in its own thread.
+///
+/// Each item is a key along with the value to insert, or [None] to remove the key.
+/// The changes go through [Change], so they send events and views of the target are updated as usual.
+/// The items are pulled one at a time, so a bounded channel holds its sender back while the target catches up.
+/// The thread stops once the source runs out, or at the first error.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, ingest};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let (tx, rx) = std::sync::mpsc::sync_channel(4);
+/// let handle = ingest(tree.clone(), rx);
+///
+/// tx.send((1u32, Some(1u32))).unwrap();
+/// tx.send((2u32, Some(2u32))).unwrap();
+/// tx.send((1u32, None)).unwrap();
+/// drop(tx);
+/// handle.join().unwrap();
+///
+/// assert_eq!(tree.get(1u32).unwrap(), None);
+/// assert_eq!(tree.get(2u32).unwrap(), Some(2));
+/// ```
+pub fn ingest<C, I>(target: C, source: I) -> Ingest
+where
+	C: 'static + Change + Send,
+	I: IntoIterator<Item = (C::Key, Option<C::Insert>)>,
+	I::IntoIter: 'static + Send,
+{
+	let source = source.into_iter();
+	let handle = std::thread::spawn(move || {
+		for (key, value) in source {
+			match value {
+				Some(value) => target.insert_owned(key, value)?,
+				None => target.remove_owned(key)?,
+			};
+		}
+		Ok(())
+	});
+	Ingest { handle }
+}
@@ -0,0 +1,330 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+type Mapper<K, V, M> = dyn Fn(&K, &V) -> M + Send + Sync;
+
+/// A struct that maps values one-to-one, so that keys can be looked up from their mapped values.
+/// You can create an [InvertibleMap] from a [View] struct.
+///
+/// The mapping should be bijective, each mapped value belonging to a single key.
+/// If two keys map to the same value, the reverse lookup returns the last one inserted.
+///
+/// [InvertibleMap] doesn't implement [View] or [Watch], you must store it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, String> = db.open_tree("tree").unwrap();
+/// let upper = tree
+///   .invertible_map(|_, name| name.to_uppercase())
+///   .load()
+///   .unwrap();
+///
+/// tree.insert(1u32, "alice").unwrap();
+///
+/// assert_eq!(upper.get(1u32).unwrap(), Some("ALICE".to_string()));
+/// assert_eq!(upper.get_key_for(&"ALICE".to_string()).unwrap(), Some(1));
+/// ```
+pub struct InvertibleMap<Previous, Mapped>
+where
+	Previous: View,
+{
+	from: Previous,
+	mapper: Arc<Mapper<Previous::Key, Previous::Value, Mapped>>,
+}
+impl<P, M> Clone for InvertibleMap<P, M>
+where
+	P: View,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			mapper: Arc::clone(&self.mapper),
+		}
+	}
+}
+
+impl<P, M> InvertibleMap<P, M>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new<Mapper>(from: P, mapper: Mapper) -> Self
+	where
+		Mapper: 'static + Fn(&P::Key, &P::Value) -> M + Sync + Send,
+	{
+		InvertibleMap {
+			from,
+			mapper: Arc::new(mapper),
+		}
+	}
+}
+
+/// A stored or loaded [InvertibleMap].
+///
+/// The forward map holds the mapped value of each key.
+/// The backward map holds the key of each mapped value.
+pub struct MaterialInvertibleMap<P, M, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	from: InvertibleMap<P, M>,
+	fwd: F,
+	bwd: B,
+	watcher: Watcher<P::Key, M>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, M, F, B> Clone for MaterialInvertibleMap<P, M, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			bwd: self.bwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, M, F, B> MaterialInvertibleMap<P, M, F, B>
+where
+	P: Watch + Sync + Send,
+	P::Key: PartialEq,
+	M: 'static + Clone + Send + Sync + PartialEq,
+	F: Clone
+		+ View<Key = P::Key, Value = M>
+		+ Change<Key = P::Key, Value = M, Insert = M>
+		+ Send
+		+ Sync,
+	B: Clone
+		+ View<Key = M, Value = P::Key>
+		+ Change<Key = M, Value = P::Key, Insert = P::Key>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: InvertibleMap<P, M>, fwd: F, bwd: B) -> Self {
+		let reader = from.from.watch();
+		let mapper = Arc::clone(&from.mapper);
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, bwd, move |event| {
+				let (key, new) = match &event {
+					Event::Insert { key, value } => (key, Some(mapper(key, value))),
+					Event::Remove { key } => (key, None),
+				};
+				let old = match &new {
+					Some(new) => fwd.insert_ref(key, new)?,
+					None => fwd.remove_ref(key)?,
+				};
+				// The old mapped value no longer points to this key
+				if let Some(old) = old.filter(|old| new.as_ref() != Some(old)) {
+					if bwd.get_ref(&old)?.as_ref() == Some(&**key) {
+						bwd.remove_ref(&old)?;
+					}
+				}
+				match new {
+					Some(new) => {
+						bwd.insert_ref(&new, key)?;
+						let (key, value) = (Arc::clone(key), Arc::new(new));
+						Ok(vec![Event::Insert { key, value }])
+					}
+					None => Ok(vec![Event::Remove {
+						key: Arc::clone(key),
+					}]),
+				}
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			bwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the map from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.bwd.clear()?;
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			let m = (self.from.mapper)(&k, &v);
+			self.fwd.insert_ref(&k, &m)?;
+			self.bwd.insert_owned(m, k)?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+}
+
+impl<P, M, F, B> MaterialInvertibleMap<P, M, F, B>
+where
+	P: View,
+	B: Clone + View<Key = M, Value = P::Key>,
+	F: Clone,
+{
+	/// Gets the key that maps to a value
+	pub fn get_key_for(&self, mapped: &M) -> Result<Option<P::Key>> {
+		self.sync.wait_checked()?;
+		self.bwd.get_ref(mapped)
+	}
+}
+
+impl<P, M, F, B> View for MaterialInvertibleMap<P, M, F, B>
+where
+	P: View,
+	M: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = P::Key, Value = M>,
+	B: Clone + View<Key = M, Value = P::Key>,
+{
+	type Key = P::Key;
+	type Value = M;
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_value(&self, value: &Self::Value) -> Result<bool>
+	where
+		Self::Value: PartialEq,
+	{
+		self.sync.wait_checked()?;
+		self.bwd.contains_key_ref(value)
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, M, F, B> for MaterialInvertibleMap<P, M, F, B>
+	where
+		P: View,
+		M: 'static + Clone + Send + Sync,
+		F: Clone + View<Key = P::Key, Value = M>,
+		B: Clone + View<Key = M, Value = P::Key>,
+);
+impl<P, M, F, B> Watch for MaterialInvertibleMap<P, M, F, B>
+where
+	P: Watch,
+	M: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = P::Key, Value = M>,
+	B: Clone + View<Key = M, Value = P::Key>,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, M> Store for InvertibleMap<P, M>
+where
+	P: Watch + Sync + Send,
+	P::Key: PartialEq,
+	M: Serial + Ord,
+	<P as View>::Key: Serial,
+{
+	type Stored = MaterialInvertibleMap<P, M, Tree<<P as View>::Key, M>, Tree<M, <P as View>::Key>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
+		Ok(MaterialInvertibleMap::new(self.clone(), fwd, bwd))
+	}
+}
+
+impl<P, M> Load for InvertibleMap<P, M>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	M: 'static + Clone + Send + Sync + Ord,
+{
+	type Loaded =
+		MaterialInvertibleMap<P, M, Loaded<<P as View>::Key, M>, Loaded<M, <P as View>::Key>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let fwd = Loaded::new();
+		let bwd = Loaded::new();
+		let res = MaterialInvertibleMap::new(self.clone(), fwd, bwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
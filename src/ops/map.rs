@@ -1,11 +1,11 @@
-use anyhow::Result;
 use bus::Bus;
 use delegate::delegate;
 use parking_lot::RwLock;
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use crate::{
-	macros::{cloned, unwrap_or_return},
+	error::Result,
+	macros::{cloned, into_iter, unwrap_or_return},
 	threads::{spawn_watcher, Synchronizer},
 	traits::{
 		change::Change,
@@ -50,6 +50,14 @@ impl<P: View, Mapped> Clone for Map<P, Mapped> {
 		}
 	}
 }
+impl<P, M> fmt::Debug for Map<P, M>
+where
+	P: View + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Map").field(&self.from).finish()
+	}
+}
 
 impl<P, Mapped> Map<P, Mapped>
 where
@@ -170,6 +178,12 @@ where
     }
   }
 }
+into_iter!(
+	impl<Previous, Mapped> for Map<Previous, Mapped>
+	where
+		Previous: View,
+		Mapped: 'static + Clone + Send + Sync,
+);
 impl<Previous, Mapped> Change for Map<Previous, Mapped>
 where
 	Previous: View + Change,
@@ -191,6 +205,11 @@ where
         key: &Self::Key,
         f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
       ) -> Result<Option<Self::Value>>;
+      fn update_and_fetch(
+        &self,
+        key: &Self::Key,
+        f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+      ) -> Result<Option<Self::Insert>>;
 	  }
 	}
 }
@@ -0,0 +1,337 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{bail, cloned, into_iter},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+type KeyMapper<K, N> = dyn Fn(&K) -> N + Send + Sync;
+
+/// A struct that changes the key of each entry, keeping one entry per key.
+/// You can create a [MapKeys] from a [View] struct.
+///
+/// Unlike an [Index](crate::ops::index::Index), each new key holds a single value instead of a list,
+/// so the mapping is expected to be one-to-one.
+/// Two keys mapping to the same new key is a collision, which fails the pipeline,
+/// unless [overwrite_on_collision](MapKeys::overwrite_on_collision) is set,
+/// in which case the last entry written takes the new key.
+///
+/// [MapKeys] doesn't implement [View] or [Watch], you must store or load it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, String> = db.open_tree("tree").unwrap();
+/// let shifted = tree.map_keys(|k| k + 100).load().unwrap();
+///
+/// tree.insert(1u32, "one").unwrap();
+/// tree.insert(2u32, "two").unwrap();
+/// tree.remove(1u32).unwrap();
+///
+/// assert_eq!(shifted.get(101u32).unwrap(), None);
+/// assert_eq!(shifted.get(102u32).unwrap(), Some("two".to_string()));
+/// ```
+pub struct MapKeys<Previous, NewKey>
+where
+	Previous: View,
+{
+	mapper: Arc<KeyMapper<Previous::Key, NewKey>>,
+	from: Previous,
+	overwrite: bool,
+}
+impl<P: View, N> Clone for MapKeys<P, N> {
+	fn clone(&self) -> Self {
+		Self {
+			mapper: Arc::clone(&self.mapper),
+			from: self.from.clone(),
+			overwrite: self.overwrite,
+		}
+	}
+}
+
+impl<P, N> MapKeys<P, N>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new<Mapper>(from: P, mapper: Mapper) -> Self
+	where
+		Mapper: 'static + Fn(&P::Key) -> N + Send + Sync,
+	{
+		let mapper = Arc::new(mapper);
+		MapKeys {
+			from,
+			mapper,
+			overwrite: false,
+		}
+	}
+	/// Lets an entry take a new key that another entry holds, instead of failing.
+	pub fn overwrite_on_collision(mut self) -> Self {
+		self.overwrite = true;
+		self
+	}
+}
+
+/// A stored or loaded [MapKeys].
+///
+/// The forward map holds the value of each new key.
+/// The backward map holds the source key that owns each new key, so a removal only retracts the entries it owns.
+pub struct MaterialMapKeys<P, N, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	from: MapKeys<P, N>,
+	fwd: F,
+	bwd: B,
+	watcher: Watcher<N, P::Value>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, N, F, B> Clone for MaterialMapKeys<P, N, F, B>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			bwd: self.bwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+/// Gives a new key to a source entry, failing if another entry owns it and overwriting isn't allowed.
+fn claim<K, N, V, F, B>(
+	fwd: &F,
+	bwd: &B,
+	overwrite: bool,
+	key: &K,
+	new: &N,
+	value: &V,
+) -> Result<()>
+where
+	K: PartialEq,
+	F: Change<Key = N, Insert = V>,
+	B: View<Key = N, Value = K> + Change<Key = N, Insert = K>,
+{
+	match bwd.get_ref(new)? {
+		Some(owner) if owner != *key && !overwrite => {
+			bail!("Two keys were mapped to the same key")
+		}
+		_ => {}
+	}
+	fwd.insert_ref(new, value)?;
+	bwd.insert_ref(new, key)?;
+	Ok(())
+}
+
+impl<P, N, F, B> MaterialMapKeys<P, N, F, B>
+where
+	P: Watch + Sync + Send,
+	P::Key: PartialEq,
+	N: 'static + Clone + Send + Sync,
+	F: Clone
+		+ View<Key = N, Value = P::Value>
+		+ Change<Key = N, Value = P::Value, Insert = P::Value>
+		+ Send
+		+ Sync,
+	B: Clone
+		+ View<Key = N, Value = P::Key>
+		+ Change<Key = N, Value = P::Key, Insert = P::Key>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: MapKeys<P, N>, fwd: F, bwd: B) -> Self {
+		let reader = from.from.watch();
+		let mapper = Arc::clone(&from.mapper);
+		let overwrite = from.overwrite;
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, bwd, move |event| {
+				let new = mapper(event.key());
+				match event {
+					Event::Insert { key, value } => {
+						claim(&fwd, &bwd, overwrite, &*key, &new, &*value)?;
+						Ok(vec![Event::Insert {
+							key: Arc::new(new),
+							value,
+						}])
+					}
+					Event::Remove { key } => {
+						// The new key may have been taken by another entry
+						if bwd.get_ref(&new)?.as_ref() != Some(&*key) {
+							return Ok(vec![]);
+						}
+						fwd.remove_ref(&new)?;
+						bwd.remove_ref(&new)?;
+						Ok(vec![Event::Remove { key: Arc::new(new) }])
+					}
+				}
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			bwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the keys from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.bwd.clear()?;
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			let new = (self.from.mapper)(&k);
+			claim(&self.fwd, &self.bwd, self.from.overwrite, &k, &new, &v)?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+}
+
+impl<P, N, F, B> View for MaterialMapKeys<P, N, F, B>
+where
+	P: View,
+	N: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = N, Value = P::Value>,
+	B: 'static + Clone,
+{
+	type Key = N;
+	type Value = P::Value;
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, N, F, B> for MaterialMapKeys<P, N, F, B>
+	where
+		P: View,
+		N: 'static + Clone + Send + Sync,
+		F: Clone + View<Key = N, Value = P::Value>,
+		B: 'static + Clone,
+);
+impl<P, N, F, B> Watch for MaterialMapKeys<P, N, F, B>
+where
+	P: Watch,
+	N: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = N, Value = P::Value>,
+	B: 'static + Clone,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, N> Store for MapKeys<P, N>
+where
+	P: Watch + Sync + Send,
+	P::Key: PartialEq + Serial,
+	P::Value: Serial,
+	N: Serial,
+{
+	type Stored = MaterialMapKeys<P, N, Tree<N, P::Value>, Tree<N, P::Key>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
+		Ok(MaterialMapKeys::new(self.clone(), fwd, bwd))
+	}
+}
+
+impl<P, N> Load for MapKeys<P, N>
+where
+	P: Watch + View + Sync + Send,
+	P::Key: PartialEq,
+	N: 'static + Clone + Send + Sync + Ord,
+{
+	type Loaded = MaterialMapKeys<P, N, Loaded<N, P::Value>, Loaded<N, P::Key>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let fwd = Loaded::new();
+		let bwd = Loaded::new();
+		let res = MaterialMapKeys::new(self.clone(), fwd, bwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
@@ -0,0 +1,127 @@
+use std::{
+	cmp::Ordering,
+	collections::{BinaryHeap, VecDeque},
+};
+
+use crate::{
+	error::{HuskyError, Result},
+	traits::view::View,
+};
+
+/// The next entry of one of the merged views
+struct Head<K, V> {
+	key: K,
+	index: usize,
+	value: V,
+}
+
+impl<K: Ord, V> PartialEq for Head<K, V> {
+	fn eq(&self, other: &Self) -> bool {
+		self.cmp(other) == Ordering::Equal
+	}
+}
+impl<K: Ord, V> Eq for Head<K, V> {}
+impl<K: Ord, V> PartialOrd for Head<K, V> {
+	fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+		Some(self.cmp(other))
+	}
+}
+impl<K: Ord, V> Ord for Head<K, V> {
+	/// Reversed, so the heap pops the lowest key, then the first view
+	fn cmp(&self, other: &Self) -> Ordering {
+		other.key.cmp(&self.key).then(other.index.cmp(&self.index))
+	}
+}
+
+/// An iterator that merges several ordered views by key, created with [merge_sorted].
+pub struct MergeSorted<P, F>
+where
+	P: View,
+	P::Key: Ord,
+{
+	sources: Vec<P::Iter>,
+	heap: BinaryHeap<Head<P::Key, P::Value>>,
+	errors: VecDeque<HuskyError>,
+	resolve: F,
+}
+
+impl<P, F> MergeSorted<P, F>
+where
+	P: View,
+	P::Key: Ord,
+{
+	/// Pulls the next entry of a view into the heap, keeping its errors to be yielded next
+	fn advance(&mut self, index: usize) {
+		for res in self.sources[index].by_ref() {
+			match res {
+				Ok((key, value)) => return self.heap.push(Head { key, index, value }),
+				Err(e) => self.errors.push_back(e),
+			}
+		}
+	}
+}
+
+impl<P, F> Iterator for MergeSorted<P, F>
+where
+	P: View,
+	P::Key: Ord,
+	F: FnMut(&P::Key, P::Value, P::Value) -> P::Value,
+{
+	type Item = Result<(P::Key, P::Value)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		// Errors are yielded as soon as they are found
+		if let Some(e) = self.errors.pop_front() {
+			return Some(Err(e));
+		}
+		let Head { key, index, value } = self.heap.pop()?;
+		self.advance(index);
+		let mut value = value;
+		while self.heap.peek().is_some_and(|head| head.key == key) {
+			let head = self.heap.pop()?;
+			self.advance(head.index);
+			value = (self.resolve)(&key, value, head.value);
+		}
+		Some(Ok((key, value)))
+	}
+}
+
+/// Merges several ordered views into a single iterator, in ascending key order.
+///
+/// It is a k-way merge, reading one entry at a time from each view.
+/// A key found in more than one view is yielded once, with its values folded by the resolver,
+/// in the order of the views.
+/// Errors from any of the views are yielded as soon as they are found.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, merge_sorted};
+/// # let db = husky::open_temp().unwrap();
+/// let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+/// let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+/// a.insert(1u32, 10u32).unwrap();
+/// a.insert(3u32, 30u32).unwrap();
+/// b.insert(2u32, 20u32).unwrap();
+/// b.insert(3u32, 1u32).unwrap();
+///
+/// let merged = merge_sorted(vec![a, b], |_, a, b| a + b);
+/// let entries = merged.collect::<husky::error::Result<Vec<_>>>().unwrap();
+/// assert_eq!(entries, vec![(1, 10), (2, 20), (3, 31)]);
+/// ```
+pub fn merge_sorted<P, F>(views: Vec<P>, resolve: F) -> MergeSorted<P, F>
+where
+	P: View,
+	P::Key: Ord,
+	F: FnMut(&P::Key, P::Value, P::Value) -> P::Value,
+{
+	let sources = views.iter().map(View::iter).collect::<Vec<_>>();
+	let mut merge = MergeSorted {
+		heap: BinaryHeap::with_capacity(sources.len()),
+		sources,
+		errors: VecDeque::new(),
+		resolve,
+	};
+	for index in 0..merge.sources.len() {
+		merge.advance(index);
+	}
+	merge
+}
@@ -1,5 +1,13 @@
+use std::{
+	hash::Hash,
+	ops::{Add, RangeBounds, Sub},
+	time::Duration,
+};
+
+use std::sync::Arc;
+
 use crate::{
-	threads::spawn_listener,
+	threads::{spawn_listener, Synchronizer},
 	traits::{
 		change::Change,
 		serial::Serial,
@@ -9,13 +17,61 @@ use crate::{
 };
 
 use self::{
-	chain::Chain, filter::Filter, filter_inserter::FilterInserter, filter_map::FilterMap,
-	filter_reducer::FilterReducer, index::Index, inserter::Inserter, map::Map, reducer::Reducer,
-	transform::Transform, zip::Zip,
+	aggregate::Aggregate,
+	bounded::Bounded,
+	cache::Cache,
+	chain::Chain,
+	cogroup::Cogroup,
+	debounce::Debounce,
+	diff::Diff,
+	distinct::Distinct,
+	enumerate::Enumerate,
+	filter::Filter,
+	filter_inserter::FilterInserter,
+	filter_map::FilterMap,
+	filter_reducer::FilterReducer,
+	flatten::Flatten,
+	group_by::GroupBy,
+	index::{Index, UniqueIndex},
+	inserter::Inserter,
+	invert::InvertibleMap,
+	map::Map,
+	map_keys::MapKeys,
+	paginate::Paginate,
+	pipe::PipeHandle,
+	reducer::Reducer,
+	sample::Sample,
+	scan::Scan,
+	set::Set,
+	sink::Sink,
+	sort::SortByValue,
+	top_k::TopK,
+	transform::Transform,
+	ttl::Ttl,
+	versioned::Versioned,
+	zip::{Zip, ZipUnordered, ZipWith},
 };
+#[cfg(feature = "rkyv")]
+use self::namespace::Namespace;
 
+/// [Aggregate] struct declaration and implementations.
+pub mod aggregate;
+/// [Bounded] struct declaration and implementations.
+pub mod bounded;
+/// [Cache] struct declaration and implementations.
+pub mod cache;
 /// [Chain] struct declaration and implementations.
 pub mod chain;
+/// [Cogroup] struct declaration and implementations.
+pub mod cogroup;
+/// [Debounce] struct declaration and implementations.
+pub mod debounce;
+/// [Diff] enum declaration and implementations.
+pub mod diff;
+/// [Distinct] struct declaration and implementations.
+pub mod distinct;
+/// [Enumerate] struct declaration and implementations.
+pub mod enumerate;
 /// [Filter] struct declaration and implementations.
 pub mod filter;
 /// [FilterInserter] struct declaration and implementations.
@@ -24,16 +80,51 @@ pub mod filter_inserter;
 pub mod filter_map;
 /// [FilterReducer] struct declaration and implementations.
 pub mod filter_reducer;
-/// [Index] struct declaration and implementations.
+/// [Flatten] struct declaration and implementations.
+pub mod flatten;
+/// [GroupBy] struct declaration and implementations.
+pub mod group_by;
+/// [Index] and [UniqueIndex] struct declarations and implementations.
 pub mod index;
+/// [Ingest](ingest::Ingest) struct and [ingest](ingest::ingest) function declarations.
+pub mod ingest;
 /// [Inserter] struct declaration and implementations.
 pub mod inserter;
+/// [InvertibleMap] struct declaration and implementations.
+pub mod invert;
 /// [Map] struct declaration and implementations.
 pub mod map;
+/// [MapKeys] struct declaration and implementations.
+pub mod map_keys;
+/// [MergeSorted](merge_sorted::MergeSorted) struct and [merge_sorted](merge_sorted::merge_sorted) function declarations.
+pub mod merge_sorted;
+/// [Namespace] struct declaration and implementations.
+#[cfg(feature = "rkyv")]
+pub mod namespace;
+/// [Paginate] struct declaration and implementations.
+pub mod paginate;
+/// [PipeHandle] struct declaration and implementations.
+pub mod pipe;
 /// [Reducer] struct declaration and implementations.
 pub mod reducer;
+/// [Sample] struct declaration and implementations.
+pub mod sample;
+/// [Scan] struct declaration and implementations.
+pub mod scan;
+/// [Set] struct declaration and implementations.
+pub mod set;
+/// [Sink] struct declaration and implementations.
+pub mod sink;
+/// [SortByValue] struct declaration and implementations.
+pub mod sort;
+/// [TopK] struct declaration and implementations.
+pub mod top_k;
 /// [Transform] struct declaration and implementations.
 pub mod transform;
+/// [Ttl] struct declaration and implementations.
+pub mod ttl;
+/// [Versioned] struct declaration and implementations.
+pub mod versioned;
 /// [Zip] struct declaration and implementations.
 pub mod zip;
 
@@ -51,6 +142,15 @@ where
 	{
 		Map::new(self.clone(), mapper)
 	}
+	/// Changes entry values one-to-one, with a reverse lookup. Please refer to [InvertibleMap]
+	fn invertible_map<M, Mapped>(&self, mapper: M) -> InvertibleMap<Self, Mapped>
+	where
+		Self: View + Watch,
+		M: 'static + Fn(&Self::Key, &Self::Value) -> Mapped + Sync + Send,
+		Mapped: Serial + Ord,
+	{
+		InvertibleMap::new(self.clone(), mapper)
+	}
 	/// Transforms an entry into multiple entries. Please refer to [Transform]
 	fn transform<K, V, T>(&self, transformer: T) -> Transform<Self, K, V>
 	where
@@ -70,13 +170,122 @@ where
 	{
 		Index::new(self.clone(), indexer)
 	}
+	/// Changes entry keys one-to-one, keeping one value per key. Please refer to [MapKeys]
+	fn map_keys<F, N>(&self, mapper: F) -> MapKeys<Self, N>
+	where
+		Self: View + Watch,
+		F: 'static + Fn(&Self::Key) -> N + Sync + Send,
+		N: Serial,
+	{
+		MapKeys::new(self.clone(), mapper)
+	}
+	/// Changes entry keys, each index key belonging to a single entry. Please refer to [UniqueIndex]
+	fn unique_index<F, I>(&self, indexer: F) -> UniqueIndex<Self, I>
+	where
+		Self: View + Watch,
+		F: 'static + Fn(&Self::Key, &Self::Value) -> Vec<I> + Sync + Send,
+		I: Serial,
+	{
+		UniqueIndex::new(self.clone(), indexer)
+	}
+	/// Turns each element of a list value into its own entry. Please refer to [Flatten]
+	fn flatten<E>(&self) -> Flatten<Self>
+	where
+		Self: View<Value = Vec<E>> + Watch,
+	{
+		Flatten::new(self.clone())
+	}
+	/// Groups entries and aggregates each group. Please refer to [GroupBy]
+	fn group_by<G, A, KeyFn, FoldFn, UnfoldFn>(
+		&self,
+		key: KeyFn,
+		fold: FoldFn,
+		unfold: UnfoldFn,
+	) -> GroupBy<Self, G, A>
+	where
+		Self: View + Watch,
+		KeyFn: 'static + Fn(&Self::Key, &Self::Value) -> G + Sync + Send,
+		FoldFn: 'static + Fn(Option<A>, &Self::Key, &Self::Value) -> A + Sync + Send,
+		UnfoldFn: 'static + Fn(A, &Self::Key, &Self::Value) -> A + Sync + Send,
+		G: Serial,
+		A: Serial,
+	{
+		GroupBy::new(self.clone(), key, fold, unfold)
+	}
+	/// Sums all values of a tree. Please refer to [Aggregate]
+	fn sum(&self) -> Aggregate<Self, Self::Value>
+	where
+		Self: View + Watch,
+		Self::Value: Default + Add<Output = Self::Value> + Sub<Output = Self::Value>,
+	{
+		Aggregate::sum(self.clone())
+	}
+	/// Counts the entries of each category. Please refer to [GroupBy]
+	///
+	/// An entry that changes its category is taken out of the old count and added to the new one.
+	fn count_by<G, KeyFn>(&self, category: KeyFn) -> GroupBy<Self, G, u64>
+	where
+		Self: View + Watch,
+		KeyFn: 'static + Fn(&Self::Key, &Self::Value) -> G + Sync + Send,
+		G: Serial,
+	{
+		GroupBy::count_by(self.clone(), category)
+	}
+	/// Counts the entries of a tree. Please refer to [Aggregate]
+	fn count(&self) -> Aggregate<Self, u64>
+	where
+		Self: View + Watch,
+	{
+		Aggregate::count(self.clone())
+	}
+	/// Finds the smallest value of a tree. Please refer to [Aggregate]
+	fn min(&self) -> Aggregate<Self, Self::Value>
+	where
+		Self: View + Watch,
+		Self::Value: Ord,
+	{
+		Aggregate::min(self.clone())
+	}
+	/// Finds the largest value of a tree. Please refer to [Aggregate]
+	fn max(&self) -> Aggregate<Self, Self::Value>
+	where
+		Self: View + Watch,
+		Self::Value: Ord,
+	{
+		Aggregate::max(self.clone())
+	}
+	/// Folds a running state over inserts. Please refer to [Scan]
+	fn scan<S, ScanFn>(&self, init: S, scan: ScanFn) -> Scan<Self, S>
+	where
+		Self: View + Watch,
+		ScanFn: 'static + Fn(&S, &Self::Key, &Self::Value) -> S + Sync + Send,
+		S: Serial,
+	{
+		Scan::new(self.clone(), init, scan)
+	}
+	/// Tags entries with a sequence number, in insertion order. Please refer to [Enumerate]
+	fn enumerate(&self) -> Enumerate<Self>
+	where
+		Self: View + Watch,
+	{
+		Enumerate::new(self.clone())
+	}
 	/// Chains two trees together. Please refer to [Chain]
 	fn chain<B>(&self, other: &B) -> Chain<Self, B>
 	where
 		Self: View + Sync + Send + Watch,
 		B: View<Key = Self::Key, Value = Self::Value> + Watch + Sync + Send,
 	{
-		Chain::new(self.clone(), other.clone())
+		self.chain_with(other, |_, a, _| a.clone())
+	}
+	/// Chains two trees together, resolving keys present in both. Please refer to [Chain]
+	fn chain_with<B, R>(&self, other: &B, resolver: R) -> Chain<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key, Value = Self::Value> + Watch + Sync + Send,
+		R: 'static + Fn(&Self::Key, &Self::Value, &Self::Value) -> Self::Value + Sync + Send,
+	{
+		Chain::new(self.clone(), other.clone(), resolver)
 	}
 	/// Zips two trees together. Please refer to [Zip]
 	fn zip<B>(&self, other: &B) -> Zip<Self, B>
@@ -86,6 +295,91 @@ where
 	{
 		Zip::new(self.clone(), other.clone())
 	}
+	/// Zips two trees together, for keys that can't be ordered. Please refer to [ZipUnordered]
+	fn zip_unordered<B>(&self, other: &B) -> ZipUnordered<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key> + Watch + Sync + Send,
+	{
+		ZipUnordered::new(self.clone(), other.clone())
+	}
+	/// Groups the values of two trees under their shared keys. Please refer to [Cogroup]
+	fn cogroup<B>(&self, other: &B) -> Cogroup<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key> + Watch + Sync + Send,
+		Self::Key: Hash + Ord,
+		Self::Value: Default,
+		B::Value: Default,
+	{
+		Cogroup::new(self.clone(), other.clone())
+	}
+	/// Zips two trees together, combining both values of each key. Please refer to [ZipWith]
+	fn zip_with<B, F, Out>(&self, other: &B, combiner: F) -> ZipWith<Self, B, Out>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key> + Watch + Sync + Send,
+		Self::Key: Hash + Ord,
+		F: 'static
+			+ Fn(&Self::Key, Option<&Self::Value>, Option<&B::Value>) -> Option<Out>
+			+ Sync
+			+ Send,
+		Out: 'static + Clone + Send + Sync,
+	{
+		ZipWith::new(self.clone(), other.clone(), combiner)
+	}
+	/// Compares two trees, skipping the keys where both are equal. Please refer to [Diff]
+	fn diff<B>(&self, other: &B) -> ZipWith<Self, B, Diff<Self::Value>>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key, Value = Self::Value> + Watch + Sync + Send,
+		Self::Key: Hash + Ord,
+		Self::Value: PartialEq,
+	{
+		self.zip_with(other, |_, a, b| Diff::new(a, b))
+	}
+	/// Keys present in either tree. Please refer to [Set]
+	fn union<B>(&self, other: &B) -> Set<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key, Value = Self::Value> + Watch + Sync + Send,
+	{
+		Set::union(self.clone(), other.clone())
+	}
+	/// Keys present in both trees. Please refer to [Set]
+	fn intersection<B>(&self, other: &B) -> Set<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key> + Watch + Sync + Send,
+	{
+		Set::intersection(self.clone(), other.clone())
+	}
+	/// Keys present in this tree but not in the other. Please refer to [Set]
+	fn difference<B>(&self, other: &B) -> Set<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key> + Watch + Sync + Send,
+	{
+		Set::difference(self.clone(), other.clone())
+	}
+	/// Rows of this tree with a matching key in the other, the same as an [intersection](Operate::intersection). Please refer to [Set]
+	fn semi_join<B>(&self, other: &B) -> Set<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key> + Watch + Sync + Send,
+	{
+		Set::intersection(self.clone(), other.clone())
+	}
+	/// Rows of this tree without a matching key in the other, the same as a [difference](Operate::difference). Please refer to [Set]
+	///
+	/// A key that shows up in the other tree later is retracted.
+	fn anti_join<B>(&self, other: &B) -> Set<Self, B>
+	where
+		Self: View + Sync + Send + Watch,
+		B: View<Key = Self::Key> + Watch + Sync + Send,
+	{
+		Set::difference(self.clone(), other.clone())
+	}
 	/// Creates two new trees from a tuple tree, essentially undoing [Zip].
 	fn unzip<A, B>(&self) -> (Map<Self, A>, Map<Self, B>)
 	where
@@ -105,6 +399,96 @@ where
 	{
 		Filter::new(self.clone(), filter)
 	}
+	/// Splits a view into the entries that pass a filter and the ones that don't. Please refer to [Filter]
+	///
+	/// Both halves share a single thread for the events of the source.
+	fn partition<F>(&self, filter: F) -> (Filter<Self>, Filter<Self>)
+	where
+		Self: View + Watch,
+		F: 'static + Fn(&Self::Key, &Self::Value) -> bool + Sync + Send,
+	{
+		Filter::partition(self.clone(), filter)
+	}
+	/// Restricts a view to a range of keys. Please refer to [Bounded]
+	fn bounded<R: RangeBounds<Self::Key>>(&self, range: R) -> Bounded<Self>
+	where
+		Self: View + Watch,
+		Self::Key: Ord,
+	{
+		Bounded::new(self.clone(), range)
+	}
+	/// Views every `step`-th entry in key order. Please refer to [Sample]
+	fn sample(&self, step: usize) -> Sample<Self>
+	where
+		Self: View + Watch,
+		Self::Key: Ord,
+	{
+		Sample::new(self.clone(), step)
+	}
+	/// Expires entries a while after they were inserted. Please refer to [Ttl]
+	fn ttl(&self, ttl: Duration) -> Ttl<Self>
+	where
+		Self: View + Watch,
+	{
+		Ttl::new(self.clone(), ttl)
+	}
+	/// Keeps the last versions of each key. Please refer to [Versioned]
+	fn versioned(&self, limit: usize) -> Versioned<Self>
+	where
+		Self: View + Watch,
+	{
+		Versioned::new(self.clone(), limit)
+	}
+	/// Keeps the entries with the highest values. Please refer to [TopK]
+	fn top_k(&self, n: usize) -> TopK<Self>
+	where
+		Self: View + Watch,
+		Self::Key: Ord,
+		Self::Value: Ord,
+	{
+		TopK::new(self.clone(), n)
+	}
+	/// Keeps the entries ordered by value. Please refer to [SortByValue]
+	fn sort_by_value(&self) -> SortByValue<Self>
+	where
+		Self: View + Watch,
+		Self::Value: Ord,
+	{
+		SortByValue::new(self.clone())
+	}
+	/// Keeps a single key for each distinct value. Please refer to [Distinct]
+	fn distinct_by_value(&self) -> Distinct<Self>
+	where
+		Self: View + Watch,
+		Self::Value: Hash + Eq,
+	{
+		Distinct::new(self.clone())
+	}
+	/// Coalesces bursts of events, sending the latest of each key. Please refer to [Debounce]
+	fn debounce(&self, window: Duration) -> Debounce<Self>
+	where
+		Self: View + Watch + 'static + Sync + Send,
+		Self::Key: Hash + Eq,
+	{
+		Debounce::new(self.clone(), window)
+	}
+	/// Keeps the most recently read entries in memory. Please refer to [Cache]
+	fn cache(&self, capacity: usize) -> Cache<Self>
+	where
+		Self: View + Watch,
+		Self: 'static + Sync + Send,
+		Self::Key: Hash + Eq,
+	{
+		Cache::new(self.clone(), capacity)
+	}
+	/// Restricts a view to the keys under a prefix. Please refer to [Namespace]
+	#[cfg(feature = "rkyv")]
+	fn namespace<P: Into<Vec<u8>>>(&self, prefix: P) -> Namespace<Self>
+	where
+		Self: View<Key = Vec<u8>> + Watch,
+	{
+		Namespace::new(self.clone(), prefix.into())
+	}
 	/// Filters values in a tree after a map. Please refer to [FilterMap]
 	fn filter_map<F, Mapped>(&self, mapper: F) -> FilterMap<Self, Mapped>
 	where
@@ -114,6 +498,22 @@ where
 	{
 		FilterMap::new(self.clone(), mapper)
 	}
+	/// Skips the first entries of a tree. Please refer to [Paginate]
+	fn skip(&self, n: usize) -> Paginate<Self>
+	where
+		Self: View,
+		Self::Key: Ord,
+	{
+		Paginate::new(self.clone()).skip(n)
+	}
+	/// Takes the first entries of a tree. Please refer to [Paginate]
+	fn take(&self, n: usize) -> Paginate<Self>
+	where
+		Self: View,
+		Self::Key: Ord,
+	{
+		Paginate::new(self.clone()).take(n)
+	}
 	/// Reduces and filters inserts to a tree. Please refer to [FilterReducer]
 	fn filter_reducer<ReduceFn, Merge>(&self, reducer: ReduceFn) -> FilterReducer<Self, Merge>
 	where
@@ -126,13 +526,11 @@ where
 		FilterReducer::new(self.clone(), reducer)
 	}
 	/// Reduces inserts to a tree. Please refer to [Reducer]
-	fn reducer<ReduceFn, Merge>(&self, reducer: ReduceFn) -> Reducer<Self, Merge>
+	fn reducer<ReduceFn, Merge, Reduced>(&self, reducer: ReduceFn) -> Reducer<Self, Merge>
 	where
 		Self: View + Change,
-		ReduceFn: 'static
-			+ Fn(Option<<Self as Change>::Value>, Merge) -> <Self as Change>::Insert
-			+ Sync
-			+ Send,
+		ReduceFn: 'static + Fn(Option<<Self as Change>::Value>, Merge) -> Reduced + Sync + Send,
+		Reduced: Into<Option<<Self as Change>::Insert>>,
 	{
 		Reducer::new(self.clone(), reducer)
 	}
@@ -152,27 +550,100 @@ where
 	{
 		Inserter::new(self.clone(), inserter)
 	}
-	/// Pipes changes to another tree.
-	fn pipe<O>(&self, other: O)
+	/// Pipes changes to another tree. Please refer to [PipeHandle]
+	fn pipe<O>(&self, other: O) -> PipeHandle
 	where
 		Self: View + Watch,
 		O: Change<Key = Self::Key, Insert = Self::Value> + Watch + Send + Sync,
 	{
-		let sync = other.sync();
-		sync.push_source(self.sync());
+		PipeHandle::new(self, other)
+	}
+	/// Keeps two trees in sync, both ways.
+	///
+	/// A change is only written to the other tree if it is still the latest in its own tree,
+	/// and if the other tree doesn't have it already, so changes don't bounce back and forth.
+	/// The trees can't wait on each other, as that would be a cycle, so use [wait_all](crate::wait_all) to wait for it.
+	fn mirror<O>(&self, other: O)
+	where
+		Self: 'static + View + Watch + Change<Key = <Self as View>::Key, Insert = <Self as View>::Value>,
+		O: 'static
+			+ View<Key = <Self as View>::Key, Value = <Self as View>::Value>
+			+ Watch
+			+ Change<Key = <Self as View>::Key, Insert = <Self as View>::Value>
+			+ Send
+			+ Sync,
+		<Self as View>::Value: PartialEq,
+	{
+		mirror_changes(self.clone(), other.clone());
+		mirror_changes(other, self.clone());
+	}
+	/// Hands every change to a function. Please refer to [Sink]
+	fn sink<F>(&self, f: F) -> Sink
+	where
+		Self: View + Watch,
+		F: 'static + Fn(Event<Self::Key, Self::Value>) -> anyhow::Result<()> + Send + Sync,
+	{
+		Sink::new(self, f)
+	}
+	/// Pipes changes to several trees, reading the source once.
+	///
+	/// The changes are written to every target, and waiting on a target waits for them.
+	/// A target that fails doesn't stop the others from being written.
+	fn tee<O, I>(&self, targets: I)
+	where
+		Self: View + Watch,
+		O: Change<Key = Self::Key, Insert = Self::Value> + Watch + Send + Sync,
+		I: IntoIterator<Item = O>,
+	{
+		let targets = targets.into_iter().collect::<Vec<_>>();
+		let sync = Arc::new(Synchronizer::from(vec![self.sync()]));
+		for target in &targets {
+			target.sync().push_source(Arc::clone(&sync));
+		}
 		spawn_listener(sync, self.watch(), move |event| {
-			let (key, value) = match event {
-				Event::Insert { key, value } => (key, Some(value)),
-				Event::Remove { key } => (key, None),
-			};
-			match value {
-				Some(value) => other.insert_ref(&*key, &*value)?,
-				None => other.remove_ref(&*key)?,
-			};
-			// No outgoing events, because the calls to insert and remove will create events already.
-			Ok(0)
+			let mut result = Ok(0);
+			for target in &targets {
+				let res = match &event {
+					Event::Insert { key, value } => target.insert_ref(key, value),
+					Event::Remove { key } => target.remove_ref(key),
+				};
+				if let (Err(e), Ok(_)) = (res, &result) {
+					result = Err(e);
+				}
+			}
+			// No outgoing events, the targets send their own
+			result
 		});
 	}
 }
 
+/// Writes the changes of a tree to another, skipping the ones that are stale or already there.
+fn mirror_changes<A, B>(from: A, to: B)
+where
+	A: 'static + View + Watch + Send + Sync,
+	B: 'static + View<Key = A::Key, Value = A::Value> + Change<Key = A::Key, Insert = A::Value> + Send + Sync,
+	A::Value: PartialEq,
+{
+	let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+	Synchronizer::register(&sync);
+	let reader = from.watch();
+	spawn_listener(sync, reader, move |event| {
+		match event {
+			Event::Insert { key, value } => {
+				let latest = from.get_ref(&key)?.as_ref() == Some(&*value);
+				if latest && to.get_ref(&key)?.as_ref() != Some(&*value) {
+					to.insert_ref(&key, &value)?;
+				}
+			}
+			Event::Remove { key } => {
+				if !from.contains_key_ref(&key)? && to.contains_key_ref(&key)? {
+					to.remove_ref(&key)?;
+				}
+			}
+		}
+		// No outgoing events, the target sends its own
+		Ok(0)
+	});
+}
+
 impl<T> Operate for T where Self: Clone + Sized + View + Watch + Sync + Send {}
@@ -0,0 +1,283 @@
+use bus::Bus;
+use parking_lot::RwLock;
+use std::{
+	iter::once,
+	ops::{Bound, RangeBounds},
+	sync::Arc,
+};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter, unwrap_or_return},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::database::Db,
+};
+
+/// A struct that restricts a view to the keys under a prefix.
+/// You can create a [Namespace] from a [View] struct with [Vec<u8>] keys.
+///
+/// Keys are read without the prefix, and the prefix is prepended to the keys written.
+/// Iterators and ranges only walk the keys under the prefix, and only their events are watched.
+///
+/// Only available with rkyv, which keeps the bytes of a [Vec<u8>] in front, so prefixed keys are next to each other.
+/// Serde serializers write the length first, which would scatter the keys of a namespace across the tree.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<Vec<u8>, u32> = db.open_tree("tree").unwrap();
+/// let users = tree.namespace(b"users/".to_vec());
+/// let posts = tree.namespace(b"posts/".to_vec());
+///
+/// users.insert(b"alice".to_vec(), 1u32).unwrap();
+///
+/// assert_eq!(tree.get(b"users/alice".to_vec()).unwrap(), Some(1));
+/// assert_eq!(users.get(b"alice".to_vec()).unwrap(), Some(1));
+/// assert_eq!(posts.get(b"alice".to_vec()).unwrap(), None);
+/// ```
+pub struct Namespace<Previous>
+where
+	Previous: View,
+{
+	prefix: Arc<Vec<u8>>,
+	from: Previous,
+	watcher: Watcher<Vec<u8>, Previous::Value>,
+	sync: Arc<Synchronizer>,
+}
+impl<P: View> Clone for Namespace<P> {
+	fn clone(&self) -> Self {
+		Self {
+			prefix: Arc::clone(&self.prefix),
+			from: self.from.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P> Namespace<P>
+where
+	P: View<Key = Vec<u8>> + Watch,
+{
+	pub(crate) fn new(from: P, prefix: Vec<u8>) -> Self
+	where
+		P: 'static + Sync + Send,
+	{
+		let prefix = Arc::new(prefix);
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		let watcher = Watcher::new(cloned!(sync, from, prefix, move || {
+			let bus = Arc::new(RwLock::new(Bus::new(128)));
+			let previous = from.watch();
+			spawn_watcher(
+				sync,
+				previous,
+				Arc::clone(&bus),
+				cloned!(prefix, move |event| {
+					let key = match &event {
+						Event::Insert { key, .. } => key,
+						Event::Remove { key } => key,
+					};
+					let key = match key.strip_prefix(prefix.as_slice()) {
+						Some(key) => Arc::new(key.to_vec()),
+						None => return Ok(vec![]),
+					};
+					let event = match event {
+						Event::Insert { value, .. } => Event::Insert { key, value },
+						Event::Remove { .. } => Event::Remove { key },
+					};
+					Ok(vec![event])
+				}),
+			);
+			bus
+		}));
+		Namespace {
+			prefix,
+			from,
+			watcher,
+			sync,
+		}
+	}
+}
+
+impl<P> Namespace<P>
+where
+	P: View<Key = Vec<u8>>,
+{
+	/// Prepends the prefix to a key
+	fn prefixed(&self, key: &[u8]) -> Vec<u8> {
+		let mut prefixed = Vec::with_capacity(self.prefix.len() + key.len());
+		prefixed.extend_from_slice(&self.prefix);
+		prefixed.extend_from_slice(key);
+		prefixed
+	}
+	/// Strips the prefix from an entry, if it is in the namespace
+	fn stripped<V>(&self, entry: Option<(Vec<u8>, V)>) -> Option<(Vec<u8>, V)> {
+		let (key, value) = entry?;
+		let key = key.strip_prefix(self.prefix.as_slice())?.to_vec();
+		Some((key, value))
+	}
+	/// The first key after every key under the prefix, if there is one
+	fn end(&self) -> Option<Vec<u8>> {
+		let mut end = self.prefix.to_vec();
+		while let Some(last) = end.pop() {
+			if last < u8::MAX {
+				end.push(last + 1);
+				return Some(end);
+			}
+		}
+		None
+	}
+	/// Translates a range of keys into a range of prefixed keys
+	fn bounds(&self, range: impl RangeBounds<Vec<u8>>) -> (Bound<Vec<u8>>, Bound<Vec<u8>>) {
+		let start = match range.start_bound() {
+			Bound::Included(k) => Bound::Included(self.prefixed(k)),
+			Bound::Excluded(k) => Bound::Excluded(self.prefixed(k)),
+			Bound::Unbounded => Bound::Included(self.prefix.to_vec()),
+		};
+		let end = match range.end_bound() {
+			Bound::Included(k) => Bound::Included(self.prefixed(k)),
+			Bound::Excluded(k) => Bound::Excluded(self.prefixed(k)),
+			Bound::Unbounded => match self.end() {
+				Some(end) => Bound::Excluded(end),
+				None => Bound::Unbounded,
+			},
+		};
+		(start, end)
+	}
+}
+
+impl<P> View for Namespace<P>
+where
+	P: View<Key = Vec<u8>>,
+{
+	type Key = Vec<u8>;
+	type Value = P::Value;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.from.get_ref(&self.prefixed(key))
+	}
+	fn iter(&self) -> Self::Iter {
+		match self.range(..) {
+			Ok(iter) => iter,
+			Err(e) => Box::new(once(Err(e))),
+		}
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.from.contains_key_ref(&self.prefixed(key))
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let entry = self.from.get_lt_ref(&self.prefixed(key))?;
+		Ok(self.stripped(entry))
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let entry = self.from.get_gt_ref(&self.prefixed(key))?;
+		Ok(self.stripped(entry))
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let entry = self.iter().next();
+		let entry = unwrap_or_return!(entry);
+		entry.map(Some)
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let entry = match self.end() {
+			Some(end) => self.from.get_lt_ref(&end)?,
+			None => self.from.last()?,
+		};
+		Ok(self.stripped(entry))
+	}
+	fn is_empty(&self) -> Option<bool> {
+		match self.iter().next() {
+			Some(Ok(_)) => Some(false),
+			Some(Err(_)) => None,
+			None => Some(true),
+		}
+	}
+	fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		let prefix = Arc::clone(&self.prefix);
+		let iter = self.from.range(self.bounds(range))?;
+		Ok(Box::new(iter.filter_map(move |r| {
+			match r {
+				Ok((k, v)) => k
+					.strip_prefix(prefix.as_slice())
+					.map(|k| Ok((k.to_vec(), v))),
+				Err(e) => Some(Err(e)),
+			}
+		})))
+	}
+}
+into_iter!(
+	impl<P> for Namespace<P>
+	where
+		P: View<Key = Vec<u8>>,
+);
+impl<P> Change for Namespace<P>
+where
+	P: View<Key = Vec<u8>> + Change<Key = Vec<u8>>,
+{
+	type Key = Vec<u8>;
+	type Value = <P as Change>::Value;
+	type Insert = <P as Change>::Insert;
+	fn insert_owned(
+		&self,
+		key: Self::Key,
+		value: Self::Insert,
+	) -> Result<Option<<Self as Change>::Value>> {
+		self.from.insert_owned(self.prefixed(&key), value)
+	}
+	fn insert_ref(
+		&self,
+		key: &Self::Key,
+		value: &Self::Insert,
+	) -> Result<Option<<Self as Change>::Value>> {
+		self.from.insert_ref(&self.prefixed(key), value)
+	}
+	fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>> {
+		self.from.remove_owned(self.prefixed(key))
+	}
+	/// Removes every key under the prefix, leaving the rest of the view as it is.
+	fn clear(&self) -> Result<()> {
+		self.from.remove_range(self.bounds(..))?;
+		Ok(())
+	}
+	fn fetch_and_update(
+		&self,
+		key: &Self::Key,
+		f: impl FnMut(Option<<Self as Change>::Value>) -> Option<Self::Insert>,
+	) -> Result<Option<<Self as Change>::Value>> {
+		self.from.fetch_and_update(&self.prefixed(key), f)
+	}
+}
+impl<P> Watch for Namespace<P>
+where
+	P: View<Key = Vec<u8>> + Watch,
+{
+	fn watch(&self) -> bus::BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.from.wait()
+	}
+}
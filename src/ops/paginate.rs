@@ -0,0 +1,207 @@
+use std::ops::{Bound, RangeBounds};
+
+use crate::traits::view::View;
+use crate::{error::Result, macros::into_iter};
+
+type Window<K, V> = Box<dyn Iterator<Item = Result<(K, V)>>>;
+
+/// A struct that views a window of entries.
+/// You can create a [Paginate] from a [View] struct with [skip](crate::Operate::skip)
+/// or [take](crate::Operate::take).
+///
+/// The window resumes from a cursor key with a range on the underlying view,
+/// so that paging does not walk again from the start.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// for i in 0..10u32 {
+///   tree.insert(i, i).unwrap();
+/// }
+///
+/// let page = tree.take(3);
+/// let keys = page.iter().map(|r| r.unwrap().0).collect::<Vec<_>>();
+/// assert_eq!(keys, vec![0, 1, 2]);
+///
+/// let page = page.next_page().unwrap().unwrap();
+/// let keys = page.iter().map(|r| r.unwrap().0).collect::<Vec<_>>();
+/// assert_eq!(keys, vec![3, 4, 5]);
+///
+/// let page = tree.take(2).after(7u32);
+/// let keys = page.iter().map(|r| r.unwrap().0).collect::<Vec<_>>();
+/// assert_eq!(keys, vec![8, 9]);
+/// ```
+pub struct Paginate<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	after: Option<Previous::Key>,
+	skip: usize,
+	take: Option<usize>,
+}
+impl<P: View> Clone for Paginate<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			after: self.after.clone(),
+			skip: self.skip,
+			take: self.take,
+		}
+	}
+}
+
+impl<P> Paginate<P>
+where
+	P: View,
+	P::Key: Ord,
+{
+	pub(crate) fn new(from: P) -> Self {
+		Paginate {
+			from,
+			after: None,
+			skip: 0,
+			take: None,
+		}
+	}
+	/// Skips entries at the start of the window.
+	pub fn skip(mut self, n: usize) -> Self {
+		self.skip += n;
+		self
+	}
+	/// Limits the window to at most `n` entries.
+	pub fn take(mut self, n: usize) -> Self {
+		self.take = Some(self.take.map_or(n, |t| t.min(n)));
+		self
+	}
+	/// Starts the window at the entries strictly greater than a cursor key.
+	pub fn after<K: Into<P::Key>>(mut self, key: K) -> Self {
+		self.after = Some(key.into());
+		self
+	}
+	/// Gets the window that starts after the last entry of this one.
+	/// Returns [None] if this window is empty.
+	pub fn next_page(&self) -> Result<Option<Self>> {
+		let last = match self.last()? {
+			Some((key, _)) => key,
+			None => return Ok(None),
+		};
+		Ok(Some(Paginate {
+			from: self.from.clone(),
+			after: Some(last),
+			skip: 0,
+			take: self.take,
+		}))
+	}
+	fn window(&self) -> Result<Window<P::Key, P::Value>> {
+		let start = match &self.after {
+			Some(key) => Bound::Excluded(key.clone()),
+			None => Bound::Unbounded,
+		};
+		let iter = self.from.range((start, Bound::Unbounded))?;
+		let iter = iter.skip(self.skip).take(self.take.unwrap_or(usize::MAX));
+		Ok(Box::new(iter))
+	}
+}
+
+impl<Previous> View for Paginate<Previous>
+where
+	Previous: View,
+	Previous::Key: Ord,
+{
+	type Key = Previous::Key;
+	type Value = Previous::Value;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		for r in self.window()? {
+			let (k, v) = r?;
+			if k == *key {
+				return Ok(Some(v));
+			}
+			if k > *key {
+				break;
+			}
+		}
+		Ok(None)
+	}
+	fn iter(&self) -> Self::Iter {
+		match self.window() {
+			Ok(iter) => iter,
+			Err(e) => Box::new(std::iter::once(Err(e))),
+		}
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.get_ref(key)?.is_some())
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let mut lesser = None;
+		for r in self.window()? {
+			let (k, v) = r?;
+			if k >= *key {
+				break;
+			}
+			lesser = Some((k, v));
+		}
+		Ok(lesser)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		for r in self.window()? {
+			let (k, v) = r?;
+			if k > *key {
+				return Ok(Some((k, v)));
+			}
+		}
+		Ok(None)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.window()?.next().transpose()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.window()?.last().transpose()
+	}
+	fn nth(&self, n: usize) -> Result<Option<(Self::Key, Self::Value)>> {
+		if self.take.is_some_and(|take| n >= take) {
+			return Ok(None);
+		}
+		// Without a cursor the position maps to the underlying view
+		if self.after.is_none() {
+			return self.from.nth(self.skip + n);
+		}
+		let mut window = self.window()?;
+		for _ in 0..n {
+			if window.next().transpose()?.is_none() {
+				return Ok(None);
+			}
+		}
+		window.next().transpose()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		Some(self.iter().next().is_none())
+	}
+	fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		let range = (range.start_bound().cloned(), range.end_bound().cloned());
+		Ok(Box::new(self.window()?.filter(move |r| match r {
+			Ok((k, _)) => range.contains(k),
+			Err(_) => true,
+		})))
+	}
+}
+into_iter!(
+	impl<Previous> for Paginate<Previous>
+	where
+		Previous: View,
+		Previous::Key: Ord,
+);
@@ -0,0 +1,101 @@
+use std::sync::{
+	atomic::{AtomicBool, Ordering::Relaxed},
+	Arc,
+};
+
+use crate::{
+	threads::{spawn_listener_until, Synchronizer},
+	traits::{
+		change::Change,
+		view::View,
+		watch::{Event, Watch},
+	},
+};
+
+/// A handle to a pipe, which writes every change of a view to another tree.
+/// You can create a [PipeHandle] from a [Watch] struct.
+///
+/// The changes are written in their own thread, and its errors are kept in [last_error](PipeHandle::last_error).
+/// Dropping the handle stops the pipe, the changes after that aren't written,
+/// and its thread lets go of the source and the other tree at the next change.
+/// To keep the pipe running for as long as the source, [detach](PipeHandle::detach) it instead.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// # let other: Tree<u32, u32> = db.open_tree("other").unwrap();
+/// let pipe = tree.pipe(other.clone());
+///
+/// tree.insert(1u32, 1u32).unwrap();
+/// pipe.wait();
+/// assert_eq!(other.get(1u32).unwrap(), Some(1));
+///
+/// drop(pipe);
+/// tree.insert(2u32, 2u32).unwrap();
+/// assert_eq!(other.get(2u32).unwrap(), None);
+/// ```
+#[must_use = "dropping the handle stops the pipe, detach it to keep it running"]
+pub struct PipeHandle {
+	stopped: Option<Arc<AtomicBool>>,
+	sync: Arc<Synchronizer>,
+	/// The synchronizer of the other tree, which waits on the pipe until it is stopped
+	target: Arc<Synchronizer>,
+}
+
+impl PipeHandle {
+	pub(crate) fn new<P, O>(from: &P, other: O) -> Self
+	where
+		P: View + Watch,
+		O: Change<Key = P::Key, Insert = P::Value> + Watch + Send + Sync,
+	{
+		let stopped = Arc::new(AtomicBool::new(false));
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		// Waiting on the other tree waits for the pipe, until the pipe is stopped
+		let target = other.sync();
+		target.push_source(Arc::clone(&sync));
+		spawn_listener_until(
+			Arc::clone(&sync),
+			from.watch(),
+			Arc::clone(&stopped),
+			move |event| {
+				match event {
+					Event::Insert { key, value } => other.insert_ref(&key, &value)?,
+					Event::Remove { key } => other.remove_ref(&key)?,
+				};
+				// No outgoing events, because the calls to insert and remove will create events already.
+				Ok(0)
+			},
+		);
+		Self {
+			stopped: Some(stopped),
+			sync,
+			target,
+		}
+	}
+	/// Waits until the pipe has written all changes.
+	pub fn wait(&self) {
+		self.sync.wait()
+	}
+	/// Returns the last error of the pipe or its source, if any.
+	pub fn last_error(&self) -> Option<Arc<anyhow::Error>> {
+		self.sync.last_error()
+	}
+	/// A synchronizer for the pipe.
+	pub fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	/// Lets go of the handle, keeping the pipe running for as long as its source.
+	pub fn detach(mut self) {
+		self.stopped = None;
+	}
+}
+
+impl Drop for PipeHandle {
+	fn drop(&mut self) {
+		if let Some(stopped) = &self.stopped {
+			stopped.store(true, Relaxed);
+			self.target.remove_source(&self.sync);
+		}
+	}
+}
@@ -1,9 +1,9 @@
-use anyhow::Result;
-
 use delegate::delegate;
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
 use crate::{
+	error::Result,
+	macros::into_iter,
 	threads::Synchronizer,
 	traits::{
 		change::Change,
@@ -14,12 +14,21 @@ use crate::{
 };
 
 type ReduceFn<P, M> =
-	dyn Fn(Option<<P as Change>::Value>, M) -> <P as Change>::Insert + Send + Sync;
+	dyn Fn(Option<<P as Change>::Value>, M) -> Option<<P as Change>::Insert> + Send + Sync;
 
 /// A struct that reduces values on insert.
 /// You can create a [Reducer] from a [Change] struct.
-/// # Important
-/// If you perform an insert that bypasses the [Reducer] struct, be in on the tree or in another reduce, you may experience data races.
+///
+/// The reducer may also return an [Option], in which case returning [None] removes the key,
+/// like a counter that goes away once it reaches zero.
+/// # Atomicity
+/// Each insert is a single [fetch_and_update](Change::fetch_and_update) on the underlying struct,
+/// so the read, the reduce and the write happen atomically on a [Tree](crate::Tree),
+/// where a compare and swap is retried until no other write got in between,
+/// and on a [Loaded](crate::traits::load::Loaded), which holds its lock for the whole update.
+/// As it may be retried, the reducer should not have side effects.
+///
+/// Any other struct is only as atomic as its own [fetch_and_update](Change::fetch_and_update).
 ///
 /// # Examples
 /// ```
@@ -33,6 +42,15 @@ type ReduceFn<P, M> =
 ///
 /// let result = reducer.get("key").unwrap();
 /// assert_eq!(result, Some("hello, world!".to_string()));
+///
+/// let counter = tree.reducer(|count: Option<String>, _: ()| match count.as_deref() {
+///   Some("one") => None,
+///   _ => Some("one".to_string()),
+/// });
+/// counter.insert("counter", ()).unwrap();
+/// assert!(tree.contains_key("counter").unwrap());
+/// counter.insert("counter", ()).unwrap();
+/// assert!(!tree.contains_key("counter").unwrap());
 /// ```
 pub struct Reducer<Previous, Merge>
 where
@@ -49,20 +67,26 @@ impl<P: Clone + View + Change, M> Clone for Reducer<P, M> {
 		}
 	}
 }
+impl<P, M> fmt::Debug for Reducer<P, M>
+where
+	P: View + Change + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Reducer").field(&self.from).finish()
+	}
+}
 
 impl<P, Merge> Reducer<P, Merge>
 where
 	P: View + Change,
 {
-	pub(crate) fn new<ReduceFn>(from: P, reducer: ReduceFn) -> Self
+	pub(crate) fn new<ReduceFn, Reduced>(from: P, reducer: ReduceFn) -> Self
 	where
-		ReduceFn: 'static
-			+ Fn(Option<<P as Change>::Value>, Merge) -> <P as Change>::Insert
-			+ Send
-			+ Sync,
+		ReduceFn: 'static + Fn(Option<<P as Change>::Value>, Merge) -> Reduced + Send + Sync,
+		Reduced: Into<Option<<P as Change>::Insert>>,
 		P: 'static + Sync + Send,
 	{
-		let reducer = Arc::new(reducer);
+		let reducer = Arc::new(move |value, merge| reducer(value, merge).into());
 		Reducer { from, reducer }
 	}
 }
@@ -98,6 +122,12 @@ where
     }
   );
 }
+into_iter!(
+	impl<Previous, Merge> for Reducer<Previous, Merge>
+	where
+		Previous: View + Change,
+		Merge: 'static + Clone + Send + Sync,
+);
 impl<Previous, Merge> Change for Reducer<Previous, Merge>
 where
 	Previous: View + Change<Key = <Previous as View>::Key>,
@@ -111,10 +141,8 @@ where
 		key: &Self::Key,
 		value: &Self::Insert,
 	) -> Result<Option<<Self as Change>::Value>> {
-		self.from.fetch_and_update(key, |old| {
-			let new = (self.reducer)(old, value.clone());
-			Some(new)
-		})
+		self.from
+			.fetch_and_update(key, |old| (self.reducer)(old, value.clone()))
 	}
 	fn fetch_and_update(
 		&self,
@@ -122,7 +150,7 @@ where
 		mut f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
 	) -> Result<Option<Self::Value>> {
 		self.from
-			.fetch_and_update(key, |v| f(v.clone()).and_then(|m| Some((self.reducer)(v, m))))
+			.fetch_and_update(key, |v| f(v.clone()).and_then(|m| (self.reducer)(v, m)))
 	}
   #[rustfmt::skip]
 	delegate! {
@@ -0,0 +1,177 @@
+use bus::BusReader;
+use std::{ops::RangeBounds, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::into_iter,
+	threads::Synchronizer,
+	traits::{
+		view::View,
+		watch::{Event, Watch},
+	},
+	wrappers::database::Db,
+};
+
+/// A struct that views every `step`-th entry of an ordered view.
+/// You can create a [Sample] from a [View] struct.
+///
+/// Sampling is count-based, so each call to [iter](View::iter) or [range](View::range) starts its own count,
+/// keeping the first entry it reads and then one entry every `step`.
+/// A range is sampled from its own first entry, while getting a key checks its position in the whole view.
+/// Since positions shift as entries change, the events of the source are passed through unchanged.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// for i in 0..10u32 {
+///   tree.insert(i, i).unwrap();
+/// }
+///
+/// let sample = tree.sample(3);
+/// let keys = sample.iter().map(|r| r.unwrap().0).collect::<Vec<_>>();
+/// assert_eq!(keys, vec![0, 3, 6, 9]);
+/// assert_eq!(sample.get(4u32).unwrap(), None);
+/// ```
+pub struct Sample<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	step: usize,
+}
+impl<P: View> Clone for Sample<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			step: self.step,
+		}
+	}
+}
+
+impl<P> Sample<P>
+where
+	P: View,
+	P::Key: Ord,
+{
+	pub(crate) fn new(from: P, step: usize) -> Self {
+		assert!(step != 0, "sample step must be non-zero");
+		Sample { from, step }
+	}
+	/// Keeps every `step`-th entry of an iterator, passing errors through
+	fn stride(&self, iter: P::Iter) -> <Self as View>::Iter {
+		let step = self.step;
+		let mut count = 0;
+		Box::new(iter.filter(move |r| match r {
+			Ok(_) => {
+				count += 1;
+				(count - 1) % step == 0
+			}
+			Err(_) => true,
+		}))
+	}
+	/// Whether a key falls on a sampled position of the whole view
+	fn is_sampled(&self, key: &P::Key) -> Result<bool> {
+		let mut position = 0;
+		for res in self.from.range(..key)? {
+			res?;
+			position += 1;
+		}
+		Ok(position % self.step == 0)
+	}
+}
+
+impl<P> View for Sample<P>
+where
+	P: View,
+	P::Key: Ord,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		match self.from.get_ref(key)? {
+			Some(value) if self.is_sampled(key)? => Ok(Some(value)),
+			_ => Ok(None),
+		}
+	}
+	fn iter(&self) -> Self::Iter {
+		self.stride(self.from.iter())
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.from.contains_key_ref(key)? && self.is_sampled(key)?)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let mut last = None;
+		for res in self.iter() {
+			let (k, v) = res?;
+			if k >= *key {
+				break;
+			}
+			last = Some((k, v));
+		}
+		Ok(last)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		for res in self.iter() {
+			let (k, v) = res?;
+			if k > *key {
+				return Ok(Some((k, v)));
+			}
+		}
+		Ok(None)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.from.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		let mut last = None;
+		for res in self.iter() {
+			last = Some(res?);
+		}
+		Ok(last)
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.from.is_empty()
+	}
+	fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		Ok(self.stride(self.from.range(range)?))
+	}
+}
+into_iter!(
+	impl<P> for Sample<P>
+	where
+		P: View,
+		P::Key: Ord,
+);
+
+impl<P> Watch for Sample<P>
+where
+	P: View + Watch,
+	P::Key: Ord,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.from.watch()
+	}
+	fn db(&self) -> Db {
+		self.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		self.from.sync()
+	}
+	fn wait(&self) {
+		self.from.wait()
+	}
+}
@@ -0,0 +1,322 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	structs::single::Single,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+type ScanFn<K, V, S> = dyn Fn(&S, &K, &V) -> S + Send + Sync;
+
+/// A struct that folds a running state over the inserts of a view.
+/// You can create a [Scan] from a [View] struct.
+///
+/// Each insert computes a new state from the previous one,
+/// and the key holds that state, like a prefix sum in the order events arrive.
+/// Events are processed one at a time, on a single thread.
+///
+/// A remove takes the key out of the view and leaves the state as it is,
+/// unless [reset_on_remove](Scan::reset_on_remove) is set,
+/// in which case the state goes back to its initial value.
+///
+/// [Scan] doesn't implement [View] or [Watch], you must store it first.
+/// Loading it scans the entries already in the view, in key order.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, i32> = db.open_tree("tree").unwrap();
+/// let balance = tree.scan(0, |balance, _, delta| balance + delta).load().unwrap();
+///
+/// tree.insert(1u32, 10).unwrap();
+/// tree.insert(2u32, -3).unwrap();
+/// tree.insert(3u32, 5).unwrap();
+///
+/// assert_eq!(balance.get(2u32).unwrap(), Some(7));
+/// assert_eq!(balance.get(3u32).unwrap(), Some(12));
+/// assert_eq!(balance.state().unwrap(), Some(12));
+/// ```
+pub struct Scan<Previous, State>
+where
+	Previous: View,
+{
+	from: Previous,
+	init: State,
+	scan: Arc<ScanFn<Previous::Key, Previous::Value, State>>,
+	reset: bool,
+}
+impl<P, S> Clone for Scan<P, S>
+where
+	P: View,
+	S: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			init: self.init.clone(),
+			scan: Arc::clone(&self.scan),
+			reset: self.reset,
+		}
+	}
+}
+
+impl<P, S> Scan<P, S>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new<ScanFn>(from: P, init: S, scan: ScanFn) -> Self
+	where
+		ScanFn: 'static + Fn(&S, &P::Key, &P::Value) -> S + Sync + Send,
+	{
+		Scan {
+			from,
+			init,
+			scan: Arc::new(scan),
+			reset: false,
+		}
+	}
+	/// Resets the state to its initial value whenever a key is removed.
+	pub fn reset_on_remove(mut self) -> Self {
+		self.reset = true;
+		self
+	}
+}
+
+/// A stored or loaded [Scan].
+///
+/// The state holds the running state, under the `()` key.
+/// The forward map holds the state each key was left with.
+pub struct MaterialScan<P, S, St, F>
+where
+	P: View,
+	St: Clone,
+	F: Clone,
+{
+	from: Scan<P, S>,
+	state: St,
+	fwd: F,
+	watcher: Watcher<P::Key, S>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, S, St, F> Clone for MaterialScan<P, S, St, F>
+where
+	P: View,
+	S: Clone,
+	St: Clone,
+	F: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			state: self.state.clone(),
+			fwd: self.fwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, S, St, F> MaterialScan<P, S, St, F>
+where
+	P: Watch + Sync + Send,
+	S: 'static + Clone + Send + Sync,
+	St: Clone + View<Key = (), Value = S> + Change<Key = (), Value = S, Insert = S> + Send + Sync,
+	F: Clone
+		+ View<Key = P::Key, Value = S>
+		+ Change<Key = P::Key, Value = S, Insert = S>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: Scan<P, S>, state: St, fwd: F) -> Self {
+		let reader = from.from.watch();
+		let init = from.init.clone();
+		let scan = Arc::clone(&from.scan);
+		let reset = from.reset;
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		// A single watcher thread, so the state sees the events in order
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(state, fwd, move |event| match event {
+				Event::Insert { key, value } => {
+					let prev = state.get_ref(&())?.unwrap_or_else(|| init.clone());
+					let next = scan(&prev, &key, &value);
+					state.insert_ref(&(), &next)?;
+					fwd.insert_ref(&key, &next)?;
+					let value = Arc::new(next);
+					Ok(vec![Event::Insert { key, value }])
+				}
+				Event::Remove { key } => {
+					if reset {
+						state.insert_ref(&(), &init)?;
+					}
+					fwd.remove_ref(&key)?;
+					Ok(vec![Event::Remove { key }])
+				}
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			state,
+			fwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the scan from the source view, in key order
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		let mut acc = self.from.init.clone();
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			acc = (self.from.scan)(&acc, &k, &v);
+			self.fwd.insert_ref(&k, &acc)?;
+		}
+		self.state.insert_owned((), acc)?;
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+	/// Gets the running state, after the last event
+	pub fn state(&self) -> Result<Option<S>> {
+		self.sync.wait_checked()?;
+		self.state.get_ref(&())
+	}
+}
+
+impl<P, S, St, F> View for MaterialScan<P, S, St, F>
+where
+	P: View,
+	S: 'static + Clone + Send + Sync,
+	St: 'static + Clone,
+	F: Clone + View<Key = P::Key, Value = S>,
+{
+	type Key = P::Key;
+	type Value = S;
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, S, St, F> for MaterialScan<P, S, St, F>
+	where
+		P: View,
+		S: 'static + Clone + Send + Sync,
+		St: 'static + Clone,
+		F: Clone + View<Key = P::Key, Value = S>,
+);
+impl<P, S, St, F> Watch for MaterialScan<P, S, St, F>
+where
+	P: Watch,
+	S: 'static + Clone + Send + Sync,
+	St: 'static + Clone,
+	F: Clone + View<Key = P::Key, Value = S>,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, S> Store for Scan<P, S>
+where
+	P: Watch + Sync + Send,
+	S: Serial,
+	<P as View>::Key: Serial,
+{
+	type Stored = MaterialScan<P, S, Single<S>, Tree<<P as View>::Key, S>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let state = db.open_store_single(&name, "state")?;
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		Ok(MaterialScan::new(self.clone(), state, fwd))
+	}
+}
+
+impl<P, S> Load for Scan<P, S>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	S: 'static + Clone + Send + Sync,
+{
+	type Loaded = MaterialScan<P, S, Loaded<(), S>, Loaded<<P as View>::Key, S>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let state = Loaded::new();
+		let fwd = Loaded::new();
+		let res = MaterialScan::new(self.clone(), state, fwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
@@ -0,0 +1,299 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::sync::Arc;
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	structs::merge::Merge,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+};
+
+type Projector<A, B> = dyn Fn(Option<A>, Option<B>) -> Option<A> + Sync + Send;
+
+/// A struct that combines the keys of two views with set semantics.
+/// You can create a [Set] with [union](crate::Operate::union),
+/// [intersection](crate::Operate::intersection) or [difference](crate::Operate::difference),
+/// as long as both views have the same key type.
+///
+/// An union holds the value of the first view that has the key,
+/// while an intersection or a difference holds the value of the first view.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let a_tree: Tree<String, ()> = db.open_tree("a").unwrap();
+/// # let b_tree: Tree<String, ()> = db.open_tree("b").unwrap();
+///
+/// let union = a_tree.union(&b_tree);
+/// let intersection = a_tree.intersection(&b_tree);
+/// let difference = a_tree.difference(&b_tree);
+///
+/// a_tree.insert("alice", ()).unwrap();
+/// a_tree.insert("bob", ()).unwrap();
+/// b_tree.insert("bob", ()).unwrap();
+/// b_tree.insert("carol", ()).unwrap();
+///
+/// // The order of the keys depends on how they are serialized
+/// let keys = |v: Vec<(String, ())>| {
+///   let mut keys = v.into_iter().map(|(k, _)| k).collect::<Vec<_>>();
+///   keys.sort();
+///   keys
+/// };
+/// assert_eq!(keys(union.iter().flatten().collect()), vec!["alice", "bob", "carol"]);
+/// assert_eq!(keys(intersection.iter().flatten().collect()), vec!["bob"]);
+/// assert_eq!(keys(difference.iter().flatten().collect()), vec!["alice"]);
+/// ```
+pub struct Set<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+{
+	a: A,
+	b: B,
+	project: Arc<Projector<A::Value, B::Value>>,
+	watcher: Watcher<A::Key, A::Value>,
+	sync: Arc<Synchronizer>,
+}
+impl<A, B> Clone for Set<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+{
+	fn clone(&self) -> Self {
+		Self {
+			a: self.a.clone(),
+			b: self.b.clone(),
+			project: Arc::clone(&self.project),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<A, B> Set<A, B>
+where
+	A: View + Watch + Sync + Send,
+	B: View<Key = <A as View>::Key> + Watch + Sync + Send,
+{
+	fn new<P>(a: A, b: B, project: P) -> Self
+	where
+		P: 'static + Fn(Option<A::Value>, Option<B::Value>) -> Option<A::Value> + Sync + Send,
+	{
+		let project: Arc<Projector<A::Value, B::Value>> = Arc::new(project);
+		let sync = Arc::new(Synchronizer::from(vec![a.sync(), b.sync()]));
+		let watcher = Watcher::new(cloned!(sync, a, b, project, move || {
+			let bus = Arc::new(RwLock::new(Bus::new(128)));
+			let a_reader = a.watch();
+			let b_reader = b.watch();
+			spawn_watcher(
+				Arc::clone(&sync),
+				a_reader,
+				Arc::clone(&bus),
+				cloned!(project, move |event| {
+					let (key, a) = match event {
+						Event::Insert { key, value } => (key, Some((*value).clone())),
+						Event::Remove { key } => (key, None),
+					};
+					let b = b.get_ref(&key)?;
+					// The key may still exist in a source, but not in the set
+					let event = match project(a, b) {
+						Some(value) => Event::Insert {
+							key,
+							value: Arc::new(value),
+						},
+						None => Event::Remove { key },
+					};
+					Ok(vec![event])
+				}),
+			);
+			spawn_watcher(sync, b_reader, Arc::clone(&bus), move |event| {
+				let (key, b) = match event {
+					Event::Insert { key, value } => (key, Some((*value).clone())),
+					Event::Remove { key } => (key, None),
+				};
+				let a = a.get_ref(&key)?;
+				let event = match project(a, b) {
+					Some(value) => Event::Insert {
+						key,
+						value: Arc::new(value),
+					},
+					None => Event::Remove { key },
+				};
+				Ok(vec![event])
+			});
+			bus
+		}));
+		Set {
+			a,
+			b,
+			project,
+			watcher,
+			sync,
+		}
+	}
+	pub(crate) fn union(a: A, b: B) -> Self
+	where
+		B: View<Value = A::Value>,
+	{
+		Self::new(a, b, |a, b| a.or(b))
+	}
+	pub(crate) fn intersection(a: A, b: B) -> Self {
+		Self::new(a, b, |a, b| match (a, b) {
+			(Some(a), Some(_)) => Some(a),
+			_ => None,
+		})
+	}
+	pub(crate) fn difference(a: A, b: B) -> Self {
+		Self::new(a, b, |a, b| match (a, b) {
+			(Some(a), None) => Some(a),
+			_ => None,
+		})
+	}
+}
+
+impl<A, B> Set<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+	A::Key: Ord,
+{
+	/// Walks the keys of both views from a starting key, until one belongs to the set.
+	fn seek(&self, mut key: A::Key, forward: bool) -> Result<Option<(A::Key, A::Value)>> {
+		loop {
+			let (a, b) = match forward {
+				true => (self.a.get_gt_ref(&key)?, self.b.get_gt_ref(&key)?),
+				false => (self.a.get_lt_ref(&key)?, self.b.get_lt_ref(&key)?),
+			};
+			key = match (a, b) {
+				(None, None) => return Ok(None),
+				(Some((a, _)), None) => a,
+				(None, Some((b, _))) => b,
+				(Some((a, _)), Some((b, _))) if forward => a.min(b),
+				(Some((a, _)), Some((b, _))) => a.max(b),
+			};
+			if let Some(value) = self.get_ref(&key)? {
+				return Ok(Some((key, value)));
+			}
+		}
+	}
+	/// Starts from the first or last key of both views, then walks until one belongs to the set.
+	fn edge(&self, forward: bool) -> Result<Option<(A::Key, A::Value)>> {
+		let (a, b) = match forward {
+			true => (self.a.first()?, self.b.first()?),
+			false => (self.a.last()?, self.b.last()?),
+		};
+		let key = match (a, b) {
+			(None, None) => return Ok(None),
+			(Some((a, _)), None) => a,
+			(None, Some((b, _))) => b,
+			(Some((a, _)), Some((b, _))) if forward => a.min(b),
+			(Some((a, _)), Some((b, _))) => a.max(b),
+		};
+		match self.get_ref(&key)? {
+			Some(value) => Ok(Some((key, value))),
+			None => self.seek(key, forward),
+		}
+	}
+}
+
+impl<A, B> View for Set<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+	A::Key: Ord,
+{
+	type Key = A::Key;
+	type Value = A::Value;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		let a = self.a.get_ref(key)?;
+		let b = self.b.get_ref(key)?;
+		Ok((self.project)(a, b))
+	}
+	fn iter(&self) -> Self::Iter {
+		let project = Arc::clone(&self.project);
+		let merge = Merge::new(self.a.iter(), self.b.iter());
+		Box::new(merge.filter_map(move |r| match r {
+			Ok((k, a, b)) => project(a, b).map(|v| Ok((k, v))),
+			Err(e) => Some(Err(e)),
+		}))
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.get_ref(key)?.is_some())
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.seek(key.clone(), false)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.seek(key.clone(), true)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.edge(true)
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.edge(false)
+	}
+	fn is_empty(&self) -> Option<bool> {
+		let a = self.a.is_empty();
+		let b = self.b.is_empty();
+		match (a, b) {
+			(Some(true), Some(true)) => Some(true),
+			_ => None,
+		}
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		let a = (range.start_bound(), range.end_bound());
+		let b = (range.start_bound(), range.end_bound());
+		let project = Arc::clone(&self.project);
+		let merge = Merge::new(self.a.range(a)?, self.b.range(b)?);
+		Ok(Box::new(merge.filter_map(move |r| match r {
+			Ok((k, a, b)) => project(a, b).map(|v| Ok((k, v))),
+			Err(e) => Some(Err(e)),
+		})))
+	}
+}
+into_iter!(
+	impl<A, B> for Set<A, B>
+	where
+		A: View,
+		B: View<Key = A::Key>,
+		A::Key: Ord,
+);
+
+impl<A, B> Watch for Set<A, B>
+where
+	A: View + Watch,
+	B: View<Key = A::Key> + Watch,
+	A::Key: Ord,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> crate::wrappers::database::Db {
+		self.a.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.a.wait();
+		self.b.wait();
+	}
+}
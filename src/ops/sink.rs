@@ -0,0 +1,78 @@
+use std::sync::{
+	atomic::{AtomicBool, Ordering::Relaxed},
+	Arc,
+};
+
+use crate::{
+	threads::{spawn_listener, Synchronizer},
+	traits::watch::{Event, Watch},
+};
+
+/// A handle to a subscription that hands every event of a view to a function.
+/// You can create a [Sink] from a [Watch] struct.
+///
+/// This is the way to push changes into something that isn't a tree, like a queue or a webhook.
+/// The function runs in its own thread, and its errors are kept in [last_error](Sink::last_error).
+/// Dropping the handle stops the subscription, the events after that are ignored.
+/// # Examples
+/// ```
+/// # use husky::{Tree, Change, Operate, traits::watch::Event};
+/// # use std::sync::{Arc, Mutex};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let keys = Arc::new(Mutex::new(Vec::new()));
+/// let sink = tree.sink({
+///   let keys = Arc::clone(&keys);
+///   move |event: Event<u32, u32>| {
+///     keys.lock().unwrap().push(**event.key());
+///     Ok(())
+///   }
+/// });
+///
+/// tree.insert(1u32, 1u32).unwrap();
+/// tree.remove(1u32).unwrap();
+/// sink.wait();
+///
+/// assert_eq!(*keys.lock().unwrap(), vec![1, 1]);
+/// ```
+pub struct Sink {
+	stopped: Arc<AtomicBool>,
+	sync: Arc<Synchronizer>,
+}
+
+impl Sink {
+	pub(crate) fn new<W, F>(from: &W, f: F) -> Self
+	where
+		W: Watch,
+		F: 'static + Fn(Event<W::Key, W::Value>) -> anyhow::Result<()> + Send + Sync,
+	{
+		let stopped = Arc::new(AtomicBool::new(false));
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		let flag = Arc::clone(&stopped);
+		spawn_listener(Arc::clone(&sync), from.watch(), move |event| {
+			if !flag.load(Relaxed) {
+				f(event)?;
+			}
+			Ok(0)
+		});
+		Self { stopped, sync }
+	}
+	/// Waits until the function has handled all events.
+	pub fn wait(&self) {
+		self.sync.wait()
+	}
+	/// Returns the last error of the function, if any.
+	pub fn last_error(&self) -> Option<Arc<anyhow::Error>> {
+		self.sync.last_error()
+	}
+	/// A synchronizer for the subscription.
+	pub fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+}
+
+impl Drop for Sink {
+	fn drop(&mut self) {
+		self.stopped.store(true, Relaxed);
+	}
+}
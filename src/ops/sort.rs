@@ -0,0 +1,301 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+type ByValue<P> = Box<dyn Iterator<Item = Result<(<P as View>::Key, <P as View>::Value)>>>;
+
+/// A struct that keeps the entries of a view ordered by value.
+/// You can create a [SortByValue] from a [View] struct with ordered values.
+///
+/// Every entry is also kept as a `(value, key)` pair, so iterating in value order is a scan over them.
+/// Entries with the same value are ordered by key.
+/// When stored, the pairs are ordered by their serialized bytes, which follows the value order for fixed-size types such as unsigned integers.
+///
+/// [SortByValue] doesn't implement [View] or [Watch], you must store or load it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let sorted = tree.sort_by_value().load().unwrap();
+///
+/// tree.insert("a", 30u32).unwrap();
+/// tree.insert("b", 10u32).unwrap();
+/// tree.insert("c", 20u32).unwrap();
+/// let keys = sorted.iter_by_value().map(|r| r.unwrap().0).collect::<Vec<_>>();
+/// assert_eq!(keys, vec!["b", "c", "a"]);
+/// ```
+pub struct SortByValue<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+}
+impl<P: View> Clone for SortByValue<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+		}
+	}
+}
+
+impl<P> SortByValue<P>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new(from: P) -> Self {
+		SortByValue { from }
+	}
+}
+
+/// A stored or loaded [SortByValue].
+///
+/// The forward map holds the entries by key, and the order holds them as `(value, key)` pairs.
+pub struct MaterialSortByValue<P, F, O>
+where
+	P: View,
+	F: Clone,
+	O: Clone,
+{
+	from: SortByValue<P>,
+	fwd: F,
+	order: O,
+	watcher: Watcher<P::Key, P::Value>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, F, O> Clone for MaterialSortByValue<P, F, O>
+where
+	P: View,
+	F: Clone,
+	O: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			order: self.order.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, F, O> MaterialSortByValue<P, F, O>
+where
+	P: Watch + Sync + Send,
+	P::Value: PartialEq,
+	F: Clone
+		+ View<Key = P::Key, Value = P::Value>
+		+ Change<Key = P::Key, Value = P::Value, Insert = P::Value>
+		+ Send
+		+ Sync,
+	O: Clone
+		+ View<Key = (P::Value, P::Key), Value = ()>
+		+ Change<Key = (P::Value, P::Key), Value = (), Insert = ()>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: SortByValue<P>, fwd: F, order: O) -> Self {
+		let reader = from.from.watch();
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, order, move |event| {
+				let (key, value) = match &event {
+					Event::Insert { key, value } => (key, Some(value)),
+					Event::Remove { key } => (key, None),
+				};
+				let old = fwd.get_ref(key)?;
+				if old.as_ref() == value.map(|v| &**v) {
+					return Ok(vec![]);
+				}
+				// The old pair is removed before the new one is added, so a key is never listed twice
+				if let Some(old) = old {
+					order.remove_owned((old, (**key).clone()))?;
+				}
+				match value {
+					Some(value) => {
+						fwd.insert_ref(key, value)?;
+						order.insert_owned(((**value).clone(), (**key).clone()), ())?;
+					}
+					None => {
+						fwd.remove_ref(key)?;
+					}
+				}
+				Ok(vec![event])
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			order,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the order from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.order.clear()?;
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			self.fwd.insert_ref(&k, &v)?;
+			self.order.insert_owned((v, k), ())?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+	/// Gets an iterator over the entries, lowest value first
+	pub fn iter_by_value(&self) -> ByValue<P> {
+		self.sync.wait();
+		Box::new(self.order.iter().map(|r| r.map(|((v, k), _)| (k, v))))
+	}
+}
+
+impl<P, F, O> View for MaterialSortByValue<P, F, O>
+where
+	P: View,
+	F: Clone + View<Key = P::Key, Value = P::Value>,
+	O: 'static + Clone,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, F, O> for MaterialSortByValue<P, F, O>
+	where
+		P: View,
+		F: Clone + View<Key = P::Key, Value = P::Value>,
+		O: 'static + Clone,
+);
+impl<P, F, O> Watch for MaterialSortByValue<P, F, O>
+where
+	P: Watch,
+	F: Clone + View<Key = P::Key, Value = P::Value>,
+	O: 'static + Clone,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P> Store for SortByValue<P>
+where
+	P: Watch + Sync + Send,
+	<P as View>::Key: Serial,
+	<P as View>::Value: Serial + Ord,
+	(<P as View>::Value, <P as View>::Key): Serial,
+{
+	type Stored = MaterialSortByValue<
+		P,
+		Tree<<P as View>::Key, P::Value>,
+		Tree<(<P as View>::Value, P::Key), ()>,
+	>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let order = db.open_store_tree(&name, "order")?;
+		Ok(MaterialSortByValue::new(self.clone(), fwd, order))
+	}
+}
+
+impl<P> Load for SortByValue<P>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	<P as View>::Value: Ord,
+{
+	type Loaded = MaterialSortByValue<
+		P,
+		Loaded<<P as View>::Key, P::Value>,
+		Loaded<(<P as View>::Value, P::Key), ()>,
+	>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let fwd = Loaded::new();
+		let order = Loaded::new();
+		let res = MaterialSortByValue::new(self.clone(), fwd, order);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
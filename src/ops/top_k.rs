@@ -0,0 +1,349 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{collections::BTreeSet, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::into_iter,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::database::Db,
+};
+
+/// A struct that keeps the entries with the highest values.
+/// You can create a [TopK] from a [View] struct with ordered values.
+///
+/// Entries are ranked by value, ties being broken by key.
+/// A new entry is admitted when it beats the lowest entry of the top, which is then evicted.
+/// When an entry leaves the top, or its value drops, the source is scanned for the next best entry.
+///
+/// [TopK] doesn't implement [View] or [Watch], you must load it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let top = tree.top_k(2).load().unwrap();
+///
+/// tree.insert("a", 10u32).unwrap();
+/// tree.insert("b", 30u32).unwrap();
+/// tree.insert("c", 20u32).unwrap();
+/// assert_eq!(top.get("a").unwrap(), None);
+///
+/// tree.remove("b").unwrap();
+/// let ranked = top.ranked().unwrap();
+/// assert_eq!(ranked, vec![("c".to_string(), 20), ("a".to_string(), 10)]);
+/// ```
+pub struct TopK<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	n: usize,
+}
+impl<P: View> Clone for TopK<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			n: self.n,
+		}
+	}
+}
+
+impl<P> TopK<P>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new(from: P, n: usize) -> Self {
+		TopK { from, n }
+	}
+}
+
+/// A loaded [TopK].
+///
+/// The forward map holds the top entries, and the ranks hold them ordered by value, lowest first.
+pub struct MaterialTopK<P>
+where
+	P: View,
+{
+	from: TopK<P>,
+	fwd: Loaded<P::Key, P::Value>,
+	ranks: Loaded<(P::Value, P::Key), ()>,
+	watcher: Watcher<P::Key, P::Value>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P> Clone for MaterialTopK<P>
+where
+	P: View,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			fwd: self.fwd.clone(),
+			ranks: self.ranks.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+/// The state of a [MaterialTopK] that is changed by its watcher
+struct Top<P: View> {
+	from: P,
+	n: usize,
+	fwd: Loaded<P::Key, P::Value>,
+	ranks: Loaded<(P::Value, P::Key), ()>,
+}
+
+impl<P> Top<P>
+where
+	P: View,
+	P::Key: Ord,
+	P::Value: Ord,
+{
+	fn admit(
+		&self,
+		key: P::Key,
+		value: P::Value,
+		events: &mut Vec<Event<P::Key, P::Value>>,
+	) -> Result<()> {
+		self.fwd.insert_ref(&key, &value)?;
+		self.ranks.insert_owned((value.clone(), key.clone()), ())?;
+		events.push(Event::Insert {
+			key: Arc::new(key),
+			value: Arc::new(value),
+		});
+		Ok(())
+	}
+	fn evict(
+		&self,
+		key: P::Key,
+		value: P::Value,
+		events: &mut Vec<Event<P::Key, P::Value>>,
+	) -> Result<()> {
+		self.fwd.remove_ref(&key)?;
+		self.ranks.remove_owned((value, key.clone()))?;
+		events.push(Event::Remove { key: Arc::new(key) });
+		Ok(())
+	}
+	/// Scans the source for the best entry that isn't in the top, and admits it
+	fn refill(&self, events: &mut Vec<Event<P::Key, P::Value>>) -> Result<()> {
+		let mut best: Option<(P::Value, P::Key)> = None;
+		for res in self.from.iter() {
+			let (k, v) = res?;
+			if self.fwd.contains_key_ref(&k)? {
+				continue;
+			}
+			let candidate = (v, k);
+			if best.as_ref() < Some(&candidate) {
+				best = Some(candidate);
+			}
+		}
+		if let Some((v, k)) = best {
+			self.admit(k, v, events)?;
+		}
+		Ok(())
+	}
+	fn handle(&self, event: Event<P::Key, P::Value>) -> Result<Vec<Event<P::Key, P::Value>>> {
+		let mut events = Vec::new();
+		let (key, value) = match event {
+			Event::Insert { key, value } => ((*key).clone(), Some((*value).clone())),
+			Event::Remove { key } => ((*key).clone(), None),
+		};
+		if let Some(old) = self.fwd.get_ref(&key)? {
+			match value {
+				// A value that doesn't drop keeps its place
+				Some(value) if (&value, &key) >= (&old, &key) => {
+					self.ranks.remove_owned((old, key.clone()))?;
+					self.admit(key, value, &mut events)?;
+				}
+				// Otherwise the best entry of the source takes the place, which may be this one
+				_ => {
+					self.evict(key, old, &mut events)?;
+					self.refill(&mut events)?;
+				}
+			}
+			return Ok(events);
+		}
+		let value = match value {
+			Some(value) => value,
+			None => return Ok(events),
+		};
+		if self.ranks.iter().count() < self.n {
+			self.admit(key, value, &mut events)?;
+			return Ok(events);
+		}
+		if let Some(((min_value, min_key), _)) = self.ranks.first()? {
+			if (&value, &key) > (&min_value, &min_key) {
+				self.evict(min_key, min_value, &mut events)?;
+				self.admit(key, value, &mut events)?;
+			}
+		}
+		Ok(events)
+	}
+}
+
+impl<P> MaterialTopK<P>
+where
+	P: Watch + Sync + Send,
+	P::Key: Ord,
+	P::Value: Ord,
+{
+	pub(crate) fn new(from: TopK<P>) -> Self {
+		let reader = from.from.watch();
+		let fwd = Loaded::new();
+		let ranks = Loaded::new();
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		let top = Top {
+			from: from.from.clone(),
+			n: from.n,
+			fwd: fwd.clone(),
+			ranks: ranks.clone(),
+		};
+		spawn_watcher(Arc::clone(&sync), reader, Arc::clone(&bus), move |event| {
+			top.handle(event)
+		});
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			fwd,
+			ranks,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the top from the source view
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.ranks.clear()?;
+		let mut top = BTreeSet::new();
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			top.insert((v, k));
+			if top.len() > self.from.n {
+				top.pop_first();
+			}
+		}
+		for (v, k) in top {
+			self.fwd.insert_ref(&k, &v)?;
+			self.ranks.insert_owned((v, k), ())?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+	/// Gets the top entries, highest value first
+	pub fn ranked(&self) -> Result<Vec<(P::Key, P::Value)>> {
+		self.sync.wait_checked()?;
+		let ranks = self.ranks.iter().collect::<Result<Vec<_>>>()?;
+		Ok(ranks.into_iter().rev().map(|((v, k), _)| (k, v)).collect())
+	}
+}
+
+impl<P> View for MaterialTopK<P>
+where
+	P: View,
+	P::Key: Ord,
+	P::Value: Ord,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = <Loaded<P::Key, P::Value> as View>::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P> for MaterialTopK<P>
+	where
+		P: View,
+		P::Key: Ord,
+		P::Value: Ord,
+);
+impl<P> Watch for MaterialTopK<P>
+where
+	P: Watch,
+	P::Key: Ord,
+	P::Value: Ord,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P> Load for TopK<P>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+	<P as View>::Value: Ord,
+{
+	type Loaded = MaterialTopK<P>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let res = MaterialTopK::new(self.clone());
+		res.rebuild()?;
+		Ok(res)
+	}
+}
@@ -1,10 +1,15 @@
+mod reduce;
 mod store;
 
-use anyhow::Result;
 use delegate::delegate;
-use std::sync::Arc;
+use std::{fmt, sync::Arc};
 
-use crate::traits::{change::Change, serial::Serial, view::View, watch::Watch};
+use crate::{
+	error::Result,
+	traits::{change::Change, serial::Serial, view::View, watch::Watch},
+};
+
+pub use self::reduce::{MaterialReduceByKey, ReduceByKey};
 
 type Transformer<K, V, NK, NV> = dyn Fn(&K, &V) -> Vec<(NK, NV)> + Send + Sync;
 
@@ -46,6 +51,14 @@ where
 		}
 	}
 }
+impl<P, K, V> fmt::Debug for Transform<P, K, V>
+where
+	P: View + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Transform").field(&self.from).finish()
+	}
+}
 
 impl<P, K, V> Transform<P, K, V>
 where
@@ -60,6 +73,13 @@ where
 		let transformer = Arc::new(transformer);
 		Transform { from, transformer }
 	}
+	/// Reduces the values that share a key into one, instead of collecting them. Please refer to [ReduceByKey]
+	pub fn reduce_by_key<Reducer>(self, reducer: Reducer) -> ReduceByKey<P, K, V>
+	where
+		Reducer: 'static + Fn(V, &V) -> V + Sync + Send,
+	{
+		ReduceByKey::new(self, reducer)
+	}
 }
 
 impl<P, K, V> Change for Transform<P, K, V>
@@ -0,0 +1,330 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	macros::{cloned, into_iter},
+	structs::stable_vec::StableVec,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+use super::{store::MaterialTransform, Transform};
+
+type ReduceFn<V> = dyn Fn(V, &V) -> V + Send + Sync;
+
+/// Folds the values of a key into one, in the order they were added.
+fn reduce<V: Clone>(reducer: &ReduceFn<V>, values: &[V]) -> Option<V> {
+	let (first, rest) = values.split_first()?;
+	Some(rest.iter().fold(first.clone(), reducer))
+}
+
+/// A struct that transforms entries and reduces the values that share a key into one.
+/// You can create a [ReduceByKey] from a [Transform] with [reduce_by_key](Transform::reduce_by_key).
+///
+/// The values of each key are kept as in a [Transform], and a key is folded again from all of its values
+/// whenever one of them changes, so the reducer doesn't need to be invertible, as in a maximum.
+///
+/// [ReduceByKey] doesn't implement [View] or [Watch], you must store or load it first.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let by_len = tree
+///   .transform(|k, v| vec![(k.len() as u32, *v)])
+///   .reduce_by_key(|a, b| a + b)
+///   .load()
+///   .unwrap();
+///
+/// tree.insert("ab", 1u32).unwrap();
+/// tree.insert("cd", 2u32).unwrap();
+/// tree.remove("ab").unwrap();
+///
+/// assert_eq!(by_len.get(2u32).unwrap(), Some(2));
+/// ```
+pub struct ReduceByKey<Previous, Key, Value>
+where
+	Previous: View,
+{
+	reducer: Arc<ReduceFn<Value>>,
+	from: Transform<Previous, Key, Value>,
+}
+impl<P, K, V> Clone for ReduceByKey<P, K, V>
+where
+	P: View,
+{
+	fn clone(&self) -> Self {
+		Self {
+			reducer: Arc::clone(&self.reducer),
+			from: self.from.clone(),
+		}
+	}
+}
+
+impl<P, K, V> ReduceByKey<P, K, V>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new<Reducer>(from: Transform<P, K, V>, reducer: Reducer) -> Self
+	where
+		Reducer: 'static + Fn(V, &V) -> V + Sync + Send,
+	{
+		let reducer = Arc::new(reducer);
+		ReduceByKey { from, reducer }
+	}
+}
+
+/// A stored or loaded [ReduceByKey].
+///
+/// It keeps the stored or loaded [Transform] for the values of each key, next to the reduced values.
+pub struct MaterialReduceByKey<P, K, V, F, B, R>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+	R: Clone,
+{
+	from: ReduceByKey<P, K, V>,
+	transform: MaterialTransform<P, K, V, F, B>,
+	fwd: R,
+	watcher: Watcher<K, V>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, K, V, F, B, R> Clone for MaterialReduceByKey<P, K, V, F, B, R>
+where
+	P: View,
+	F: Clone,
+	B: Clone,
+	R: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			transform: self.transform.clone(),
+			fwd: self.fwd.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, K, V, F, B, R> MaterialReduceByKey<P, K, V, F, B, R>
+where
+	P: Watch,
+	K: 'static + Clone + Send + Sync,
+	V: 'static + Clone + Send + Sync,
+	F: Clone + View<Key = K, Value = StableVec<V>>,
+	B: View,
+	R: Clone + View<Key = K, Value = V> + Change<Key = K, Value = V, Insert = V> + Send + Sync,
+{
+	pub(crate) fn new(
+		from: ReduceByKey<P, K, V>,
+		transform: MaterialTransform<P, K, V, F, B>,
+		fwd: R,
+	) -> Self {
+		let reader = transform.watch();
+		let reducer = Arc::clone(&from.reducer);
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![transform.sync()]));
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(fwd, move |event| {
+				// The transform sends all the values of a key, so it is folded from scratch
+				let reduced = match &event {
+					Event::Insert { value, .. } => reduce(&*reducer, value),
+					Event::Remove { .. } => None,
+				};
+				let key = Arc::clone(event.key());
+				match reduced {
+					Some(value) => {
+						fwd.insert_ref(&key, &value)?;
+						let value = Arc::new(value);
+						Ok(vec![Event::Insert { key, value }])
+					}
+					None => {
+						fwd.remove_ref(&key)?;
+						Ok(vec![Event::Remove { key }])
+					}
+				}
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			transform,
+			fwd,
+			watcher,
+			sync,
+		}
+	}
+	/// Reduces every key of the transform again
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		for res in self.transform.iter() {
+			let (k, values) = res?;
+			if let Some(value) = reduce(&*self.from.reducer, &values) {
+				self.fwd.insert_owned(k, value)?;
+			}
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the transform
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+}
+
+impl<P, K, V, F, B, R> View for MaterialReduceByKey<P, K, V, F, B, R>
+where
+	P: View,
+	K: 'static + Clone + Send + Sync,
+	V: 'static + Clone + Send + Sync,
+	F: 'static + Clone,
+	B: 'static + Clone,
+	R: Clone + View<Key = K, Value = V>,
+{
+	type Key = K;
+	type Value = V;
+	type Iter = R::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, K, V, F, B, R> for MaterialReduceByKey<P, K, V, F, B, R>
+	where
+		P: View,
+		K: 'static + Clone + Send + Sync,
+		V: 'static + Clone + Send + Sync,
+		F: 'static + Clone,
+		B: 'static + Clone,
+		R: Clone + View<Key = K, Value = V>,
+);
+impl<P, K, V, F, B, R> Watch for MaterialReduceByKey<P, K, V, F, B, R>
+where
+	P: Watch,
+	K: 'static + Clone + Send + Sync,
+	V: 'static + Clone + Send + Sync,
+	F: 'static + Clone,
+	B: 'static + Clone,
+	R: Clone + View<Key = K, Value = V>,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P, K, V> Store for ReduceByKey<P, K, V>
+where
+	P: Watch,
+	K: Serial + Hash + Eq,
+	V: Serial,
+	<P as View>::Key: Serial,
+	StableVec<(K, usize)>: Serial,
+{
+	type Stored = MaterialReduceByKey<
+		P,
+		K,
+		V,
+		Tree<K, StableVec<V>>,
+		Tree<<P as View>::Key, StableVec<(K, usize)>>,
+		Tree<K, V>,
+	>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.from.db();
+		let transform = self.from.store(&name)?;
+		let fwd = db.open_store_tree(&name, "reduced")?;
+		Ok(MaterialReduceByKey::new(self.clone(), transform, fwd))
+	}
+}
+
+impl<P, K, V> Load for ReduceByKey<P, K, V>
+where
+	P: Watch + View,
+	<P as View>::Key: Ord,
+	K: 'static + Clone + Send + Sync + Hash + Ord,
+	V: 'static + Clone + Send + Sync,
+{
+	type Loaded = MaterialReduceByKey<
+		P,
+		K,
+		V,
+		Loaded<K, StableVec<V>>,
+		Loaded<<P as View>::Key, StableVec<(K, usize)>>,
+		Loaded<K, V>,
+	>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let transform = self.from.load()?;
+		let fwd = Loaded::new();
+		let res = MaterialReduceByKey::new(self.clone(), transform, fwd);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
@@ -1,11 +1,11 @@
-use anyhow::Result;
 use bus::{Bus, BusReader};
 use delegate::delegate;
 use parking_lot::RwLock;
 use std::{collections::HashMap, hash::Hash, sync::Arc};
 
 use crate::{
-	macros::{cloned, hash, unwrap_or_return},
+	error::Result,
+	macros::{cloned, into_iter, unwrap_or_return},
 	structs::stable_vec::StableVec,
 	threads::{spawn_watcher, Synchronizer},
 	traits::{serial::Serial, watch::Watcher},
@@ -20,6 +20,9 @@ use crate::traits::{
 	watch::{Event, Watch},
 };
 
+#[cfg(feature = "rayon")]
+use crate::helpers::{par_group, REBUILD_CHUNK};
+
 use super::Transform;
 
 pub struct MaterialTransform<P, K, V, F, B>
@@ -145,6 +148,9 @@ where
 	pub fn rebuild(&self) -> Result<()> {
 		self.fwd.clear()?;
 		self.bwd.clear()?;
+		#[cfg(feature = "rayon")]
+		self.rebuild_chunks()?;
+		#[cfg(not(feature = "rayon"))]
 		for res in self.from.from.iter() {
 			let (k, v) = res?;
 			let entries = (self.from.transformer)(&k, &v);
@@ -170,6 +176,197 @@ where
 		self.sync.reset();
 		Ok(())
 	}
+	/// Transforms the source a chunk at a time, with the `rayon` feature.
+	/// The entries of a chunk are grouped by key in parallel, then each key is written once.
+	/// Only [REBUILD_CHUNK] entries of the source are held in memory at once.
+	#[cfg(feature = "rayon")]
+	fn rebuild_chunks(&self) -> Result<()> {
+		let transformer = &self.from.transformer;
+		let mut iter = self.from.from.iter();
+		loop {
+			let chunk = iter.by_ref().take(REBUILD_CHUNK).collect::<Result<Vec<_>>>()?;
+			if chunk.is_empty() {
+				return Ok(());
+			}
+			let groups = par_group(&chunk, |(k, v)| transformer(k, v));
+			// The keys each entry of the chunk was transformed into, along with the position of its values
+			let mut links = vec![Vec::new(); chunk.len()];
+			for (k, members) in groups {
+				let (positions, values): (Vec<_>, Vec<_>) = members.into_iter().unzip();
+				let mut entry = self.fwd.entry_ref(&k)?;
+				let stored = entry.or_insert_with(StableVec::new);
+				let indexes = stored.extend(values.into_iter());
+				for (p, index) in positions.into_iter().zip(indexes) {
+					links[p].push((k.clone(), index));
+				}
+			}
+			for ((k, _), links) in chunk.into_iter().zip(links) {
+				let mut entry = self.bwd.entry(k)?;
+				entry.or_insert_with(StableVec::new).extend(links.into_iter());
+			}
+		}
+	}
+	/// Gets the values under a key, to push or remove them one by one.
+	/// Please refer to [TransformEntry]
+	pub fn bucket(&self, key: K) -> Result<TransformEntry<'_, P, K, V, F, B>> {
+		self.sync.wait_checked()?;
+		let values = self.fwd.get_ref(&key)?.unwrap_or_default();
+		Ok(TransformEntry {
+			key,
+			values,
+			removed: Vec::new(),
+			changed: false,
+			from: self,
+		})
+	}
+}
+
+/// The values of a [MaterialTransform] under a single key.
+///
+/// Changes are written back when the entry is dropped, and sent as a single event.
+/// Pushed values don't come from an entry of the source, so they stay until removed or rebuilt.
+/// Removing a value that came from the source also unlinks it from that entry,
+/// which walks the backward map of the transform.
+///
+/// The source should not change while an entry is held, or one of the changes may be lost.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// let transform = tree.transform(|k, v| vec![(*v, *k)]).load().unwrap();
+/// tree.insert(1u32, 0u32).unwrap();
+/// tree.insert(2u32, 0u32).unwrap();
+///
+/// let mut bucket = transform.bucket(0).unwrap();
+/// bucket.remove(0);
+/// bucket.push(3);
+/// drop(bucket);
+///
+/// assert_eq!(transform.get(0u32).unwrap(), Some(vec![3, 2]));
+/// ```
+pub struct TransformEntry<'a, P, K, V, F, B>
+where
+	P: View,
+	K: 'static + Clone + Send + Sync + Hash + Eq,
+	V: 'static + Clone + Send + Sync,
+	F: Clone
+		+ View<Key = K, Value = StableVec<V>>
+		+ Change<Key = K, Value = StableVec<V>, Insert = StableVec<V>>,
+	B: Clone
+		+ View<Key = <P as View>::Key, Value = StableVec<(K, usize)>>
+		+ Change<
+			Key = <P as View>::Key,
+			Value = StableVec<(K, usize)>,
+			Insert = StableVec<(K, usize)>,
+		>,
+{
+	key: K,
+	values: StableVec<V>,
+	removed: Vec<usize>,
+	changed: bool,
+	from: &'a MaterialTransform<P, K, V, F, B>,
+}
+impl<'a, P, K, V, F, B> TransformEntry<'a, P, K, V, F, B>
+where
+	P: View,
+	K: 'static + Clone + Send + Sync + Hash + Eq,
+	V: 'static + Clone + Send + Sync,
+	F: Clone
+		+ View<Key = K, Value = StableVec<V>>
+		+ Change<Key = K, Value = StableVec<V>, Insert = StableVec<V>>,
+	B: Clone
+		+ View<Key = <P as View>::Key, Value = StableVec<(K, usize)>>
+		+ Change<
+			Key = <P as View>::Key,
+			Value = StableVec<(K, usize)>,
+			Insert = StableVec<(K, usize)>,
+		>,
+{
+	/// The current values under the key
+	pub fn values(&self) -> Vec<&V> {
+		self.values.to_vec()
+	}
+	/// Appends a value to the key
+	pub fn push(&mut self, value: V) {
+		self.values.push(value);
+		self.changed = true;
+	}
+	/// Removes the value at an index of [values](TransformEntry::values)
+	pub fn remove(&mut self, index: usize) -> Option<V> {
+		let position = self.values.position(index)?;
+		self.changed = true;
+		self.removed.push(position);
+		self.values.take(position)
+	}
+	/// Removes every value that doesn't match the predicate
+	pub fn retain(&mut self, f: impl Fn(&V) -> bool) {
+		let positions = self
+			.values
+			.slots()
+			.filter(|(_, v)| !f(v))
+			.map(|(i, _)| i)
+			.collect::<Vec<_>>();
+		for position in positions {
+			self.changed = true;
+			self.removed.push(position);
+			self.values.take(position);
+		}
+	}
+	/// Writes the values back, unlinking the removed ones from the source entries
+	fn write(&mut self) -> Result<()> {
+		let from = self.from;
+		if !self.removed.is_empty() {
+			for res in from.bwd.iter() {
+				let (k, mut links) = res?;
+				let unlinked = links
+					.slots()
+					.filter(|(_, (key, p))| *key == self.key && self.removed.contains(p))
+					.map(|(i, _)| i)
+					.collect::<Vec<_>>();
+				if unlinked.is_empty() {
+					continue;
+				}
+				for i in unlinked {
+					links.take(i);
+				}
+				from.bwd.insert_owned(k, links)?;
+			}
+		}
+		let key = Arc::new(self.key.clone());
+		from.sync.outgoing(1);
+		if self.values.is_empty() {
+			from.fwd.remove_ref(&self.key)?;
+			from.watcher.send(Event::Remove { key });
+		} else {
+			from.fwd.insert_ref(&self.key, &self.values)?;
+			let value = Arc::new(self.values.to_vec().into_iter().cloned().collect());
+			from.watcher.send(Event::Insert { key, value });
+		}
+		Ok(())
+	}
+}
+impl<'a, P, K, V, F, B> Drop for TransformEntry<'a, P, K, V, F, B>
+where
+	P: View,
+	K: 'static + Clone + Send + Sync + Hash + Eq,
+	V: 'static + Clone + Send + Sync,
+	F: Clone
+		+ View<Key = K, Value = StableVec<V>>
+		+ Change<Key = K, Value = StableVec<V>, Insert = StableVec<V>>,
+	B: Clone
+		+ View<Key = <P as View>::Key, Value = StableVec<(K, usize)>>
+		+ Change<
+			Key = <P as View>::Key,
+			Value = StableVec<(K, usize)>,
+			Insert = StableVec<(K, usize)>,
+		>,
+{
+	fn drop(&mut self) {
+		if self.changed {
+			self.write().unwrap();
+		}
+	}
 }
 
 impl<P, K, V, F, B> View for MaterialTransform<P, K, V, F, B>
@@ -184,7 +381,7 @@ where
 	type Value = Vec<V>;
 	type Iter = Box<dyn Iterator<Item = Result<(K, Vec<V>)>>>;
 	fn get_ref(&self, key: &K) -> Result<Option<Vec<V>>> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let v = self.fwd.get_ref(key)?;
 		let v = unwrap_or_return!(v);
 		Ok(Some(v.into_vec()))
@@ -193,14 +390,14 @@ where
 		Box::new(self.fwd.iter().map(|v| v.map(|(k, v)| (k, v.into_vec()))))
 	}
 	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.fwd.contains_key_ref(key)
 	}
 	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.get_lt_ref(key)?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -210,7 +407,7 @@ where
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.get_gt_ref(key)?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -220,7 +417,7 @@ where
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.first()?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -230,7 +427,7 @@ where
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let e = self.fwd.last()?;
 		let e = unwrap_or_return!(e);
 		let (k, v) = e;
@@ -240,11 +437,20 @@ where
 		self.from.from.is_empty()
 	}
 	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		let iter = self.fwd.range(range)?;
 		Ok(Box::new(iter.map(|v| v.map(|(k, v)| (k, v.into_vec())))))
 	}
 }
+into_iter!(
+	impl<P, K, V, F, B> for MaterialTransform<P, K, V, F, B>
+	where
+		P: View,
+		K: 'static + Clone + Send + Sync,
+		V: 'static + Clone + Send + Sync,
+		F: Clone + View<Key = K, Value = StableVec<V>>,
+		B: View,
+);
 impl<P, K, V, F, B> Change for MaterialTransform<P, K, V, F, B>
 where
 	P: View + Change,
@@ -294,8 +500,6 @@ where
 	}
 }
 
-use std::collections::hash_map::DefaultHasher;
-use std::hash::Hasher;
 
 impl<P, K, V> Store for Transform<P, K, V>
 where
@@ -314,10 +518,8 @@ where
 	>;
 	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
 		let db = self.from.db();
-		let fwd = hash!(name, "fwd");
-		let bwd = hash!(name, "bwd");
-		let fwd = db.open_tree(fwd)?;
-		let bwd = db.open_tree(bwd)?;
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let bwd = db.open_store_tree(&name, "bwd")?;
 		Ok(MaterialTransform::new(self.clone(), fwd, bwd))
 	}
 }
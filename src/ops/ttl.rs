@@ -0,0 +1,450 @@
+use bus::{Bus, BusReader};
+use parking_lot::{Mutex, RwLock};
+use std::{
+	hash::Hash,
+	ops::RangeBounds,
+	sync::{
+		atomic::{AtomicBool, Ordering::Relaxed},
+		Arc,
+	},
+	time::Duration,
+};
+
+use crate::{
+	error::Result,
+	helpers::now_millis,
+	macros::{cloned, into_iter},
+	threads::{broadcast, spawn_listener, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+/// A struct that expires entries a while after they were inserted.
+/// You can create a [Ttl] from a [View] struct.
+///
+/// Each entry is kept along with the time it was inserted, and is hidden from reads once it expires.
+/// A thread sweeps the expired entries every interval, removing them and sending their [Remove](Event::Remove) events,
+/// so the views built on top are updated too.
+/// The interval is the time to live itself, unless set with [sweep_every](Ttl::sweep_every).
+/// The thread stops once every clone of the stored view is dropped.
+/// With the `sync` feature there is no thread, so the entries are only swept by [sweep](MaterialTtl::sweep).
+///
+/// [Ttl] doesn't implement [View] or [Watch], you must store it first.
+/// A loaded [Ttl] starts the time of every entry over, as the times aren't kept.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # use std::time::Duration;
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let sessions = tree.ttl(Duration::from_millis(50)).load().unwrap();
+///
+/// tree.insert("session", 1u32).unwrap();
+/// assert_eq!(sessions.get("session").unwrap(), Some(1));
+///
+/// std::thread::sleep(Duration::from_millis(60));
+/// assert_eq!(sessions.get("session").unwrap(), None);
+/// ```
+pub struct Ttl<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	ttl: Duration,
+	interval: Duration,
+}
+impl<P: View> Clone for Ttl<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			ttl: self.ttl,
+			interval: self.interval,
+		}
+	}
+}
+
+impl<P> Ttl<P>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new(from: P, ttl: Duration) -> Self {
+		Ttl {
+			from,
+			ttl,
+			interval: ttl,
+		}
+	}
+	/// Sets how often the expired entries are swept
+	pub fn sweep_every(mut self, interval: Duration) -> Self {
+		self.interval = interval;
+		self
+	}
+}
+
+/// Checks if an entry inserted at a time is still alive
+fn is_live(inserted_at: u64, ttl: Duration) -> bool {
+	now_millis() < inserted_at.saturating_add(ttl.as_millis() as u64)
+}
+
+/// Stops the sweeper thread once dropped
+struct Sweeper {
+	stopped: Arc<AtomicBool>,
+	thread: std::thread::Thread,
+}
+impl Drop for Sweeper {
+	fn drop(&mut self) {
+		self.stopped.store(true, Relaxed);
+		self.thread.unpark();
+	}
+}
+
+/// The parts of a [MaterialTtl] that are shared with its threads.
+///
+/// The lock keeps the sweeper from removing an entry while an event changes it,
+/// and it is held while the events are sent, so they go out in the order of the changes.
+struct Shared<S, K, V> {
+	store: S,
+	ttl: Duration,
+	lock: Mutex<()>,
+	bus: Arc<RwLock<Bus<Event<K, V>>>>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<S, K, V> Shared<S, K, V>
+where
+	S: View<Key = K, Value = (u64, V)> + Change<Key = K, Value = (u64, V), Insert = (u64, V)>,
+	K: 'static + Clone + Send + Sync,
+	V: 'static + Clone + Send + Sync,
+{
+	fn send(&self, event: Event<K, V>) {
+		self.sync.outgoing(1);
+		broadcast(&self.bus, event);
+	}
+	fn handle(&self, event: Event<K, V>) -> Result<()> {
+		let _guard = self.lock.lock();
+		match &event {
+			Event::Insert { key, value } => {
+				self.store
+					.insert_owned((**key).clone(), (now_millis(), (**value).clone()))?;
+			}
+			Event::Remove { key } => {
+				self.store.remove_ref(key)?;
+			}
+		}
+		self.send(event);
+		Ok(())
+	}
+	fn sweep(&self) -> Result<usize> {
+		let mut expired = Vec::new();
+		for res in self.store.iter() {
+			let (key, (inserted_at, _)) = res?;
+			if !is_live(inserted_at, self.ttl) {
+				expired.push(key);
+			}
+		}
+		let mut removed = 0;
+		for key in expired {
+			let _guard = self.lock.lock();
+			// It could have been inserted again since
+			match self.store.get_ref(&key)? {
+				Some((inserted_at, _)) if !is_live(inserted_at, self.ttl) => (),
+				_ => continue,
+			}
+			self.store.remove_ref(&key)?;
+			self.send(Event::Remove { key: Arc::new(key) });
+			removed += 1;
+		}
+		Ok(removed)
+	}
+}
+
+/// A stored or loaded [Ttl].
+///
+/// The store holds the values along with the time they were inserted, in milliseconds since the epoch.
+pub struct MaterialTtl<P, S>
+where
+	P: View,
+{
+	from: Ttl<P>,
+	shared: Arc<Shared<S, P::Key, P::Value>>,
+	sweeper: Option<Arc<Sweeper>>,
+	watcher: Watcher<P::Key, P::Value>,
+}
+
+impl<P, S> Clone for MaterialTtl<P, S>
+where
+	P: View,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			shared: Arc::clone(&self.shared),
+			sweeper: self.sweeper.clone(),
+			watcher: self.watcher.clone(),
+		}
+	}
+}
+
+impl<P, S> MaterialTtl<P, S>
+where
+	P: Watch + Sync + Send,
+	S: 'static
+		+ Clone
+		+ View<Key = P::Key, Value = (u64, P::Value)>
+		+ Change<Key = P::Key, Value = (u64, P::Value), Insert = (u64, P::Value)>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: Ttl<P>, store: S) -> Self {
+		let reader = from.from.watch();
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		let shared = Arc::new(Shared {
+			store,
+			ttl: from.ttl,
+			lock: Mutex::new(()),
+			bus: Arc::clone(&bus),
+			sync: Arc::clone(&sync),
+		});
+		// The events are sent by the callback itself, so that they are sent under the lock
+		spawn_listener(
+			sync,
+			reader,
+			cloned!(shared, move |event| {
+				shared.handle(event)?;
+				Ok(0)
+			}),
+		);
+		#[cfg(not(feature = "sync"))]
+		let sweeper = Some(Self::spawn_sweeper(Arc::clone(&shared), from.interval));
+		#[cfg(feature = "sync")]
+		let sweeper = None;
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			shared,
+			sweeper,
+			watcher,
+		}
+	}
+	/// Spawns the thread that sweeps the expired entries every interval
+	#[cfg(not(feature = "sync"))]
+	fn spawn_sweeper(shared: Arc<Shared<S, P::Key, P::Value>>, interval: Duration) -> Arc<Sweeper> {
+		let stopped = Arc::new(AtomicBool::new(false));
+		let handle = std::thread::spawn(cloned!(stopped, move || {
+			loop {
+				std::thread::park_timeout(interval);
+				if stopped.load(Relaxed) {
+					break;
+				}
+				if let Err(e) = shared.sweep() {
+					shared.sync.fail(e);
+				}
+			}
+		}));
+		Arc::new(Sweeper {
+			stopped,
+			thread: handle.thread().clone(),
+		})
+	}
+	/// Rebuilds the view from its source, every entry being inserted now
+	pub fn rebuild(&self) -> Result<()> {
+		let _guard = self.shared.lock.lock();
+		self.shared.store.clear()?;
+		let now = now_millis();
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			self.shared.store.insert_owned(k, (now, v))?;
+		}
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.shared.sync.reset();
+		Ok(())
+	}
+	/// Removes the expired entries, sending their events, and returns how many were removed
+	pub fn sweep(&self) -> Result<usize> {
+		self.shared.sync.wait_checked()?;
+		self.shared.sweep()
+	}
+	/// Gets a value along with the time it expires, in milliseconds since the epoch
+	pub fn get_with_expiry(&self, key: &P::Key) -> Result<Option<(P::Value, u64)>> {
+		let value = self.get_ref(key)?;
+		let entry = self.shared.store.get_ref(key)?;
+		Ok(value.zip(entry).map(|(value, (inserted_at, _))| {
+			(value, inserted_at + self.shared.ttl.as_millis() as u64)
+		}))
+	}
+}
+
+impl<P, S> MaterialTtl<P, S>
+where
+	P: View,
+	S: View<Key = P::Key, Value = (u64, P::Value)>,
+{
+	/// Filters an iterator of the store down to the entries that didn't expire
+	fn live_iter(&self, iter: S::Iter) -> <Self as View>::Iter {
+		let ttl = self.shared.ttl;
+		Box::new(iter.filter_map(move |r| match r {
+			Ok((k, (inserted_at, v))) => is_live(inserted_at, ttl).then_some(Ok((k, v))),
+			Err(e) => Some(Err(e)),
+		}))
+	}
+	/// Finds the closest entry that didn't expire, going forward or backward from a key
+	fn seek(&self, key: &P::Key, forward: bool) -> Result<Option<(P::Key, P::Value)>>
+	where
+		P::Key: Ord,
+	{
+		let mut key = key.clone();
+		loop {
+			let entry = match forward {
+				true => self.shared.store.get_gt_ref(&key)?,
+				false => self.shared.store.get_lt_ref(&key)?,
+			};
+			match entry {
+				Some((k, (inserted_at, v))) if is_live(inserted_at, self.shared.ttl) => {
+					return Ok(Some((k, v)))
+				}
+				Some((k, _)) => key = k,
+				None => return Ok(None),
+			}
+		}
+	}
+	/// Finds the first or last entry that didn't expire
+	fn edge(&self, forward: bool) -> Result<Option<(P::Key, P::Value)>>
+	where
+		P::Key: Ord,
+	{
+		let entry = match forward {
+			true => self.shared.store.first()?,
+			false => self.shared.store.last()?,
+		};
+		match entry {
+			Some((k, (inserted_at, v))) if is_live(inserted_at, self.shared.ttl) => {
+				Ok(Some((k, v)))
+			}
+			Some((k, _)) => self.seek(&k, forward),
+			None => Ok(None),
+		}
+	}
+}
+
+impl<P, S> View for MaterialTtl<P, S>
+where
+	P: View,
+	S: View<Key = P::Key, Value = (u64, P::Value)>,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.shared.sync.wait_checked()?;
+		let entry = self.shared.store.get_ref(key)?;
+		let entry = entry.filter(|(inserted_at, _)| is_live(*inserted_at, self.shared.ttl));
+		Ok(entry.map(|(_, v)| v))
+	}
+	fn iter(&self) -> Self::Iter {
+		self.shared.sync.wait();
+		self.live_iter(self.shared.store.iter())
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.get_ref(key)?.is_some())
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.shared.sync.wait_checked()?;
+		self.seek(key, false)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.shared.sync.wait_checked()?;
+		self.seek(key, true)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.shared.sync.wait_checked()?;
+		self.edge(true)
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.shared.sync.wait_checked()?;
+		self.edge(false)
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.shared.sync.wait();
+		match self.shared.store.is_empty() {
+			Some(true) => Some(true),
+			_ => None,
+		}
+	}
+	fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.shared.sync.wait_checked()?;
+		Ok(self.live_iter(self.shared.store.range(range)?))
+	}
+}
+into_iter!(
+	impl<P, S> for MaterialTtl<P, S>
+	where
+		P: View,
+		S: View<Key = P::Key, Value = (u64, P::Value)>,
+);
+impl<P, S> Watch for MaterialTtl<P, S>
+where
+	P: Watch,
+	S: View<Key = P::Key, Value = (u64, P::Value)>,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.shared.sync)
+	}
+	fn wait(&self) {
+		self.shared.sync.wait()
+	}
+}
+
+impl<P> Store for Ttl<P>
+where
+	P: Watch + Sync + Send,
+	<P as View>::Key: Serial,
+	(u64, <P as View>::Value): Serial,
+{
+	type Stored = MaterialTtl<P, Tree<<P as View>::Key, (u64, P::Value)>>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let store = db.open_store_tree(&name, "store")?;
+		Ok(MaterialTtl::new(self.clone(), store))
+	}
+}
+
+impl<P> Load for Ttl<P>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+{
+	type Loaded = MaterialTtl<P, Loaded<<P as View>::Key, (u64, P::Value)>>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let res = MaterialTtl::new(self.clone(), Loaded::new());
+		res.rebuild()?;
+		Ok(res)
+	}
+}
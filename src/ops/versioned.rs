@@ -0,0 +1,346 @@
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{hash::Hash, sync::Arc};
+
+use crate::{
+	error::Result,
+	helpers::now_millis,
+	macros::{cloned, into_iter},
+	structs::single::Single,
+	threads::{spawn_watcher, Synchronizer},
+	traits::{
+		change::Change,
+		load::{Load, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::{database::Db, tree::Tree},
+};
+
+/// The versions of a key, oldest first, as `(version, milliseconds since the epoch, value)`.
+/// A removal is kept as a version without a value.
+pub type History<V> = Vec<(u64, u64, Option<V>)>;
+
+/// A struct that keeps the last versions of each key.
+/// You can create a [Versioned] from a [View] struct.
+///
+/// Every change gets the next version number, counted across all keys, along with the time it was handled.
+/// Reading a key gets its latest value, and [get_at](MaterialVersioned::get_at) gets the value it had at a version.
+/// Each key keeps up to a number of versions, the older ones are dropped.
+///
+/// [Versioned] doesn't implement [View] or [Watch], you must store it first.
+/// A loaded versioned view starts with a single version for each entry, as the history isn't kept.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate, Load};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let versioned = tree.versioned(10).load().unwrap();
+///
+/// tree.insert("key", 1u32).unwrap();
+/// let version = versioned.version().unwrap();
+/// tree.insert("key", 2u32).unwrap();
+///
+/// assert_eq!(versioned.get("key").unwrap(), Some(2));
+/// assert_eq!(versioned.get_at(&"key".to_string(), version).unwrap(), Some(1));
+/// ```
+pub struct Versioned<Previous>
+where
+	Previous: View,
+{
+	from: Previous,
+	limit: usize,
+}
+impl<P: View> Clone for Versioned<P> {
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			limit: self.limit,
+		}
+	}
+}
+
+impl<P> Versioned<P>
+where
+	P: View + Watch,
+{
+	pub(crate) fn new(from: P, limit: usize) -> Self {
+		Versioned { from, limit }
+	}
+}
+
+/// A stored or loaded [Versioned].
+///
+/// The counter holds the last version, under the `()` key.
+/// The forward map holds the latest value of each key, and the history map holds its versions.
+pub struct MaterialVersioned<P, C, F, H>
+where
+	P: View,
+	C: Clone,
+	F: Clone,
+	H: Clone,
+{
+	from: Versioned<P>,
+	counter: C,
+	fwd: F,
+	history: H,
+	watcher: Watcher<P::Key, P::Value>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<P, C, F, H> Clone for MaterialVersioned<P, C, F, H>
+where
+	P: View,
+	C: Clone,
+	F: Clone,
+	H: Clone,
+{
+	fn clone(&self) -> Self {
+		Self {
+			from: self.from.clone(),
+			counter: self.counter.clone(),
+			fwd: self.fwd.clone(),
+			history: self.history.clone(),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<P, C, F, H> MaterialVersioned<P, C, F, H>
+where
+	P: Watch + Sync + Send,
+	C: Clone
+		+ View<Key = (), Value = u64>
+		+ Change<Key = (), Value = u64, Insert = u64>
+		+ Send
+		+ Sync,
+	F: Clone
+		+ View<Key = P::Key, Value = P::Value>
+		+ Change<Key = P::Key, Value = P::Value, Insert = P::Value>
+		+ Send
+		+ Sync,
+	H: Clone
+		+ View<Key = P::Key, Value = History<P::Value>>
+		+ Change<Key = P::Key, Value = History<P::Value>, Insert = History<P::Value>>
+		+ Send
+		+ Sync,
+{
+	pub(crate) fn new(from: Versioned<P>, counter: C, fwd: F, history: H) -> Self {
+		let reader = from.from.watch();
+		let limit = from.limit;
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let sync = Arc::new(Synchronizer::from(vec![from.from.sync()]));
+		// A single watcher thread, so the versions follow the order of the events
+		spawn_watcher(
+			Arc::clone(&sync),
+			reader,
+			Arc::clone(&bus),
+			cloned!(counter, fwd, history, move |event| {
+				let version = counter.get_ref(&())?.unwrap_or(0) + 1;
+				counter.insert_owned((), version)?;
+				let (key, value) = match &event {
+					Event::Insert { key, value } => {
+						fwd.insert_ref(key, value)?;
+						(key, Some((**value).clone()))
+					}
+					Event::Remove { key } => {
+						fwd.remove_ref(key)?;
+						(key, None)
+					}
+				};
+				let mut versions = history.get_ref(key)?.unwrap_or_default();
+				versions.push((version, now_millis(), value));
+				if versions.len() > limit {
+					versions.drain(..versions.len() - limit);
+				}
+				history.insert_ref(key, &versions)?;
+				Ok(vec![event])
+			}),
+		);
+		let watcher = Watcher::new(move || bus);
+		Self {
+			from,
+			counter,
+			fwd,
+			history,
+			watcher,
+			sync,
+		}
+	}
+	/// Rebuilds the view from its source, each entry getting a single version
+	pub fn rebuild(&self) -> Result<()> {
+		self.fwd.clear()?;
+		self.history.clear()?;
+		let mut version = self.counter.get_ref(&())?.unwrap_or(0);
+		let time = now_millis();
+		for res in self.from.from.iter() {
+			let (k, v) = res?;
+			version += 1;
+			self.fwd.insert_ref(&k, &v)?;
+			self.history
+				.insert_owned(k, vec![(version, time, Some(v))])?;
+		}
+		self.counter.insert_owned((), version)?;
+		// The sync needs to be reset
+		// For the received field to be equal to the outgoing field in the source
+		// Otherwise they would never be equal, and it would wait forever on get
+		self.sync.reset();
+		Ok(())
+	}
+	/// Gets the last version, or 0 if nothing was changed yet
+	pub fn version(&self) -> Result<u64> {
+		self.sync.wait_checked()?;
+		Ok(self.counter.get_ref(&())?.unwrap_or(0))
+	}
+	/// Gets the value a key had at a version
+	/// Returns [None] if the key was removed or didn't exist then, or if that version was already dropped
+	pub fn get_at(&self, key: &P::Key, version: u64) -> Result<Option<P::Value>> {
+		self.sync.wait_checked()?;
+		let versions = self.history.get_ref(key)?.unwrap_or_default();
+		let value = versions
+			.into_iter()
+			.rev()
+			.find(|(v, _, _)| *v <= version)
+			.and_then(|(_, _, value)| value);
+		Ok(value)
+	}
+	/// Gets the versions kept for a key, oldest first
+	pub fn history(&self, key: &P::Key) -> Result<History<P::Value>> {
+		self.sync.wait_checked()?;
+		Ok(self.history.get_ref(key)?.unwrap_or_default())
+	}
+}
+
+impl<P, C, F, H> View for MaterialVersioned<P, C, F, H>
+where
+	P: View,
+	C: 'static + Clone,
+	F: Clone + View<Key = P::Key, Value = P::Value>,
+	H: 'static + Clone,
+{
+	type Key = P::Key;
+	type Value = P::Value;
+	type Iter = F::Iter;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		self.sync.wait_checked()?;
+		self.fwd.get_ref(key)
+	}
+	fn iter(&self) -> Self::Iter {
+		self.sync.wait();
+		self.fwd.iter()
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		self.sync.wait_checked()?;
+		self.fwd.contains_key_ref(key)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.sync.wait_checked()?;
+		self.fwd.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.sync.wait();
+		self.fwd.is_empty()
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		self.sync.wait_checked()?;
+		self.fwd.range(range)
+	}
+}
+into_iter!(
+	impl<P, C, F, H> for MaterialVersioned<P, C, F, H>
+	where
+		P: View,
+		C: 'static + Clone,
+		F: Clone + View<Key = P::Key, Value = P::Value>,
+		H: 'static + Clone,
+);
+impl<P, C, F, H> Watch for MaterialVersioned<P, C, F, H>
+where
+	P: Watch,
+	C: 'static + Clone,
+	F: Clone + View<Key = P::Key, Value = P::Value>,
+	H: 'static + Clone,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		self.from.from.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
+}
+
+impl<P> Store for Versioned<P>
+where
+	P: Watch + Sync + Send,
+	<P as View>::Key: Serial,
+	<P as View>::Value: Serial,
+	History<<P as View>::Value>: Serial,
+{
+	type Stored = MaterialVersioned<
+		P,
+		Single<u64>,
+		Tree<<P as View>::Key, P::Value>,
+		Tree<<P as View>::Key, History<P::Value>>,
+	>;
+	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
+		let db = self.from.db();
+		let counter = db.open_store_single(&name, "counter")?;
+		let fwd = db.open_store_tree(&name, "fwd")?;
+		let history = db.open_store_tree(&name, "history")?;
+		Ok(MaterialVersioned::new(self.clone(), counter, fwd, history))
+	}
+}
+
+impl<P> Load for Versioned<P>
+where
+	P: Watch + View + Sync + Send,
+	<P as View>::Key: Ord,
+{
+	type Loaded = MaterialVersioned<
+		P,
+		Loaded<(), u64>,
+		Loaded<<P as View>::Key, P::Value>,
+		Loaded<<P as View>::Key, History<P::Value>>,
+	>;
+	fn load(&self) -> Result<Self::Loaded> {
+		let counter = Loaded::new();
+		let fwd = Loaded::new();
+		let history = Loaded::new();
+		let res = MaterialVersioned::new(self.clone(), counter, fwd, history);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
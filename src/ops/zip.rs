@@ -1,10 +1,11 @@
-use anyhow::Result;
 use bus::{Bus, BusReader};
 use parking_lot::RwLock;
-use std::{cmp::Ordering, collections::HashMap, hash::Hash, sync::Arc};
+use std::{cmp::Ordering, collections::HashMap, fmt, hash::Hash, sync::Arc};
 
 use crate::{
-	macros::cloned,
+	error::Result,
+	macros::{cloned, into_iter},
+	structs::merge::Merge,
 	threads::{spawn_watcher, Synchronizer},
 	traits::{
 		view::View,
@@ -16,6 +17,9 @@ type ZipItem<A, B> = (Option<<A as View>::Value>, Option<<B as View>::Value>);
 
 /// A struct that zips two views together.
 /// You can create a [Zip] from two [View] structs, as long as they have the same key type.
+///
+/// Iterating a [Zip] merges both views, so the entries come in ascending key order.
+/// For keys that can be hashed but not ordered, use a [ZipUnordered] instead.
 /// # Examples
 /// ```
 /// # use husky::{Tree, View, Change, Operate};
@@ -55,6 +59,15 @@ where
 		}
 	}
 }
+impl<A, B> fmt::Debug for Zip<A, B>
+where
+	A: View + fmt::Debug,
+	B: View<Key = A::Key> + fmt::Debug,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Zip").field(&self.a).field(&self.b).finish()
+	}
+}
 
 impl<A, B> Zip<A, B>
 where
@@ -117,7 +130,7 @@ impl<A, B> View for Zip<A, B>
 where
 	A: View,
 	B: View<Key = A::Key>,
-	<A as View>::Key: Hash + Eq,
+	<A as View>::Key: Ord,
 {
 	type Key = A::Key;
 	type Value = (Option<A::Value>, Option<B::Value>);
@@ -131,25 +144,8 @@ where
 		}
 	}
 	fn iter(&self) -> Self::Iter {
-		let a = self.a.iter();
-		let b = self.b.iter();
-		let mut map = HashMap::new();
-		let mut err = Vec::new();
-		a.into_iter().for_each(|r| match r {
-			Ok((k, v)) => {
-				let e = map.entry(k).or_insert((None, None));
-				e.0 = Some(v);
-			}
-			Err(e) => err.push(Err(e)),
-		});
-		b.into_iter().for_each(|r| match r {
-			Ok((k, v)) => {
-				let e = map.entry(k).or_insert((None, None));
-				e.1 = Some(v);
-			}
-			Err(e) => err.push(Err(e)),
-		});
-		Box::new(err.into_iter().chain(map.into_iter().map(Ok)))
+		let merge = Merge::new(self.a.iter(), self.b.iter());
+		Box::new(merge.map(|r| r.map(|(k, a, b)| (k, (a, b)))))
 	}
 	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
 		Ok(self.a.contains_key_ref(key)? || self.b.contains_key_ref(key)?)
@@ -287,27 +283,17 @@ where
 	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
 		let a = (range.start_bound(), range.end_bound());
 		let b = (range.start_bound(), range.end_bound());
-		let a = self.a.range(a)?;
-		let b = self.b.range(b)?;
-		let mut map = HashMap::new();
-		let mut err = Vec::new();
-		a.for_each(|r| match r {
-			Ok((k, v)) => {
-				let e = map.entry(k).or_insert((None, None));
-				e.0 = Some(v);
-			}
-			Err(e) => err.push(Err(e)),
-		});
-		b.for_each(|r| match r {
-			Ok((k, v)) => {
-				let e = map.entry(k).or_insert((None, None));
-				e.1 = Some(v);
-			}
-			Err(e) => err.push(Err(e)),
-		});
-		Ok(Box::new(err.into_iter().chain(map.into_iter().map(Ok))))
+		let merge = Merge::new(self.a.range(a)?, self.b.range(b)?);
+		Ok(Box::new(merge.map(|r| r.map(|(k, a, b)| (k, (a, b))))))
 	}
 }
+into_iter!(
+	impl<A, B> for Zip<A, B>
+	where
+		A: View,
+		B: View<Key = A::Key>,
+		<A as View>::Key: Ord,
+);
 
 impl<A, B> Watch for Zip<A, B>
 where
@@ -329,3 +315,390 @@ where
 		self.b.wait();
 	}
 }
+
+/// A [Zip] whose keys only need to be hashable, for views over keys that can't be ordered.
+/// You can create a [ZipUnordered] from two [View] structs with [zip_unordered](crate::Operate::zip_unordered).
+///
+/// Iterating it collects both views in a [HashMap], so the entries come in no particular order.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let a_tree: Tree<String, u32> = db.open_tree("a").unwrap();
+/// # let b_tree: Tree<String, u32> = db.open_tree("b").unwrap();
+/// let zip = a_tree.zip_unordered(&b_tree);
+///
+/// a_tree.insert("key", 1u32).unwrap();
+/// b_tree.insert("key", 2u32).unwrap();
+///
+/// assert_eq!(zip.get("key").unwrap(), Some((Some(1), Some(2))));
+/// ```
+pub struct ZipUnordered<A, B>(Zip<A, B>)
+where
+	A: View,
+	B: View<Key = A::Key>;
+impl<A, B> Clone for ZipUnordered<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+{
+	fn clone(&self) -> Self {
+		Self(self.0.clone())
+	}
+}
+
+impl<A, B> ZipUnordered<A, B>
+where
+	A: View + Watch + Sync + Send,
+	B: View<Key = <A as View>::Key> + Watch + Sync + Send,
+{
+	pub(crate) fn new(a: A, b: B) -> Self {
+		Self(Zip::new(a, b))
+	}
+}
+
+type UnorderedIter<K, A, B> = Box<dyn Iterator<Item = Result<(K, (Option<A>, Option<B>))>>>;
+
+/// Collects the entries of two iterators by key
+fn collect_unordered<K, A, B>(
+	a: impl Iterator<Item = Result<(K, A)>>,
+	b: impl Iterator<Item = Result<(K, B)>>,
+) -> UnorderedIter<K, A, B>
+where
+	K: 'static + Hash + Eq,
+	A: 'static,
+	B: 'static,
+{
+	let mut map = HashMap::new();
+	let mut err = Vec::new();
+	a.for_each(|r| match r {
+		Ok((k, v)) => map.entry(k).or_insert((None, None)).0 = Some(v),
+		Err(e) => err.push(Err(e)),
+	});
+	b.for_each(|r| match r {
+		Ok((k, v)) => map.entry(k).or_insert((None, None)).1 = Some(v),
+		Err(e) => err.push(Err(e)),
+	});
+	Box::new(err.into_iter().chain(map.into_iter().map(Ok)))
+}
+
+impl<A, B> View for ZipUnordered<A, B>
+where
+	A: View,
+	B: View<Key = A::Key>,
+	<A as View>::Key: Hash + Eq,
+{
+	type Key = A::Key;
+	type Value = (Option<A::Value>, Option<B::Value>);
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		let a = self.0.a.get_ref(key)?;
+		let b = self.0.b.get_ref(key)?;
+		match (&a, &b) {
+			(None, None) => Ok(None),
+			_ => Ok(Some((a, b))),
+		}
+	}
+	fn iter(&self) -> Self::Iter {
+		collect_unordered(self.0.a.iter(), self.0.b.iter())
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.0.a.contains_key_ref(key)? || self.0.b.contains_key_ref(key)?)
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.0.get_lt_ref(key)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.0.get_gt_ref(key)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.0.first()
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.0.last()
+	}
+	fn is_empty(&self) -> Option<bool> {
+		match (self.0.a.is_empty(), self.0.b.is_empty()) {
+			(Some(a), Some(b)) => Some(a && b),
+			(Some(false), None) | (None, Some(false)) => Some(false),
+			_ => None,
+		}
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		let a = (range.start_bound(), range.end_bound());
+		let b = (range.start_bound(), range.end_bound());
+		Ok(collect_unordered(self.0.a.range(a)?, self.0.b.range(b)?))
+	}
+}
+into_iter!(
+	impl<A, B> for ZipUnordered<A, B>
+	where
+		A: View,
+		B: View<Key = A::Key>,
+		<A as View>::Key: Hash + Eq,
+);
+
+impl<A, B> Watch for ZipUnordered<A, B>
+where
+	A: View + Watch,
+	B: View<Key = A::Key> + Watch,
+	<A as View>::Key: Hash + Eq,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.0.watcher.new_reader()
+	}
+	fn db(&self) -> crate::wrappers::database::Db {
+		self.0.a.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.0.sync)
+	}
+	fn wait(&self) {
+		self.0.a.wait();
+		self.0.b.wait();
+	}
+}
+
+type Combiner<A, B, O> = dyn Fn(&<A as View>::Key, Option<&<A as View>::Value>, Option<&<B as View>::Value>) -> Option<O>
+	+ Send
+	+ Sync;
+
+/// A struct that zips two views together, then combines both sides of each key.
+/// You can create a [ZipWith] from two [View] structs, as long as they have the same key type.
+///
+/// It works as a [Zip] followed by a filter map, keys are skipped when the combiner returns [None].
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// # let a_tree: Tree<String, u32> = db.open_tree("a").unwrap();
+/// # let b_tree: Tree<String, u32> = db.open_tree("b").unwrap();
+///
+/// let both = a_tree.zip_with(&b_tree, |_, a, b| Some(a? + b?));
+///
+/// a_tree.insert("key", 1u32).unwrap();
+/// assert_eq!(both.get("key").unwrap(), None);
+///
+/// b_tree.insert("key", 2u32).unwrap();
+/// assert_eq!(both.get("key").unwrap(), Some(3));
+/// ```
+pub struct ZipWith<A, B, Out>
+where
+	A: View,
+	B: View<Key = A::Key>,
+{
+	zip: Zip<A, B>,
+	combiner: Arc<Combiner<A, B, Out>>,
+	watcher: Watcher<A::Key, Out>,
+	sync: Arc<Synchronizer>,
+}
+impl<A, B, O> Clone for ZipWith<A, B, O>
+where
+	A: View,
+	B: View<Key = A::Key>,
+{
+	fn clone(&self) -> Self {
+		Self {
+			zip: self.zip.clone(),
+			combiner: Arc::clone(&self.combiner),
+			watcher: self.watcher.clone(),
+			sync: Arc::clone(&self.sync),
+		}
+	}
+}
+
+impl<A, B, Out> ZipWith<A, B, Out>
+where
+	A: View + Watch + Sync + Send,
+	B: View<Key = <A as View>::Key> + Watch + Sync + Send,
+	<A as View>::Key: Hash + Ord,
+	Out: 'static + Clone + Send + Sync,
+{
+	pub(crate) fn new<C>(a: A, b: B, combiner: C) -> Self
+	where
+		C: 'static + Fn(&A::Key, Option<&A::Value>, Option<&B::Value>) -> Option<Out> + Sync + Send,
+	{
+		let zip = Zip::new(a, b);
+		let combiner: Arc<Combiner<A, B, Out>> = Arc::new(combiner);
+		let sync = Arc::new(Synchronizer::from(vec![zip.sync()]));
+		let watcher = Watcher::new(cloned!(sync, zip, combiner, move || {
+			let bus = Arc::new(RwLock::new(Bus::new(128)));
+			spawn_watcher(sync, zip.watch(), Arc::clone(&bus), move |event| {
+				let (key, value) = match event {
+					Event::Insert { key, value } => {
+						let (a, b) = &*value;
+						let value = combiner(&key, a.as_ref(), b.as_ref());
+						(key, value)
+					}
+					Event::Remove { key } => (key, None),
+				};
+				let event = match value {
+					Some(value) => Event::Insert {
+						key,
+						value: Arc::new(value),
+					},
+					None => Event::Remove { key },
+				};
+				Ok(vec![event])
+			});
+			bus
+		}));
+		ZipWith {
+			zip,
+			combiner,
+			watcher,
+			sync,
+		}
+	}
+}
+
+impl<A, B, Out> ZipWith<A, B, Out>
+where
+	A: View,
+	B: View<Key = A::Key>,
+	A::Key: Ord,
+	Out: 'static + Clone + Send + Sync,
+{
+	fn combine(&self, entry: Option<(A::Key, ZipItem<A, B>)>) -> Option<(A::Key, Out)> {
+		let (k, (a, b)) = entry?;
+		let value = (self.combiner)(&k, a.as_ref(), b.as_ref())?;
+		Some((k, value))
+	}
+	/// Walks the zipped keys from a starting key, until the combiner accepts one.
+	fn seek(&self, mut key: A::Key, forward: bool) -> Result<Option<(A::Key, Out)>> {
+		loop {
+			let entry = match forward {
+				true => self.zip.get_gt_ref(&key)?,
+				false => self.zip.get_lt_ref(&key)?,
+			};
+			key = match &entry {
+				Some((k, _)) => k.clone(),
+				None => return Ok(None),
+			};
+			if let Some(entry) = self.combine(entry) {
+				return Ok(Some(entry));
+			}
+		}
+	}
+	/// Starts from the first or last zipped key, then walks until the combiner accepts one.
+	fn edge(&self, forward: bool) -> Result<Option<(A::Key, Out)>> {
+		let entry = match forward {
+			true => self.zip.first()?,
+			false => self.zip.last()?,
+		};
+		let key = match &entry {
+			Some((k, _)) => k.clone(),
+			None => return Ok(None),
+		};
+		match self.combine(entry) {
+			Some(entry) => Ok(Some(entry)),
+			None => self.seek(key, forward),
+		}
+	}
+}
+
+impl<A, B, Out> View for ZipWith<A, B, Out>
+where
+	A: View,
+	B: View<Key = A::Key>,
+	A::Key: Ord,
+	Out: 'static + Clone + Send + Sync,
+{
+	type Key = A::Key;
+	type Value = Out;
+	type Iter = Box<dyn Iterator<Item = Result<(Self::Key, Self::Value)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		let a = self.zip.a.get_ref(key)?;
+		let b = self.zip.b.get_ref(key)?;
+		Ok((self.combiner)(key, a.as_ref(), b.as_ref()))
+	}
+	fn iter(&self) -> Self::Iter {
+		let combiner = Arc::clone(&self.combiner);
+		Box::new(self.zip.iter().filter_map(move |r| match r {
+			Ok((k, (a, b))) => combiner(&k, a.as_ref(), b.as_ref()).map(|v| Ok((k, v))),
+			Err(e) => Some(Err(e)),
+		}))
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.get_ref(key)?.is_some())
+	}
+	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.seek(key.clone(), false)
+	}
+	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.seek(key.clone(), true)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.edge(true)
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.edge(false)
+	}
+	fn is_empty(&self) -> Option<bool> {
+		match self.zip.is_empty() {
+			Some(true) => Some(true),
+			_ => None,
+		}
+	}
+	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		let combiner = Arc::clone(&self.combiner);
+		let iter = self.zip.range(range)?;
+		Ok(Box::new(iter.filter_map(move |r| match r {
+			Ok((k, (a, b))) => combiner(&k, a.as_ref(), b.as_ref()).map(|v| Ok((k, v))),
+			Err(e) => Some(Err(e)),
+		})))
+	}
+}
+into_iter!(
+	impl<A, B, Out> for ZipWith<A, B, Out>
+	where
+		A: View,
+		B: View<Key = A::Key>,
+		A::Key: Ord,
+		Out: 'static + Clone + Send + Sync,
+);
+
+impl<A, B, Out> Watch for ZipWith<A, B, Out>
+where
+	A: View + Watch,
+	B: View<Key = A::Key> + Watch,
+	<A as View>::Key: Hash + Ord,
+	Out: 'static + Clone + Send + Sync,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> crate::wrappers::database::Db {
+		self.zip.db()
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.zip.wait()
+	}
+}
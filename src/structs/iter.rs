@@ -1,6 +1,6 @@
-use std::marker::PhantomData;
+use std::{collections::VecDeque, marker::PhantomData};
 
-use anyhow::Result;
+use crate::error::Result;
 
 /// An iterator over a tree
 pub struct Iter<F, O, R>
@@ -38,3 +38,104 @@ where
 		self.from.next().map(|item| (self.operation)(item))
 	}
 }
+
+/// An iterator over the entries of a view in chunks, returned by [chunks](crate::View::chunks).
+///
+/// Stops after the first error, dropping the entries read for that chunk.
+pub struct Chunks<I> {
+	from: I,
+	size: usize,
+	done: bool,
+}
+
+impl<I> Chunks<I> {
+	pub(crate) fn new(from: I, size: usize) -> Self {
+		assert!(size != 0, "chunk size must be non-zero");
+		Self {
+			from,
+			size,
+			done: false,
+		}
+	}
+}
+
+impl<I, T> Iterator for Chunks<I>
+where
+	I: Iterator<Item = Result<T>>,
+{
+	type Item = Result<Vec<T>>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		let mut chunk = Vec::with_capacity(self.size);
+		while chunk.len() < self.size {
+			match self.from.next() {
+				Some(Ok(item)) => chunk.push(item),
+				Some(Err(e)) => {
+					self.done = true;
+					return Some(Err(e));
+				}
+				None => {
+					self.done = true;
+					break;
+				}
+			}
+		}
+		(!chunk.is_empty()).then_some(Ok(chunk))
+	}
+}
+
+/// An iterator over overlapping windows of the entries of a view, returned by [windows](crate::View::windows).
+///
+/// Stops after the first error.
+pub struct Windows<I, T> {
+	from: I,
+	size: usize,
+	window: VecDeque<T>,
+	done: bool,
+}
+
+impl<I, T> Windows<I, T> {
+	pub(crate) fn new(from: I, size: usize) -> Self {
+		assert!(size != 0, "window size must be non-zero");
+		Self {
+			from,
+			size,
+			window: VecDeque::with_capacity(size),
+			done: false,
+		}
+	}
+}
+
+impl<I, T> Iterator for Windows<I, T>
+where
+	I: Iterator<Item = Result<T>>,
+	T: Clone,
+{
+	type Item = Result<Vec<T>>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		if self.done {
+			return None;
+		}
+		if self.window.len() == self.size {
+			self.window.pop_front();
+		}
+		while self.window.len() < self.size {
+			match self.from.next() {
+				Some(Ok(item)) => self.window.push_back(item),
+				Some(Err(e)) => {
+					self.done = true;
+					return Some(Err(e));
+				}
+				None => {
+					self.done = true;
+					return None;
+				}
+			}
+		}
+		Some(Ok(self.window.iter().cloned().collect()))
+	}
+}
@@ -1,17 +1,19 @@
-use anyhow::Result;
 use bus::BusReader;
 use delegate::delegate;
-use std::{hash::Hash, ops::Deref, sync::Arc};
+use std::{fmt, hash::Hash, ops::Deref, sync::Arc};
 
 use crate::{
-	macros::cloned,
+	error::Result,
+	macros::{cloned, into_iter},
+	ops::diff::Diff,
+	structs::merge::Merge,
 	threads::{spawn_listener, Synchronizer},
-	wrappers::{database::Db, tree::Tree},
+	wrappers::{batch::Batch, database::Db, tree::Tree},
 };
 
 use crate::traits::{
 	change::Change,
-	load::{Load, Loaded},
+	load::{Load, LoadUnordered, Loaded, LoadedUnordered},
 	serial::Serial,
 	store::Store,
 	view::View,
@@ -42,6 +44,15 @@ where
 		}
 	}
 }
+impl<F, I> fmt::Debug for Material<F, I>
+where
+	F: View + Watch + fmt::Debug,
+	I: View + Change,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Material").field(&self.from).finish()
+	}
+}
 
 impl<From, Inner> Material<From, Inner>
 where
@@ -83,6 +94,67 @@ where
 	}
 }
 
+impl<From, Inner> Material<From, Inner>
+where
+	From: View + Watch<Key = <Inner as Change>::Key, Value = <Inner as Change>::Insert>,
+	Inner: View<Key = <From as View>::Key, Value = <From as View>::Value> + Change,
+	<From as View>::Key: Ord,
+	<From as View>::Value: PartialEq,
+{
+	/// Walks the source and the inner view side by side, yielding the keys where they differ
+	/// The source is the left side of each [Diff]
+	fn diffs(&self) -> impl Iterator<Item = Result<(From::Key, Diff<From::Value>)>> {
+		let merge = Merge::new(self.from.iter(), self.inner.iter());
+		merge.filter_map(|r| match r {
+			Ok((k, a, b)) => Diff::new(a.as_ref(), b.as_ref()).map(|diff| Ok((k, diff))),
+			Err(e) => Some(Err(e)),
+		})
+	}
+	/// Repairs the inner view by writing only the entries that differ from the source, returning how many were written
+	/// Unlike [rebuild](Material::rebuild), the entries that match are left alone, so the view is never empty meanwhile
+	/// Both views are read in key order, so it doesn't work with unordered loaded views
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change, Operate, Store};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+	/// tree.insert(1u32, 1u32).unwrap();
+	/// let stored = tree.map(|_, v| v * 2).store("stored").unwrap();
+	/// tree.insert(2u32, 2u32).unwrap();
+	///
+	/// // Entries written before the view existed are missing, as after a crash
+	/// assert!(!stored.verify().unwrap());
+	/// assert_eq!(stored.reconcile().unwrap(), 1);
+	/// assert_eq!(stored.get(1u32).unwrap(), Some(2));
+	/// assert!(stored.verify().unwrap());
+	/// ```
+	pub fn reconcile(&self) -> Result<usize> {
+		self.sync.wait_checked()?;
+		let mut count = 0;
+		for res in self.diffs() {
+			match res? {
+				(k, Diff::OnlyLeft(v) | Diff::Changed(v, _)) => {
+					self.inner.insert_owned(k, v)?;
+				}
+				(k, Diff::OnlyRight(_)) => {
+					self.inner.remove_owned(k)?;
+				}
+			}
+			count += 1;
+		}
+		Ok(count)
+	}
+	/// Checks if the inner view matches its source, without changing it
+	/// Both views are read in key order, so it doesn't work with unordered loaded views
+	pub fn verify(&self) -> Result<bool> {
+		self.sync.wait_checked()?;
+		match self.diffs().next() {
+			Some(res) => res.map(|_| false),
+			None => Ok(true),
+		}
+	}
+}
+
 impl<From, Inner> Deref for Material<From, Inner>
 where
 	From: View + Watch,
@@ -103,7 +175,7 @@ where
 	type Value = <Inner as View>::Value;
 	type Iter = Inner::Iter;
 	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.inner.get_ref(key)
 	}
 	fn iter(&self) -> Self::Iter {
@@ -111,35 +183,35 @@ where
 		self.inner.iter()
 	}
 	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.inner.contains_key_ref(key)
 	}
 	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.inner.get_lt_ref(key)
 	}
 	fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.inner.get_gt_ref(key)
 	}
 	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.inner.first()
 	}
 	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
 	where
 		Self::Key: Ord,
 	{
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.inner.last()
 	}
 	fn is_empty(&self) -> Option<bool> {
@@ -147,10 +219,20 @@ where
 		self.inner.is_empty()
 	}
 	fn range(&self, range: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
-		self.sync.wait();
+		self.sync.wait_checked()?;
 		self.inner.range(range)
 	}
 }
+into_iter!(
+	impl<From, Inner> for Material<From, Inner>
+	where
+		From: View + Watch,
+		Inner: View + Change,
+);
+/// Changes are written to the source view, and reach the inner view through a thread.
+/// Reads on a [Material] wait for the changes sent before them, so a change made through it
+/// is seen by the reads that follow on the same thread, without calling [wait](Watch::wait).
+/// Methods reached through [Deref] read the inner view as it is, without waiting.
 impl<From, Inner> Change for Material<From, Inner>
 where
 	From: View + Change + Watch,
@@ -167,11 +249,17 @@ where
       fn remove_owned(&self, key: <Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
       fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>>;
       fn clear(&self) -> Result<()>;
+      fn apply_batch(&self, batch: Batch<Self::Key, Self::Insert>) -> Result<()>;
       fn fetch_and_update(
         &self,
         key: &Self::Key,
         f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
       ) -> Result<Option<Self::Value>>;
+      fn update_and_fetch(
+        &self,
+        key: &Self::Key,
+        f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+      ) -> Result<Option<Self::Insert>>;
 	  }
 	}
 }
@@ -203,7 +291,8 @@ where
 {
 	type Stored = Material<Self, Tree<<T as View>::Key, <T as View>::Value>>;
 	fn store(&self, name: impl Hash) -> Result<Self::Stored> {
-		let inner = self.db().open_tree(name)?;
+		// Keeps the name of the store, so views stored before their parts were tracked open the same tree
+		let inner = self.db().open_named_store_tree(&name)?;
 		Ok(Material::new(self.clone(), inner))
 	}
 }
@@ -222,3 +311,17 @@ where
 		Ok(res)
 	}
 }
+
+impl<T> LoadUnordered for T
+where
+	T: View + Watch,
+	<T as View>::Key: Hash + Eq,
+{
+	type Loaded = Material<Self, LoadedUnordered<<T as View>::Key, <T as View>::Value>>;
+	fn load_unordered(&self) -> Result<Self::Loaded> {
+		let inner = LoadedUnordered::new();
+		let res = Material::new(self.clone(), inner);
+		res.rebuild()?;
+		Ok(res)
+	}
+}
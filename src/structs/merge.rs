@@ -0,0 +1,55 @@
+use std::{cmp::Ordering, iter::Peekable};
+
+use crate::error::Result;
+
+/// An iterator that merges two sorted iterators by key.
+/// Yields each key once, with the values found on each side.
+pub struct Merge<K, A, B, IA, IB>
+where
+	IA: Iterator<Item = Result<(K, A)>>,
+	IB: Iterator<Item = Result<(K, B)>>,
+{
+	a: Peekable<IA>,
+	b: Peekable<IB>,
+}
+
+impl<K, A, B, IA, IB> Merge<K, A, B, IA, IB>
+where
+	IA: Iterator<Item = Result<(K, A)>>,
+	IB: Iterator<Item = Result<(K, B)>>,
+{
+	pub fn new(a: IA, b: IB) -> Self {
+		Self {
+			a: a.peekable(),
+			b: b.peekable(),
+		}
+	}
+}
+
+impl<K, A, B, IA, IB> Iterator for Merge<K, A, B, IA, IB>
+where
+	K: Ord,
+	IA: Iterator<Item = Result<(K, A)>>,
+	IB: Iterator<Item = Result<(K, B)>>,
+{
+	type Item = Result<(K, Option<A>, Option<B>)>;
+
+	fn next(&mut self) -> Option<Self::Item> {
+		// Errors are yielded as soon as they are found
+		let ordering = match (self.a.peek(), self.b.peek()) {
+			(None, None) => return None,
+			(Some(Err(_)), _) | (Some(_), None) => Ordering::Less,
+			(_, Some(Err(_))) | (None, Some(_)) => Ordering::Greater,
+			(Some(Ok((ka, _))), Some(Ok((kb, _)))) => ka.cmp(kb),
+		};
+		match ordering {
+			Ordering::Less => Some(self.a.next()?.map(|(k, a)| (k, Some(a), None))),
+			Ordering::Greater => Some(self.b.next()?.map(|(k, b)| (k, None, Some(b)))),
+			Ordering::Equal => {
+				let a = self.a.next()?;
+				let b = self.b.next()?;
+				Some(a.and_then(|(k, a)| b.map(|(_, b)| (k, Some(a), Some(b)))))
+			}
+		}
+	}
+}
@@ -1,4 +1,5 @@
 pub mod iter;
 pub mod material;
+pub mod merge;
 pub mod single;
 pub mod stable_vec;
@@ -1,18 +1,60 @@
-use anyhow::Result;
-use std::marker::PhantomData;
+use bus::{Bus, BusReader};
+use parking_lot::RwLock;
+use std::{marker::PhantomData, ops::RangeBounds, sync::Arc};
 
-use crate::{helpers::deserialize_option, traits::serial::Serial};
+use crate::{
+	error::Result,
+	helpers::deserialize_option,
+	macros::into_iter,
+	threads::Synchronizer,
+	traits::{
+		change::Change,
+		serial::{deserialize_value, serialize_value, Serial},
+		view::View,
+		watch::{Event, Watch, Watcher},
+	},
+	wrappers::database::Db,
+};
 
 /// Represents an entry in the database's top level tree
 /// Can be used for singletons
+///
+/// It is also a tree with a single `()` key, so it can be operated on.
+/// # Examples
+/// ```
+/// # use husky::{View, Operate};
+/// # let db = husky::open_temp().unwrap();
+/// let single = db.open_single::<_, u32>("counter".to_string()).unwrap();
+/// let double = single.map(|_, v| v * 2);
+///
+/// single.insert(2u32).unwrap();
+///
+/// assert_eq!(double.get(()).unwrap(), Some(4));
+/// ```
 pub struct Single<V>
 where
 	V: Serial,
 {
 	db: sled::Db,
 	key: Vec<u8>,
+	watcher: Arc<Watcher<(), V>>,
+	sync: Arc<Synchronizer>,
 	v: PhantomData<V>,
 }
+impl<V> Clone for Single<V>
+where
+	V: Serial,
+{
+	fn clone(&self) -> Self {
+		Self {
+			db: self.db.clone(),
+			key: self.key.clone(),
+			watcher: Arc::clone(&self.watcher),
+			sync: Arc::clone(&self.sync),
+			v: PhantomData,
+		}
+	}
+}
 impl<V> Single<V>
 where
 	V: Serial,
@@ -24,28 +66,40 @@ where
 		K: Serial,
 	{
 		let key = key.serialize()?;
+		let sync = Synchronizer::new();
+		let watcher = Watcher::new(move || Arc::new(RwLock::new(Bus::new(128))));
+		let watcher = Arc::new(watcher);
 		Ok(Self {
 			db,
 			key,
+			watcher,
+			sync,
 			v: PhantomData,
 		})
 	}
 	/// Loads the value from the entry
 	pub fn get(&self) -> Result<Option<V>> {
+		self.sync.wait();
 		let value = self.db.get(&self.key)?;
 		deserialize_option(value.map(|v| v.to_vec()))
 	}
 	/// Inserts an owned value into the entry
 	pub fn insert_owned(&self, value: V) -> Result<Option<V>> {
-		let value = value.serialize()?;
-		let old_value = self.db.insert(self.key.clone(), value)?;
+		self.sync.outgoing(1);
+		let old_value = {
+			let value = serialize_value(&value)?;
+			self.db.insert(self.key.clone(), value)?
+		};
+		let value = Arc::new(value);
+		self.watcher.send(Event::Insert {
+			key: Arc::new(()),
+			value,
+		});
 		deserialize_option(old_value.map(|v| v.to_vec()))
 	}
 	/// Inserts a borrowed value into the entry
 	pub fn insert_ref(&self, value: &V) -> Result<Option<V>> {
-		let value = value.serialize()?;
-		let old_value = self.db.insert(self.key.clone(), value)?;
-		deserialize_option(old_value.map(|v| v.to_vec()))
+		self.insert_owned(value.clone())
 	}
 	/// Inserts something that can be converted into a value into the entry
 	pub fn insert<IV>(&self, value: IV) -> Result<Option<V>>
@@ -55,4 +109,120 @@ where
 		let value = value.into();
 		self.insert_owned(value)
 	}
+	/// Removes the value from the entry
+	pub fn remove(&self) -> Result<Option<V>> {
+		self.sync.outgoing(1);
+		self.watcher.send(Event::Remove { key: Arc::new(()) });
+		let old_value = self.db.remove(&self.key)?;
+		deserialize_option(old_value.map(|v| v.to_vec()))
+	}
+	/// Checks if the entry has a value
+	pub fn contains(&self) -> Result<bool> {
+		Ok(self.db.contains_key(&self.key)?)
+	}
+}
+
+impl<V> View for Single<V>
+where
+	V: Serial,
+{
+	type Key = ();
+	type Value = V;
+	type Iter = std::vec::IntoIter<Result<((), V)>>;
+	fn get_ref(&self, _: &Self::Key) -> Result<Option<Self::Value>> {
+		Single::get(self)
+	}
+	fn contains_key_ref(&self, _: &Self::Key) -> Result<bool> {
+		self.contains()
+	}
+	fn get_lt_ref(&self, _: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		Ok(None)
+	}
+	fn get_gt_ref(&self, _: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		Ok(None)
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		Ok(Single::get(self)?.map(|v| ((), v)))
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		Ok(Single::get(self)?.map(|v| ((), v)))
+	}
+	fn is_empty(&self) -> Option<bool> {
+		self.contains().ok().map(|c| !c)
+	}
+	fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		match range.contains(&()) {
+			true => Ok(self.iter()),
+			false => Ok(Vec::new().into_iter()),
+		}
+	}
+	fn iter(&self) -> Self::Iter {
+		let entry = Single::get(self).transpose().map(|r| r.map(|v| ((), v)));
+		entry.into_iter().collect::<Vec<_>>().into_iter()
+	}
+}
+into_iter!(
+	impl<V> for Single<V>
+	where
+		V: Serial,
+);
+
+impl<V> Change for Single<V>
+where
+	V: Serial,
+{
+	type Key = ();
+	type Value = V;
+	type Insert = V;
+	fn insert_owned(&self, _: Self::Key, value: Self::Insert) -> Result<Option<Self::Value>> {
+		Single::insert_owned(self, value)
+	}
+	fn remove_owned(&self, _: Self::Key) -> Result<Option<Self::Value>> {
+		Single::remove(self)
+	}
+	fn fetch_and_update(
+		&self,
+		_: &Self::Key,
+		mut f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+	) -> Result<Option<Self::Value>> {
+		// Keeps the value of the last attempt, which is the one that got written
+		let mut new = None;
+		let old_value = self.db.fetch_and_update(&self.key, |v| {
+			let value = v.and_then(|v| deserialize_value(v.to_vec()).ok());
+			new = f(value);
+			new.as_ref().and_then(|value| serialize_value(value).ok())
+		})?;
+		self.sync.outgoing(1);
+		let key = Arc::new(());
+		match new {
+			Some(value) => self.watcher.send(Event::Insert {
+				key,
+				value: Arc::new(value),
+			}),
+			None => self.watcher.send(Event::Remove { key }),
+		}
+		deserialize_option(old_value.map(|v| v.to_vec()))
+	}
+	fn clear(&self) -> Result<()> {
+		Single::remove(self)?;
+		Ok(())
+	}
+}
+
+impl<V> Watch for Single<V>
+where
+	V: Serial,
+{
+	fn watch(&self) -> BusReader<Event<Self::Key, Self::Value>> {
+		self.watcher.new_reader()
+	}
+	fn db(&self) -> Db {
+		Db::from(self.db.clone())
+	}
+	fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	fn wait(&self) {
+		self.sync.wait()
+	}
 }
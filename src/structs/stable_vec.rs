@@ -1,6 +1,4 @@
-use anyhow::Result;
-
-use crate::traits::serial::Serial;
+use crate::{error::Result, traits::serial::Serial};
 
 #[derive(Debug, Clone)]
 pub struct StableVec<T>(Vec<Option<T>>);
@@ -22,7 +20,7 @@ impl<T> StableVec<T> {
 		let free = self.0.iter().position(|x| x.is_none());
 		match free {
 			Some(index) => {
-        self.0.insert(index, Some(item));
+        self.0[index] = Some(item);
         index
 			}
 			_ => {
@@ -53,7 +51,7 @@ impl<T> StableVec<T> {
 		let mut indexes = Vec::with_capacity(to_insert);
 		for idx in free {
 			if let Some(item) = iter.next() {
-				self.0.insert(idx, Some(item));
+				self.0[idx] = Some(item);
 				indexes.push(idx);
 			} else {
 				break;
@@ -67,7 +65,27 @@ impl<T> StableVec<T> {
 		indexes
 	}
 	pub fn remove(&mut self, index: usize) {
-		self.0.insert(index, None);
+		self.take(index);
+	}
+	/// Empties a slot, returning what it held
+	pub fn take(&mut self, index: usize) -> Option<T> {
+		self.0.get_mut(index).and_then(|x| x.take())
+	}
+	/// The slot of the nth item, skipping empty slots
+	pub fn position(&self, nth: usize) -> Option<usize> {
+		self.0
+			.iter()
+			.enumerate()
+			.filter(|(_, x)| x.is_some())
+			.nth(nth)
+			.map(|(i, _)| i)
+	}
+	/// Iterates over the items along with their slots
+	pub fn slots(&self) -> impl Iterator<Item = (usize, &T)> {
+		self.0
+			.iter()
+			.enumerate()
+			.filter_map(|(i, x)| x.as_ref().map(|x| (i, x)))
 	}
 	pub fn to_vec(&self) -> Vec<&T> {
 		self.0
@@ -1,7 +1,18 @@
+use std::sync::Arc;
+
 use crate::{
+	batch::Batch,
 	database::Db,
-	ops::Operate,
-	traits::{change::Change, load::Load, serial::Serial, store::Store, view::View},
+	macros::cloned,
+	ops::{diff::Diff, paginate::Paginate, Operate},
+	traits::{
+		change::Change,
+		load::{Load, LoadUnordered, Loaded},
+		serial::Serial,
+		store::Store,
+		view::View,
+		watch::{Event, Watch},
+	},
 	tree::Tree,
 };
 
@@ -23,6 +34,118 @@ fn with_db(f: impl FnOnce(Db)) {
 	f(db);
 }
 
+#[test]
+fn config() {
+	let db = Db::builder()
+		.temporary(true)
+		.cache_capacity(1024)
+		.flush_every_ms(Some(1))
+		.mode(crate::Mode::LowSpace)
+		.open()
+		.unwrap();
+	let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+	insert(&tree, 2);
+	assert_u32(&tree, 2);
+}
+
+#[test]
+fn flush_all() {
+	let path = std::env::temp_dir().join(format!("husky_flush_all_{}", std::process::id()));
+	{
+		let db = crate::Config::new().path(&path).flush_every_ms(None).open().unwrap();
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		insert(&tree, 2);
+		assert!(db.flush_all().unwrap() > 0);
+	}
+	{
+		let db = crate::open(&path).unwrap();
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		assert_u32(&tree, 2);
+	}
+	std::fs::remove_dir_all(&path).unwrap();
+}
+
+#[test]
+fn debug() {
+	with_db(|db: Db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let other: Tree<u32, u32> = db.open_tree("other").unwrap();
+		let pipeline = tree.filter(|k, _| k % 2 == 0).map(|_, v| v * 2);
+		assert_eq!(format!("{:?}", pipeline), r#"Map(Filter(Tree("tree")))"#);
+
+		let zip = pipeline.zip(&other);
+		assert_eq!(
+			format!("{:?}", zip),
+			r#"Zip(Map(Filter(Tree("tree"))), Tree("other"))"#
+		);
+		let loaded = tree.map(|_, v| v + 1).load().unwrap();
+		assert_eq!(format!("{:?}", loaded), r#"Material(Map(Tree("tree")))"#);
+	})
+}
+
+#[test]
+fn clone_to() {
+	with_db(|db: Db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let copy: Tree<u32, u32> = db.open_tree("copy").unwrap();
+		let stored = copy.map(|_, v| v + 1).store("stored").unwrap();
+		insert(&tree, 2);
+
+		assert_eq!(tree.clone_to(&copy).unwrap(), TEST_SIZE as usize);
+		for (a, b) in tree.iter().zip(copy.iter()) {
+			assert_eq!(a.unwrap(), b.unwrap());
+		}
+		assert_eq!(copy.iter().count(), TEST_SIZE as usize);
+		assert_eq!(stored.get(3u32).unwrap(), Some(10));
+	})
+}
+
+#[test]
+fn watch_range() {
+	with_db(|db: Db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let mut range = tree.watch_range(10..20);
+		insert(&tree, 1);
+		tree.remove(15u32).unwrap();
+		tree.remove(25u32).unwrap();
+		range.wait();
+		let keys = std::iter::from_fn(|| range.try_recv().ok())
+			.map(|event| **event.key())
+			.collect::<Vec<_>>();
+		assert_eq!(keys, (10..20).chain([15]).collect::<Vec<_>>());
+
+		let tree: Tree<String, u32> = db.open_tree("strings").unwrap();
+		let mut users = tree.watch_prefix("users/");
+		tree.insert("users/alice", 1u32).unwrap();
+		tree.insert("posts/alice", 2u32).unwrap();
+		tree.insert("users/bob", 3u32).unwrap();
+		users.wait();
+		let keys = std::iter::from_fn(|| users.try_recv().ok())
+			.map(|event| (**event.key()).clone())
+			.collect::<Vec<_>>();
+		assert_eq!(keys, vec!["users/alice", "users/bob"]);
+	})
+}
+
+#[test]
+fn tree_stats() {
+	with_tree(|tree: Tree<u32, u32>| {
+		assert_eq!(tree.stats().unwrap(), Default::default());
+		insert(&tree, 1);
+		let stats = tree.stats().unwrap();
+		assert_eq!(stats.len, TEST_SIZE as usize);
+		assert_eq!(stats.len, tree.len());
+		assert!(stats.approximate_size_bytes > 0);
+
+		tree.insert(TEST_SIZE, TEST_SIZE).unwrap();
+		let grown = tree.approximate_size_bytes().unwrap();
+		assert!(grown > stats.approximate_size_bytes);
+		assert_eq!(tree.stats().unwrap().len, TEST_SIZE as usize + 1);
+		tree.remove(TEST_SIZE).unwrap();
+		assert_eq!(tree.stats().unwrap(), stats);
+	})
+}
+
 #[test]
 fn tree_name_persists() {
 	with_db(|db: Db| {
@@ -46,6 +169,59 @@ fn serial() {
 	assert_eq!(value, deserial);
 }
 
+#[test]
+fn serialize_into() {
+	let value = ("hello".to_string(), vec![1u32, 2, 3]);
+	let mut buffer = vec![0xff, 0xfe];
+	value.serialize_into(&mut buffer).unwrap();
+	assert_eq!(buffer[..2], [0xff, 0xfe]);
+	assert_eq!(buffer[2..], Serial::serialize(&value).unwrap()[..]);
+	let deserial: (String, Vec<u32>) = Serial::deserialize(buffer[2..].to_vec()).unwrap();
+	assert_eq!(value, deserial);
+
+	// The buffer of the writes is reused between values of different sizes
+	with_tree(|tree: Tree<u32, String>| {
+		tree.insert(1u32, "a".repeat(100)).unwrap();
+		tree.insert(2u32, "b".to_string()).unwrap();
+		assert_eq!(tree.get(1u32).unwrap(), Some("a".repeat(100)));
+		assert_eq!(tree.get(2u32).unwrap(), Some("b".to_string()));
+	})
+}
+
+#[cfg(feature = "bytecheck")]
+#[test]
+fn get_archived() {
+	use bytecheck::CheckBytes;
+	use rkyv::{Archive, Deserialize, Serialize};
+	#[derive(Archive, Serialize, Deserialize, Clone, Debug, PartialEq)]
+	#[archive_attr(derive(CheckBytes))]
+	struct Profile {
+		id: u32,
+		name: String,
+		scores: Vec<u64>,
+	}
+	with_tree(|tree: Tree<u32, Profile>| {
+		for i in 0..TEST_SIZE {
+			let profile = Profile {
+				id: i,
+				name: format!("profile {}", i),
+				scores: (0..1000).collect(),
+			};
+			tree.insert(i, profile).unwrap();
+		}
+		for i in 0..TEST_SIZE {
+			let profile = tree.get(i).unwrap().unwrap();
+			let id = tree.get_archived(&i, |p| p.id.value()).unwrap();
+			let name = tree.get_archived(&i, |p| p.name.to_string()).unwrap();
+			let score = tree.get_archived(&i, |p| p.scores[999].value()).unwrap();
+			assert_eq!(id, Some(profile.id));
+			assert_eq!(name, Some(profile.name));
+			assert_eq!(score, Some(999));
+		}
+		assert_eq!(tree.get_archived(&TEST_SIZE, |p| p.id.value()).unwrap(), None);
+	})
+}
+
 const TEST_SIZE: u32 = 40;
 fn insert<C: Change<Key = u32, Value = u32, Insert = u32>>(tree: &C, pow: u32) {
 	for i in 0..TEST_SIZE {
@@ -156,6 +332,271 @@ fn transform_reindex() {
 	})
 }
 
+#[test]
+fn reduce_by_key() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let sums = tree.transform(|k, v| vec![(k % 4, *v)]).reduce_by_key(|a, b| a + b);
+		let stored = sums.store("stored_sums").unwrap();
+		let loaded = sums.load().unwrap();
+		let maxes = tree
+			.transform(|k, v| vec![(k % 4, *v)])
+			.reduce_by_key(|a, b| a.max(*b))
+			.load()
+			.unwrap();
+
+		insert(&tree, 1);
+		let sum = |r: u32| (0..TEST_SIZE).filter(|i| i % 4 == r).sum::<u32>();
+		for r in 0..4 {
+			assert_eq!(stored.get(r).unwrap(), Some(sum(r)));
+			assert_eq!(loaded.get(r).unwrap(), Some(sum(r)));
+			assert_eq!(maxes.get(r).unwrap(), Some(TEST_SIZE - 4 + r));
+		}
+
+		// Removing the largest values retracts them from the sums and the maxes
+		for i in TEST_SIZE - 4..TEST_SIZE {
+			tree.remove(i).unwrap();
+		}
+		for r in 0..4 {
+			assert_eq!(stored.get(r).unwrap(), Some(sum(r) - (TEST_SIZE - 4 + r)));
+			assert_eq!(maxes.get(r).unwrap(), Some(TEST_SIZE - 8 + r));
+		}
+
+		remove(&tree);
+		assert!(stored.iter().next().is_none());
+		assert!(loaded.iter().next().is_none());
+	})
+}
+
+#[test]
+fn transform_bucket() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let transform = tree.transform(|k, v| vec![(v % 2, *k)]);
+		let stored = transform.store("stored_transform").unwrap();
+		let loaded = transform.load().unwrap();
+		insert(&tree, 1);
+
+		let evens = (0..TEST_SIZE).filter(|i| i % 2 == 0).collect::<Vec<_>>();
+		let odds = (0..TEST_SIZE).filter(|i| i % 2 == 1).collect::<Vec<_>>();
+		{
+			let mut bucket = stored.bucket(0).unwrap();
+			assert_eq!(bucket.remove(0), Some(0));
+			bucket.push(100);
+		}
+		{
+			let mut bucket = loaded.bucket(0).unwrap();
+			assert_eq!(bucket.remove(0), Some(0));
+			bucket.push(100);
+		}
+		let mut expected = evens.clone();
+		expected[0] = 100;
+		assert_eq!(stored.get(0u32).unwrap(), Some(expected.clone()));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(expected.clone()));
+		assert_eq!(stored.get(1u32).unwrap(), Some(odds.clone()));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(odds.clone()));
+
+		// The removed value was unlinked, so removing its source keeps the pushed value
+		tree.remove(0u32).unwrap();
+		tree.remove(2u32).unwrap();
+		expected.remove(1);
+		assert_eq!(stored.get(0u32).unwrap(), Some(expected.clone()));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(expected));
+		assert_eq!(stored.get(1u32).unwrap(), Some(odds.clone()));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(odds));
+	})
+}
+
+#[test]
+fn index_bucket() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let index = tree.index(|_, v| vec![v % 2]);
+		let stored = index.store("stored_index").unwrap();
+		let loaded = index.load().unwrap();
+		insert(&tree, 1);
+
+		{
+			let mut bucket = stored.bucket(0).unwrap();
+			assert_eq!(bucket.remove(0), Some(0));
+			bucket.push(1);
+		}
+		{
+			let mut bucket = loaded.bucket(0).unwrap();
+			assert_eq!(bucket.remove(0), Some(0));
+			bucket.push(1);
+		}
+		let mut expected = (0..TEST_SIZE).filter(|i| i % 2 == 0).collect::<Vec<_>>();
+		let odds = (0..TEST_SIZE).filter(|i| i % 2 == 1).collect::<Vec<_>>();
+		expected[0] = 1;
+		assert_eq!(stored.get(0u32).unwrap(), Some(expected.clone()));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(expected.clone()));
+		assert_eq!(stored.get(1u32).unwrap(), Some(odds.clone()));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(odds.clone()));
+
+		// Removing the unlinked key leaves the bucket as it is
+		tree.remove(0u32).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), Some(expected.clone()));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(expected.clone()));
+
+		// Removing the pushed key unlinks it from both buckets
+		tree.remove(1u32).unwrap();
+		expected.remove(0);
+		assert_eq!(stored.get(0u32).unwrap(), Some(expected.clone()));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(expected));
+		assert_eq!(stored.get(1u32).unwrap(), Some(odds[1..].to_vec()));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(odds[1..].to_vec()));
+	})
+}
+
+#[test]
+fn unique_index() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let index = tree.unique_index(|_, v| vec![v % 10]);
+		let stored = index.store("stored_unique_index").unwrap();
+		let loaded = index.load().unwrap();
+
+		stored.insert(1u32, 13u32).unwrap();
+		assert!(stored.insert(2u32, 23u32).is_err());
+		assert!(loaded.insert(2u32, 23u32).is_err());
+		assert!(loaded.insert(1u32, 3u32).is_ok());
+		assert_eq!(stored.get_by_index(3u32).unwrap(), Some((1, 3)));
+		assert_eq!(loaded.get_by_index(3u32).unwrap(), Some((1, 3)));
+		assert_eq!(tree.get(2u32).unwrap(), None);
+		tree.clear().unwrap();
+
+		tree.insert(1u32, 13u32).unwrap();
+		tree.insert(2u32, 23u32).unwrap();
+		assert_eq!(stored.get_by_index(3u32).unwrap(), Some((1, 13)));
+		assert_eq!(loaded.get_by_index(3u32).unwrap(), Some((1, 13)));
+		tree.remove(1u32).unwrap();
+		assert_eq!(stored.get(3u32).unwrap(), None);
+		assert_eq!(loaded.get(3u32).unwrap(), None);
+	})
+}
+
+#[test]
+fn unique_index_concurrent() {
+	use std::sync::{Arc, Barrier};
+	with_tree(|tree: Tree<u32, u32>| {
+		let index = tree.unique_index(|_, v| vec![*v]);
+		let stored = index.store("stored_unique_index_concurrent").unwrap();
+		let loaded = index.load().unwrap();
+
+		// Every thread claims the same index keys at once, through either view
+		let threads = 8;
+		let barrier = Arc::new(Barrier::new(threads as usize));
+		let handles = (0..threads)
+			.map(|t| {
+				cloned!(stored, loaded, barrier, move || {
+					barrier.wait();
+					let inserted = (0..TEST_SIZE).filter(|&v| match t % 2 {
+						0 => stored.insert(t * TEST_SIZE + v, v).is_ok(),
+						_ => loaded.insert(t * TEST_SIZE + v, v).is_ok(),
+					});
+					inserted.count()
+				})
+			})
+			.map(std::thread::spawn)
+			.collect::<Vec<_>>();
+		let inserted = handles.into_iter().map(|h| h.join().unwrap());
+		assert_eq!(inserted.sum::<usize>(), TEST_SIZE as usize);
+		assert_eq!(tree.len(), TEST_SIZE as usize);
+	})
+}
+
+#[test]
+fn map_keys() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let reversed = tree.map_keys(|k| TEST_SIZE - 1 - k);
+		let stored = reversed.store("stored_map_keys").unwrap();
+		let loaded = reversed.load().unwrap();
+
+		insert(&tree, 2);
+		for i in 0..TEST_SIZE {
+			assert_eq!(stored.get(TEST_SIZE - 1 - i).unwrap(), Some(i.pow(2)));
+			assert_eq!(loaded.get(TEST_SIZE - 1 - i).unwrap(), Some(i.pow(2)));
+		}
+		remove(&tree);
+		assert_none(&stored);
+		assert_none(&loaded);
+	})
+}
+
+#[test]
+fn map_keys_collision() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let halved = tree.map_keys(|k| k / 2);
+		let failing = halved.load().unwrap();
+		let overwriting = halved.clone().overwrite_on_collision().load().unwrap();
+
+		tree.insert(0u32, 0u32).unwrap();
+		tree.insert(1u32, 1u32).unwrap();
+		assert!(failing.get(0u32).is_err());
+		assert!(halved.load().is_err());
+		assert_eq!(overwriting.get(0u32).unwrap(), Some(1));
+
+		// Only the entry that holds the key retracts it
+		tree.remove(0u32).unwrap();
+		assert_eq!(overwriting.get(0u32).unwrap(), Some(1));
+		tree.remove(1u32).unwrap();
+		assert_eq!(overwriting.get(0u32).unwrap(), None);
+	})
+}
+
+#[test]
+fn first_key() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let loaded = tree.map(|_, v| *v).load().unwrap();
+		assert_eq!(tree.first_key().unwrap(), None);
+		assert_eq!(loaded.last_key().unwrap(), None);
+
+		insert(&tree, 2);
+		tree.remove(0u32).unwrap();
+		let first = tree.first().unwrap().map(|(k, _)| k);
+		let last = tree.last().unwrap().map(|(k, _)| k);
+		assert_eq!(first, Some(1));
+		assert_eq!(last, Some(TEST_SIZE - 1));
+		assert_eq!(tree.first_key().unwrap(), first);
+		assert_eq!(tree.last_key().unwrap(), last);
+		assert_eq!(loaded.first_key().unwrap(), first);
+		assert_eq!(loaded.last_key().unwrap(), last);
+
+		// The value isn't read, so it doesn't need to be valid
+		let (key, _) = tree.to_inner().last().unwrap().unwrap();
+		tree.to_inner().insert(key, Vec::new()).unwrap();
+		assert!(tree.last().is_err());
+		assert_eq!(tree.last_key().unwrap(), last);
+	})
+}
+
+#[test]
+fn flatten() {
+	with_tree(|tree: Tree<u32, Vec<u32>>| {
+		let flat = tree.flatten();
+		let stored = flat.store("stored_flatten").unwrap();
+		let loaded = flat.load().unwrap();
+		tree.insert(0u32, vec![1u32, 2, 3]).unwrap();
+		tree.insert(1u32, vec![4u32]).unwrap();
+		assert_eq!(stored.get((0u32, 2u64)).unwrap(), Some(3));
+		assert_eq!(loaded.iter().count(), 4);
+
+		// Replacing a list only sends the positions that changed
+		let mut reader = loaded.watch();
+		tree.insert(0u32, vec![1u32, 5]).unwrap();
+		loaded.wait();
+		let inserted = reader.recv().unwrap();
+		assert!(matches!(inserted, Event::Insert { key, value } if *key == (0, 1) && *value == 5));
+		assert!(matches!(reader.recv().unwrap(), Event::Remove { key } if *key == (0, 2)));
+		assert!(reader.try_recv().is_err());
+		let expected = vec![((0, 0), 1), ((0, 1), 5), ((1, 0), 4)];
+		assert_eq!(stored.iter().map(|r| r.unwrap()).collect::<Vec<_>>(), expected);
+		assert_eq!(loaded.iter().map(|r| r.unwrap()).collect::<Vec<_>>(), expected);
+
+		tree.remove(0u32).unwrap();
+		tree.insert(1u32, Vec::new()).unwrap();
+		assert_eq!(stored.iter().count(), 0);
+		assert_eq!(loaded.iter().count(), 0);
+	})
+}
+
 #[test]
 fn map() {
 	with_tree(|tree: Tree<u32, u32>| {
@@ -177,6 +618,30 @@ fn map() {
 	});
 }
 
+#[test]
+fn invertible_map() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let map = tree.invertible_map(|_, v| v * 2);
+		let stored = map.store("stored_invertible_map").unwrap();
+		let loaded = map.load().unwrap();
+
+		tree.insert(1u32, 1u32).unwrap();
+		assert_eq!(stored.get_key_for(&2).unwrap(), Some(1));
+		assert_eq!(loaded.get_key_for(&2).unwrap(), Some(1));
+
+		tree.insert(1u32, 3u32).unwrap();
+		assert_eq!(stored.get(1u32).unwrap(), Some(6));
+		assert_eq!(stored.get_key_for(&6).unwrap(), Some(1));
+		assert_eq!(stored.get_key_for(&2).unwrap(), None);
+		assert_eq!(loaded.get_key_for(&6).unwrap(), Some(1));
+		assert_eq!(loaded.get_key_for(&2).unwrap(), None);
+
+		tree.remove(1u32).unwrap();
+		assert_eq!(stored.get_key_for(&6).unwrap(), None);
+		assert_eq!(loaded.get_key_for(&6).unwrap(), None);
+	})
+}
+
 #[test]
 fn map_replaces() {
 	with_tree(|tree: Tree<u32, u32>| {
@@ -219,6 +684,153 @@ fn map_rebuild() {
 	});
 }
 
+#[cfg(feature = "metrics")]
+#[test]
+fn metrics() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let filtered = tree.filter(|k, _| k % 2 == 0).load().unwrap();
+		let stored = tree.map(|_, v| *v).store("stored_metrics").unwrap();
+		insert(&tree, 1);
+		filtered.wait();
+		stored.wait();
+
+		// Tree::stats counts the entries instead
+		assert_eq!(Watch::stats(&tree).emitted, TEST_SIZE as u64);
+		let stats = stored.stats();
+		assert_eq!((stats.received, stats.emitted, stats.lag), (TEST_SIZE as u64, TEST_SIZE as u64, 0));
+		// The filter is handled in the thread of the loaded view, which gets every event
+		assert_eq!(filtered.stats().received, TEST_SIZE as u64);
+		assert_eq!(filtered.iter().count(), TEST_SIZE as usize / 2);
+	})
+}
+
+#[cfg(feature = "tracing")]
+#[test]
+fn tracing_spans() {
+	use std::sync::atomic::{AtomicU64, Ordering::Relaxed};
+	use tracing::{field::Field, span, Event as TraceEvent, Metadata, Subscriber};
+
+	/// Counts the event spans of the materializations keyed by [i16], which no other test uses
+	struct Counter {
+		spans: Arc<AtomicU64>,
+		ids: AtomicU64,
+	}
+	#[derive(Default)]
+	struct Fields {
+		op: String,
+		key: String,
+	}
+	impl tracing::field::Visit for Fields {
+		fn record_str(&mut self, field: &Field, value: &str) {
+			match field.name() {
+				"op" => self.op = value.to_string(),
+				"key" => self.key = value.to_string(),
+				_ => {}
+			}
+		}
+		fn record_debug(&mut self, _: &Field, _: &dyn std::fmt::Debug) {}
+	}
+	impl Subscriber for Counter {
+		fn enabled(&self, _: &Metadata<'_>) -> bool {
+			true
+		}
+		fn new_span(&self, attrs: &span::Attributes<'_>) -> span::Id {
+			let mut fields = Fields::default();
+			attrs.record(&mut fields);
+			if attrs.metadata().name() == "event" && fields.op == "material" && fields.key == "i16" {
+				self.spans.fetch_add(1, Relaxed);
+			}
+			span::Id::from_u64(self.ids.fetch_add(1, Relaxed) + 1)
+		}
+		fn record(&self, _: &span::Id, _: &span::Record<'_>) {}
+		fn record_follows_from(&self, _: &span::Id, _: &span::Id) {}
+		fn event(&self, _: &TraceEvent<'_>) {}
+		fn enter(&self, _: &span::Id) {}
+		fn exit(&self, _: &span::Id) {}
+	}
+
+	let spans = Arc::new(AtomicU64::new(0));
+	let counter = Counter {
+		spans: Arc::clone(&spans),
+		ids: AtomicU64::new(0),
+	};
+	// Global, since the events are handled in other threads
+	tracing::subscriber::set_global_default(counter).unwrap();
+	with_tree(|tree: Tree<i16, u32>| {
+		let stored = tree.filter(|_, v| v % 2 == 0).store("stored_tracing").unwrap();
+		for i in 0..TEST_SIZE as i16 {
+			tree.insert(i, i as u32).unwrap();
+		}
+		stored.wait();
+		// The filter sends the odd values as removes, so the stored view handles every event
+		assert_eq!(spans.load(Relaxed), TEST_SIZE as u64);
+	})
+}
+
+#[test]
+fn reconcile() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 1);
+		let stored = tree.map(|_, v| v * v).store("stored_reconcile").unwrap();
+		stored.rebuild().unwrap();
+		assert!(stored.verify().unwrap());
+		assert_eq!(stored.reconcile().unwrap(), 0);
+
+		// Corrupts the inner tree directly, as a crash between writes would
+		let inner: &Tree<u32, u32> = &stored;
+		inner.insert(0u32, 7u32).unwrap();
+		inner.remove(1u32).unwrap();
+		inner.insert(TEST_SIZE, 1u32).unwrap();
+		assert!(!stored.verify().unwrap());
+		assert_eq!(stored.get(0u32).unwrap(), Some(7));
+
+		// Only the three broken entries are written
+		let mut reader = inner.watch();
+		assert_eq!(stored.reconcile().unwrap(), 3);
+		let mut writes = 0;
+		while reader.try_recv().is_ok() {
+			writes += 1;
+		}
+		assert_eq!(writes, 3);
+		assert!(stored.verify().unwrap());
+		assert_u32(&stored, 2);
+		assert_eq!(stored.get(TEST_SIZE).unwrap(), None);
+	});
+}
+
+#[test]
+fn merge_sorted() {
+	with_db(|db| {
+		let shards: Vec<Tree<u32, u32>> = (0..3)
+			.map(|i| db.open_tree(format!("shard_{i}")).unwrap())
+			.collect();
+		// Each shard gets the keys of its remainder, and every shard gets the multiples of 5
+		for i in 0..TEST_SIZE {
+			shards[(i % 3) as usize].insert(i, 1u32).unwrap();
+			if i % 5 == 0 {
+				for shard in &shards {
+					shard.insert(i, 1u32).unwrap();
+				}
+			}
+		}
+		let merged = crate::merge_sorted(shards.clone(), |_, a, b| a + b)
+			.collect::<crate::error::Result<Vec<_>>>()
+			.unwrap();
+		let keys = merged.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+		assert_eq!(keys, (0..TEST_SIZE).collect::<Vec<_>>());
+		for (k, v) in merged {
+			assert_eq!(v, if k % 5 == 0 { 3 } else { 1 });
+		}
+
+		// The resolver gets the values in the order of the views
+		shards[2].insert(TEST_SIZE, 2u32).unwrap();
+		shards[0].insert(TEST_SIZE, 1u32).unwrap();
+		let mut merged = crate::merge_sorted(shards, |_, a, b| a * 10 + b);
+		assert_eq!(merged.nth(TEST_SIZE as usize).unwrap().unwrap(), (TEST_SIZE, 12));
+		assert!(merged.next().is_none());
+	})
+}
+
 #[test]
 fn chain() {
 	with_db(|db| {
@@ -256,6 +868,80 @@ fn chain() {
 	});
 }
 
+#[test]
+fn chain_range() {
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		let chained = a.chain(&b);
+
+		// a holds the even keys, b holds the multiples of three
+		for i in (0..TEST_SIZE).filter(|i| i % 2 == 0) {
+			a.insert(i, 0u32).unwrap();
+		}
+		for i in (0..TEST_SIZE).filter(|i| i % 3 == 0) {
+			b.insert(i, 1u32).unwrap();
+		}
+
+		let entries = chained
+			.range(5..TEST_SIZE)
+			.unwrap()
+			.map(|r| r.unwrap())
+			.collect::<Vec<_>>();
+		let expected = (5..TEST_SIZE)
+			.filter(|i| i % 2 == 0 || i % 3 == 0)
+			.map(|i| (i, if i % 2 == 0 { 0 } else { 1 }))
+			.collect::<Vec<_>>();
+		assert_eq!(entries, expected);
+
+		// Keys in both trees keep the value of the first one, as in get
+		let all = (0..TEST_SIZE)
+			.filter(|i| i % 2 == 0 || i % 3 == 0)
+			.map(|i| (i, if i % 2 == 0 { 0 } else { 1 }))
+			.collect::<Vec<_>>();
+		let collected = chained.collect_btree().unwrap();
+		assert_eq!(collected.into_iter().collect::<Vec<_>>(), all);
+		assert_eq!(chained.collect_hash().unwrap().get(&0), Some(&0));
+		// The first and last keys come from both trees, in order
+		assert_eq!(chained.first_n(3).unwrap(), all[..3].to_vec());
+		assert_eq!(chained.last_n(3).unwrap(), all[all.len() - 3..].to_vec());
+		assert_eq!(chained.nth(2).unwrap(), Some(all[2]));
+		assert_eq!(chained.nth(all.len()).unwrap(), None);
+	});
+}
+
+#[test]
+fn chain_with() {
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		let chained = a.chain_with(&b, |_, a, b| a + b);
+		let stored = chained.store("stored_chain_with").unwrap();
+
+		insert(&a, 2);
+		assert_u32(&chained, 2);
+		assert_u32(&stored, 2);
+
+		// Keys in both trees go through the resolver, from either side
+		insert(&b, 3);
+		for i in 0..TEST_SIZE {
+			let expected = Some(i.pow(2) + i.pow(3));
+			assert_eq!(chained.get(i).unwrap(), expected);
+			assert_eq!(stored.get(i).unwrap(), expected);
+		}
+		let entries = chained.range(..).unwrap().map(|r| r.unwrap());
+		let expected = (0..TEST_SIZE).map(|i| (i, i.pow(2) + i.pow(3)));
+		assert!(entries.eq(expected));
+		assert_eq!(chained.first().unwrap(), Some((0, 0)));
+		assert_eq!(chained.get_gt(1u32).unwrap(), Some((2, 12)));
+
+		insert(&a, 1);
+		remove(&b);
+		assert_u32(&chained, 1);
+		assert_u32(&stored, 1);
+	});
+}
+
 #[test]
 fn zip() {
 	with_db(|db| {
@@ -306,6 +992,133 @@ fn zip() {
 	});
 }
 
+#[test]
+fn zip_order() {
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, String> = db.open_tree("b").unwrap();
+		let zipped = a.zip(&b);
+
+		// Inserted backwards, a skips the keys where i % 3 == 1, b skips the ones where i % 3 == 0
+		for i in (0..TEST_SIZE).rev() {
+			if i % 3 != 1 {
+				a.insert(i, i).unwrap();
+			}
+			if i % 3 != 0 {
+				b.insert(i, i.to_string()).unwrap();
+			}
+		}
+
+		let keys = zipped.iter().map(|r| r.unwrap().0).collect::<Vec<_>>();
+		assert_eq!(keys, (0..TEST_SIZE).collect::<Vec<_>>());
+		let entries = zipped.range(3..6).unwrap().map(|r| r.unwrap());
+		let expected = vec![
+			(3, (Some(3), None)),
+			(4, (None, Some("4".to_string()))),
+			(5, (Some(5), Some("5".to_string()))),
+		];
+		assert_eq!(entries.collect::<Vec<_>>(), expected);
+	});
+}
+
+#[test]
+fn cogroup() {
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		// Both sides are grouped by the remainder of their values
+		let a_index = a.index(|_, v| vec![v % 3]).store("a_index").unwrap();
+		let b_index = b.index(|_, v| vec![v % 5]).load().unwrap();
+		let cogroup = a_index.cogroup(&b_index);
+		let stored = cogroup.map(|_, (a, b)| (a.len() as u32, b.len() as u32)).store("stored").unwrap();
+		insert(&a, 1);
+		insert(&b, 1);
+
+		for k in 0..5u32 {
+			let (a_rows, b_rows) = cogroup.get(k).unwrap().unwrap();
+			let expected_a = (0..TEST_SIZE).filter(|v| v % 3 == k).collect::<Vec<_>>();
+			let expected_b = (0..TEST_SIZE).filter(|v| v % 5 == k).collect::<Vec<_>>();
+			assert_eq!(a_rows, expected_a);
+			assert_eq!(b_rows, expected_b);
+			let counts = (expected_a.len() as u32, expected_b.len() as u32);
+			assert_eq!(stored.get(k).unwrap(), Some(counts));
+		}
+		assert_eq!(cogroup.get(5u32).unwrap(), None);
+		assert_eq!(cogroup.iter().count(), 5);
+
+		// A change on either side updates the pair
+		remove(&a);
+		assert_eq!(stored.get(0u32).unwrap(), Some((0, 8)));
+		assert_eq!(stored.get(4u32).unwrap(), Some((0, 8)));
+		remove(&b);
+		assert_eq!(stored.get(0u32).unwrap(), None);
+	})
+}
+
+#[test]
+fn zip_with() {
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		let zipped = a.zip_with(&b, |_, a, b| Some(a? * b?));
+		let stored = zipped.store("stored_zip_with").unwrap();
+
+		insert(&a, 1);
+		assert_none(&zipped);
+		assert_none(&stored);
+
+		insert(&b, 2);
+		assert_u32(&zipped, 3);
+		assert_u32(&stored, 3);
+
+		// Keys missing from one side are skipped
+		b.remove(0u32).unwrap();
+		a.remove(TEST_SIZE - 1).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), None);
+		assert_eq!(zipped.first().unwrap(), Some((1, 1)));
+		assert_eq!(zipped.last().unwrap(), Some((TEST_SIZE - 2, (TEST_SIZE - 2).pow(3))));
+		assert_eq!(zipped.iter().count(), TEST_SIZE as usize - 2);
+	});
+}
+
+#[test]
+fn diff() {
+	with_db(|db| {
+		let source: Tree<u32, u32> = db.open_tree("source").unwrap();
+		let target: Tree<u32, u32> = db.open_tree("target").unwrap();
+		let diff = source.diff(&target);
+		let stored = diff.store("stored_diff").unwrap();
+
+		insert(&source, 1);
+		insert(&target, 1);
+		assert!(diff.iter().next().is_none());
+		assert!(stored.iter().next().is_none());
+
+		// Additions, deletions and changes
+		source.insert(TEST_SIZE, 0u32).unwrap();
+		target.remove(0u32).unwrap();
+		source.insert(1u32, 10u32).unwrap();
+		let expected = vec![
+			(0, Diff::OnlyLeft(0)),
+			(1, Diff::Changed(10, 1)),
+			(TEST_SIZE, Diff::OnlyLeft(0)),
+		];
+		assert_eq!(diff.iter().map(|r| r.unwrap()).collect::<Vec<_>>(), expected);
+		assert_eq!(stored.iter().map(|r| r.unwrap()).collect::<Vec<_>>(), expected);
+
+		source.remove(2u32).unwrap();
+		assert_eq!(stored.get(2u32).unwrap(), Some(Diff::OnlyRight(2)));
+
+		// Making the target match the source empties the diff
+		target.insert(0u32, 0u32).unwrap();
+		target.insert(1u32, 10u32).unwrap();
+		target.insert(TEST_SIZE, 0u32).unwrap();
+		target.remove(2u32).unwrap();
+		assert!(diff.iter().next().is_none());
+		assert!(stored.iter().next().is_none());
+	});
+}
+
 #[test]
 fn filter() {
 	with_tree(|tree: Tree<u32, u32>| {
@@ -347,6 +1160,75 @@ fn filter() {
 	});
 }
 
+#[cfg(feature = "rkyv")]
+#[test]
+fn namespace() {
+	with_tree(|tree: Tree<Vec<u8>, u32>| {
+		let a = tree.namespace("a/");
+		let b = tree.namespace("b/");
+		let watched = b.map(|_, v| *v).load().unwrap();
+
+		for i in 0..TEST_SIZE {
+			a.insert(vec![i as u8], i).unwrap();
+			b.insert(vec![i as u8], i * 2).unwrap();
+		}
+		tree.insert(b"c".to_vec(), 0u32).unwrap();
+
+		assert_eq!(a.get(vec![1]).unwrap(), Some(1));
+		assert_eq!(b.get(vec![1]).unwrap(), Some(2));
+		assert_eq!(tree.get(b"a/\x01".to_vec()).unwrap(), Some(1));
+		assert_eq!(a.iter().count(), TEST_SIZE as usize);
+		assert!(b.iter().all(|r| matches!(r, Ok((k, v)) if v == k[0] as u32 * 2)));
+		assert_eq!(a.range(vec![2]..vec![4]).unwrap().count(), 2);
+		assert_eq!(b.first().unwrap(), Some((vec![0], 0)));
+		assert_eq!(b.last().unwrap(), Some((vec![TEST_SIZE as u8 - 1], (TEST_SIZE - 1) * 2)));
+		assert_eq!(watched.get(vec![3]).unwrap(), Some(6));
+
+		a.clear().unwrap();
+		assert_eq!(a.is_empty(), Some(true));
+		assert_eq!(b.iter().count(), TEST_SIZE as usize);
+		assert_eq!(tree.get(b"c".to_vec()).unwrap(), Some(0));
+		assert_eq!(watched.iter().count(), TEST_SIZE as usize);
+	})
+}
+
+#[test]
+fn bounded() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 1);
+		let half = TEST_SIZE / 2;
+		let low = tree.bounded(..half);
+		let high = tree.bounded(half..=TEST_SIZE - 2);
+		let watched = high.map(|_, v| *v).load().unwrap();
+
+		assert_eq!(low.get(half - 1).unwrap(), Some(half - 1));
+		assert_eq!(low.get(half).unwrap(), None);
+		assert_eq!(high.get(half).unwrap(), Some(half));
+		assert_eq!(high.get(TEST_SIZE - 1).unwrap(), None);
+		assert_eq!(low.iter().count(), half as usize);
+		assert_eq!(high.range(..).unwrap().count(), (half - 1) as usize);
+		assert_eq!(high.range(..half).unwrap().count(), 0);
+		assert_eq!(low.last().unwrap(), Some((half - 1, half - 1)));
+		assert_eq!(high.first().unwrap(), Some((half, half)));
+		assert_eq!(high.last().unwrap(), Some((TEST_SIZE - 2, TEST_SIZE - 2)));
+		assert_eq!(high.get_lt(half).unwrap(), None);
+		assert_eq!(high.get_gt(0u32).unwrap(), Some((half, half)));
+		assert_eq!(low.get_lt(TEST_SIZE).unwrap(), Some((half - 1, half - 1)));
+
+		assert!(low.insert(half, 0u32).is_err());
+		assert!(high.insert(TEST_SIZE - 1, 0u32).is_err());
+		assert!(high.insert(half, 0u32).is_ok());
+		assert_eq!(watched.get(half).unwrap(), Some(0));
+		assert_eq!(low.remove(half).unwrap(), None);
+
+		high.clear().unwrap();
+		assert_eq!(high.is_empty(), Some(true));
+		assert_eq!(low.iter().count(), half as usize);
+		assert_eq!(tree.get(TEST_SIZE - 1).unwrap(), Some(TEST_SIZE - 1));
+		assert_eq!(watched.iter().count(), 0);
+	})
+}
+
 #[test]
 fn filter_map() {
 	with_tree(|tree: Tree<u32, u32>| {
@@ -430,27 +1312,2184 @@ fn reduce() {
 }
 
 #[test]
-fn gt() {
+fn reduce_retraction() {
 	with_tree(|tree: Tree<u32, u32>| {
-		insert(&tree, 2);
-		for i in 0..(TEST_SIZE - 1) {
-			let gt = tree.get_gt(i).unwrap().unwrap();
-			let e = i + 1;
-			let e = (e, e.pow(2));
-			assert_eq!(gt, e);
+		let counter = tree.reducer(|count, add: i32| {
+			let count = count.unwrap_or(0) as i32 + add;
+			// The counter goes away at zero
+			(count > 0).then_some(count as u32)
+		});
+		let stored = counter.store("stored_counter").unwrap();
+
+		for i in 0..TEST_SIZE {
+			counter.insert(i, 2).unwrap();
+		}
+		for i in 0..TEST_SIZE {
+			counter.insert(i, -1).unwrap();
+		}
+		for i in 0..TEST_SIZE {
+			assert_eq!(counter.get(i).unwrap(), Some(1));
+			assert_eq!(stored.get(i).unwrap(), Some(1));
 		}
+
+		for i in 0..TEST_SIZE {
+			counter.insert(i, -1).unwrap();
+		}
+		assert_none(&counter);
+		assert_none(&stored);
 	});
 }
 
 #[test]
-fn range() {
+fn reduce_concurrent() {
 	with_tree(|tree: Tree<u32, u32>| {
-		insert(&tree, 2);
-		let mut range = tree.range(10..TEST_SIZE).unwrap();
-		for i in 10..TEST_SIZE {
-			let e = (i, i.pow(2));
-			let r = range.next().unwrap().unwrap();
-			assert_eq!(e, r);
+		let threads = 8;
+		let handles = (0..threads)
+			.map(|_| {
+				let reducer = tree.reducer(|a, b: u32| a.unwrap_or(0) + b);
+				std::thread::spawn(move || {
+					for i in 0..TEST_SIZE {
+						reducer.insert(0u32, i).unwrap();
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+		for handle in handles {
+			handle.join().unwrap();
+		}
+
+		// No reduce was lost to another thread
+		let total = threads * (0..TEST_SIZE).sum::<u32>();
+		assert_eq!(tree.get(0u32).unwrap(), Some(total));
+	});
+}
+
+#[test]
+fn pop() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.map(|_, v| v * 2).store("stored_pop").unwrap();
+		insert(&tree, 1);
+
+		// A work queue, dequeued from the smallest key
+		assert_eq!(Change::pop_min(&tree).unwrap(), Some((0, 0)));
+		assert_eq!(Change::pop_max(&tree).unwrap(), Some((TEST_SIZE - 1, TEST_SIZE - 1)));
+		assert_eq!(stored.get(0u32).unwrap(), None);
+		assert_eq!(stored.get(TEST_SIZE - 1).unwrap(), None);
+		assert_eq!(stored.get(1u32).unwrap(), Some(2));
+
+		// The default goes through first and remove
+		let inserter = tree.inserter(|v: u32| v);
+		assert_eq!(inserter.pop_min().unwrap(), Some((1, 1)));
+		assert_eq!(stored.get(1u32).unwrap(), None);
+
+		while tree.pop_min().unwrap().is_some() {}
+		assert_none(&stored);
+	});
+}
+
+#[test]
+fn retain() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.map(|_, v| v * 2).store("stored_retain").unwrap();
+		insert(&tree, 1);
+
+		// Keeps the even values
+		let removed = tree.retain(|_, v| v % 2 == 0).unwrap();
+		assert_eq!(removed, TEST_SIZE as usize / 2);
+		for i in 0..TEST_SIZE {
+			let expected = (i % 2 == 0).then_some(i * 2);
+			assert_eq!(stored.get(i).unwrap(), expected);
+		}
+
+		// The default goes through the view and removes one by one
+		let inserter = tree.inserter(|v: u32| v);
+		let removed = inserter.retain(|k, _| *k >= 10).unwrap();
+		assert_eq!(removed, 5);
+		assert_eq!(stored.get(8u32).unwrap(), None);
+		assert_eq!(stored.get(10u32).unwrap(), Some(20));
+
+		// A value written after the check is kept, as it wasn't the one checked
+		tree.insert(1u32, 1u32).unwrap();
+		let removed = tree
+			.retain(|k, v| {
+				if *k == 1 {
+					tree.insert(1u32, 2u32).unwrap();
+				}
+				v % 2 == 0
+			})
+			.unwrap();
+		assert_eq!(removed, 0);
+		assert_eq!(tree.get(1u32).unwrap(), Some(2));
+		assert_eq!(stored.get(1u32).unwrap(), Some(4));
+	});
+}
+
+#[test]
+fn remove_range() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.map(|_, v| v * 2).store("stored_remove_range").unwrap();
+		insert(&tree, 1);
+
+		let removed = tree.remove_range(0..10).unwrap();
+		assert_eq!(removed, 10);
+		let keys = tree.iter().map(|r| r.unwrap().0).collect::<Vec<_>>();
+		assert_eq!(keys, (10..TEST_SIZE).collect::<Vec<_>>());
+		for i in 0..TEST_SIZE {
+			let expected = (i >= 10).then_some(i * 2);
+			assert_eq!(stored.get(i).unwrap(), expected);
+		}
+
+		// The default goes through the view and removes one by one
+		let inserter = tree.inserter(|v: u32| v);
+		assert_eq!(inserter.remove_range(..=20).unwrap(), 11);
+		assert_eq!(inserter.remove_range(..=20).unwrap(), 0);
+		assert_eq!(stored.get(20u32).unwrap(), None);
+		assert_eq!(stored.get(21u32).unwrap(), Some(42));
+
+		// A key that can't be read fails the call before it is removed, and nothing is left to wait on
+		let bad: Tree<Unserializable, u32> = tree.db().open_tree("bad").unwrap();
+		let downstream = crate::threads::Synchronizer::from(vec![bad.sync()]);
+		bad.to_inner().insert(vec![0u8], Serial::serialize(&1u32).unwrap()).unwrap();
+		assert!(bad.remove_range(..).is_err());
+		assert_eq!(bad.to_inner().len(), 1);
+		assert!(downstream.is_caught_up());
+	});
+}
+
+#[test]
+fn watch_events() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let mut mapped = tree.map_events(|event| match event {
+			Event::Insert { key, value } => Event::Insert {
+				key,
+				value: Arc::new(*value as u64 * 2),
+			},
+			Event::Remove { key } => Event::Remove { key },
+		});
+		let mut filtered = tree.filter_events(|event| matches!(event, Event::Remove { .. }));
+
+		insert(&tree, 1);
+		remove(&tree);
+		mapped.wait();
+		filtered.wait();
+
+		for i in 0..TEST_SIZE {
+			match mapped.try_recv().unwrap() {
+				Event::Insert { key, value } => assert_eq!((*key, *value), (i, i as u64 * 2)),
+				Event::Remove { .. } => panic!("Expected an insert"),
+			}
+		}
+		for _ in 0..TEST_SIZE {
+			assert!(matches!(mapped.try_recv().unwrap(), Event::Remove { .. }));
+			assert!(matches!(filtered.try_recv().unwrap(), Event::Remove { .. }));
+		}
+		assert!(filtered.try_recv().is_err());
+	})
+}
+
+#[test]
+fn merge_watch() {
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		let mut events = crate::merge_watch(vec![(a.watch(), a.sync()), (b.watch(), b.sync())]);
+
+		for i in 0..TEST_SIZE {
+			a.insert(i, 0u32).unwrap();
+			b.insert(i, 1u32).unwrap();
+		}
+		events.wait();
+
+		let mut counts = [0; 2];
+		while let Ok(event) = events.try_recv() {
+			match event {
+				Event::Insert { value, .. } => counts[*value as usize] += 1,
+				Event::Remove { .. } => panic!("Expected an insert"),
+			}
+		}
+		assert_eq!(counts, [TEST_SIZE; 2]);
+	})
+}
+
+// The stalled thread would block the writing thread without threads
+#[cfg(not(feature = "sync"))]
+#[test]
+fn wait_timeout() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let gate = Arc::new(parking_lot::Mutex::new(()));
+		let stalled = tree
+			.filter(cloned!(gate, move |_, _| {
+				drop(gate.lock());
+				true
+			}))
+			.load()
+			.unwrap();
+
+		let guard = gate.lock();
+		tree.insert(1u32, 1u32).unwrap();
+		assert!(!stalled.wait_timeout(std::time::Duration::from_millis(50)));
+
+		drop(guard);
+		assert!(stalled.wait_timeout(std::time::Duration::from_secs(10)));
+		assert_eq!(stalled.get(1u32).unwrap(), Some(1));
+	})
+}
+
+#[cfg(not(feature = "sync"))]
+#[test]
+fn wait_unlists() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let gate = Arc::new(parking_lot::Mutex::new(()));
+		let stalled = tree
+			.filter(cloned!(gate, move |_, _| {
+				drop(gate.lock());
+				true
+			}))
+			.load()
+			.unwrap();
+
+		// Waits that time out leave nothing behind, in the view or its source
+		let guard = gate.lock();
+		tree.insert(1u32, 1u32).unwrap();
+		for _ in 0..100 {
+			assert!(!stalled.wait_timeout(std::time::Duration::from_millis(1)));
+		}
+		assert!(stalled.sync().waiting.lock().is_empty());
+		assert!(tree.sync().waiting.lock().is_empty());
+
+		drop(guard);
+		for _ in 0..100 {
+			tree.insert(1u32, 1u32).unwrap();
+			stalled.wait();
+		}
+		assert!(stalled.sync().waiting.lock().is_empty());
+		assert!(tree.sync().waiting.lock().is_empty());
+	})
+}
+
+#[derive(Clone)]
+struct Unserializable;
+impl Serial for Unserializable {
+	fn serialize(&self) -> crate::error::Result<Vec<u8>> {
+		Err(anyhow::anyhow!("Unserializable").into())
+	}
+	fn deserialize(_: Vec<u8>) -> crate::error::Result<Self> {
+		Err(anyhow::anyhow!("Unserializable").into())
+	}
+}
+
+#[test]
+fn watch_with_snapshot() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let n = TEST_SIZE * 5;
+		let writer = std::thread::spawn(cloned!(tree, move || {
+			for i in 0..n {
+				tree.insert(i, i).unwrap();
+			}
+		}));
+		// Subscribes in the middle of the writes
+		while tree.to_inner().len() < TEST_SIZE as usize {
+			std::thread::yield_now();
+		}
+		let (snapshot, mut reader) = tree.watch_with_snapshot().unwrap();
+		let mut seen = std::collections::BTreeSet::new();
+		seen.extend(snapshot.into_iter().map(|(k, _)| k));
+		while seen.len() < n as usize {
+			let event = reader.recv_timeout(std::time::Duration::from_secs(5)).unwrap();
+			seen.insert(**event.key());
+		}
+		writer.join().unwrap();
+		assert_eq!(seen.into_iter().collect::<Vec<_>>(), (0..n).collect::<Vec<_>>());
+	})
+}
+
+#[test]
+fn lag() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.map(|_, v| v * 2).store("stored").unwrap();
+		assert_eq!(stored.lag(), 0);
+		assert!(stored.is_caught_up());
+
+		let writer = std::thread::spawn(cloned!(tree, move || insert(&tree, 1)));
+		// The lag is read while the writes go on
+		for _ in 0..TEST_SIZE {
+			assert!(stored.lag() <= TEST_SIZE);
+		}
+		writer.join().unwrap();
+		stored.wait();
+		assert_eq!(stored.lag(), 0);
+		assert!(stored.is_caught_up());
+		assert_eq!(stored.get(3u32).unwrap(), Some(6));
+	})
+}
+
+#[test]
+fn material_read_after_write() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let stored = tree.store("stored").unwrap();
+		let loaded = tree.load().unwrap();
+		for i in 0..TEST_SIZE {
+			// Written through the material, then read at once
+			stored.insert(i, i).unwrap();
+			assert_eq!(stored.get(i).unwrap(), Some(i));
+			assert_eq!(loaded.get(i).unwrap(), Some(i));
+			assert_eq!(stored.remove(i).unwrap(), Some(i));
+			assert_eq!(stored.get(i).unwrap(), None);
+			assert!(!loaded.contains_key(i).unwrap());
+		}
+		assert_none(&stored);
+	})
+}
+
+#[test]
+fn last_error() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.map(|_, _| Unserializable).store("unserializable").unwrap();
+		assert!(stored.last_error().is_none());
+
+		tree.insert(1u32, 1u32).unwrap();
+
+		assert!(stored.get(1u32).is_err());
+		assert!(stored.last_error().is_some());
+		assert!(tree.last_error().is_none());
+	})
+}
+
+#[test]
+fn husky_error() {
+	with_db(|db| {
+		use crate::error::HuskyError;
+		let bad: Tree<u32, Unserializable> = db.open_tree("bad").unwrap();
+		let key = Serial::serialize(&1u32).unwrap();
+		bad.to_inner().insert(key, vec![0u8]).unwrap();
+		let err = bad.get(1u32).err().unwrap();
+		assert!(matches!(err, HuskyError::Serialization(_)));
+		assert!(matches!(
+			bad.insert(2u32, Unserializable).err().unwrap(),
+			HuskyError::Serialization(_)
+		));
+
+		// Errors of a background thread are raised as pipeline errors
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let stored = tree.map(|_, _| Unserializable).store("unserializable").unwrap();
+		tree.insert(1u32, 1u32).unwrap();
+		let err = stored.get(1u32).err().unwrap();
+		assert!(matches!(err, HuskyError::Pipeline(_)));
+		assert!(err.to_string().contains("Unserializable"));
+
+		// Other errors are kept as they are
+		let err = HuskyError::from(anyhow::anyhow!("Other"));
+		assert!(matches!(err, HuskyError::Other(_)));
+	})
+}
+
+#[test]
+fn for_each() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		insert(&tree, 1);
+		let mut sum = 0;
+		tree.map(|_, v| v * 2).for_each(|_, v| sum += v).unwrap();
+		assert_eq!(sum, (0..TEST_SIZE).sum::<u32>() * 2);
+
+		// The closure stops the iteration with its first error
+		let mut seen = Vec::new();
+		let res = tree.filter(|k, _| k % 2 == 0).try_for_each(|k, _| {
+			if k >= 10 {
+				anyhow::bail!("Stop");
+			}
+			seen.push(k);
+			Ok(())
+		});
+		assert!(res.is_err());
+		assert_eq!(seen, vec![0, 2, 4, 6, 8]);
+
+		// Errors from the view stop it too
+		let bad: Tree<u32, Unserializable> = db.open_tree("bad").unwrap();
+		let key = Serial::serialize(&1u32).unwrap();
+		bad.to_inner().insert(key, vec![0u8]).unwrap();
+		let mut calls = 0;
+		let map = bad.map(|_, _| 0u32);
+		assert!(map.for_each(|_, _| calls += 1).is_err());
+		let filter = bad.filter(|_, _| true);
+		let res = filter.try_for_each(|_, _| {
+			calls += 1;
+			Ok(())
+		});
+		assert!(res.is_err());
+		let chain = map.chain(&tree);
+		assert!(chain.for_each(|_, _| calls += 1).is_err());
+		assert_eq!(calls, 0);
+	})
+}
+
+#[test]
+fn into_iter() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		insert(&tree, 2);
+		let filter = tree.filter(|k, _| k % 2 == 0);
+		let mut keys = Vec::new();
+		for entry in &filter {
+			let (k, v) = entry.unwrap();
+			assert_eq!(v, k.pow(2));
+			keys.push(k);
+		}
+		assert_eq!(keys, (0..TEST_SIZE).step_by(2).collect::<Vec<_>>());
+		let entries = (&filter).into_iter().collect::<crate::error::Result<Vec<_>>>().unwrap();
+		assert_eq!(entries.len(), keys.len());
+
+		// Errors are yielded as items, so they can be collected into a result
+		let bad: Tree<u32, Unserializable> = db.open_tree("bad").unwrap();
+		let key = Serial::serialize(&1u32).unwrap();
+		bad.to_inner().insert(key, vec![0u8]).unwrap();
+		let map = bad.map(|_, _| 0u32);
+		assert!((&map).into_iter().collect::<crate::error::Result<Vec<_>>>().is_err());
+	})
+}
+
+#[test]
+fn chunks() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		insert(&tree, 1);
+		let sizes = |size| {
+			tree.chunks(size)
+				.map(|c| c.unwrap().len())
+				.collect::<Vec<_>>()
+		};
+		// An exact multiple, and a remainder in the last chunk
+		assert_eq!(sizes(10), vec![10; TEST_SIZE as usize / 10]);
+		assert_eq!(sizes(30), vec![30, TEST_SIZE as usize - 30]);
+		let flat = tree.chunks(7).flat_map(|c| c.unwrap()).collect::<Vec<_>>();
+		assert_eq!(flat, tree.iter().collect::<crate::error::Result<Vec<_>>>().unwrap());
+
+		let windows = tree.windows(3).collect::<crate::error::Result<Vec<_>>>().unwrap();
+		assert_eq!(windows.len(), TEST_SIZE as usize - 2);
+		assert_eq!(windows[1], vec![(1, 1), (2, 2), (3, 3)]);
+		assert_eq!(tree.windows(TEST_SIZE as usize + 1).count(), 0);
+
+		// Errors are yielded, then the iteration stops
+		let bad: Tree<u32, Unserializable> = db.open_tree("bad").unwrap();
+		let key = Serial::serialize(&1u32).unwrap();
+		bad.to_inner().insert(key, vec![0u8]).unwrap();
+		let chunks = bad.chunks(2).collect::<Vec<_>>();
+		assert!(chunks.len() == 1 && chunks[0].is_err());
+		let windows = bad.windows(1).collect::<Vec<_>>();
+		assert!(windows.len() == 1 && windows[0].is_err());
+	})
+}
+
+#[test]
+fn contains_value() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		insert(&tree, 2);
+		assert!(tree.contains_value(&(7 * 7)).unwrap());
+		assert!(!tree.contains_value(&2).unwrap());
+		let filter = tree.filter(|k, _| k % 2 == 0);
+		assert!(!filter.contains_value(&(7 * 7)).unwrap());
+
+		// Looked up in the backward map
+		let inverted = tree.invertible_map(|_, v| v + 1).load().unwrap();
+		assert!(inverted.contains_value(&(7 * 7 + 1)).unwrap());
+		assert!(!inverted.contains_value(&(7 * 7)).unwrap());
+
+		// Errors stop the scan
+		let bad: Tree<u32, Unserializable> = db.open_tree("bad").unwrap();
+		let key = Serial::serialize(&1u32).unwrap();
+		bad.to_inner().insert(key, vec![0u8]).unwrap();
+		assert!(bad.map(|_, _| 0u32).contains_value(&0).is_err());
+	})
+}
+
+#[test]
+fn collect_map() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 2);
+		let filter = tree.filter(|k, _| k % 3 == 0);
+		let expected = (0..TEST_SIZE)
+			.filter(|k| k % 3 == 0)
+			.map(|k| (k, k.pow(2)))
+			.collect::<Vec<_>>();
+
+		let btree = filter.collect_btree().unwrap();
+		assert_eq!(btree.into_iter().collect::<Vec<_>>(), expected);
+		let hash = filter.collect_hash().unwrap();
+		assert_eq!(hash.len(), expected.len());
+		for (k, v) in expected {
+			assert_eq!(hash.get(&k), Some(&v));
+		}
+	})
+}
+
+#[test]
+fn extend() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.map(|_, v| v * 2).store("stored_map").unwrap();
+		tree.extend((0..1000u32).map(|i| (i, i))).unwrap();
+
+		assert_eq!(tree.len(), 1000);
+		for i in 0..1000u32 {
+			assert_eq!(stored.get(i).unwrap(), Some(i * 2));
+		}
+
+		let loaded: Loaded<u32, u32> = tree.iter().map(|r| r.unwrap()).collect();
+		assert_eq!(loaded.iter().count(), 1000);
+		assert_eq!(loaded.get(999u32).unwrap(), Some(999));
+	})
+}
+
+#[test]
+fn distinct_by_value() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.distinct_by_value().store("stored_distinct").unwrap();
+		for i in 0..TEST_SIZE {
+			tree.insert(i, i % 4).unwrap();
+		}
+		let loaded = tree.distinct_by_value().load().unwrap();
+		for distinct in [stored.iter().collect::<Vec<_>>(), loaded.iter().collect()] {
+			let distinct = distinct.into_iter().map(|r| r.unwrap()).collect::<Vec<_>>();
+			assert_eq!(distinct, vec![(0, 0), (1, 1), (2, 2), (3, 3)]);
+		}
+		assert_eq!(stored.keys_of(&1).unwrap().len(), TEST_SIZE as usize / 4);
+
+		// Removing a representative promotes the next key with its value
+		tree.remove(0u32).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), None);
+		assert_eq!(stored.get(4u32).unwrap(), Some(0));
+		assert_eq!(loaded.get(4u32).unwrap(), Some(0));
+		// So does changing its value, and a new value gets its first key
+		tree.insert(1u32, 100u32).unwrap();
+		assert_eq!(stored.get(1u32).unwrap(), Some(100));
+		assert_eq!(stored.get(5u32).unwrap(), Some(1));
+		// A duplicate doesn't show up
+		tree.insert(1u32, 2u32).unwrap();
+		assert_eq!(stored.get(1u32).unwrap(), None);
+		assert_eq!(stored.get(2u32).unwrap(), Some(2));
+		assert_eq!(stored.iter().count(), 4);
+		assert_eq!(loaded.iter().count(), 4);
+	})
+}
+
+#[test]
+fn partition() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let (even, odd) = tree.partition(|k, _| k % 2 == 0);
+		let stored_even = even.store("stored_even").unwrap();
+		let stored_odd = odd.store("stored_odd").unwrap();
+
+		insert(&tree, 1);
+		for i in 0..TEST_SIZE {
+			let (matched, unmatched) = match i % 2 {
+				0 => (&stored_even, &stored_odd),
+				_ => (&stored_odd, &stored_even),
+			};
+			assert_eq!(matched.get(i).unwrap(), Some(i));
+			assert_eq!(unmatched.get(i).unwrap(), None);
+		}
+		assert_eq!(even.iter().count(), TEST_SIZE as usize / 2);
+		assert_eq!(odd.iter().count(), TEST_SIZE as usize / 2);
+
+		tree.remove(0u32).unwrap();
+		tree.remove(1u32).unwrap();
+		assert_eq!(stored_even.iter().count(), TEST_SIZE as usize / 2 - 1);
+		assert_eq!(stored_odd.iter().count(), TEST_SIZE as usize / 2 - 1);
+	})
+}
+
+#[test]
+fn versioned_history() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.versioned(3).store("stored_versioned").unwrap();
+		let mut versions = Vec::new();
+		for i in 0..TEST_SIZE {
+			tree.insert(0u32, i).unwrap();
+			versions.push(stored.version().unwrap());
+		}
+		tree.remove(0u32).unwrap();
+
+		assert_eq!(stored.get(0u32).unwrap(), None);
+		assert_eq!(stored.version().unwrap(), TEST_SIZE as u64 + 1);
+		let last = versions[TEST_SIZE as usize - 1];
+		assert_eq!(stored.get_at(&0, last).unwrap(), Some(TEST_SIZE - 1));
+		assert_eq!(stored.get_at(&0, last - 1).unwrap(), Some(TEST_SIZE - 2));
+		// Older versions are dropped
+		assert_eq!(stored.get_at(&0, last - 2).unwrap(), None);
+		let history = stored.history(&0).unwrap();
+		assert_eq!(history.len(), 3);
+		assert_eq!(history[2].0, TEST_SIZE as u64 + 1);
+		assert_eq!(history[2].2, None);
+
+		let loaded = tree.versioned(3).load().unwrap();
+		tree.insert(1u32, 1u32).unwrap();
+		tree.insert(1u32, 2u32).unwrap();
+		assert_eq!(loaded.get(1u32).unwrap(), Some(2));
+		assert_eq!(loaded.get_at(&1, loaded.version().unwrap() - 1).unwrap(), Some(1));
+	})
+}
+
+#[test]
+fn sort_by_value() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let loaded = tree.sort_by_value().load().unwrap();
+		let stored = tree.sort_by_value().store("sorted").unwrap();
+		// Values in the reverse order of the keys
+		for k in 0..TEST_SIZE {
+			tree.insert(k, TEST_SIZE - k).unwrap();
+		}
+		let keys = |sorted: Vec<crate::error::Result<(u32, u32)>>| {
+			sorted.into_iter().map(|r| r.unwrap().0).collect::<Vec<_>>()
+		};
+		let expected = (0..TEST_SIZE).rev().collect::<Vec<_>>();
+		assert_eq!(keys(loaded.iter_by_value().collect()), expected);
+		assert_eq!(keys(stored.iter_by_value().collect()), expected);
+
+		// A changed value moves the key, leaving its old place
+		tree.insert(0u32, 0u32).unwrap();
+		tree.insert(TEST_SIZE - 1, TEST_SIZE).unwrap();
+		tree.remove(1u32).unwrap();
+		let mut expected = (2..TEST_SIZE - 1).rev().collect::<Vec<_>>();
+		expected.insert(0, 0);
+		expected.push(TEST_SIZE - 1);
+		assert_eq!(keys(loaded.iter_by_value().collect()), expected);
+		assert_eq!(keys(stored.iter_by_value().collect()), expected);
+		assert_eq!(loaded.get(TEST_SIZE - 1).unwrap(), Some(TEST_SIZE));
+		assert_eq!(stored.get(1u32).unwrap(), None);
+
+		loaded.rebuild().unwrap();
+		assert_eq!(keys(loaded.iter_by_value().collect()), expected);
+	})
+}
+
+#[test]
+fn top_k() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let top = tree.top_k(3).load().unwrap();
+		for i in 0..TEST_SIZE {
+			tree.insert(i, i).unwrap();
+		}
+		let keys = |top: &Vec<(u32, u32)>| top.iter().map(|(k, _)| *k).collect::<Vec<_>>();
+		let last = TEST_SIZE - 1;
+		assert_eq!(keys(&top.ranked().unwrap()), vec![last, last - 1, last - 2]);
+		assert_eq!(top.iter().count(), 3);
+
+		// A low entry climbs to the top, evicting the lowest one
+		tree.insert(0u32, TEST_SIZE).unwrap();
+		assert_eq!(keys(&top.ranked().unwrap()), vec![0, last, last - 1]);
+		assert_eq!(top.get(last - 2).unwrap(), None);
+
+		// A top entry drops, and the next best takes its place
+		tree.insert(last, 0u32).unwrap();
+		assert_eq!(keys(&top.ranked().unwrap()), vec![0, last - 1, last - 2]);
+
+		// A top entry is removed, and the next best is pulled from the source
+		tree.remove(0u32).unwrap();
+		assert_eq!(keys(&top.ranked().unwrap()), vec![last - 1, last - 2, last - 3]);
+		assert_eq!(top.first().unwrap(), Some((last - 3, last - 3)));
+
+		// Removing an entry out of the top changes nothing
+		tree.remove(1u32).unwrap();
+		assert_eq!(top.iter().count(), 3);
+	})
+}
+
+#[test]
+fn ttl() {
+	use std::time::Duration;
+	with_tree(|tree: Tree<u32, u32>| {
+		// Long enough for the inserts to be read before they expire, even under load
+		let ttl = Duration::from_secs(2);
+		let stored = tree
+			.ttl(ttl)
+			// Swept by hand below, so the sweeper doesn't race the asserts
+			.sweep_every(Duration::from_secs(3600))
+			.store("stored_ttl")
+			.unwrap();
+		let mapped = stored.map(|_, v| v * 2);
+		let mut events = mapped.watch();
+		for i in 0..TEST_SIZE {
+			tree.insert(i, i).unwrap();
+		}
+		assert_u32(&stored, 1);
+		assert_eq!(mapped.get(1u32).unwrap(), Some(2));
+
+		std::thread::sleep(ttl + Duration::from_millis(100));
+		// Expired entries are hidden even before they are swept
+		assert_none(&stored);
+		assert_eq!(mapped.get(1u32).unwrap(), None);
+		assert_eq!(stored.iter().count(), 0);
+
+		assert_eq!(stored.sweep().unwrap(), TEST_SIZE as usize);
+		// Waits on the thread of the map, which forwards the swept events
+		mapped.sync().wait();
+		let removed = std::iter::from_fn(|| events.try_recv().ok())
+			.filter(|e| matches!(e, Event::Remove { .. }))
+			.count();
+		assert_eq!(removed, TEST_SIZE as usize);
+		assert_eq!(stored.sweep().unwrap(), 0);
+
+		// A key inserted again lives again
+		tree.insert(1u32, 1u32).unwrap();
+		assert_eq!(mapped.get(1u32).unwrap(), Some(2));
+	})
+}
+
+#[test]
+fn enumerate() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.enumerate().store("stored_enumerate").unwrap();
+		for i in (0..TEST_SIZE).rev() {
+			tree.insert(i, i).unwrap();
+		}
+		for i in 0..TEST_SIZE {
+			assert_eq!(stored.get(i).unwrap(), Some(((TEST_SIZE - 1 - i) as u64, i)));
+		}
+
+		// Replacing keeps the number, unless it is bumped
+		tree.insert(0u32, 1u32).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), Some(((TEST_SIZE - 1) as u64, 1)));
+		let bumped = tree.enumerate().bump_on_replace().load().unwrap();
+		tree.insert(0u32, 2u32).unwrap();
+		assert_eq!(bumped.get(0u32).unwrap(), Some((TEST_SIZE as u64, 2)));
+		assert_eq!(stored.get(0u32).unwrap(), Some(((TEST_SIZE - 1) as u64, 2)));
+
+		// The counter is kept after reopening the store
+		drop(stored);
+		let reopened = tree.enumerate().store("stored_enumerate").unwrap();
+		assert_eq!(reopened.sequence().unwrap(), TEST_SIZE as u64);
+		tree.insert(TEST_SIZE, 0u32).unwrap();
+		assert_eq!(reopened.get(TEST_SIZE).unwrap(), Some((TEST_SIZE as u64, 0)));
+	})
+}
+
+#[test]
+fn debounce() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let debounced = tree.debounce(std::time::Duration::from_millis(100));
+		let mut reader = debounced.watch();
+		let stored = debounced.map(|_, v| v * 2).store("stored_debounce").unwrap();
+
+		for i in 0..TEST_SIZE {
+			tree.insert(0u32, i).unwrap();
+			tree.insert(1u32, i).unwrap();
+		}
+		tree.remove(1u32).unwrap();
+
+		assert_eq!(stored.get(0u32).unwrap(), Some((TEST_SIZE - 1) * 2));
+		assert_eq!(stored.get(1u32).unwrap(), None);
+		let mut events = 0;
+		while reader.try_recv().is_ok() {
+			events += 1;
+		}
+		if cfg!(not(feature = "sync")) {
+			assert!(events < TEST_SIZE);
+		}
+	})
+}
+
+#[test]
+fn batch_watcher() {
+	use bus::Bus;
+	use crate::threads::{spawn_batch_watcher, Synchronizer};
+	use parking_lot::RwLock;
+	use std::sync::atomic::{AtomicU32, Ordering::Relaxed};
+	with_tree(|tree: Tree<u32, u32>| {
+		let reader = tree.watch();
+		let sync = Arc::new(Synchronizer::from(vec![tree.sync()]));
+		let index = tree.index(|_, v| vec![v % 2]).store("index").unwrap();
+
+		// The events pile up before the thread starts, so they all come in one batch
+		insert(&tree, 1);
+		let batches = Arc::new(AtomicU32::new(0));
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		let mut events = bus.write().add_rx();
+		spawn_batch_watcher(
+			Arc::clone(&sync),
+			reader,
+			bus,
+			cloned!(batches, move |batch| {
+				batches.fetch_add(1, Relaxed);
+				Ok(batch)
+			}),
+		);
+		sync.wait();
+
+		assert_eq!(batches.load(Relaxed), 1);
+		let mut sent = 0;
+		while events.try_recv().is_ok() {
+			sent += 1;
+		}
+		assert_eq!(sent, TEST_SIZE);
+		assert_eq!(index.get(0u32).unwrap().unwrap().len(), TEST_SIZE as usize / 2);
+		assert_eq!(index.get(1u32).unwrap().unwrap().len(), TEST_SIZE as usize / 2);
+	})
+}
+
+#[test]
+fn pipe() {
+	use std::time::{Duration, Instant};
+	with_db(|db: Db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let piped: Tree<u32, u32> = db.open_tree("piped").unwrap();
+		let detached: Tree<u32, u32> = db.open_tree("detached").unwrap();
+		let pipe = tree.pipe(piped.clone());
+		tree.pipe(detached.clone()).detach();
+
+		insert(&tree, 1);
+		pipe.wait();
+		assert!(pipe.last_error().is_none());
+		assert_u32(&piped, 1);
+
+		let sync = pipe.sync();
+		drop(pipe);
+		insert(&tree, 2);
+		tree.remove(0u32).unwrap();
+		piped.wait();
+		assert_u32(&piped, 1);
+		assert_eq!(detached.get(0u32).unwrap(), None);
+		for i in 1..TEST_SIZE {
+			assert_eq!(detached.get(i).unwrap(), Some(i.pow(2)));
+		}
+
+		// The piped tree stops waiting on the pipe, and its thread lets go of it
+		let deadline = Instant::now() + Duration::from_secs(5);
+		while Arc::strong_count(&sync) > 1 && Instant::now() < deadline {
+			std::thread::sleep(Duration::from_millis(1));
+		}
+		assert_eq!(Arc::strong_count(&sync), 1);
+	})
+}
+
+#[test]
+fn syncs_dropped() {
+	use crate::threads::{Synchronizer, SYNCS};
+	for _ in 0..10_000 {
+		drop(Synchronizer::new());
+	}
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 1);
+		crate::wait_all();
+		// Other tests may be running, so only the trees they keep open are left
+		assert!(SYNCS.lock().len() < 1_000);
+	})
+}
+
+// Without threads the pipe writes inline, so it never lags
+#[cfg(not(feature = "sync"))]
+#[test]
+fn wait_all_timeout() {
+	use std::time::Duration;
+	with_db(|db: Db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let slow: Tree<u32, u32> = db.open_tree("slow").unwrap();
+		tree.pipe(slow.inserter(|v: u32| {
+			std::thread::sleep(Duration::from_millis(200));
+			v
+		}))
+		.detach();
+
+		tree.insert(0u32, 0u32).unwrap();
+		let lagging = crate::wait_all_timeout(Duration::from_millis(10)).unwrap();
+		assert!(lagging.iter().any(|s| Arc::ptr_eq(s, &slow.sync())));
+		assert!(!lagging.iter().any(|s| Arc::ptr_eq(s, &tree.sync())));
+		let lagging = crate::wait_all_timeout(Duration::from_secs(10)).unwrap();
+		assert!(!lagging.iter().any(|s| Arc::ptr_eq(s, &slow.sync())));
+		assert_eq!(slow.get(0u32).unwrap(), Some(0));
+	})
+}
+
+#[test]
+fn sync_cycles() {
+	use crate::threads::Synchronizer;
+	// Cycles are found without waiting on them, these aren't registered so other tests don't see them
+	let a = Arc::new(Synchronizer::from(vec![]));
+	let b = Arc::new(Synchronizer::from(vec![Arc::clone(&a)]));
+	assert!(!b.has_cycle());
+	a.push_source(Arc::clone(&b));
+	assert!(a.has_cycle() && b.has_cycle());
+}
+
+#[test]
+fn tee() {
+	with_db(|db: Db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		tree.tee([a.clone(), b.clone()]);
+		let stored = a.map(|_, v| v + 1).store("stored").unwrap();
+
+		insert(&tree, 2);
+		tree.remove(0u32).unwrap();
+
+		b.wait();
+		assert_eq!(b.get(0u32).unwrap(), None);
+		assert_eq!(b.get(3u32).unwrap(), Some(9));
+		crate::wait_all();
+		assert_eq!(a.get(0u32).unwrap(), None);
+		assert_eq!(a.get(3u32).unwrap(), Some(9));
+		assert_eq!(stored.get(3u32).unwrap(), Some(10));
+	})
+}
+
+#[test]
+fn sink() {
+	use parking_lot::Mutex;
+	with_tree(|tree: Tree<u32, u32>| {
+		let events = Arc::new(Mutex::new(Vec::new()));
+		let sink = tree.sink(cloned!(events, move |event| {
+			if let Event::Insert { value, .. } = &event {
+				if **value == u32::MAX {
+					anyhow::bail!("Rejected");
+				}
+			}
+			events.lock().push(event);
+			Ok(())
+		}));
+
+		insert(&tree, 1);
+		tree.remove(0u32).unwrap();
+		sink.wait();
+		assert_eq!(events.lock().len(), TEST_SIZE as usize + 1);
+		assert!(sink.last_error().is_none());
+
+		tree.insert(0u32, u32::MAX).unwrap();
+		sink.wait();
+		assert!(sink.last_error().is_some());
+
+		drop(sink);
+		insert(&tree, 2);
+		tree.wait();
+		assert_eq!(events.lock().len(), TEST_SIZE as usize + 1);
+	})
+}
+
+#[test]
+fn ingest() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.map(|_, v| v + 1).store("stored").unwrap();
+		let (tx, rx) = crossbeam_channel::bounded(4);
+		let handle = crate::ingest(tree.clone(), rx);
+
+		for i in 0..TEST_SIZE {
+			tx.send((i, Some(i.pow(2)))).unwrap();
+		}
+		tx.send((0, None)).unwrap();
+		drop(tx);
+		handle.join().unwrap();
+
+		assert_eq!(tree.get(0u32).unwrap(), None);
+		for i in 1..TEST_SIZE {
+			assert_eq!(tree.get(i).unwrap(), Some(i.pow(2)));
+			assert_eq!(stored.get(i).unwrap(), Some(i.pow(2) + 1));
+		}
+		assert_eq!(stored.get(0u32).unwrap(), None);
+	})
+}
+
+#[test]
+fn mirror() {
+	with_db(|db: Db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		a.mirror(b.clone());
+		let mut a_events = a.watch();
+		let mut b_events = b.watch();
+
+		insert(&a, 1);
+		crate::wait_all();
+		assert_u32(&b, 1);
+		insert(&b, 2);
+		b.remove(0u32).unwrap();
+		crate::wait_all();
+		assert_eq!(a.get(0u32).unwrap(), None);
+		for i in 1..TEST_SIZE {
+			assert_eq!(a.get(i).unwrap(), Some(i.pow(2)));
+		}
+
+		// Each change shows up once on each side
+		// Keys 0 and 1 are squared to the same value, so they aren't written back to the first tree
+		let count = |events: &mut bus::BusReader<_>| std::iter::from_fn(|| events.try_recv().ok()).count();
+		assert_eq!(count(&mut a_events), TEST_SIZE as usize * 2 - 1);
+		assert_eq!(count(&mut b_events), TEST_SIZE as usize * 2 + 1);
+	})
+}
+
+#[cfg(feature = "serde_json")]
+#[test]
+fn json() {
+	with_db(|db: Db| {
+		let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+		let copy: Tree<String, u32> = db.open_tree("copy").unwrap();
+		let stored = copy.map(|_, v| v * 2).store("stored").unwrap();
+		for i in 0..TEST_SIZE {
+			tree.insert(format!("key {}", i), i).unwrap();
+		}
+
+		let mut json = Vec::new();
+		tree.export_json(&mut json).unwrap();
+		let json = String::from_utf8(json).unwrap();
+		assert_eq!(json.lines().count(), TEST_SIZE as usize);
+		assert!(json.contains(r#"{"key":"key 1","value":1}"#));
+
+		assert_eq!(copy.import_json(json.as_bytes()).unwrap(), TEST_SIZE as usize);
+		for i in 0..TEST_SIZE {
+			assert_eq!(copy.get(format!("key {}", i)).unwrap(), Some(i));
+			assert_eq!(stored.get(format!("key {}", i)).unwrap(), Some(i * 2));
+		}
+	})
+}
+
+#[cfg(feature = "csv")]
+#[test]
+fn csv() {
+	with_db(|db: Db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let mut csv = Vec::new();
+		tree.export_csv(&mut csv).unwrap();
+		assert_eq!(String::from_utf8(csv).unwrap(), "key,value\n");
+
+		tree.insert(1u32, 10u32).unwrap();
+		tree.insert(0u32, 20u32).unwrap();
+		let mut csv = Vec::new();
+		tree.export_csv(&mut csv).unwrap();
+		assert_eq!(String::from_utf8(csv).unwrap(), "key,value\n0,20\n1,10\n");
+
+		let tree: Tree<String, (u32, String)> = db.open_tree("tuples").unwrap();
+		tree.insert("b", (2, "y".to_string())).unwrap();
+		tree.insert("a", (1, "x,z".to_string())).unwrap();
+		let mut csv = Vec::new();
+		tree.export_csv(&mut csv).unwrap();
+		assert_eq!(
+			String::from_utf8(csv).unwrap(),
+			"key,value0,value1\na,1,\"x,z\"\nb,2,y\n"
+		);
+	})
+}
+
+#[cfg(feature = "sync")]
+#[test]
+fn sync_inline() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let threads = Arc::new(parking_lot::Mutex::new(Vec::new()));
+		let map = tree.map(cloned!(threads, move |_, v| {
+			threads.lock().push(std::thread::current().id());
+			*v * 2
+		}));
+		let stored = map.store("sync_inline").unwrap();
+
+		insert(&tree, 1);
+
+		// The inner tree is read directly, without waiting on the stored map
+		for i in 0..TEST_SIZE {
+			assert_eq!((*stored).get_ref(&i).unwrap(), Some(i * 2));
+		}
+		let current = std::thread::current().id();
+		assert!(threads.lock().iter().all(|id| *id == current));
+	})
+}
+
+#[cfg(feature = "rayon")]
+#[test]
+fn par_rebuild() {
+	use std::collections::BTreeMap;
+	with_tree(|tree: Tree<u32, u32>| {
+		const ROWS: u32 = 200_000;
+		for i in 0..ROWS {
+			tree.insert(i, i % 1000).unwrap();
+		}
+		let index = tree.index(|_, v| vec![v % 100]).load().unwrap();
+		let transform = tree.transform(|k, v| vec![(v % 100, *k)]).load().unwrap();
+
+		// The same grouping, computed sequentially in key order
+		let mut values: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+		let mut keys: BTreeMap<u32, Vec<u32>> = BTreeMap::new();
+		for res in tree.iter() {
+			let (k, v) = res.unwrap();
+			values.entry(v % 100).or_default().push(v);
+			keys.entry(v % 100).or_default().push(k);
+		}
+
+		let index: BTreeMap<_, _> = index.iter().map(Result::unwrap).collect();
+		let transform: BTreeMap<_, _> = transform.iter().map(Result::unwrap).collect();
+		assert_eq!(index, values);
+		assert_eq!(transform, keys);
+	})
+}
+
+/// A key that can be hashed, but has no order
+#[derive(Clone, PartialEq, Eq, Hash)]
+struct Color(u8, u8, u8);
+impl Serial for Color {
+	fn serialize(&self) -> crate::error::Result<Vec<u8>> {
+		Ok(vec![self.0, self.1, self.2])
+	}
+	fn deserialize(bytes: Vec<u8>) -> crate::error::Result<Self> {
+		match bytes[..] {
+			[r, g, b] => Ok(Color(r, g, b)),
+			_ => Err(anyhow::anyhow!("Invalid color").into()),
+		}
+	}
+}
+
+#[test]
+fn loaded_snapshot() {
+	with_db(|db: Db| {
+		let snapshot: Tree<u32, u32> = db.open_tree("snapshot").unwrap();
+		snapshot.insert(TEST_SIZE, 0u32).unwrap();
+		{
+			let loaded: Loaded<u32, u32> = (0..TEST_SIZE).map(|i| (i, i * i)).collect();
+			loaded.snapshot(&snapshot).unwrap();
+		}
+		// Stale entries are cleared from the snapshot
+		assert_eq!(snapshot.get(TEST_SIZE).unwrap(), None);
+
+		let restored = Loaded::restore(&snapshot).unwrap();
+		assert_u32(&restored, 2);
+		assert_eq!(restored.iter().count(), TEST_SIZE as usize);
+	})
+}
+
+#[test]
+fn load_unordered() {
+	with_tree(|tree: Tree<Color, u32>| {
+		tree.insert(Color(255, 0, 0), 1u32).unwrap();
+		let loaded = tree.load_unordered().unwrap();
+		tree.insert(Color(0, 255, 0), 2u32).unwrap();
+
+		assert_eq!(loaded.get(Color(255, 0, 0)).unwrap(), Some(1));
+		assert_eq!(loaded.get(Color(0, 255, 0)).unwrap(), Some(2));
+		assert_eq!(loaded.get(Color(0, 0, 255)).unwrap(), None);
+		assert_eq!(loaded.iter().count(), 2);
+		assert!(loaded.range(..).is_err());
+
+		tree.remove(Color(255, 0, 0)).unwrap();
+		assert_eq!(loaded.get(Color(255, 0, 0)).unwrap(), None);
+	})
+}
+
+#[test]
+fn zip_unordered() {
+	with_db(|db| {
+		let a: Tree<Color, u32> = db.open_tree("a").unwrap();
+		let b: Tree<Color, u32> = db.open_tree("b").unwrap();
+		let zipped = a.zip_unordered(&b);
+		let loaded = zipped.load_unordered().unwrap();
+
+		a.insert(Color(255, 0, 0), 1u32).unwrap();
+		a.insert(Color(0, 255, 0), 2u32).unwrap();
+		b.insert(Color(0, 255, 0), 3u32).unwrap();
+
+		let mut values = zipped.iter().map(|r| r.unwrap().1).collect::<Vec<_>>();
+		values.sort();
+		assert_eq!(values, vec![(Some(1), None), (Some(2), Some(3))]);
+		assert_eq!(zipped.get(Color(0, 0, 255)).unwrap(), None);
+		assert_eq!(loaded.get(Color(0, 255, 0)).unwrap(), Some((Some(2), Some(3))));
+
+		a.remove(Color(255, 0, 0)).unwrap();
+		assert_eq!(loaded.get(Color(255, 0, 0)).unwrap(), None);
+	})
+}
+
+#[test]
+fn incr() {
+	with_tree(|tree: Tree<u32, i64>| {
+		let total = 8 * TEST_SIZE as i64;
+		let mut reader = tree.watch();
+		let threads = (0..8)
+			.map(|_| {
+				let tree = tree.clone();
+				std::thread::spawn(move || {
+					for _ in 0..TEST_SIZE {
+						tree.incr(0u32, 1).unwrap();
+					}
+				})
+			})
+			.collect::<Vec<_>>();
+		// Every increment sends the count it wrote, so the highest one is the total
+		let mut highest = 0;
+		for _ in 0..total {
+			if let Event::Insert { value, .. } = reader.recv().unwrap() {
+				highest = highest.max(*value);
+			}
+		}
+		for thread in threads {
+			thread.join().unwrap();
+		}
+		assert_eq!(highest, total);
+		assert_eq!(tree.get(0u32).unwrap(), Some(total));
+		assert_eq!(tree.incr(0u32, -total).unwrap(), 0);
+	})
+}
+
+#[test]
+fn swap() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 1);
+		let map = tree.map(|_, v| v * 2);
+		let loaded = map.load().unwrap();
+		assert_eq!(tree.swap(&TEST_SIZE, &0).unwrap(), None);
+
+		let mut reader = tree.watch();
+		assert_eq!(tree.swap(&1, &TEST_SIZE).unwrap(), Some(1));
+		assert!(matches!(reader.recv().unwrap(), Event::Remove { key } if *key == 1));
+		let inserted = reader.recv().unwrap();
+		assert!(matches!(inserted, Event::Insert { key, value } if *key == TEST_SIZE && *value == 1));
+
+		assert_eq!(loaded.get(1u32).unwrap(), None);
+		assert_eq!(loaded.get(TEST_SIZE).unwrap(), Some(2));
+		assert_eq!(map.get(TEST_SIZE).unwrap(), Some(2));
+
+		// The default moves through a remove and an insert
+		let temp: Loaded<u32, u32> = Loaded::new();
+		temp.insert(0u32, 1u32).unwrap();
+		assert_eq!(temp.swap(&0, &1).unwrap(), Some(1));
+		assert_eq!(temp.iter().map(|r| r.unwrap()).collect::<Vec<_>>(), vec![(1, 1)]);
+
+		// A value that can't be read isn't moved, since its events couldn't be sent
+		let bad: Tree<u32, Unserializable> = tree.db().open_tree("bad").unwrap();
+		let key = Serial::serialize(&1u32).unwrap();
+		bad.to_inner().insert(&key, vec![0u8]).unwrap();
+		assert!(bad.swap(&1, &2).is_err());
+		assert!(bad.to_inner().contains_key(&key).unwrap());
+		assert_eq!(bad.to_inner().len(), 1);
+	})
+}
+
+#[test]
+fn insert_if_absent() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let mut reader = tree.watch();
+		let threads = (0..8)
+			.map(|i| {
+				let tree = tree.clone();
+				std::thread::spawn(move || tree.insert_if_absent(&0, &i).unwrap())
+			})
+			.collect::<Vec<_>>();
+		let results = threads.into_iter().map(|t| t.join().unwrap()).collect::<Vec<_>>();
+
+		// Exactly one thread inserted, and the others got its value
+		let value = tree.get(0u32).unwrap().unwrap();
+		assert_eq!(results.iter().filter(|r| r.is_none()).count(), 1);
+		assert!(results.iter().flatten().all(|v| *v == value));
+		assert!(matches!(reader.recv().unwrap(), Event::Insert { value: v, .. } if *v == value));
+		tree.wait();
+		assert!(reader.try_recv().is_err());
+
+		// The default looks the key up first
+		let loaded: Loaded<u32, u32> = Loaded::new();
+		assert_eq!(loaded.insert_if_absent(&0, &1).unwrap(), None);
+		assert_eq!(loaded.insert_if_absent(&0, &2).unwrap(), Some(1));
+	})
+}
+
+#[test]
+fn cache() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 2);
+		let cache = tree.cache(3);
+		for i in 0..3u32 {
+			assert_eq!(cache.get(i).unwrap(), Some(i.pow(2)));
+		}
+		// Using the first key makes the second the least recently used
+		cache.get(0u32).unwrap();
+		cache.get(3u32).unwrap();
+		assert!(cache.is_cached(&0));
+		assert!(!cache.is_cached(&1));
+		assert!(cache.is_cached(&2));
+		assert!(cache.is_cached(&3));
+
+		// Evicted keys are loaded from the source again
+		tree.insert(1u32, 1000u32).unwrap();
+		assert_eq!(cache.get(1u32).unwrap(), Some(1000));
+		assert!(cache.is_cached(&1));
+		assert!(!cache.is_cached(&2));
+
+		// Cached keys follow the source
+		tree.insert(3u32, 3000u32).unwrap();
+		assert_eq!(cache.get(3u32).unwrap(), Some(3000));
+		tree.remove(0u32).unwrap();
+		assert!(!cache.is_cached(&0));
+		assert_eq!(cache.get(0u32).unwrap(), None);
+	})
+}
+
+#[test]
+fn apply_batch() {
+	with_tree(|tree: Tree<u32, String>| {
+		let inserter = tree.inserter(|v: u32| v.to_string());
+		let loaded = tree.load().unwrap();
+		inserter.insert(0u32, 0u32).unwrap();
+
+		let mut batch = Batch::default();
+		for i in 1..TEST_SIZE {
+			batch.insert(i, i).unwrap();
+		}
+		batch.remove(0u32).unwrap();
+		inserter.apply_batch(batch).unwrap();
+
+		assert_eq!(tree.get(0u32).unwrap(), None);
+		for i in 1..TEST_SIZE {
+			assert_eq!(tree.get(i).unwrap(), Some(i.to_string()));
+			assert_eq!(loaded.get(i).unwrap(), Some(i.to_string()));
+		}
+		assert_eq!(loaded.get(0u32).unwrap(), None);
+	})
+}
+
+#[test]
+fn batch_len() {
+	let mut batch: Batch<u32, u32> = Batch::default();
+	assert!(batch.is_empty());
+	for i in 0..3u32 {
+		batch.insert(i, i.pow(2)).unwrap();
+	}
+	batch.remove(1u32).unwrap();
+	assert_eq!(batch.len(), 4);
+	let ops = batch.iter().map(|(k, v)| (*k, v.copied())).collect::<Vec<_>>();
+	assert_eq!(ops, vec![(0, Some(0)), (1, Some(1)), (2, Some(4)), (1, None)]);
+}
+
+#[test]
+fn compare_and_swap() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let mut reader = tree.watch();
+		tree.insert(1u32, 1u32).unwrap();
+		reader.recv().unwrap();
+
+		assert!(!tree.compare_and_swap(&1, Some(&2), Some(&3)).unwrap());
+		assert_eq!(tree.get(1u32).unwrap(), Some(1));
+		assert!(reader.try_recv().is_err());
+
+		assert!(tree.compare_and_swap(&1, Some(&1), Some(&3)).unwrap());
+		assert_eq!(tree.get(1u32).unwrap(), Some(3));
+		match reader.recv().unwrap() {
+			Event::Insert { key, value } => assert_eq!((*key, *value), (1, 3)),
+			Event::Remove { .. } => panic!("Expected an insert"),
+		}
+	})
+}
+
+#[test]
+fn merge() {
+	with_tree(|tree: Tree<u32, u32>| {
+		assert!(tree.merge(&0, &1).is_err());
+		tree.set_merge_operator(|_, old, add| match old.unwrap_or(0) + add {
+			0 => None,
+			sum => Some(sum),
+		});
+		let stored = tree.map(|_, v| *v).store("stored_merge").unwrap();
+		std::thread::scope(|s| {
+			for _ in 0..4 {
+				s.spawn(|| {
+					for i in 0..TEST_SIZE {
+						tree.merge(&0, &i).unwrap();
+					}
+				});
+			}
+		});
+		let total = 4 * (0..TEST_SIZE).sum::<u32>();
+		assert_eq!(tree.get(0u32).unwrap(), Some(total));
+		assert_eq!(stored.get(0u32).unwrap(), Some(total));
+
+		// A merge that returns nothing removes the key
+		let mut reader = tree.watch();
+		assert_eq!(tree.merge(&1, &0).unwrap(), None);
+		assert!(matches!(reader.recv().unwrap(), Event::Remove { .. }));
+		assert_eq!(tree.merge(&1, &5).unwrap(), Some(5));
+	})
+}
+
+#[test]
+fn update_and_fetch() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let map = tree.map(|_, v| v.to_string());
+		std::thread::scope(|s| {
+			for _ in 0..4 {
+				s.spawn(|| {
+					for _ in 0..TEST_SIZE {
+						let inc = |v: Option<u32>| Some(v.unwrap_or(0) + 1);
+						map.update_and_fetch(&0, inc).unwrap();
+					}
+				});
+			}
+		});
+		assert_eq!(map.get(0u32).unwrap(), Some((TEST_SIZE * 4).to_string()));
+		let new = map.update_and_fetch(&0, |v| v.map(|v| v * 2)).unwrap();
+		assert_eq!(new, Some(TEST_SIZE * 8));
+		assert_eq!(map.update_and_fetch(&0, |_| None).unwrap(), None);
+		assert_eq!(tree.get(0u32).unwrap(), None);
+
+		let inserter = tree.inserter(|v: u32| v + 1);
+		assert_eq!(inserter.update_and_fetch(&1, |_| Some(1)).unwrap(), Some(1));
+		assert_eq!(tree.get(1u32).unwrap(), Some(2));
+	})
+}
+
+#[test]
+fn update_errors() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let mut reader = tree.watch();
+		// Nothing changes when a missing key stays missing
+		assert_eq!(tree.update_and_fetch(&0, |_| None).unwrap(), None);
+
+		// A value that can't be read or written leaves the key as it was
+		let bad: Tree<u32, Unserializable> = tree.db().open_tree("bad").unwrap();
+		let mut bad_reader = bad.watch();
+		let key = Serial::serialize(&1u32).unwrap();
+		bad.to_inner().insert(&key, vec![0u8]).unwrap();
+		assert!(bad.update_and_fetch(&1, |_| None).is_err());
+		assert_eq!(bad.to_inner().get(&key).unwrap().unwrap(), vec![0u8]);
+		assert!(bad.update_and_fetch(&2, |_| Some(Unserializable)).is_err());
+		assert_eq!(bad.to_inner().len(), 1);
+
+		assert!(reader.try_recv().is_err());
+		assert!(bad_reader.try_recv().is_err());
+	})
+}
+
+#[test]
+fn gt() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 2);
+		for i in 0..(TEST_SIZE - 1) {
+			let gt = tree.get_gt(i).unwrap().unwrap();
+			let e = i + 1;
+			let e = (e, e.pow(2));
+			assert_eq!(gt, e);
+		}
+	});
+}
+
+#[test]
+fn range() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 2);
+		let mut range = tree.range(10..TEST_SIZE).unwrap();
+		for i in 10..TEST_SIZE {
+			let e = (i, i.pow(2));
+			let r = range.next().unwrap().unwrap();
+			assert_eq!(e, r);
+		}
+	});
+}
+
+#[test]
+fn count_by() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let stored = tree.count_by(|_, v| v % 3).store("stored_count_by").unwrap();
+		let loaded = tree.count_by(|_, v| v % 3).load().unwrap();
+		insert(&tree, 1);
+		let expected = vec![(0, 14), (1, 13), (2, 13)];
+		assert_eq!(stored.iter().flatten().collect::<Vec<_>>(), expected);
+		assert_eq!(loaded.iter().flatten().collect::<Vec<_>>(), expected);
+
+		// Moving every row into category 0 empties the others
+		for i in 0..TEST_SIZE {
+			tree.insert(i, i * 3).unwrap();
+		}
+		assert_eq!(stored.get(0u32).unwrap(), Some(TEST_SIZE as u64));
+		assert_eq!(stored.get(1u32).unwrap(), None);
+		assert_eq!(loaded.get(0u32).unwrap(), Some(TEST_SIZE as u64));
+		assert_eq!(loaded.get(2u32).unwrap(), None);
+
+		// Moving a row back, then removing rows
+		tree.insert(0u32, 1u32).unwrap();
+		tree.remove(1u32).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), Some(TEST_SIZE as u64 - 2));
+		assert_eq!(stored.get(1u32).unwrap(), Some(1));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(TEST_SIZE as u64 - 2));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(1));
+	})
+}
+
+#[test]
+fn group_by() {
+	with_tree(|tree: Tree<u32, (u32, u32)>| {
+		let grouped = tree.group_by(
+			|_, (group, _)| *group,
+			|acc, _, (_, v)| acc.unwrap_or(0) + v,
+			|acc, _, (_, v)| acc - v,
+		);
+		let stored = grouped
+			.store("stored_group_by")
+			.expect("Failed to store group by");
+		let loaded = grouped.load().unwrap();
+
+		for i in 0..TEST_SIZE {
+			tree.insert(i, (i % 2, i)).unwrap();
+		}
+
+		let evens = (0..TEST_SIZE).filter(|i| i % 2 == 0).sum::<u32>();
+		let odds = (0..TEST_SIZE).filter(|i| i % 2 == 1).sum::<u32>();
+		assert_eq!(stored.get(0u32).unwrap(), Some(evens));
+		assert_eq!(stored.get(1u32).unwrap(), Some(odds));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(evens));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(odds));
+
+		// Moves a row into a different group
+		tree.insert(2u32, (1u32, 2u32)).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), Some(evens - 2));
+		assert_eq!(stored.get(1u32).unwrap(), Some(odds + 2));
+		assert_eq!(loaded.get(0u32).unwrap(), Some(evens - 2));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(odds + 2));
+
+		// Moves a row into a new group
+		tree.insert(3u32, (2u32, 3u32)).unwrap();
+		assert_eq!(stored.get(1u32).unwrap(), Some(odds - 1));
+		assert_eq!(stored.get(2u32).unwrap(), Some(3));
+		assert_eq!(loaded.get(1u32).unwrap(), Some(odds - 1));
+		assert_eq!(loaded.get(2u32).unwrap(), Some(3));
+
+		// Removing the last row removes the group
+		tree.remove(3u32).unwrap();
+		assert_eq!(stored.get(2u32).unwrap(), None);
+		assert_eq!(loaded.get(2u32).unwrap(), None);
+
+		for i in 0..TEST_SIZE {
+			tree.remove(i).unwrap();
+		}
+
+		assert!(stored.iter().next().is_none());
+		assert!(loaded.iter().next().is_none());
+	});
+}
+
+#[test]
+fn aggregate() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let sum = tree.sum().store("stored_sum").unwrap();
+		let count = tree.count().load().unwrap();
+		let min = tree.min().store("stored_min").unwrap();
+		let max = tree.max().load().unwrap();
+
+		insert(&tree, 1);
+		let total = (0..TEST_SIZE).sum::<u32>();
+		assert_eq!(sum.get().unwrap(), Some(total));
+		assert_eq!(count.get().unwrap(), Some(TEST_SIZE as u64));
+		assert_eq!(min.get().unwrap(), Some(0));
+		assert_eq!(max.get().unwrap(), Some(TEST_SIZE - 1));
+
+		// Replaces a value, the old one is taken back out
+		tree.insert(1u32, 100u32).unwrap();
+		assert_eq!(sum.get().unwrap(), Some(total + 99));
+		assert_eq!(count.get().unwrap(), Some(TEST_SIZE as u64));
+		assert_eq!(max.get().unwrap(), Some(100));
+
+		// Replacing the extremes with worse values rescans
+		tree.insert(1u32, 1u32).unwrap();
+		tree.insert(0u32, 50u32).unwrap();
+		assert_eq!(min.get().unwrap(), Some(1));
+		assert_eq!(max.get().unwrap(), Some(50));
+
+		// Removing the extremes rescans
+		tree.remove(0u32).unwrap();
+		tree.remove(1u32).unwrap();
+		assert_eq!(sum.get().unwrap(), Some(total - 1));
+		assert_eq!(count.get().unwrap(), Some(TEST_SIZE as u64 - 2));
+		assert_eq!(min.get().unwrap(), Some(2));
+		assert_eq!(max.get().unwrap(), Some(TEST_SIZE - 1));
+
+		for i in 0..TEST_SIZE {
+			tree.remove(i).unwrap();
+		}
+		assert_eq!(sum.get().unwrap(), Some(0));
+		assert_eq!(count.get().unwrap(), Some(0));
+		assert_eq!(min.get().unwrap(), None);
+		assert_eq!(max.get().unwrap(), None);
+	});
+}
+
+#[test]
+fn scan() {
+	with_tree(|tree: Tree<u32, i32>| {
+		let stored = tree
+			.scan(0, |acc, _, delta| acc + delta)
+			.store("stored_scan")
+			.unwrap();
+		let reset = tree
+			.scan(0, |acc, _, delta| acc + delta)
+			.reset_on_remove()
+			.load()
+			.unwrap();
+
+		// Alternating deltas, the running sum goes 1, -1, 2, -2...
+		let mut total = 0;
+		for i in 0..TEST_SIZE {
+			let delta = match i % 2 {
+				0 => i as i32 + 1,
+				_ => -(i as i32 + 1),
+			};
+			total += delta;
+			tree.insert(i, delta).unwrap();
+			assert_eq!(stored.get(i).unwrap(), Some(total));
+			assert_eq!(reset.get(i).unwrap(), Some(total));
+		}
+		assert_eq!(stored.state().unwrap(), Some(total));
+
+		// A remove keeps the state, unless it is reset
+		tree.remove(0u32).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), None);
+		assert_eq!(reset.get(0u32).unwrap(), None);
+		assert_eq!(stored.state().unwrap(), Some(total));
+		assert_eq!(reset.state().unwrap(), Some(0));
+
+		tree.insert(0u32, 5).unwrap();
+		assert_eq!(stored.get(0u32).unwrap(), Some(total + 5));
+		assert_eq!(reset.get(0u32).unwrap(), Some(5));
+	});
+}
+
+#[test]
+fn anti_join() {
+	with_db(|db| {
+		let orders: Tree<u32, u32> = db.open_tree("orders").unwrap();
+		let shipped: Tree<u32, String> = db.open_tree("shipped").unwrap();
+		let pending = orders.anti_join(&shipped).store("pending").unwrap();
+		let done = orders.semi_join(&shipped).store("done").unwrap();
+		insert(&orders, 1);
+		assert_u32(&pending, 1);
+		assert_none(&done);
+
+		// A late shipment retracts the order from the anti join
+		for i in (0..TEST_SIZE).filter(|i| i % 2 == 0) {
+			shipped.insert(i, "shipped").unwrap();
+		}
+		for i in 0..TEST_SIZE {
+			let shipped = i % 2 == 0;
+			assert_eq!(pending.get(i).unwrap(), (!shipped).then_some(i));
+			assert_eq!(done.get(i).unwrap(), shipped.then_some(i));
+		}
+
+		// And it comes back once the shipment is removed
+		shipped.remove(0u32).unwrap();
+		assert_eq!(pending.get(0u32).unwrap(), Some(0));
+		assert_eq!(done.get(0u32).unwrap(), None);
+	})
+}
+
+#[test]
+fn set() {
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+		let union = a.union(&b).store("stored_union").unwrap();
+		let intersection = a.intersection(&b).store("stored_intersection").unwrap();
+		let difference = a.difference(&b).store("stored_difference").unwrap();
+
+		// a holds the even keys, b holds the multiples of three
+		for i in (0..TEST_SIZE).filter(|i| i % 2 == 0) {
+			a.insert(i, 2u32).unwrap();
+		}
+		for i in (0..TEST_SIZE).filter(|i| i % 3 == 0) {
+			b.insert(i, 3u32).unwrap();
+		}
+
+		for i in 0..TEST_SIZE {
+			let (in_a, in_b) = (i % 2 == 0, i % 3 == 0);
+			let expected = if in_a { Some(2) } else if in_b { Some(3) } else { None };
+			assert_eq!(union.get(i).unwrap(), expected);
+			let expected = (in_a && in_b).then_some(2);
+			assert_eq!(intersection.get(i).unwrap(), expected);
+			let expected = (in_a && !in_b).then_some(2);
+			assert_eq!(difference.get(i).unwrap(), expected);
+		}
+
+		let keys = |v: &dyn Fn() -> Vec<(u32, u32)>| v().into_iter().map(|(k, _)| k).collect::<Vec<_>>();
+		let live = a.intersection(&b);
+		let expected = (0..TEST_SIZE).filter(|i| i % 6 == 0).collect::<Vec<_>>();
+		assert_eq!(keys(&|| live.iter().flatten().collect()), expected);
+		assert_eq!(live.first().unwrap().map(|(k, _)| k), Some(0));
+		assert_eq!(live.get_gt(0u32).unwrap().map(|(k, _)| k), Some(6));
+		assert_eq!(live.get_lt(12u32).unwrap().map(|(k, _)| k), Some(6));
+		let last = expected.last().copied();
+		assert_eq!(live.last().unwrap().map(|(k, _)| k), last);
+
+		let live = a.difference(&b);
+		let expected = (0..TEST_SIZE).filter(|i| i % 2 == 0 && i % 3 != 0).collect::<Vec<_>>();
+		assert_eq!(keys(&|| live.iter().flatten().collect()), expected);
+		assert_eq!(live.first().unwrap().map(|(k, _)| k), Some(2));
+		assert_eq!(live.get_gt(4u32).unwrap().map(|(k, _)| k), Some(8));
+
+		// A key leaves the difference when it's inserted into b
+		b.insert(2u32, 3u32).unwrap();
+		assert_eq!(difference.get(2u32).unwrap(), None);
+		assert_eq!(intersection.get(2u32).unwrap(), Some(2));
+		assert_eq!(union.get(2u32).unwrap(), Some(2));
+
+		// A key leaves the intersection when it's removed from a
+		a.remove(2u32).unwrap();
+		assert_eq!(intersection.get(2u32).unwrap(), None);
+		assert_eq!(union.get(2u32).unwrap(), Some(3));
+	});
+}
+
+#[test]
+fn first_n() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 1);
+		let loaded = tree.load().unwrap();
+		let filter = tree.filter(|_, _| true);
+		let entries = |range: std::ops::Range<u32>| range.map(|k| (k, k)).collect::<Vec<_>>();
+		let first = entries(0..5);
+		let last = entries(TEST_SIZE - 5..TEST_SIZE);
+		assert_eq!(tree.first_n(5).unwrap(), first);
+		assert_eq!(tree.last_n(5).unwrap(), last);
+		assert_eq!(loaded.first_n(5).unwrap(), first);
+		assert_eq!(loaded.last_n(5).unwrap(), last);
+		assert_eq!(filter.first_n(5).unwrap(), first);
+		assert_eq!(filter.last_n(5).unwrap(), last);
+
+		// Asking for more than there is returns everything
+		let all = entries(0..TEST_SIZE);
+		assert_eq!(tree.last_n(TEST_SIZE as usize + 10).unwrap(), all);
+		assert_eq!(loaded.last_n(TEST_SIZE as usize + 10).unwrap(), all);
+		assert_eq!(filter.last_n(TEST_SIZE as usize + 10).unwrap(), all);
+		assert_eq!(filter.first_n(TEST_SIZE as usize + 10).unwrap(), all);
+		assert!(filter.last_n(0).unwrap().is_empty());
+	})
+}
+
+#[test]
+fn nth() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 2);
+		tree.remove(3u32).unwrap();
+		let entries = tree.iter().collect::<crate::error::Result<Vec<_>>>().unwrap();
+		let loaded = tree.map(|_, v| *v).load().unwrap();
+		let filter = tree.filter(|k, _| k % 2 == 0);
+		let evens = filter.iter().collect::<crate::error::Result<Vec<_>>>().unwrap();
+		for n in 0..TEST_SIZE as usize + 1 {
+			assert_eq!(tree.nth(n).unwrap(), entries.get(n).cloned());
+			assert_eq!(loaded.nth(n).unwrap(), entries.get(n).cloned());
+			assert_eq!(filter.nth(n).unwrap(), evens.get(n).cloned());
+		}
+
+		let page = tree.skip(5).take(3);
+		assert_eq!(page.nth(0).unwrap(), entries.get(5).cloned());
+		assert_eq!(page.nth(2).unwrap(), entries.get(7).cloned());
+		assert_eq!(page.nth(3).unwrap(), None);
+		let page = tree.take(3).after(20u32).skip(1);
+		assert_eq!(page.nth(1).unwrap(), Some((23, 23u32.pow(2))));
+	});
+}
+
+#[test]
+fn sample() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 1);
+		let sample = tree.sample(4);
+		let keys = |iter: Box<dyn Iterator<Item = crate::error::Result<(u32, u32)>>>| {
+			iter.map(|r| r.unwrap().0).collect::<Vec<_>>()
+		};
+		let expected = (0..TEST_SIZE).step_by(4).collect::<Vec<_>>();
+		// Each iteration starts its own count
+		assert_eq!(keys(sample.iter()), expected);
+		assert_eq!(keys(sample.iter()), expected);
+		assert_eq!(keys(sample.range(5..15).unwrap()), vec![5, 9, 13]);
+		assert_eq!(sample.get(8u32).unwrap(), Some(8));
+		assert_eq!(sample.get(9u32).unwrap(), None);
+		assert_eq!(sample.get_lt(8u32).unwrap(), Some((4, 4)));
+		assert_eq!(sample.get_gt(8u32).unwrap(), Some((12, 12)));
+		assert_eq!(sample.last().unwrap().map(|(k, _)| k), expected.last().copied());
+
+		// Positions shift as entries change, and events pass through
+		let mut reader = sample.watch();
+		tree.remove(0u32).unwrap();
+		assert!(matches!(reader.recv().unwrap(), Event::Remove { .. }));
+		assert_eq!(sample.first().unwrap(), Some((1, 1)));
+		assert_eq!(sample.get(5u32).unwrap(), Some(5));
+	});
+}
+
+#[test]
+fn paginate() {
+	with_tree(|tree: Tree<u32, u32>| {
+		insert(&tree, 2);
+		let keys = |page: &Paginate<Tree<u32, u32>>| {
+			page.iter().map(|r| r.unwrap().0).collect::<Vec<_>>()
+		};
+
+		let page = tree.skip(10).take(5);
+		assert_eq!(keys(&page), (10..15).collect::<Vec<_>>());
+		assert_eq!(page.first().unwrap(), Some((10, 100)));
+		assert_eq!(page.last().unwrap(), Some((14, 196)));
+		assert_eq!(page.get(9u32).unwrap(), None);
+		assert_eq!(page.get(12u32).unwrap(), Some(144));
+		assert_eq!(page.get(15u32).unwrap(), None);
+		assert_eq!(page.get_lt(10u32).unwrap(), None);
+		assert_eq!(page.get_gt(14u32).unwrap(), None);
+		let range = page.range(12..20).unwrap().map(|r| r.unwrap().0);
+		assert_eq!(range.collect::<Vec<_>>(), vec![12, 13, 14]);
+
+		// Pages through the tree from a cursor
+		let mut page = tree.take(7);
+		let mut seen = Vec::new();
+		loop {
+			seen.extend(keys(&page));
+			match page.next_page().unwrap() {
+				Some(next) => page = next,
+				None => break,
+			}
+		}
+		assert_eq!(seen, (0..TEST_SIZE).collect::<Vec<_>>());
+
+		// Removing entries before the cursor does not shift the page
+		let page = tree.take(3).after(20u32);
+		tree.remove(5u32).unwrap();
+		assert_eq!(keys(&page), vec![21, 22, 23]);
+	});
+}
+
+#[test]
+fn single() {
+	with_db(|db| {
+		let single = db.open_single::<_, u32>("single".to_string()).unwrap();
+		let mapped = single.map(|_, v| v * 2);
+		let stored = mapped.store("stored_single").unwrap();
+
+		single.insert(2u32).unwrap();
+		assert_eq!(mapped.get(()).unwrap(), Some(4));
+		assert_eq!(stored.get(()).unwrap(), Some(4));
+
+		// Reinserting updates the operations
+		single.insert(3u32).unwrap();
+		assert_eq!(mapped.get(()).unwrap(), Some(6));
+		assert_eq!(stored.get(()).unwrap(), Some(6));
+
+		single.fetch_and_update(&(), |v| v.map(|v| v + 1)).unwrap();
+		assert_eq!(stored.get(()).unwrap(), Some(8));
+
+		single.remove().unwrap();
+		assert_eq!(mapped.get(()).unwrap(), None);
+		assert_eq!(stored.get(()).unwrap(), None);
+		assert!(stored.iter().next().is_none());
+	});
+}
+
+#[test]
+fn push() {
+	with_tree(|tree: Tree<u32, u32>| {
+		let threads = 8;
+		let handles = (0..threads)
+			.map(|t| {
+				let tree = tree.clone();
+				std::thread::spawn(move || {
+					(0..TEST_SIZE)
+						.map(|i| tree.push(t * TEST_SIZE + i).unwrap())
+						.collect::<Vec<_>>()
+				})
+			})
+			.collect::<Vec<_>>();
+		let mut keys = handles
+			.into_iter()
+			.flat_map(|h| h.join().unwrap())
+			.collect::<Vec<_>>();
+		keys.sort_unstable();
+
+		// Every push got its own key, and every value was kept
+		let total = threads * TEST_SIZE;
+		assert_eq!(keys, (1..=total).collect::<Vec<_>>());
+		let mut values = tree.iter().map(|r| r.unwrap().1).collect::<Vec<_>>();
+		values.sort_unstable();
+		assert_eq!(values, (0..total).collect::<Vec<_>>());
+	});
+}
+
+#[test]
+fn auto_inc_overflow() {
+	use crate::traits::auto_inc::{AutoInc, Sequence};
+	assert_eq!(254u8.try_next(), Some(255));
+	assert_eq!(255u8.try_next(), None);
+	assert_eq!(u64::MAX.try_next(), None);
+
+	let last = Sequence::<7>::new(Sequence::<7>::MAX_COUNTER).unwrap();
+	assert_eq!(last.shard(), 7);
+	assert_eq!(last.try_next(), None);
+	assert_eq!(Sequence::<7>::new(Sequence::<7>::MAX_COUNTER + 1), None);
+
+	with_db(|db| {
+		// Pushing fails once the sequence is exhausted
+		let tree: Tree<u8, u32> = db.open_tree("u8").unwrap();
+		for i in 1..=255u8 {
+			assert_eq!(tree.push(0u32).unwrap(), i);
+		}
+		assert!(tree.push(0u32).is_err());
+
+		// Each shard counts on its own, and the shards sort apart
+		let a: Tree<Sequence<1>, u32> = db.open_tree("a").unwrap();
+		let b: Tree<Sequence<2>, u32> = db.open_tree("b").unwrap();
+		for i in 1..=TEST_SIZE as u64 {
+			let key = a.push(0u32).unwrap();
+			assert_eq!((key.shard(), key.counter()), (1, i));
+			let key = b.push(0u32).unwrap();
+			assert_eq!((key.shard(), key.counter()), (2, i));
+		}
+		let a_last = a.last().unwrap().unwrap().0;
+		let b_first = b.first().unwrap().unwrap().0;
+		assert!(Serial::serialize(&a_last).unwrap() < Serial::serialize(&b_first).unwrap());
+	})
+}
+
+#[test]
+fn tree_names_original() {
+	with_db(|db| {
+		let _users: Tree<u32, u32> = db.open_tree("users").unwrap();
+		let _orders: Tree<u32, u32> = db.open_tree("orders".to_string()).unwrap();
+		let _users: Tree<u32, u32> = db.open_tree("users").unwrap();
+
+		let mut names = db.tree_names_original().unwrap();
+		names.sort();
+		assert_eq!(names, vec!["orders", "users"]);
+		assert_eq!(db.tree_names().unwrap().len(), 2);
+
+		db.drop_tree(&"users").unwrap();
+		assert_eq!(db.tree_names_original().unwrap(), vec!["orders"]);
+	});
+}
+
+#[test]
+fn rename_tree() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("old").unwrap();
+		insert(&tree, 2);
+		let other: Tree<u32, u32> = db.open_tree("other").unwrap();
+		other.insert(0u32, 0u32).unwrap();
+
+		assert!(db.rename_tree(&"old", "other").is_err());
+		assert!(db.rename_tree(&"old", "new").unwrap());
+		assert!(!db.rename_tree(&"old", "new").unwrap());
+
+		let tree: Tree<u32, u32> = db.open_tree("new").unwrap();
+		assert_u32(&tree, 2);
+		let mut names = db.tree_names_original().unwrap();
+		names.sort();
+		assert_eq!(names, vec!["new", "other"]);
+		let old: Tree<u32, u32> = db.open_tree("old").unwrap();
+		assert_none(&old);
+	});
+}
+
+#[test]
+fn drop_store() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let index = tree.index(|_, v| vec![*v]).store("index").unwrap();
+		let enumerated = tree.enumerate().store("enumerated").unwrap();
+		insert(&tree, 2);
+		assert_eq!(index.get(4u32).unwrap(), Some(vec![4]));
+		enumerated.wait();
+		assert_eq!(db.tree_names().unwrap().len(), 4);
+
+		// Only the trees and singles of that store are dropped
+		drop(index);
+		assert!(db.drop_store(&"index").unwrap());
+		assert!(!db.drop_store(&"index").unwrap());
+		assert_eq!(db.tree_names().unwrap().len(), 2);
+		assert!(!db.to_inner().is_empty());
+
+		drop(enumerated);
+		assert!(db.drop_store(&"enumerated").unwrap());
+		assert_eq!(db.tree_names().unwrap().len(), 1);
+		assert!(db.to_inner().is_empty());
+		assert_u32(&tree, 2);
+	});
+}
+
+#[test]
+fn drop_stored_map() {
+	with_db(|db| {
+		let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+		let map = tree.map(|_, v| v * 2).store("map").unwrap();
+		insert(&tree, 2);
+		assert_eq!(map.get(2u32).unwrap(), Some(8));
+		assert_eq!(db.tree_names().unwrap().len(), 2);
+
+		drop(map);
+		assert!(db.drop_store(&"map").unwrap());
+		assert_eq!(db.tree_names().unwrap().len(), 1);
+		assert_u32(&tree, 2);
+	});
+}
+
+#[test]
+fn transaction() {
+	use sled::transaction::ConflictableTransactionError::Abort;
+	with_db(|db| {
+		let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+		let b: Tree<u64, u32> = db.open_tree("b").unwrap();
+		insert(&a, 2);
+
+		// Moves every value from a to b
+		db.transaction((&a, &b), |(a, b)| {
+			for i in 0..TEST_SIZE {
+				let value = a.remove(i).map_err(Abort)?;
+				b.insert(i as u64, value.unwrap()).map_err(Abort)?;
+			}
+			Ok(())
+		})
+		.unwrap();
+		assert_none(&a);
+		for i in 0..TEST_SIZE {
+			assert_eq!(b.get(i as u64).unwrap(), Some(i.pow(2)));
+		}
+
+		// An aborted transaction changes neither tree
+		let result = db.transaction((&a, &b), |(a, b)| {
+			let value = b.remove(0).map_err(Abort)?;
+			a.insert(0, value.unwrap()).map_err(Abort)?;
+			Err::<(), _>(Abort(anyhow::anyhow!("aborted").into()))
+		});
+		assert!(result.is_err());
+		assert_eq!(a.get(0u32).unwrap(), None);
+		assert_eq!(b.get(0u64).unwrap(), Some(0));
+	});
+}
+
+#[cfg(feature = "compress_values")]
+#[test]
+fn compress() {
+	use crate::traits::serial::compress;
+	with_tree(|tree: Tree<u32, String>| {
+		compress::set_threshold(64);
+		let large = "husky ".repeat(100);
+		tree.insert(0u32, "small").unwrap();
+		tree.insert(1u32, large.clone()).unwrap();
+
+		// Only the large value is compressed
+		let raw = tree.to_inner();
+		let small = raw.get(Serial::serialize(&0u32).unwrap()).unwrap().unwrap();
+		let stored = raw.get(Serial::serialize(&1u32).unwrap()).unwrap().unwrap();
+		assert_eq!(small.to_vec(), Serial::serialize(&"small".to_string()).unwrap());
+		assert!(stored.len() < large.len());
+
+		assert_eq!(tree.get(0u32).unwrap(), Some("small".to_string()));
+		assert_eq!(tree.get(1u32).unwrap(), Some(large.clone()));
+		let values = tree.iter().map(|r| r.unwrap().1).collect::<Vec<_>>();
+		assert_eq!(values, vec!["small".to_string(), large]);
+	});
+}
+
+impl crate::traits::serial::Migrate for u32 {
+	const VERSION: u32 = 1;
+}
+impl crate::traits::serial::Migrate for (u32, String) {
+	const VERSION: u32 = 2;
+	fn migrate(version: u32, bytes: Vec<u8>) -> crate::error::Result<Self> {
+		let count = <u32 as crate::traits::serial::Migrate>::migrate(version, bytes)?;
+		Ok((count, "migrated".to_string()))
+	}
+}
+
+#[test]
+#[cfg(feature = "messagepack")]
+fn messagepack() {
+	with_tree(|tree: Tree<u32, (String, Vec<u8>)>| {
+		let value = ("husky".to_string(), vec![1u8, 2]);
+		tree.insert(1u32, value.clone()).unwrap();
+		assert_eq!(tree.get(1u32).unwrap(), Some(value.clone()));
+
+		// The bytes are plain MessagePack, so other languages can read them
+		let bytes = Serial::serialize(&value).unwrap();
+		assert_eq!(bytes, rmp_serde::to_vec(&value).unwrap());
+		assert_eq!(bytes, [0x92, 0xa5, b'h', b'u', b's', b'k', b'y', 0x92, 0x01, 0x02]);
+		let (_, stored) = tree.to_inner().first().unwrap().unwrap();
+		assert_eq!(&*stored, &bytes[..]);
+	})
+}
+
+#[test]
+#[cfg(feature = "uuid")]
+fn uuid_keys() {
+	use crate::traits::serial::UuidKey;
+	with_tree(|tree: Tree<UuidKey, u32>| {
+		// Ids spread over all the bytes, inserted out of order
+		let ids = (0..TEST_SIZE)
+			.map(|i| uuid::Uuid::from_u128((i as u128).wrapping_mul(u128::MAX / 37)))
+			.collect::<Vec<_>>();
+		for (i, id) in ids.iter().enumerate().rev() {
+			tree.insert(*id, i as u32).unwrap();
+		}
+		let mut sorted = ids.clone();
+		sorted.sort();
+		let keys = tree.iter().map(|r| *r.unwrap().0).collect::<Vec<_>>();
+		assert_eq!(keys, sorted);
+
+		let (lo, hi) = (UuidKey(sorted[5]), UuidKey(sorted[15]));
+		let range = tree.range(lo..hi).unwrap();
+		let keys = range.map(|r| *r.unwrap().0).collect::<Vec<_>>();
+		assert_eq!(keys, sorted[5..15]);
+	})
+}
+
+#[test]
+#[cfg(feature = "chrono")]
+fn timestamp_keys() {
+	use crate::traits::serial::Timestamp;
+	use chrono::{TimeZone, Utc};
+	with_tree(|tree: Tree<Timestamp, u32>| {
+		// Times before and after the epoch, down to the nanosecond
+		let times = (0..TEST_SIZE as i64)
+			.map(|i| Utc.timestamp_nanos((i - 20) * 1_000_000_007))
+			.collect::<Vec<_>>();
+		for (i, time) in times.iter().enumerate().rev() {
+			tree.insert(*time, i as u32).unwrap();
+		}
+		let values = tree.iter().map(|r| r.unwrap().1).collect::<Vec<_>>();
+		assert_eq!(values, (0..TEST_SIZE).collect::<Vec<_>>());
+
+		let range = tree.range(Timestamp(times[10])..=Timestamp(times[30])).unwrap();
+		let keys = range.map(|r| *r.unwrap().0).collect::<Vec<_>>();
+		assert_eq!(keys, times[10..=30]);
+
+		let far = Utc.with_ymd_and_hms(3000, 1, 1, 0, 0, 0).unwrap();
+		assert!(tree.insert(far, 0u32).is_err());
+	})
+}
+
+#[test]
+fn versioned() {
+	use crate::traits::serial::Versioned;
+	with_db(|db| {
+		let v1: Tree<u32, Versioned<u32>> = db.open_tree("versioned").unwrap();
+		v1.insert(1u32, Versioned(10u32)).unwrap();
+		let legacy: Tree<u32, u32> = db.open_tree("versioned").unwrap();
+		legacy.insert(2u32, 20u32).unwrap();
+
+		// Both the v1 and the unversioned values are migrated on read
+		let v2: Tree<u32, Versioned<(u32, String)>> = db.open_tree("versioned").unwrap();
+		let migrated = (10, "migrated".to_string());
+		assert_eq!(v2.get(1u32).unwrap(), Some(Versioned(migrated)));
+		let migrated = (20, "migrated".to_string());
+		assert_eq!(v2.get(2u32).unwrap(), Some(Versioned(migrated)));
+
+		// Current values are read as they are
+		v2.insert(3u32, Versioned((30u32, "current".to_string()))).unwrap();
+		let current = (30, "current".to_string());
+		assert_eq!(v2.get(3u32).unwrap(), Some(Versioned(current)));
+
+		// Newer values can't be read by older schemas
+		assert!(v1.get(3u32).is_err());
+	});
+}
+
+#[test]
+#[cfg(feature = "rkyv")]
+fn versioned_legacy() {
+	use crate::traits::serial::Versioned;
+	with_db(|db| {
+		// Unversioned values that start like a versioned payload are still read as version 0
+		let legacy: Tree<u32, u32> = db.open_tree("versioned").unwrap();
+		let values = [0x6876_0000, u32::from_be_bytes([0xff, 0x68, 0x75, 0x76])];
+		assert!(Serial::serialize(&values[0]).unwrap().starts_with(&[0x68, 0x76]));
+		for (k, v) in values.iter().enumerate() {
+			legacy.insert(k as u32, *v).unwrap();
+		}
+		let v2: Tree<u32, Versioned<(u32, String)>> = db.open_tree("versioned").unwrap();
+		for (k, v) in values.iter().enumerate() {
+			let migrated = (*v, "migrated".to_string());
+			assert_eq!(v2.get(k as u32).unwrap(), Some(Versioned(migrated)));
 		}
 	});
 }
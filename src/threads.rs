@@ -1,47 +1,310 @@
+use bus::{Bus, BusReader};
 use parking_lot::{Mutex, RwLock};
 use once_cell::sync::Lazy;
 use std::{
+	hash::Hash,
 	sync::{
-		atomic::{AtomicU32, Ordering::Relaxed},
-		Arc,
+		atomic::{
+			AtomicBool, AtomicU32,
+			Ordering::{Relaxed, SeqCst},
+		},
+		Arc, Weak,
 	},
 	thread::Thread,
+	time::Duration,
 };
 
-use anyhow::Result;
-use bus::{Bus, BusReader};
+use crate::{
+	error::{HuskyError, Result},
+	macros::bail,
+	traits::watch::{Event, Events},
+};
+
+/// Logs that a thread stopped, once its source was dropped.
+#[cfg(not(feature = "sync"))]
+fn log_exit() {
+	#[cfg(feature = "tracing")]
+	tracing::warn!("Husky thread exiting");
+	#[cfg(not(feature = "tracing"))]
+	eprintln!("Husky thread exiting");
+}
+
+/// Names the op of a thread after the module of its callback, as in `filter` or `material`.
+#[cfg(feature = "tracing")]
+fn op_name<F>() -> &'static str {
+	let path = std::any::type_name::<F>();
+	let path = path.split('<').next().unwrap_or(path);
+	path.split("::")
+		.take_while(|s| s.starts_with(|c: char| c.is_lowercase()))
+		.last()
+		.unwrap_or(path)
+}
 
-use crate::traits::watch::Event;
+/// Enters the span of an event, tagged with the op handling it and the key type.
+/// Keys aren't required to implement [Debug], so the span only gets their type.
+#[cfg(feature = "tracing")]
+fn enter<K, V>(op: &'static str, event: &Event<K, V>) -> tracing::span::EnteredSpan {
+	let kind = match event {
+		Event::Insert { .. } => "insert",
+		Event::Remove { .. } => "remove",
+	};
+	let key = std::any::type_name::<K>();
+	tracing::debug_span!("event", op, kind, key).entered()
+}
 
+#[cfg(not(feature = "sync"))]
 pub fn spawn(f: impl FnOnce() + Send + 'static) {
 	std::thread::spawn(f);
 }
 
-pub fn spawn_listener<K, V, F>(
-	synchronizer: Arc<Synchronizer>,
+/// Handles each event of a reader, in its own thread.
+fn run<K, V>(reader: BusReader<Event<K, V>>, handle: impl FnMut(Event<K, V>) + Send + 'static)
+where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+{
+	run_until(reader, Arc::default(), handle)
+}
+
+/// Like [run], but the reader is let go at the first event after `stopped` is set.
+#[cfg(not(feature = "sync"))]
+fn run_until<K, V>(
 	mut reader: BusReader<Event<K, V>>,
-	cb: F,
+	stopped: Arc<AtomicBool>,
+	mut handle: impl FnMut(Event<K, V>) + Send + 'static,
 ) where
 	K: 'static + Sync + Send,
 	V: 'static + Sync + Send,
-	F: 'static + Fn(Event<K, V>) -> Result<u32> + Send + Sync,
 {
 	spawn(move || {
 		while let Ok(event) = reader.recv() {
-			let sent = cb(event);
-			synchronizer.received();
-			match sent {
-				Ok(sent) => synchronizer.outgoing(sent),
-				Err(e) => eprint!("Error in Husky thread {:?}", e),
+			if stopped.load(Relaxed) {
+				return;
+			}
+			handle(event);
+		}
+		log_exit();
+	});
+}
+
+/// Handles each event of a reader, whenever the events are pumped, until `stopped` is set.
+#[cfg(feature = "sync")]
+fn run_until<K, V>(
+	mut reader: BusReader<Event<K, V>>,
+	stopped: Arc<AtomicBool>,
+	mut handle: impl FnMut(Event<K, V>) + Send + 'static,
+) where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+{
+	use std::sync::mpsc::TryRecvError;
+	let pump: Pump = Box::new(move || {
+		let mut progress = false;
+		loop {
+			if stopped.load(Relaxed) {
+				return None;
+			}
+			match reader.try_recv() {
+				Ok(event) => handle(event),
+				Err(TryRecvError::Empty) => return Some(progress),
+				Err(TryRecvError::Disconnected) => return None,
+			}
+			progress = true;
+		}
+	});
+	PUMPS.lock().push(Arc::new(Mutex::new(pump)));
+}
+
+/// The most events handled at once by [run_batched], so a busy source doesn't hold the events back forever.
+const BATCH_SIZE: usize = 128;
+
+/// Handles the events of a reader in batches, in its own thread.
+/// A batch holds the first event along with every event that is already available.
+#[cfg(not(feature = "sync"))]
+fn run_batched<K, V>(mut reader: BusReader<Event<K, V>>, mut handle: impl FnMut(Vec<Event<K, V>>) + Send + 'static)
+where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+{
+	spawn(move || {
+		while let Ok(event) = reader.recv() {
+			let mut batch = vec![event];
+			while batch.len() < BATCH_SIZE {
+				match reader.try_recv() {
+					Ok(event) => batch.push(event),
+					Err(_) => break,
+				}
+			}
+			handle(batch);
+		}
+		log_exit();
+	});
+}
+
+/// Handles the events of a reader in batches, whenever the events are pumped.
+#[cfg(feature = "sync")]
+fn run_batched<K, V>(mut reader: BusReader<Event<K, V>>, mut handle: impl FnMut(Vec<Event<K, V>>) + Send + 'static)
+where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+{
+	use std::sync::mpsc::TryRecvError;
+	let pump: Pump = Box::new(move || {
+		let mut progress = false;
+		loop {
+			let mut batch = Vec::new();
+			let mut open = true;
+			while batch.len() < BATCH_SIZE {
+				match reader.try_recv() {
+					Ok(event) => batch.push(event),
+					Err(TryRecvError::Empty) => break,
+					Err(TryRecvError::Disconnected) => {
+						open = false;
+						break;
+					}
+				}
+			}
+			let full = batch.len() == BATCH_SIZE;
+			if !batch.is_empty() {
+				handle(batch);
+				progress = true;
+			}
+			if !open {
+				return None;
+			}
+			if !full {
+				return Some(progress);
 			}
 		}
-		eprintln!("Husky thread exiting");
+	});
+	PUMPS.lock().push(Arc::new(Mutex::new(pump)));
+}
+
+/// Drains the readers of a single-threaded pipeline.
+/// Returns whether any event was handled, or [None] if the reader is disconnected.
+#[cfg(feature = "sync")]
+type Pump = Box<dyn FnMut() -> Option<bool> + Send>;
+
+#[cfg(feature = "sync")]
+static PUMPS: Lazy<Mutex<Vec<Arc<Mutex<Pump>>>>> = Lazy::new(Mutex::default);
+
+/// Handles pending events on the current thread, until there are none left.
+///
+/// With the `sync` feature there are no threads, so views call this after sending events.
+/// A pump that is already running, further up the stack or on another thread, is skipped.
+#[cfg(feature = "sync")]
+pub(crate) fn pump() {
+	loop {
+		let pumps = PUMPS.lock().clone();
+		let mut progress = false;
+		let mut closed = Vec::new();
+		for p in pumps {
+			let result = match p.try_lock() {
+				Some(mut pump) => pump(),
+				None => continue,
+			};
+			match result {
+				Some(handled) => progress |= handled,
+				None => closed.push(p),
+			}
+		}
+		if !closed.is_empty() {
+			PUMPS.lock().retain(|p| !closed.iter().any(|c| Arc::ptr_eq(p, c)));
+		}
+		if !progress {
+			break;
+		}
+	}
+}
+
+/// Broadcasts an event to the readers of a bus.
+/// With the `sync` feature, a full bus is drained by pumping, instead of blocking forever.
+pub(crate) fn broadcast<E>(bus: &RwLock<Bus<E>>, event: E) {
+	#[cfg(not(feature = "sync"))]
+	bus.write().broadcast(event);
+	#[cfg(feature = "sync")]
+	{
+		let mut event = event;
+		loop {
+			let result = bus.write().try_broadcast(event);
+			match result {
+				Ok(()) => break,
+				Err(e) => event = e,
+			}
+			pump();
+		}
+	}
+}
+
+/// Broadcasts several events to the readers of a bus, taking the lock once.
+/// With the `sync` feature, the lock is let go to pump whenever the bus is full.
+pub(crate) fn broadcast_all<E>(bus: &RwLock<Bus<E>>, events: Vec<E>) {
+	#[cfg(not(feature = "sync"))]
+	{
+		let mut bus = bus.write();
+		for event in events {
+			bus.broadcast(event);
+		}
+	}
+	#[cfg(feature = "sync")]
+	{
+		let mut events = events.into_iter();
+		let mut pending = None;
+		loop {
+			{
+				let mut bus = bus.write();
+				while let Some(event) = pending.take().or_else(|| events.next()) {
+					if let Err(event) = bus.try_broadcast(event) {
+						pending = Some(event);
+						break;
+					}
+				}
+			}
+			if pending.is_none() {
+				break;
+			}
+			pump();
+		}
+	}
+}
+
+pub fn spawn_listener<K, V, F>(synchronizer: Arc<Synchronizer>, reader: BusReader<Event<K, V>>, cb: F)
+where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+	F: 'static + Fn(Event<K, V>) -> Result<u32> + Send + Sync,
+{
+	spawn_listener_until(synchronizer, reader, Arc::default(), cb)
+}
+
+/// Like [spawn_listener], but the listener stops once `stopped` is set, letting go of the reader and the callback.
+pub(crate) fn spawn_listener_until<K, V, F>(
+	synchronizer: Arc<Synchronizer>,
+	reader: BusReader<Event<K, V>>,
+	stopped: Arc<AtomicBool>,
+	cb: F,
+) where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+	F: 'static + Fn(Event<K, V>) -> Result<u32> + Send + Sync,
+{
+	#[cfg(feature = "tracing")]
+	let op = op_name::<F>();
+	run_until(reader, stopped, move |event| {
+		#[cfg(feature = "tracing")]
+		let _span = enter(op, &event);
+		let sent = synchronizer.timed(|| cb(event));
+		match sent {
+			Ok(sent) => synchronizer.outgoing(sent),
+			Err(e) => synchronizer.fail(e),
+		}
+		synchronizer.received();
 	});
 }
 
 pub fn spawn_watcher<K, V, E, F>(
 	synchronizer: Arc<Synchronizer>,
-	mut reader: BusReader<Event<K, V>>,
+	reader: BusReader<Event<K, V>>,
 	bus: Arc<RwLock<Bus<E>>>,
 	cb: F,
 ) where
@@ -50,50 +313,268 @@ pub fn spawn_watcher<K, V, E, F>(
 	E: 'static + Sync + Send,
 	F: 'static + Fn(Event<K, V>) -> Result<Vec<E>> + Send + Sync,
 {
-	spawn(move || {
-		while let Ok(event) = reader.recv() {
-			let events = cb(event);
+	#[cfg(feature = "tracing")]
+	let op = op_name::<F>();
+	run(reader, move |event| {
+		#[cfg(feature = "tracing")]
+		let _span = enter(op, &event);
+		let events = synchronizer.timed(|| cb(event));
+		match events {
+			Ok(events) => {
+				let sent = events.len();
+				synchronizer.outgoing(sent as u32);
+				for event in events {
+					broadcast(&bus, event);
+				}
+				// Received only once broadcast, so that a reader that waited finds the events
+				synchronizer.received();
+			}
+			Err(e) => {
+				synchronizer.fail(e);
+				synchronizer.received();
+			}
+		}
+	});
+}
+
+/// Like [spawn_watcher], but the callback gets every event that is already available at once.
+///
+/// The events it returns are broadcast under a single lock, which helps with bulk loads.
+/// The whole batch is marked as received once broadcast, or once the callback fails.
+pub fn spawn_batch_watcher<K, V, E, F>(
+	synchronizer: Arc<Synchronizer>,
+	reader: BusReader<Event<K, V>>,
+	bus: Arc<RwLock<Bus<E>>>,
+	cb: F,
+) where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+	E: 'static + Sync + Send,
+	F: 'static + Fn(Vec<Event<K, V>>) -> Result<Vec<E>> + Send + Sync,
+{
+	#[cfg(feature = "tracing")]
+	let op = op_name::<F>();
+	run_batched(reader, move |batch| {
+		let received = batch.len();
+		#[cfg(feature = "tracing")]
+		let _span = tracing::debug_span!("batch", op, received).entered();
+		match synchronizer.timed(|| cb(batch)) {
+			Ok(events) => {
+				synchronizer.outgoing(events.len() as u32);
+				broadcast_all(&bus, events);
+			}
+			Err(e) => synchronizer.fail(e),
+		}
+		for _ in 0..received {
 			synchronizer.received();
-			match events {
-				Ok(events) => {
-					let sent = events.len();
-					synchronizer.outgoing(sent as u32);
-					for event in events {
-						let mut bus = bus.write();
-						bus.broadcast(event);
+		}
+	});
+}
+
+/// Forwards the events of a reader in bursts, keeping only the latest event of each key.
+///
+/// A burst starts with an event and lasts for the window, the events are then sent in the order of their last change.
+/// The events of a burst are only marked as received once the burst is sent,
+/// so waiting on the synchronizer waits for the window to close.
+/// With the `sync` feature there are no threads to wait on a window, so every event is sent as it comes.
+#[cfg(not(feature = "sync"))]
+pub fn spawn_debouncer<K, V>(
+	synchronizer: Arc<Synchronizer>,
+	mut reader: BusReader<Event<K, V>>,
+	bus: Arc<RwLock<Bus<Event<K, V>>>>,
+	window: Duration,
+) where
+	K: 'static + Sync + Send + Hash + Eq,
+	V: 'static + Sync + Send,
+{
+	use std::{collections::HashMap, sync::mpsc::RecvTimeoutError, time::Instant};
+	spawn(move || {
+		let mut open = true;
+		while open {
+			let first = match reader.recv() {
+				Ok(event) => event,
+				Err(_) => break,
+			};
+			let deadline = Instant::now() + window;
+			let mut burst = vec![first];
+			while let Some(timeout) = deadline.checked_duration_since(Instant::now()) {
+				match reader.recv_timeout(timeout) {
+					Ok(event) => burst.push(event),
+					Err(RecvTimeoutError::Timeout) => break,
+					Err(RecvTimeoutError::Disconnected) => {
+						open = false;
+						break;
 					}
 				}
-				Err(e) => eprint!("Error in Husky thread {:?}", e),
+			}
+			// Keeps the last event of each key
+			let received = burst.len();
+			let mut last = HashMap::new();
+			for (i, event) in burst.iter().enumerate() {
+				last.insert(event.key(), i);
+			}
+			let mut indexes = last.into_values().collect::<Vec<_>>();
+			indexes.sort_unstable();
+			let mut burst = burst.into_iter().map(Some).collect::<Vec<_>>();
+			let events = indexes
+				.into_iter()
+				.filter_map(|i| burst[i].take())
+				.collect::<Vec<_>>();
+			synchronizer.outgoing(events.len() as u32);
+			for event in events {
+				broadcast(&bus, event);
+			}
+			for _ in 0..received {
+				synchronizer.received();
 			}
 		}
-		eprintln!("Husky thread exiting");
+		log_exit();
 	});
 }
+#[cfg(feature = "sync")]
+pub fn spawn_debouncer<K, V>(
+	synchronizer: Arc<Synchronizer>,
+	reader: BusReader<Event<K, V>>,
+	bus: Arc<RwLock<Bus<Event<K, V>>>>,
+	_: Duration,
+) where
+	K: 'static + Sync + Send + Hash + Eq,
+	V: 'static + Sync + Send,
+{
+	spawn_watcher(synchronizer, reader, bus, |event| Ok(vec![event]));
+}
+
+/// A reader of events along with the synchronizer of its view.
+pub type Source<K, V> = (BusReader<Event<K, V>>, Arc<Synchronizer>);
+
+/// Merges the events of several sources into a single reader.
+///
+/// Each source is a reader along with the synchronizer of its view, as in `(tree.watch(), tree.sync())`.
+/// A thread forwards the events of each source, and waiting on the merged events waits for all of them.
+/// Events from different sources are interleaved in the order they are forwarded.
+/// # Examples
+/// ```
+/// # use husky::{Tree, Change, Watch, merge_watch};
+/// # let db = husky::open_temp().unwrap();
+/// # let a: Tree<u32, u32> = db.open_tree("a").unwrap();
+/// # let b: Tree<u32, u32> = db.open_tree("b").unwrap();
+/// let mut events = merge_watch(vec![(a.watch(), a.sync()), (b.watch(), b.sync())]);
+///
+/// a.insert(1u32, 1u32).unwrap();
+/// b.insert(2u32, 2u32).unwrap();
+/// events.wait();
+///
+/// assert!(events.try_recv().is_ok());
+/// assert!(events.try_recv().is_ok());
+/// ```
+pub fn merge_watch<K, V>(sources: Vec<Source<K, V>>) -> Events<K, V>
+where
+	K: 'static + Sync + Send,
+	V: 'static + Sync + Send,
+{
+	let syncs = sources.iter().map(|(_, sync)| Arc::clone(sync)).collect();
+	let sync = Arc::new(Synchronizer::from(syncs));
+	let bus = Arc::new(RwLock::new(Bus::new(128)));
+	// The reader is added before the threads start, so no event is missed
+	let reader = bus.write().add_rx();
+	for (source, _) in sources {
+		spawn_watcher(Arc::clone(&sync), source, Arc::clone(&bus), |event| Ok(vec![event]));
+	}
+	Events::from_reader(reader, sync)
+}
 
-static SYNCS: Lazy<Mutex<Vec<Arc<Synchronizer>>>> = Lazy::new(|| Mutex::default());
+/// The synchronizers waited on by [wait_all], which are let go once dropped everywhere else.
+pub(crate) static SYNCS: Lazy<Mutex<Vec<Weak<Synchronizer>>>> = Lazy::new(|| Mutex::default());
+
+/// The counters of a view, read with [stats](crate::Watch::stats) with the `metrics` feature.
+#[cfg(feature = "metrics")]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct SyncStats {
+	/// The events handled by the view
+	pub received: u64,
+	/// The events sent by the view
+	pub emitted: u64,
+	/// The events sent by the sources of the view that it is yet to handle
+	pub lag: u32,
+	/// How long the view took to handle its last event, or its last batch of events
+	pub last_duration: Duration,
+}
+
+/// The counters behind [SyncStats], which unlike the synchronizer counters are never reset
+#[cfg(feature = "metrics")]
+#[derive(Default, Debug)]
+struct Metrics {
+	received: std::sync::atomic::AtomicU64,
+	emitted: std::sync::atomic::AtomicU64,
+	last_nanos: std::sync::atomic::AtomicU64,
+}
 
 #[derive(Default, Debug)]
 pub struct Synchronizer {
 	source: RwLock<Vec<Arc<Synchronizer>>>,
 	received: AtomicU32,
 	outgoing: AtomicU32,
-	waiting: Mutex<Vec<Thread>>,
+	/// The threads waiting on this synchronizer or the ones it feeds, which unlist themselves once done
+	pub(crate) waiting: Mutex<Vec<Thread>>,
+	error: Mutex<Option<Arc<anyhow::Error>>>,
+	#[cfg(feature = "metrics")]
+	metrics: Metrics,
 }
 
 /// Waits for all synchronizers to finish propagating.
 pub fn wait_all() {
-  let syncs = SYNCS.lock();
-  for sync in syncs.iter() {
-    sync.wait();
-  }
+	for sync in registered() {
+		sync.wait();
+	}
+}
+
+/// Waits for all synchronizers to finish propagating, giving each of them up to a timeout.
+///
+/// Returns the synchronizers that didn't catch up in time, which can be told apart with [Arc::ptr_eq]
+/// against the [sync](crate::Watch::sync) of a view.
+/// Fails without waiting if a synchronizer is a source of itself, as in trees piped into each other,
+/// since it could never catch up.
+/// # Examples
+/// ```
+/// # use husky::{Tree, Change, Watch};
+/// # use std::time::Duration;
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+/// tree.insert(1u32, 1u32).unwrap();
+/// let lagging = husky::wait_all_timeout(Duration::from_secs(1)).unwrap();
+/// assert!(!lagging.iter().any(|s| std::sync::Arc::ptr_eq(s, &tree.sync())));
+/// ```
+pub fn wait_all_timeout(dur: Duration) -> Result<Vec<Arc<Synchronizer>>> {
+	let syncs = registered();
+	if syncs.iter().any(|s| s.has_cycle()) {
+		bail!("A synchronizer is a source of itself, so it can't be waited on");
+	}
+	Ok(syncs.into_iter().filter(|s| !s.wait_timeout(dur)).collect())
+}
+
+/// The synchronizers that are still alive, pruning the dropped ones.
+/// The lock is let go before returning, so that new synchronizers can be registered while waiting.
+fn registered() -> Vec<Arc<Synchronizer>> {
+	let mut syncs = SYNCS.lock();
+	syncs.retain(|s| s.strong_count() > 0);
+	syncs.iter().filter_map(Weak::upgrade).collect()
 }
 
 impl Synchronizer {
 	pub fn new() -> Arc<Self> {
 		let s = Arc::default();
-    SYNCS.lock().push(Arc::clone(&s));
+    Self::register(&s);
     s
 	}
+	/// Lists a synchronizer to be waited on by [wait_all], for threads that nothing else waits on.
+	pub(crate) fn register(sync: &Arc<Self>) {
+		let mut syncs = SYNCS.lock();
+		// Dropped synchronizers are pruned whenever the list would grow, so it stays bounded by the live ones
+		if syncs.len() == syncs.capacity() {
+			syncs.retain(|s| s.strong_count() > 0);
+		}
+		syncs.push(Arc::downgrade(sync));
+	}
 	pub fn from(source: Vec<Arc<Synchronizer>>) -> Self {
 		let received = source.iter().map(|s| s.outgoing.load(Relaxed)).sum();
 		Self {
@@ -101,11 +582,18 @@ impl Synchronizer {
 			received: AtomicU32::new(received),
 			outgoing: AtomicU32::new(0),
 			waiting: Mutex::default(),
+			error: Mutex::default(),
+			#[cfg(feature = "metrics")]
+			metrics: Metrics::default(),
 		}
 	}
 	pub(crate) fn push_source(&self, source: Arc<Synchronizer>) {
 		self.source.write().push(source);
 	}
+	/// Stops waiting on a source that was pushed with [push_source](Synchronizer::push_source).
+	pub(crate) fn remove_source(&self, source: &Arc<Synchronizer>) {
+		self.source.write().retain(|s| !Arc::ptr_eq(s, source));
+	}
 	pub(crate) fn reset(&self) {
 		let received = self.incoming();
 		self.received.store(received, Relaxed);
@@ -114,7 +602,7 @@ impl Synchronizer {
 		self.source
 			.read()
 			.iter()
-			.map(|i| i.outgoing.load(Relaxed))
+			.map(|i| i.outgoing.load(SeqCst))
 			.sum()
 	}
 	fn is_sync(&self) -> bool {
@@ -126,25 +614,157 @@ impl Synchronizer {
 	}
 	pub(crate) fn received(&self) {
 		self.received.fetch_add(1, Relaxed);
+		#[cfg(feature = "metrics")]
+		self.metrics.received.fetch_add(1, Relaxed);
 		if self.is_sync() {
-			let mut waiting = self.waiting.lock();
-			for thread in waiting.drain(..) {
+			for thread in self.waiting.lock().iter() {
 				thread.unpark();
 			}
 		}
 	}
+	/// Records an error of the thread that feeds this synchronizer.
+	pub(crate) fn fail(&self, e: impl Into<anyhow::Error>) {
+		let e = e.into();
+		#[cfg(feature = "tracing")]
+		tracing::error!("Error in Husky thread {:?}", e);
+		#[cfg(not(feature = "tracing"))]
+		eprintln!("Error in Husky thread {:?}", e);
+		*self.error.lock() = Some(Arc::new(e));
+	}
+	/// Returns the last error of a thread that feeds this synchronizer or any of its sources.
+	pub fn last_error(&self) -> Option<Arc<anyhow::Error>> {
+		if let Some(e) = &*self.error.lock() {
+			return Some(Arc::clone(e));
+		}
+		self.source.read().iter().find_map(|s| s.last_error())
+	}
+	/// Waits until all events are processed, then fails if a thread had an error.
+	pub(crate) fn wait_checked(&self) -> Result<()> {
+		self.wait();
+		match self.last_error() {
+			Some(e) => Err(HuskyError::Pipeline(e)),
+			None => Ok(()),
+		}
+	}
 	pub(crate) fn outgoing(&self, amount: u32) {
 		self.outgoing.fetch_add(amount, Relaxed);
+		#[cfg(feature = "metrics")]
+		self.metrics.emitted.fetch_add(amount as u64, Relaxed);
 	}
-	pub fn wait(&self) {
-		loop {
-			if self.is_sync() {
-				break;
+	/// Handles events, timing it with the `metrics` feature.
+	pub(crate) fn timed<T>(&self, f: impl FnOnce() -> T) -> T {
+		#[cfg(feature = "metrics")]
+		{
+			let start = std::time::Instant::now();
+			let result = f();
+			let nanos = start.elapsed().as_nanos().min(u64::MAX as u128) as u64;
+			self.metrics.last_nanos.store(nanos, Relaxed);
+			result
+		}
+		#[cfg(not(feature = "metrics"))]
+		f()
+	}
+	/// The events sent by the sources that this synchronizer is yet to handle.
+	/// The handled events are read before the sent ones, which only grow, so the lag is never negative.
+	pub fn lag(&self) -> u32 {
+		let received = self.received.load(SeqCst);
+		self.incoming().saturating_sub(received)
+	}
+	/// Whether this synchronizer or any of its sources is a source of itself, directly or through others.
+	pub(crate) fn has_cycle(&self) -> bool {
+		fn visit(sync: &Synchronizer, path: &mut Vec<*const Synchronizer>) -> bool {
+			let ptr = sync as *const Synchronizer;
+			if path.contains(&ptr) {
+				return true;
 			}
-			let mut waiting = self.waiting.lock();
-			waiting.push(std::thread::current());
-			drop(waiting);
+			path.push(ptr);
+			// Cloned so that no lock is held while visiting the sources
+			let sources = sync.source.read().clone();
+			let found = sources.iter().any(|s| visit(s, path));
+			path.pop();
+			found
+		}
+		visit(self, &mut Vec::new())
+	}
+	/// Whether this synchronizer and all of its sources have handled every event sent to them.
+	pub fn is_caught_up(&self) -> bool {
+		self.is_sync()
+	}
+	/// Reads the counters of this synchronizer.
+	#[cfg(feature = "metrics")]
+	pub fn stats(&self) -> SyncStats {
+		SyncStats {
+			received: self.metrics.received.load(Relaxed),
+			emitted: self.metrics.emitted.load(Relaxed),
+			lag: self.lag(),
+			last_duration: Duration::from_nanos(self.metrics.last_nanos.load(Relaxed)),
+		}
+	}
+	/// Lists a thread to be woken up when this synchronizer or any of its sources receives an event.
+	/// A source can drop an event without sending anything, so the thread must be listed there too.
+	/// The thread is listed once per wait, and unlisted when the wait ends.
+	#[cfg(not(feature = "sync"))]
+	fn listen(&self, thread: &Thread) {
+		self.waiting.lock().push(thread.clone());
+		for source in self.source.read().iter() {
+			source.listen(thread);
+		}
+	}
+	/// Removes a thread listed with [listen](Synchronizer::listen), from this synchronizer and its sources.
+	#[cfg(not(feature = "sync"))]
+	fn unlisten(&self, thread: &Thread) {
+		self.waiting.lock().retain(|t| t.id() != thread.id());
+		for source in self.source.read().iter() {
+			source.unlisten(thread);
+		}
+	}
+	#[cfg(not(feature = "sync"))]
+	pub fn wait(&self) {
+		if self.is_sync() {
+			return;
+		}
+		let thread = std::thread::current();
+		self.listen(&thread);
+		// Checks again after being listed
+		// Otherwise the last event could be received before this thread is listed, and it would never be woken up
+		while !self.is_sync() {
 			std::thread::park();
 		}
+		self.unlisten(&thread);
+	}
+	/// Waits until all events are processed, or until the timeout elapses.
+	/// Returns `false` on timeout, which can mean that a thread upstream is stuck or has died.
+	#[cfg(not(feature = "sync"))]
+	pub fn wait_timeout(&self, dur: Duration) -> bool {
+		use std::time::Instant;
+		let deadline = Instant::now() + dur;
+		if self.is_sync() {
+			return true;
+		}
+		let thread = std::thread::current();
+		self.listen(&thread);
+		let caught_up = loop {
+			if self.is_sync() {
+				break true;
+			}
+			let now = Instant::now();
+			if now >= deadline {
+				break false;
+			}
+			std::thread::park_timeout(deadline - now);
+		};
+		self.unlisten(&thread);
+		caught_up
+	}
+	/// Handles the pending events, there are no threads to wait for.
+	#[cfg(feature = "sync")]
+	pub fn wait(&self) {
+		pump();
+	}
+	/// Handles the pending events, there are no threads to wait for.
+	#[cfg(feature = "sync")]
+	pub fn wait_timeout(&self, _dur: Duration) -> bool {
+		pump();
+		true
 	}
 }
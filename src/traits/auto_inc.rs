@@ -1,9 +1,19 @@
 /// An auto-incrementable key.
-pub trait AutoInc {
+pub trait AutoInc
+where
+	Self: Sized,
+{
 	/// The next item in the sequence.
 	fn next(&self) -> Self;
 	/// The first item in the sequence.
 	fn first() -> Self;
+	/// The next item in the sequence, or [None] once the sequence is exhausted.
+	///
+	/// Pushing a value fails instead of panicking once this returns [None].
+	/// By default the sequence never runs out.
+	fn try_next(&self) -> Option<Self> {
+		Some(self.next())
+	}
 }
 
 macro_rules! impl_auto_inc {
@@ -15,6 +25,9 @@ macro_rules! impl_auto_inc {
 			fn first() -> Self {
 				1
 			}
+			fn try_next(&self) -> Option<Self> {
+				self.checked_add(1)
+			}
 		}
 	};
 }
@@ -25,3 +38,71 @@ impl_auto_inc!(u32);
 impl_auto_inc!(u64);
 impl_auto_inc!(u128);
 impl_auto_inc!(usize);
+
+/// A key made of a shard id and a counter, so several writers can push to their own shard without colliding.
+///
+/// The shard takes the upper 16 bits of a [u64] and the counter the lower 48 bits,
+/// so keys are ordered by shard, then by counter, and the trees of different shards can be merged.
+/// Pushing appends after the last key of the tree, so each shard should push to a tree that only holds its own keys.
+/// # Examples
+/// ```
+/// # use husky::{Tree, Change, Sequence};
+/// # let db = husky::open_temp().unwrap();
+/// let tree: Tree<Sequence<3>, u32> = db.open_tree("tree").unwrap();
+/// let key = tree.push(10u32).unwrap();
+/// assert_eq!((key.shard(), key.counter()), (3, 1));
+/// ```
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[cfg_attr(
+	feature = "rkyv",
+	derive(rkyv::Archive, rkyv::Serialize, rkyv::Deserialize)
+)]
+#[cfg_attr(feature = "bytecheck", archive_attr(derive(bytecheck::CheckBytes)))]
+pub struct Sequence<const SHARD: u16>(u64);
+
+impl<const SHARD: u16> Sequence<SHARD> {
+	/// The bits of the counter.
+	const COUNTER_BITS: u32 = 48;
+	/// The largest counter of a shard.
+	pub const MAX_COUNTER: u64 = (1 << Self::COUNTER_BITS) - 1;
+	/// Creates the key of a counter in this shard, or [None] if the counter doesn't fit in 48 bits.
+	pub fn new(counter: u64) -> Option<Self> {
+		if counter > Self::MAX_COUNTER {
+			return None;
+		}
+		Some(Sequence(((SHARD as u64) << Self::COUNTER_BITS) | counter))
+	}
+	/// The shard of the key.
+	pub fn shard(&self) -> u16 {
+		(self.0 >> Self::COUNTER_BITS) as u16
+	}
+	/// The counter of the key.
+	pub fn counter(&self) -> u64 {
+		self.0 & Self::MAX_COUNTER
+	}
+}
+
+impl<const SHARD: u16> AutoInc for Sequence<SHARD> {
+	fn next(&self) -> Self {
+		self.try_next().expect("Sequence counter overflowed")
+	}
+	fn first() -> Self {
+		Sequence::new(1).unwrap()
+	}
+	fn try_next(&self) -> Option<Self> {
+		Sequence::new(self.counter() + 1)
+	}
+}
+
+#[cfg(feature = "serde")]
+impl<const SHARD: u16> serde::Serialize for Sequence<SHARD> {
+	fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+		serializer.serialize_u64(self.0)
+	}
+}
+#[cfg(feature = "serde")]
+impl<'de, const SHARD: u16> serde::Deserialize<'de> for Sequence<SHARD> {
+	fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+		u64::deserialize(deserializer).map(Sequence)
+	}
+}
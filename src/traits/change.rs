@@ -1,4 +1,6 @@
-use anyhow::Result;
+use std::ops::{Add, RangeBounds};
+
+use crate::{error::Result, macros::bail, wrappers::batch::Batch};
 
 use super::{auto_inc::AutoInc, view::View};
 
@@ -95,12 +97,52 @@ where
 	) -> Result<Option<<Self as Change>::Value>> {
 		self.insert_owned(key.into(), value.into())
 	}
-	/// Updates an entry atomically
+	/// Updates an entry atomically, returning the previous value.
 	fn fetch_and_update(
 		&self,
 		key: &Self::Key,
 		f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
 	) -> Result<Option<Self::Value>>;
+	/// Updates an entry, returning what got inserted, or [None] if the entry was removed.
+	/// The default is built on [fetch_and_update](Change::fetch_and_update), so it is just as atomic.
+	/// The function may be called more than once, and only its last result is returned.
+	fn update_and_fetch(
+		&self,
+		key: &Self::Key,
+		mut f: impl FnMut(Option<Self::Value>) -> Option<Self::Insert>,
+	) -> Result<Option<Self::Insert>> {
+		let mut new = None;
+		self.fetch_and_update(key, |v| {
+			new = f(v);
+			new.clone()
+		})?;
+		Ok(new)
+	}
+	/// Adds a delta to a value atomically, returning the new value.
+	/// A missing entry counts from the default value, usually zero.
+	///
+	/// It is built on [update_and_fetch](Change::update_and_fetch), so concurrent increments are never lost,
+	/// and a [Tree](crate::Tree) sends an insert event with the value it wrote.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// let counts: Tree<String, i64> = db.open_tree("counts").unwrap();
+	/// assert_eq!(counts.incr("visits", 2).unwrap(), 2);
+	/// assert_eq!(counts.incr("visits", -1).unwrap(), 1);
+	/// assert_eq!(counts.get("visits").unwrap(), Some(1));
+	/// ```
+	fn incr<IK: Into<Self::Key>>(&self, key: IK, delta: Self::Insert) -> Result<Self::Insert>
+	where
+		Self::Value: Into<Self::Insert>,
+		Self::Insert: Add<Output = Self::Insert> + Default,
+	{
+		let new = self.update_and_fetch(&key.into(), |v| {
+			let count = v.map(Into::into).unwrap_or_default();
+			Some(count + delta.clone())
+		})?;
+		Ok(new.unwrap_or_default())
+	}
 	/// Gets an [Entry] from a key reference.
 	fn entry_ref<'a>(&'a self, key: &'a <Self as Change>::Key) -> Result<Entry<'a, Self>>
 	where
@@ -136,22 +178,25 @@ where
 			from: self,
 		})
 	}
-	/// Pushes a value onto the end of the tree.
-	fn push_owned(&self, value: <Self as Change>::Insert) -> Result<()>
+	/// Pushes a value onto the end of the tree, returning its key.
+	fn push_owned(&self, value: <Self as Change>::Insert) -> Result<<Self as Change>::Key>
 	where
 		Self: View<Key = <Self as Change>::Key>,
 		<Self as View>::Key: AutoInc + Ord,
 	{
 		let l = self.last()?;
 		let k = match l {
-			Some((k, _)) => k.next(),
+			Some((k, _)) => match k.try_next() {
+				Some(k) => k,
+				None => bail!("The key sequence is exhausted"),
+			},
 			None => <Self as View>::Key::first(),
 		};
-		self.insert_owned(k, value)?;
-		Ok(())
+		self.insert_owned(k.clone(), value)?;
+		Ok(k)
 	}
-	/// Pushes a value onto the end of the tree.
-	fn push<V: Into<<Self as Change>::Insert>>(&self, value: V) -> Result<()>
+	/// Pushes a value onto the end of the tree, returning its key.
+	fn push<V: Into<<Self as Change>::Insert>>(&self, value: V) -> Result<<Self as Change>::Key>
 	where
 		Self: View<Key = <Self as Change>::Key>,
 		<Self as View>::Key: AutoInc + Ord,
@@ -173,6 +218,155 @@ where
 		let key = key.into();
 		self.remove_owned(key)
 	}
+	/// Inserts a value only if the key is missing, returning the value it already had, or [None] if it got inserted.
+	/// The default looks the key up, then inserts, while a [Tree](crate::Tree) does both atomically.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// let settings: Tree<String, u32> = db.open_tree("settings").unwrap();
+	/// let key = "retries".to_string();
+	///
+	/// assert_eq!(settings.insert_if_absent(&key, &3).unwrap(), None);
+	/// assert_eq!(settings.insert_if_absent(&key, &5).unwrap(), Some(3));
+	/// assert_eq!(settings.get(key).unwrap(), Some(3));
+	/// ```
+	fn insert_if_absent(
+		&self,
+		key: &<Self as Change>::Key,
+		value: &<Self as Change>::Insert,
+	) -> Result<Option<<Self as Change>::Value>>
+	where
+		Self: View<Key = <Self as Change>::Key, Value = <Self as Change>::Value>,
+	{
+		if let Some(existing) = self.get_ref(key)? {
+			return Ok(Some(existing));
+		}
+		self.insert_ref(key, value)?;
+		Ok(None)
+	}
+	/// Moves the value of a key to another, returning it, or [None] if there was nothing to move.
+	/// The value it replaces, if any, is dropped.
+	/// The default removes the key, then inserts its value, while a [Tree](crate::Tree) does both in a transaction.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// let parents: Tree<String, String> = db.open_tree("parents").unwrap();
+	/// parents.insert("a", "root").unwrap();
+	///
+	/// parents.swap(&"a".to_string(), &"b".to_string()).unwrap();
+	/// assert_eq!(parents.get("a").unwrap(), None);
+	/// assert_eq!(parents.get("b").unwrap(), Some("root".to_string()));
+	/// ```
+	fn swap(
+		&self,
+		from: &<Self as Change>::Key,
+		to: &<Self as Change>::Key,
+	) -> Result<Option<<Self as Change>::Value>>
+	where
+		<Self as Change>::Value: Into<Self::Insert>,
+	{
+		let value = match self.remove_ref(from)? {
+			Some(value) => value,
+			None => return Ok(None),
+		};
+		self.insert_owned(to.clone(), value.clone().into())?;
+		Ok(Some(value))
+	}
+	/// Removes and returns the entry with the smallest key.
+	/// The default looks the key up with [first](View::first), then removes it.
+	fn pop_min(&self) -> Result<Option<(<Self as Change>::Key, <Self as Change>::Value)>>
+	where
+		Self: View<Key = <Self as Change>::Key>,
+		<Self as View>::Key: Ord,
+	{
+		loop {
+			let (key, _) = match self.first()? {
+				Some(entry) => entry,
+				None => return Ok(None),
+			};
+			// Another remove may have taken it first
+			if let Some(value) = self.remove_ref(&key)? {
+				return Ok(Some((key, value)));
+			}
+		}
+	}
+	/// Removes and returns the entry with the greatest key.
+	/// The default looks the key up with [last](View::last), then removes it.
+	fn pop_max(&self) -> Result<Option<(<Self as Change>::Key, <Self as Change>::Value)>>
+	where
+		Self: View<Key = <Self as Change>::Key>,
+		<Self as View>::Key: Ord,
+	{
+		loop {
+			let (key, _) = match self.last()? {
+				Some(entry) => entry,
+				None => return Ok(None),
+			};
+			if let Some(value) = self.remove_ref(&key)? {
+				return Ok(Some((key, value)));
+			}
+		}
+	}
+	/// Removes every entry that doesn't match the predicate, returning how many were removed.
+	/// The default walks the view, then removes the keys one by one.
+	fn retain(
+		&self,
+		pred: impl Fn(&<Self as Change>::Key, &<Self as Change>::Value) -> bool,
+	) -> Result<usize>
+	where
+		Self: View<Key = <Self as Change>::Key, Value = <Self as Change>::Value>,
+	{
+		let mut keys = Vec::new();
+		for res in self.iter() {
+			let (key, value) = res?;
+			if !pred(&key, &value) {
+				keys.push(key);
+			}
+		}
+		let mut count = 0;
+		for key in keys {
+			if self.remove_ref(&key)?.is_some() {
+				count += 1;
+			}
+		}
+		Ok(count)
+	}
+	/// Removes every key in a range, returning how many were removed.
+	/// The default walks the range of the view, then removes the keys one by one.
+	fn remove_range(&self, range: impl RangeBounds<<Self as Change>::Key>) -> Result<usize>
+	where
+		Self: View<Key = <Self as Change>::Key>,
+	{
+		let keys = self
+			.range(range)?
+			.map(|r| r.map(|(key, _)| key))
+			.collect::<Result<Vec<_>>>()?;
+		let mut count = 0;
+		for key in keys {
+			if self.remove_ref(&key)?.is_some() {
+				count += 1;
+			}
+		}
+		Ok(count)
+	}
+	/// Applies a [Batch] of inserts and removes.
+	/// The default replays the operations one by one, so views parse or reduce each insert,
+	/// while a [Tree](crate::Tree) applies them atomically.
+	fn apply_batch(
+		&self,
+		batch: Batch<<Self as Change>::Key, <Self as Change>::Insert>,
+	) -> Result<()> {
+		let (_, ops) = batch.into_parts();
+		for (key, value) in ops {
+			match value {
+				Some(value) => self.insert_owned(key, value)?,
+				None => self.remove_owned(key)?,
+			};
+		}
+		Ok(())
+	}
 	/// Clears the tree.
 	fn clear(&self) -> Result<()>;
 }
@@ -1,19 +1,37 @@
-use parking_lot::RwLock;
+use parking_lot::{Mutex, RwLock};
 use std::sync::Arc;
-use std::{collections::BTreeMap, ops::Bound};
+use std::{
+	collections::{BTreeMap, HashMap},
+	fmt,
+	hash::Hash,
+	ops::Bound,
+};
 
-use anyhow::Result;
-
-use super::{change::Change, view::View};
+use super::{change::Change, serial::Serial, view::View};
+use crate::{
+	error::Result,
+	macros::{bail, into_iter},
+	wrappers::{batch::Batch, tree::Tree},
+};
 
 /// Allows for loading a tree into memory. Please refer to [Loaded]
 pub trait Load {
 	/// The loaded type
 	type Loaded;
 	/// Loads the tree into memory
+	///
+	/// Nothing is kept between runs, so the source is walked every time it is loaded.
 	fn load(&self) -> Result<Self::Loaded>;
 }
 
+/// Allows for loading a tree into memory, without ordering its keys. Please refer to [LoadedUnordered]
+pub trait LoadUnordered {
+	/// The loaded type
+	type Loaded;
+	/// Loads the tree into memory, in a hash map
+	fn load_unordered(&self) -> Result<Self::Loaded>;
+}
+
 /// A tree loaded in memory.
 pub struct Loaded<K, V> {
 	inner: Arc<RwLock<BTreeMap<K, V>>>,
@@ -25,6 +43,20 @@ impl<K, V> Loaded<K, V> {
 		}
 	}
 }
+/// Builds a tree in memory from its entries.
+/// # Examples
+/// ```
+/// # use husky::{View, traits::load::Loaded};
+/// let loaded: Loaded<u32, u32> = (0..10u32).map(|i| (i, i * 2)).collect();
+/// assert_eq!(loaded.get(4u32).unwrap(), Some(8));
+/// ```
+impl<K: Ord, V> FromIterator<(K, V)> for Loaded<K, V> {
+	fn from_iter<I: IntoIterator<Item = (K, V)>>(iter: I) -> Self {
+		Self {
+			inner: Arc::new(RwLock::new(iter.into_iter().collect())),
+		}
+	}
+}
 impl<K, V> Clone for Loaded<K, V> {
 	fn clone(&self) -> Self {
 		Self {
@@ -32,6 +64,48 @@ impl<K, V> Clone for Loaded<K, V> {
 		}
 	}
 }
+impl<K, V> Loaded<K, V>
+where
+	K: 'static + Serial + Ord + Sync + Send,
+	V: 'static + Serial + Sync + Send,
+{
+	/// Writes the entries to a tree, replacing its contents, so they can be restored without the source.
+	///
+	/// The tree is cleared, then written in a single batch.
+	/// Clearing doesn't send events, so views over the tree should be rebuilt after it.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, traits::load::Loaded};
+	/// # let db = husky::open_temp().unwrap();
+	/// let loaded: Loaded<u32, u32> = (0..10u32).map(|i| (i, i * 2)).collect();
+	/// let snapshot: Tree<u32, u32> = db.open_tree("snapshot").unwrap();
+	/// loaded.snapshot(&snapshot).unwrap();
+	///
+	/// let restored = Loaded::restore(&snapshot).unwrap();
+	/// assert_eq!(restored.get(4u32).unwrap(), Some(8));
+	/// ```
+	pub fn snapshot(&self, tree: &Tree<K, V>) -> Result<()> {
+		let mut batch = Batch::default();
+		for (key, value) in self.inner.read().iter() {
+			batch.insert(key.clone(), value.clone())?;
+		}
+		tree.clear()?;
+		tree.apply_batch(batch)
+	}
+	/// Reads the entries of a tree written by [snapshot](Loaded::snapshot) back into memory.
+	///
+	/// The loaded tree isn't kept in sync with the tree it is read from.
+	pub fn restore(tree: &Tree<K, V>) -> Result<Self> {
+		tree.iter().collect()
+	}
+}
+impl<K, V> fmt::Debug for Loaded<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("Loaded")
+			.field(&self.inner.read().len())
+			.finish()
+	}
+}
 
 impl<K, V> View for Loaded<K, V>
 where
@@ -81,6 +155,23 @@ where
 		let value = map.range(..).next_back();
 		Ok(value.map(|(k, v)| (k.clone(), v.clone())))
 	}
+	fn nth(&self, n: usize) -> Result<Option<(Self::Key, Self::Value)>> {
+		let map = self.inner.read();
+		let value = map.iter().nth(n);
+		Ok(value.map(|(k, v)| (k.clone(), v.clone())))
+	}
+	fn first_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>> {
+		let map = self.inner.read();
+		let entries = map.iter().take(n);
+		Ok(entries.map(|(k, v)| (k.clone(), v.clone())).collect())
+	}
+	fn last_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>> {
+		let map = self.inner.read();
+		let entries = map.iter().rev().take(n);
+		let mut last = entries.map(|(k, v)| (k.clone(), v.clone())).collect::<Vec<_>>();
+		last.reverse();
+		Ok(last)
+	}
 	fn is_empty(&self) -> Option<bool> {
 		Some(self.inner.read().is_empty())
 	}
@@ -97,6 +188,12 @@ where
 		))
 	}
 }
+into_iter!(
+	impl<K, V> for Loaded<K, V>
+	where
+		K: 'static + Clone + Send + Sync + Ord,
+		V: 'static + Clone + Send + Sync,
+);
 
 impl<K, V> Change for Loaded<K, V>
 where
@@ -137,3 +234,338 @@ where
 		}
 	}
 }
+
+/// A tree loaded in memory, in a hash map.
+///
+/// Its keys only need to be hashable, but they have no order,
+/// so the methods that depend on it, such as [first](View::first) or [range](View::range), return an error.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, traits::load::LoadUnordered};
+/// # let db = husky::open_temp().unwrap();
+/// # let tree: Tree<String, u32> = db.open_tree("tree").unwrap();
+/// let cache = tree.load_unordered().unwrap();
+///
+/// tree.insert("key", 1u32).unwrap();
+///
+/// assert_eq!(cache.get("key").unwrap(), Some(1));
+/// assert!(cache.first().is_err());
+/// ```
+pub struct LoadedUnordered<K, V> {
+	inner: Arc<RwLock<HashMap<K, V>>>,
+}
+impl<K, V> LoadedUnordered<K, V> {
+	pub(crate) fn new() -> Self {
+		Self {
+			inner: Arc::default(),
+		}
+	}
+}
+impl<K, V> Clone for LoadedUnordered<K, V> {
+	fn clone(&self) -> Self {
+		Self {
+			inner: self.inner.clone(),
+		}
+	}
+}
+impl<K, V> fmt::Debug for LoadedUnordered<K, V> {
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		f.debug_tuple("LoadedUnordered")
+			.field(&self.inner.read().len())
+			.finish()
+	}
+}
+
+impl<K, V> View for LoadedUnordered<K, V>
+where
+	K: 'static + Clone + Send + Sync + Hash + Eq,
+	V: 'static + Clone + Send + Sync,
+{
+	type Key = K;
+	type Value = V;
+	type Iter = Box<dyn Iterator<Item = Result<(K, V)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		let map = self.inner.read();
+		let value = map.get(key).cloned();
+		Ok(value)
+	}
+	fn iter(&self) -> Self::Iter {
+		Box::new(
+			self.inner
+				.read()
+				.iter()
+				.map(|(k, v)| Ok((k.clone(), v.clone())))
+				.collect::<Vec<_>>()
+				.into_iter(),
+		)
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.inner.read().contains_key(key))
+	}
+	fn get_lt_ref(&self, _: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("An unordered tree has no lesser entries")
+	}
+	fn get_gt_ref(&self, _: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("An unordered tree has no greater entries")
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("An unordered tree has no first entry")
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("An unordered tree has no last entry")
+	}
+	fn nth(&self, n: usize) -> Result<Option<(Self::Key, Self::Value)>> {
+		let map = self.inner.read();
+		let value = map.iter().nth(n);
+		Ok(value.map(|(k, v)| (k.clone(), v.clone())))
+	}
+	fn is_empty(&self) -> Option<bool> {
+		Some(self.inner.read().is_empty())
+	}
+	fn range(&self, _: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		bail!("An unordered tree has no ranges")
+	}
+}
+into_iter!(
+	impl<K, V> for LoadedUnordered<K, V>
+	where
+		K: 'static + Clone + Send + Sync + Hash + Eq,
+		V: 'static + Clone + Send + Sync,
+);
+
+impl<K, V> Change for LoadedUnordered<K, V>
+where
+	K: 'static + Clone + Send + Sync + Hash + Eq,
+	V: 'static + Clone + Send + Sync,
+{
+	type Key = K;
+	type Value = V;
+	type Insert = V;
+	fn insert_owned(&self, key: K, value: V) -> Result<Option<<Self as Change>::Value>> {
+		let mut map = self.inner.write();
+		let prev = HashMap::insert(&mut map, key, value);
+		Ok(prev)
+	}
+	fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>> {
+		let mut map = self.inner.write();
+		let prev = HashMap::remove(&mut map, key);
+		Ok(prev)
+	}
+	fn clear(&self) -> Result<()> {
+		let mut map = self.inner.write();
+		HashMap::clear(&mut map);
+		Ok(())
+	}
+	fn fetch_and_update(
+		&self,
+		key: &Self::Key,
+		mut f: impl FnMut(Option<Self::Value>) -> Option<Self::Value>,
+	) -> Result<Option<Self::Value>> {
+		let mut map = self.inner.write();
+		let prev = HashMap::get(&map, key).cloned();
+		let next = f(prev);
+		if let Some(next) = next {
+			Ok(HashMap::insert(&mut map, key.clone(), next))
+		} else {
+			Ok(HashMap::remove(&mut map, key))
+		}
+	}
+}
+
+type Loader<K, V> = dyn Fn(&K) -> anyhow::Result<Option<V>> + Send + Sync;
+
+/// The entries of a [LoadedLru], with the tick of their last use.
+struct Lru<K, V> {
+	map: HashMap<K, (V, u64)>,
+	order: BTreeMap<u64, K>,
+	tick: u64,
+	capacity: usize,
+}
+impl<K, V> Lru<K, V>
+where
+	K: Clone + Hash + Eq,
+	V: Clone,
+{
+	fn touch(&mut self, key: &K) -> Option<V> {
+		let (value, tick) = self.map.get_mut(key)?;
+		self.order.remove(tick);
+		self.tick += 1;
+		*tick = self.tick;
+		self.order.insert(self.tick, key.clone());
+		Some(value.clone())
+	}
+	fn insert(&mut self, key: K, value: V) -> Option<V> {
+		self.tick += 1;
+		let prev = self.map.insert(key.clone(), (value, self.tick));
+		let prev = prev.map(|(value, tick)| {
+			self.order.remove(&tick);
+			value
+		});
+		self.order.insert(self.tick, key);
+		while self.map.len() > self.capacity {
+			match self.order.pop_first() {
+				Some((_, key)) => self.map.remove(&key),
+				None => break,
+			};
+		}
+		prev
+	}
+	fn remove(&mut self, key: &K) -> Option<V> {
+		let (value, tick) = self.map.remove(key)?;
+		self.order.remove(&tick);
+		Some(value)
+	}
+	fn clear(&mut self) {
+		self.map.clear();
+		self.order.clear();
+	}
+}
+
+/// A tree loaded in memory, keeping only the most recently used entries.
+///
+/// Once the capacity is reached, inserting evicts the least recently used entry.
+/// Reading a key that isn't in memory calls the loader, and keeps what it returns.
+///
+/// Its keys only need to be hashable, so the methods that depend on an order return an error.
+/// Iterating goes over the entries in memory, from the least to the most recently used.
+/// # Examples
+/// ```
+/// # use husky::{View, Change, traits::load::LoadedLru};
+/// let squares = LoadedLru::new(2, |k: &u32| Ok(Some(k * k)));
+///
+/// assert_eq!(squares.get(3u32).unwrap(), Some(9));
+/// assert!(squares.is_cached(&3));
+/// squares.get(4u32).unwrap();
+/// squares.get(5u32).unwrap();
+/// assert!(!squares.is_cached(&3));
+/// ```
+pub struct LoadedLru<K, V> {
+	inner: Arc<Mutex<Lru<K, V>>>,
+	loader: Arc<Loader<K, V>>,
+}
+impl<K, V> LoadedLru<K, V>
+where
+	K: Clone + Hash + Eq,
+	V: Clone,
+{
+	/// Creates a cache of a given capacity, that loads missing keys with a function.
+	pub fn new<L>(capacity: usize, loader: L) -> Self
+	where
+		L: 'static + Fn(&K) -> anyhow::Result<Option<V>> + Send + Sync,
+	{
+		let lru = Lru {
+			map: HashMap::new(),
+			order: BTreeMap::new(),
+			tick: 0,
+			capacity,
+		};
+		Self {
+			inner: Arc::new(Mutex::new(lru)),
+			loader: Arc::new(loader),
+		}
+	}
+	/// Checks if a key is in memory, without loading it or marking it as used.
+	pub fn is_cached(&self, key: &K) -> bool {
+		self.inner.lock().map.contains_key(key)
+	}
+}
+impl<K, V> Clone for LoadedLru<K, V> {
+	fn clone(&self) -> Self {
+		Self {
+			inner: Arc::clone(&self.inner),
+			loader: Arc::clone(&self.loader),
+		}
+	}
+}
+
+impl<K, V> View for LoadedLru<K, V>
+where
+	K: 'static + Clone + Send + Sync + Hash + Eq,
+	V: 'static + Clone + Send + Sync,
+{
+	type Key = K;
+	type Value = V;
+	type Iter = Box<dyn Iterator<Item = Result<(K, V)>>>;
+	fn get_ref(&self, key: &Self::Key) -> Result<Option<Self::Value>> {
+		if let Some(value) = self.inner.lock().touch(key) {
+			return Ok(Some(value));
+		}
+		let value = (self.loader)(key)?;
+		if let Some(value) = &value {
+			self.inner.lock().insert(key.clone(), value.clone());
+		}
+		Ok(value)
+	}
+	fn iter(&self) -> Self::Iter {
+		let lru = self.inner.lock();
+		Box::new(
+			lru.order
+				.values()
+				.filter_map(|k| lru.map.get(k).map(|(v, _)| Ok((k.clone(), v.clone()))))
+				.collect::<Vec<_>>()
+				.into_iter(),
+		)
+	}
+	fn contains_key_ref(&self, key: &Self::Key) -> Result<bool> {
+		Ok(self.get_ref(key)?.is_some())
+	}
+	fn get_lt_ref(&self, _: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("A cache has no lesser entries")
+	}
+	fn get_gt_ref(&self, _: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("A cache has no greater entries")
+	}
+	fn first(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("A cache has no first entry")
+	}
+	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>> {
+		bail!("A cache has no last entry")
+	}
+	fn is_empty(&self) -> Option<bool> {
+		// Missing keys may still be loaded
+		None
+	}
+	fn range(&self, _: impl std::ops::RangeBounds<Self::Key>) -> Result<Self::Iter> {
+		bail!("A cache has no ranges")
+	}
+}
+into_iter!(
+	impl<K, V> for LoadedLru<K, V>
+	where
+		K: 'static + Clone + Send + Sync + Hash + Eq,
+		V: 'static + Clone + Send + Sync,
+);
+
+/// Changes the entries in memory, the loader is left as it is.
+impl<K, V> Change for LoadedLru<K, V>
+where
+	K: 'static + Clone + Send + Sync + Hash + Eq,
+	V: 'static + Clone + Send + Sync,
+{
+	type Key = K;
+	type Value = V;
+	type Insert = V;
+	fn insert_owned(&self, key: K, value: V) -> Result<Option<<Self as Change>::Value>> {
+		Ok(self.inner.lock().insert(key, value))
+	}
+	fn remove_ref(&self, key: &<Self as Change>::Key) -> Result<Option<<Self as Change>::Value>> {
+		Ok(self.inner.lock().remove(key))
+	}
+	fn clear(&self) -> Result<()> {
+		self.inner.lock().clear();
+		Ok(())
+	}
+	fn fetch_and_update(
+		&self,
+		key: &Self::Key,
+		mut f: impl FnMut(Option<Self::Value>) -> Option<Self::Value>,
+	) -> Result<Option<Self::Value>> {
+		let mut lru = self.inner.lock();
+		let prev = lru.touch(key);
+		match f(prev.clone()) {
+			Some(next) => lru.insert(key.clone(), next),
+			None => lru.remove(key),
+		};
+		Ok(prev)
+	}
+}
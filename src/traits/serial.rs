@@ -1,4 +1,9 @@
-use anyhow::Result;
+use std::{cmp::Ordering, ops::Deref};
+
+use crate::{
+	error::{HuskyError, Result},
+	macros::bail,
+};
 
 /// Represents values that can be transformed into bytes.
 pub trait Serial
@@ -7,10 +12,342 @@ where
 {
 	/// Converts the value into bytes.
 	fn serialize(&self) -> Result<Vec<u8>>;
+	/// Appends the bytes of the value to a buffer, so the buffer can be reused between values.
+	fn serialize_into(&self, out: &mut Vec<u8>) -> Result<()> {
+		out.extend(self.serialize()?);
+		Ok(())
+	}
 	/// Recovers the value from bytes.
 	fn deserialize(bytes: Vec<u8>) -> Result<Self>;
 }
 
+/// A schema that can be read from payloads of its older versions.
+/// Please refer to [Versioned].
+pub trait Migrate: Serial {
+	/// The version written with the current schema.
+	const VERSION: u32;
+	/// Upgrades a payload written with an older version.
+	/// Unversioned legacy bytes have version `0`.
+	///
+	/// By default the payload is read as the current schema.
+	/// To migrate through a chain of versions, read the payload as the previous schema with its own [migrate](Migrate::migrate).
+	fn migrate(version: u32, bytes: Vec<u8>) -> Result<Self> {
+		let _ = version;
+		Self::deserialize(bytes)
+	}
+}
+
+/// The bytes that start a versioned payload.
+/// The version after them is followed by a check byte, see [envelope_check].
+const ENVELOPE: [u8; 4] = [0xff, 0x68, 0x75, 0x76];
+
+/// The byte that follows the version of a versioned payload.
+/// A legacy payload is only read as versioned if it starts with [ENVELOPE] and a version that this byte matches.
+fn envelope_check(version: &[u8]) -> u8 {
+	version.iter().fold(0xa5u8, |check, byte| check.rotate_left(3) ^ byte)
+}
+
+/// Reads the version and the length of the header of a versioned payload.
+/// Returns `None` for unversioned payloads.
+fn read_envelope(bytes: &[u8]) -> Option<(u32, usize)> {
+	let rest = bytes.strip_prefix(&ENVELOPE)?;
+	let mut version = 0u32;
+	// The version is written as a LEB128 varint of at most 5 bytes
+	let len = rest.iter().take(5).position(|byte| byte & 0x80 == 0)? + 1;
+	for (i, byte) in rest[..len].iter().enumerate() {
+		version |= ((byte & 0x7f) as u32).checked_shl(7 * i as u32)?;
+	}
+	if *rest.get(len)? != envelope_check(&rest[..len]) {
+		return None;
+	}
+	Some((version, ENVELOPE.len() + len + 1))
+}
+
+/// A value stored along with the version of its schema.
+/// Older payloads are upgraded with [Migrate] when read, and unversioned payloads are read as version `0`.
+///
+/// Versioned payloads start with a four byte tag, then the version and a check byte over it.
+/// An unversioned payload that happens to start with the same tag and a matching check byte would be misread,
+/// so trees holding arbitrary bytes should be rewritten as versioned once.
+/// # Examples
+/// The schemas derive rkyv, so the example only runs with the default serializer
+#[cfg_attr(feature = "rkyv", doc = "```")]
+#[cfg_attr(not(feature = "rkyv"), doc = "```ignore")]
+/// # use husky::traits::serial::{Migrate, Serial, Versioned};
+/// # use rkyv::{Archive, Deserialize, Serialize};
+/// # use bytecheck::CheckBytes;
+/// #[derive(Archive, Serialize, Deserialize, Clone)]
+/// #[archive_attr(derive(CheckBytes))]
+/// struct V1 {
+///   degrees: u32,
+/// }
+/// impl Migrate for V1 {
+///   const VERSION: u32 = 1;
+/// }
+///
+/// #[derive(Archive, Serialize, Deserialize, Clone)]
+/// #[archive_attr(derive(CheckBytes))]
+/// struct V2 {
+///   degrees: u32,
+///   unit: String,
+/// }
+/// impl Migrate for V2 {
+///   const VERSION: u32 = 2;
+///   fn migrate(version: u32, bytes: Vec<u8>) -> husky::error::Result<Self> {
+///     let V1 { degrees } = V1::migrate(version, bytes)?;
+///     Ok(V2 { degrees, unit: "celsius".to_string() })
+///   }
+/// }
+///
+/// let old = Versioned(V1 { degrees: 20 }).serialize().unwrap();
+/// let new: Versioned<V2> = Serial::deserialize(old).unwrap();
+/// assert_eq!((new.degrees, new.unit.as_str()), (20, "celsius"));
+/// ```
+#[derive(Clone, Debug, Default, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Versioned<T>(pub T);
+impl<T> From<T> for Versioned<T> {
+	fn from(value: T) -> Self {
+		Versioned(value)
+	}
+}
+impl<T> Deref for Versioned<T> {
+	type Target = T;
+	fn deref(&self) -> &T {
+		&self.0
+	}
+}
+impl<T> Serial for Versioned<T>
+where
+	T: Migrate,
+{
+	fn serialize(&self) -> Result<Vec<u8>> {
+		let mut bytes = Vec::new();
+		self.serialize_into(&mut bytes)?;
+		Ok(bytes)
+	}
+	fn serialize_into(&self, bytes: &mut Vec<u8>) -> Result<()> {
+		bytes.extend(ENVELOPE);
+		// The version is written as a LEB128 varint
+		let start = bytes.len();
+		let mut version = T::VERSION;
+		loop {
+			let byte = (version & 0x7f) as u8;
+			version >>= 7;
+			if version == 0 {
+				bytes.push(byte);
+				break;
+			}
+			bytes.push(byte | 0x80);
+		}
+		bytes.push(envelope_check(&bytes[start..]));
+		self.0.serialize_into(bytes)
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		let (version, header) = match read_envelope(&bytes) {
+			Some(envelope) => envelope,
+			None => return Ok(Versioned(T::migrate(0, bytes)?)),
+		};
+		let payload = bytes[header..].to_vec();
+		let value = match version.cmp(&T::VERSION) {
+			Ordering::Equal => T::deserialize(payload)?,
+			Ordering::Less => T::migrate(version, payload)?,
+			Ordering::Greater => bail!(
+				"Payload version {} is newer than the schema version {}",
+				version,
+				T::VERSION
+			),
+		};
+		Ok(Versioned(value))
+	}
+}
+
+/// A [uuid::Uuid] key, stored as its 16 bytes so keys are ordered as the uuids.
+/// Time-ordered uuids, as v7, are then iterated in creation order.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, UuidKey};
+/// # let db = husky::open_temp().unwrap();
+/// let tree: Tree<UuidKey, u32> = db.open_tree("tree").unwrap();
+/// let id = uuid::Uuid::from_u128(1);
+/// tree.insert(id, 10u32).unwrap();
+/// assert_eq!(tree.get(id).unwrap(), Some(10));
+/// ```
+#[cfg(feature = "uuid")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct UuidKey(pub uuid::Uuid);
+#[cfg(feature = "uuid")]
+impl From<uuid::Uuid> for UuidKey {
+	fn from(uuid: uuid::Uuid) -> Self {
+		UuidKey(uuid)
+	}
+}
+#[cfg(feature = "uuid")]
+impl From<UuidKey> for uuid::Uuid {
+	fn from(key: UuidKey) -> Self {
+		key.0
+	}
+}
+#[cfg(feature = "uuid")]
+impl Deref for UuidKey {
+	type Target = uuid::Uuid;
+	fn deref(&self) -> &uuid::Uuid {
+		&self.0
+	}
+}
+#[cfg(feature = "uuid")]
+impl Serial for UuidKey {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		Ok(self.0.as_bytes().to_vec())
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		match uuid::Uuid::from_slice(&bytes) {
+			Ok(uuid) => Ok(UuidKey(uuid)),
+			Err(_) => bail!("Expected 16 bytes for a uuid, got {}", bytes.len()),
+		}
+	}
+}
+
+/// A [chrono::DateTime] key in UTC, stored as its nanoseconds since the epoch so keys are ordered in time.
+///
+/// The nanoseconds are written big-endian with the sign bit flipped, so times before the epoch come first.
+/// Only times between the years 1677 and 2262 fit, storing others fails.
+/// # Examples
+/// ```
+/// # use husky::{Tree, View, Change, Timestamp};
+/// # use chrono::{TimeZone, Utc};
+/// # let db = husky::open_temp().unwrap();
+/// let tree: Tree<Timestamp, u32> = db.open_tree("tree").unwrap();
+/// tree.insert(Utc.timestamp_opt(20, 0).unwrap(), 2u32).unwrap();
+/// tree.insert(Utc.timestamp_opt(-10, 0).unwrap(), 1u32).unwrap();
+/// let values = tree.iter().map(|r| r.unwrap().1).collect::<Vec<_>>();
+/// assert_eq!(values, vec![1, 2]);
+/// ```
+#[cfg(feature = "chrono")]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Timestamp(pub chrono::DateTime<chrono::Utc>);
+#[cfg(feature = "chrono")]
+impl From<chrono::DateTime<chrono::Utc>> for Timestamp {
+	fn from(time: chrono::DateTime<chrono::Utc>) -> Self {
+		Timestamp(time)
+	}
+}
+#[cfg(feature = "chrono")]
+impl From<Timestamp> for chrono::DateTime<chrono::Utc> {
+	fn from(key: Timestamp) -> Self {
+		key.0
+	}
+}
+#[cfg(feature = "chrono")]
+impl Deref for Timestamp {
+	type Target = chrono::DateTime<chrono::Utc>;
+	fn deref(&self) -> &chrono::DateTime<chrono::Utc> {
+		&self.0
+	}
+}
+#[cfg(feature = "chrono")]
+impl Serial for Timestamp {
+	fn serialize(&self) -> Result<Vec<u8>> {
+		let nanos = match self.0.timestamp_nanos_opt() {
+			Some(nanos) => nanos,
+			None => bail!("Timestamp {} doesn't fit in nanoseconds", self.0),
+		};
+		Ok(((nanos as u64) ^ (1 << 63)).to_be_bytes().to_vec())
+	}
+	fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+		use chrono::TimeZone;
+		let bytes: [u8; 8] = match bytes.try_into() {
+			Ok(bytes) => bytes,
+			Err(bytes) => bail!("Expected 8 bytes for a timestamp, got {}", bytes.len()),
+		};
+		let nanos = (u64::from_be_bytes(bytes) ^ (1 << 63)) as i64;
+		Ok(Timestamp(chrono::Utc.timestamp_nanos(nanos)))
+	}
+}
+
+/// Serializes a value to be stored in a tree.
+/// With the `compress_values` feature, large values are compressed. Keys are never compressed, so their order is kept.
+pub(crate) fn serialize_value<V: Serial>(value: &V) -> Result<Vec<u8>> {
+	let bytes = value.serialize().map_err(HuskyError::serialization)?;
+	#[cfg(feature = "compress_values")]
+	let bytes = compress::compress(bytes)?;
+	Ok(bytes)
+}
+
+/// Like [serialize_value], but appends the bytes to a buffer.
+/// With the `compress_values` feature the value is compressed as a whole, so it still goes through its own buffer.
+pub(crate) fn serialize_value_into<V: Serial>(value: &V, out: &mut Vec<u8>) -> Result<()> {
+	#[cfg(feature = "compress_values")]
+	out.extend(compress::compress(
+		value.serialize().map_err(HuskyError::serialization)?,
+	)?);
+	#[cfg(not(feature = "compress_values"))]
+	value
+		.serialize_into(out)
+		.map_err(HuskyError::serialization)?;
+	Ok(())
+}
+
+/// Recovers a value stored in a tree, decompressing it if needed.
+pub(crate) fn deserialize_value<V: Serial>(bytes: Vec<u8>) -> Result<V> {
+	#[cfg(feature = "compress_values")]
+	let bytes = compress::decompress(bytes).map_err(HuskyError::serialization)?;
+	V::deserialize(bytes).map_err(HuskyError::serialization)
+}
+
+/// Transparent compression of large values, enabled with the `compress_values` feature.
+///
+/// Values larger than the [threshold](compress::threshold) are compressed with zstd.
+/// A compressed value starts with a flag byte followed by the zstd frame,
+/// so values stored uncompressed, including the ones written without the feature, can still be read.
+#[cfg(feature = "compress_values")]
+pub mod compress {
+	use std::sync::atomic::{AtomicUsize, Ordering::Relaxed};
+
+	use crate::error::{HuskyError, Result};
+
+	/// The byte that flags a compressed value.
+	const FLAG: u8 = 0xc0;
+	/// The magic number that starts every zstd frame.
+	const MAGIC: [u8; 4] = [0x28, 0xb5, 0x2f, 0xfd];
+	/// The zstd compression level.
+	const LEVEL: i32 = 3;
+
+	static THRESHOLD: AtomicUsize = AtomicUsize::new(1024);
+
+	/// Sets the size in bytes above which values are compressed.
+	pub fn set_threshold(bytes: usize) {
+		THRESHOLD.store(bytes, Relaxed);
+	}
+	/// Gets the size in bytes above which values are compressed.
+	pub fn threshold() -> usize {
+		THRESHOLD.load(Relaxed)
+	}
+
+	pub(crate) fn compress(bytes: Vec<u8>) -> Result<Vec<u8>> {
+		if bytes.len() <= threshold() {
+			return Ok(bytes);
+		}
+		let compressed = zstd::encode_all(&bytes[..], LEVEL).map_err(HuskyError::serialization)?;
+		// Keeps the value as it is if compressing doesn't make it smaller
+		if compressed.len() + 1 >= bytes.len() {
+			return Ok(bytes);
+		}
+		let mut flagged = Vec::with_capacity(compressed.len() + 1);
+		flagged.push(FLAG);
+		flagged.extend(compressed);
+		Ok(flagged)
+	}
+
+	pub(crate) fn decompress(bytes: Vec<u8>) -> Result<Vec<u8>> {
+		match bytes.split_first() {
+			Some((&FLAG, frame)) if frame.starts_with(&MAGIC) => {
+				zstd::decode_all(frame).map_err(HuskyError::serialization)
+			}
+			_ => Ok(bytes),
+		}
+	}
+}
+
 #[cfg(all(not(feature = "rkyv"), not(feature = "serde")))]
 compile_error!("You must specify a serializer, either rkyv or serde");
 #[cfg(all(not(feature = "rkyv"), feature = "bytecheck"))]
@@ -22,17 +359,74 @@ compile_error!(
                 or in the Cargo.toml with 'husky = { default-features = false }'"
 );
 
+#[cfg(feature = "rkyv")]
+pub use self::rkyv::ReadArchived;
+
 #[cfg(feature = "rkyv")]
 mod rkyv {
+	use rkyv::{
+		ser::{
+			serializers::{AlignedSerializer, AllocSerializer},
+			Serializer,
+		},
+		AlignedVec, Archive, Serialize,
+	};
+	use std::cell::RefCell;
+
+	use crate::error::{HuskyError, Result};
+
+	/// Represents values whose archived form can be read in place, without deserializing them.
+	/// With the `bytecheck` feature the bytes are validated first.
+	pub trait ReadArchived: Archive {
+		/// Reads the archived value at the end of some bytes.
+		/// The bytes must be aligned, as the ones of an [AlignedVec].
+		fn read_archived(bytes: &[u8]) -> Result<&Self::Archived>;
+	}
+
+	thread_local! {
+		/// The buffer reused by the serializations of this thread
+		static BUFFER: RefCell<AlignedVec> = RefCell::new(AlignedVec::new());
+	}
+
+	/// Serializes a value into the buffer of this thread, then appends it to the output.
+	/// The buffer is taken out while in use, so a nested serialization gets a buffer of its own.
+	fn serialize_into<T>(value: &T, out: &mut Vec<u8>) -> Result<()>
+	where
+		T: Serialize<AllocSerializer<256>>,
+	{
+		let mut buffer = BUFFER.with(|b| b.take());
+		buffer.clear();
+		let serializer = AlignedSerializer::new(buffer);
+		let mut serializer = AllocSerializer::<256>::new(serializer, Default::default(), Default::default());
+		let res = serializer.serialize_value(value);
+		let buffer = serializer.into_serializer().into_inner();
+		if res.is_ok() {
+			out.extend_from_slice(&buffer);
+		}
+		BUFFER.with(|b| b.replace(buffer));
+		res.map_err(HuskyError::serialization)?;
+		Ok(())
+	}
+
 	#[cfg(feature = "bytecheck")]
 	mod checked {
-		use crate::{macros::unwrap_or_error, traits::serial::Serial};
-		use anyhow::Result;
+		use super::ReadArchived;
+		use crate::{error::Result, macros::unwrap_or_error, traits::serial::Serial};
 		use bytecheck::CheckBytes;
 		use rkyv::{
 			ser::serializers::AllocSerializer, validation::validators::DefaultValidator, Archive,
 			Deserialize, Infallible, Serialize,
 		};
+		impl<T> ReadArchived for T
+		where
+			T: Archive,
+			<T as Archive>::Archived: for<'a> CheckBytes<DefaultValidator<'a>>,
+		{
+			fn read_archived(bytes: &[u8]) -> Result<&Self::Archived> {
+				let archived = rkyv::check_archived_root::<T>(bytes);
+				Ok(unwrap_or_error!(archived))
+			}
+		}
 		impl<T> Serial for T
 		where
 			T: 'static + Sized + Clone + Archive + Serialize<AllocSerializer<256>> + Sync + Send,
@@ -40,8 +434,12 @@ mod rkyv {
 				for<'a> CheckBytes<DefaultValidator<'a>> + Deserialize<T, Infallible>,
 		{
 			fn serialize(&self) -> Result<Vec<u8>> {
-				let serialized = rkyv::to_bytes::<_, 256>(self)?;
-				Ok(serialized.to_vec())
+				let mut bytes = Vec::new();
+				super::serialize_into(self, &mut bytes)?;
+				Ok(bytes)
+			}
+			fn serialize_into(&self, out: &mut Vec<u8>) -> Result<()> {
+				super::serialize_into(self, out)
 			}
 			fn deserialize(bytes: Vec<u8>) -> Result<Self> {
 				let archived = rkyv::check_archived_root::<T>(&bytes);
@@ -53,19 +451,29 @@ mod rkyv {
 	}
 	#[cfg(not(feature = "bytecheck"))]
 	mod unchecked {
-		use crate::traits::serial::Serial;
-		use anyhow::Result;
+		use super::ReadArchived;
+		use crate::{error::Result, traits::serial::Serial};
 		use rkyv::{
 			ser::serializers::AllocSerializer, Archive, Deserialize, Infallible, Serialize,
 		};
+		impl<T: Archive> ReadArchived for T {
+			fn read_archived(bytes: &[u8]) -> Result<&Self::Archived> {
+				// Only bytes written by husky are read, as in deserialize
+				Ok(unsafe { rkyv::archived_root::<T>(bytes) })
+			}
+		}
 		impl<T> Serial for T
 		where
 			T: 'static + Sized + Clone + Archive + Serialize<AllocSerializer<256>> + Sync + Send,
 			<T as Archive>::Archived: Deserialize<T, Infallible>,
 		{
 			fn serialize(&self) -> Result<Vec<u8>> {
-				let serialized = rkyv::to_bytes::<_, 256>(self)?;
-				Ok(serialized.to_vec())
+				let mut bytes = Vec::new();
+				super::serialize_into(self, &mut bytes)?;
+				Ok(bytes)
+			}
+			fn serialize_into(&self, out: &mut Vec<u8>) -> Result<()> {
+				super::serialize_into(self, out)
 			}
 			fn deserialize(bytes: Vec<u8>) -> Result<Self> {
 				let archived = unsafe { rkyv::archived_root::<T>(&bytes) };
@@ -75,10 +483,12 @@ mod rkyv {
 		}
 	}
 }
-#[cfg(all(feature = "serde", not(feature = "rkyv")))]
+#[cfg(all(feature = "serde", not(feature = "rkyv"), not(feature = "messagepack")))]
 mod serde {
-	use crate::traits::serial::Serial;
-	use anyhow::Result;
+	use crate::{
+		error::{HuskyError, Result},
+		traits::serial::Serial,
+	};
 	use bincode::{
 		config::{BigEndian, WithOtherEndian},
 		DefaultOptions, Options,
@@ -92,10 +502,45 @@ mod serde {
 		T: 'static + Sized + Clone + Serialize + for<'a> Deserialize<'a> + Sync + Send,
 	{
 		fn serialize(&self) -> Result<Vec<u8>> {
-			Ok(big_endian().serialize(&self)?)
+			big_endian()
+				.serialize(&self)
+				.map_err(HuskyError::serialization)
+		}
+		fn serialize_into(&self, out: &mut Vec<u8>) -> Result<()> {
+			big_endian()
+				.serialize_into(out, &self)
+				.map_err(HuskyError::serialization)
+		}
+		fn deserialize(bytes: Vec<u8>) -> Result<Self> {
+			big_endian()
+				.deserialize(&bytes)
+				.map_err(HuskyError::serialization)
+		}
+	}
+}
+/// Serialization with MessagePack, for trees that are read from other languages.
+///
+/// Keys are ordered by their encoded bytes, which differs from bincode's big-endian order:
+/// negative numbers sort after positive ones, and strings sort by length before their contents.
+#[cfg(all(feature = "messagepack", not(feature = "rkyv")))]
+mod messagepack {
+	use crate::{
+		error::{HuskyError, Result},
+		traits::serial::Serial,
+	};
+	use serde::{Deserialize, Serialize};
+	impl<T> Serial for T
+	where
+		T: 'static + Sized + Clone + Serialize + for<'a> Deserialize<'a> + Sync + Send,
+	{
+		fn serialize(&self) -> Result<Vec<u8>> {
+			rmp_serde::to_vec(&self).map_err(HuskyError::serialization)
+		}
+		fn serialize_into(&self, out: &mut Vec<u8>) -> Result<()> {
+			rmp_serde::encode::write(out, &self).map_err(HuskyError::serialization)
 		}
 		fn deserialize(bytes: Vec<u8>) -> Result<Self> {
-			Ok(big_endian().deserialize(&bytes)?)
+			rmp_serde::from_slice(&bytes).map_err(HuskyError::serialization)
 		}
 	}
 }
@@ -1,11 +1,15 @@
 use std::hash::Hash;
 
-use anyhow::Result;
+use crate::error::Result;
 
 /// Represents a struct that can be stored in a tree.
 pub trait Store {
 	/// The stored type
 	type Stored;
 	/// Stores the struct
+	///
+	/// Opening a stored view doesn't walk its source, it only follows the changes from then on.
+	/// Changes made while it wasn't open are caught up with [reconcile](crate::Material::reconcile),
+	/// which writes only the entries that differ, or with a full rebuild.
 	fn store(&self, name: impl Hash) -> Result<Self::Stored>;
 }
@@ -1,21 +1,23 @@
-use anyhow::Result;
 use bus::BusReader;
 use delegate::delegate;
 use std::{ops::RangeBounds, sync::Arc};
 
 use crate::{
+	error::Result,
+	macros::into_iter,
 	traits::{
 		change::Change,
 		view::View,
 		watch::{Event, Watch},
 	},
 	wrappers::{
+		batch::Batch,
 		database::Db,
 		tree::{self, Tree},
 	},
 };
 
-use super::serial::Serial;
+use super::{auto_inc::AutoInc, serial::Serial};
 
 impl<Key, Value> View for Tree<Key, Value>
 where
@@ -34,6 +36,11 @@ where
       fn get_gt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>;
       fn first(&self) -> Result<Option<(Self::Key, Self::Value)>>;
       fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>;
+      fn first_key(&self) -> Result<Option<Self::Key>>;
+      fn last_key(&self) -> Result<Option<Self::Key>>;
+      fn nth(&self, n: usize) -> Result<Option<(Self::Key, Self::Value)>>;
+      fn first_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>>;
+      fn last_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>>;
       fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter>;
       fn iter(&self) -> Self::Iter;
 	  }
@@ -42,6 +49,12 @@ where
 		Some(self.is_empty())
 	}
 }
+into_iter!(
+	impl<Key, Value> for Tree<Key, Value>
+	where
+		Key: Serial,
+		Value: Serial,
+);
 
 impl<Key, Value> Watch for Tree<Key, Value>
 where
@@ -73,6 +86,51 @@ where
 	fn clear(&self) -> Result<()> {
 		Ok(self.clear()?)
 	}
+	fn push_owned(&self, value: Self::Insert) -> Result<Self::Key>
+	where
+		Self: View<Key = Key>,
+		Key: AutoInc + Ord,
+	{
+		self.push_owned(value)
+	}
+	fn insert_if_absent(&self, key: &Key, value: &Value) -> Result<Option<Value>>
+	where
+		Self: View<Key = Key, Value = Value>,
+	{
+		self.insert_if_absent(key, value)
+	}
+	fn swap(&self, from: &Key, to: &Key) -> Result<Option<Value>> {
+		self.swap(from, to)
+	}
+	fn pop_min(&self) -> Result<Option<(Key, Value)>>
+	where
+		Self: View<Key = Key>,
+		Key: Ord,
+	{
+		self.pop_min()
+	}
+	fn pop_max(&self) -> Result<Option<(Key, Value)>>
+	where
+		Self: View<Key = Key>,
+		Key: Ord,
+	{
+		self.pop_max()
+	}
+	fn retain(&self, pred: impl Fn(&Key, &Value) -> bool) -> Result<usize>
+	where
+		Self: View<Key = Key, Value = Value>,
+	{
+		self.retain(pred)
+	}
+	fn remove_range(&self, range: impl RangeBounds<Key>) -> Result<usize>
+	where
+		Self: View<Key = Key>,
+	{
+		self.remove_range(range)
+	}
+	fn apply_batch(&self, batch: Batch<Key, Value>) -> Result<()> {
+		self.apply_batch(batch)
+	}
   #[rustfmt::skip]
 	delegate! {
 	  to self {
@@ -82,6 +140,11 @@ where
         &self,
         key: &Self::Key,
         f: impl FnMut(Option<Self::Value>) -> Option<Self::Value>,
+      ) -> Result<Option<Self::Value>>;
+      fn update_and_fetch(
+        &self,
+        key: &Self::Key,
+        f: impl FnMut(Option<Self::Value>) -> Option<Self::Value>,
       ) -> Result<Option<Self::Value>>;
 	  }
 	}
@@ -1,6 +1,13 @@
-use std::ops::RangeBounds;
+use std::{
+	collections::{BTreeMap, HashMap, VecDeque},
+	hash::Hash,
+	ops::RangeBounds,
+};
 
-use anyhow::Result;
+use crate::{
+	error::Result,
+	structs::iter::{Chunks, Windows},
+};
 
 /// Allows for viewing entries in a tree.
 pub trait View
@@ -25,6 +32,21 @@ where
 	fn contains_key<K: Into<Self::Key>>(&self, key: K) -> Result<bool> {
 		self.contains_key_ref(&key.into())
 	}
+	/// Checks if any key holds a value.
+	/// By default the whole view is scanned, so it takes linear time,
+	/// unless the view keeps its values indexed, as a loaded or stored [invertible_map](crate::Operate::invertible_map).
+	/// Fails on the first error of the scan.
+	fn contains_value(&self, value: &Self::Value) -> Result<bool>
+	where
+		Self::Value: PartialEq,
+	{
+		for res in self.iter() {
+			if res?.1 == *value {
+				return Ok(true);
+			}
+		}
+		Ok(false)
+	}
 	/// Gets the immediate lesser item by key reference.
 	fn get_lt_ref(&self, key: &Self::Key) -> Result<Option<(Self::Key, Self::Value)>>
 	where
@@ -55,10 +77,129 @@ where
 	fn last(&self) -> Result<Option<(Self::Key, Self::Value)>>
 	where
 		Self::Key: Ord;
+	/// Gets the first key.
+	/// By default the value is read too, a [Tree](crate::Tree) only deserializes the key.
+	fn first_key(&self) -> Result<Option<Self::Key>>
+	where
+		Self::Key: Ord,
+	{
+		Ok(self.first()?.map(|(k, _)| k))
+	}
+	/// Gets the last key.
+	/// By default the value is read too, a [Tree](crate::Tree) only deserializes the key.
+	fn last_key(&self) -> Result<Option<Self::Key>>
+	where
+		Self::Key: Ord,
+	{
+		Ok(self.last()?.map(|(k, _)| k))
+	}
 	/// Checks if tree is empty
 	fn is_empty(&self) -> Option<bool>;
 	/// Gets an iterator over a key range in the tree
 	fn range(&self, range: impl RangeBounds<Self::Key>) -> Result<Self::Iter>;
 	/// Gets an iterator over the entries in the tree.
 	fn iter(&self) -> Self::Iter;
+	/// Gets the entry at a position, counting from 0.
+	/// Positions follow the key order on ordered views, and the iteration order otherwise.
+	/// Fails on the first error before that position.
+	fn nth(&self, n: usize) -> Result<Option<(Self::Key, Self::Value)>> {
+		let mut iter = self.iter();
+		for _ in 0..n {
+			if iter.next().transpose()?.is_none() {
+				return Ok(None);
+			}
+		}
+		iter.next().transpose()
+	}
+	/// Gets the first `n` entries in key order, or fewer if the view is smaller.
+	fn first_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		self.iter().take(n).collect()
+	}
+	/// Gets the last `n` entries in key order, or fewer if the view is smaller.
+	/// By default the whole view is read, keeping the last entries.
+	fn last_n(&self, n: usize) -> Result<Vec<(Self::Key, Self::Value)>>
+	where
+		Self::Key: Ord,
+	{
+		if n == 0 {
+			return Ok(Vec::new());
+		}
+		let mut last = VecDeque::with_capacity(n);
+		for res in self.iter() {
+			if last.len() == n {
+				last.pop_front();
+			}
+			last.push_back(res?);
+		}
+		Ok(last.into())
+	}
+	/// Calls a function on each entry, in order.
+	/// Stops at the first error, either from the view or from the function.
+	fn try_for_each(
+		&self,
+		mut f: impl FnMut(Self::Key, Self::Value) -> anyhow::Result<()>,
+	) -> Result<()> {
+		for res in self.iter() {
+			let (k, v) = res?;
+			f(k, v)?;
+		}
+		Ok(())
+	}
+	/// Calls a function on each entry, in order.
+	/// Stops at the first error from the view.
+	fn for_each(&self, mut f: impl FnMut(Self::Key, Self::Value)) -> Result<()> {
+		self.try_for_each(|k, v| {
+			f(k, v);
+			Ok(())
+		})
+	}
+	/// Collects the entries into a [BTreeMap], failing on the first error.
+	fn collect_btree(&self) -> Result<BTreeMap<Self::Key, Self::Value>>
+	where
+		Self::Key: Ord,
+	{
+		self.iter().collect()
+	}
+	/// Collects the entries into a [HashMap], failing on the first error.
+	fn collect_hash(&self) -> Result<HashMap<Self::Key, Self::Value>>
+	where
+		Self::Key: Hash + Eq,
+	{
+		self.iter().collect()
+	}
+	/// Gets an iterator over the entries in chunks of `size`, the last chunk possibly being smaller.
+	///
+	/// Panics if `size` is 0.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+	/// for i in 0..5u32 {
+	///   tree.insert(i, i).unwrap();
+	/// }
+	/// let sizes: Vec<usize> = tree.chunks(2).map(|c| c.unwrap().len()).collect();
+	/// assert_eq!(sizes, vec![2, 2, 1]);
+	/// ```
+	fn chunks(&self, size: usize) -> Chunks<Self::Iter> {
+		Chunks::new(self.iter(), size)
+	}
+	/// Gets an iterator over overlapping windows of `size` entries, moving one entry at a time.
+	/// There are no windows if the view has fewer than `size` entries.
+	///
+	/// Panics if `size` is 0.
+	fn windows(&self, size: usize) -> Windows<Self::Iter, (Self::Key, Self::Value)> {
+		Windows::new(self.iter(), size)
+	}
+	/// Gets a parallel iterator over the entries in the tree, with the `rayon` feature.
+	/// The entries are read in order, then processed in parallel.
+	/// The whole view is read into memory first, so prefer [View::iter] for large views.
+	#[cfg(feature = "rayon")]
+	fn par_iter(&self) -> rayon::vec::IntoIter<Result<(Self::Key, Self::Value)>> {
+		use rayon::iter::IntoParallelIterator;
+		self.iter().collect::<Vec<_>>().into_par_iter()
+	}
 }
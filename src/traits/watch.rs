@@ -1,8 +1,18 @@
 use bus::{Bus, BusReader};
 use parking_lot::{Mutex, RwLock};
-use std::sync::Arc;
+use std::{
+	ops::{Deref, DerefMut},
+	sync::Arc,
+	time::Duration,
+};
 
-use crate::{threads::Synchronizer, wrappers::database::Db};
+#[cfg(feature = "sync")]
+use crate::threads::pump;
+use crate::{
+	error::Result,
+	threads::{broadcast, spawn_watcher, Synchronizer},
+	wrappers::database::Db,
+};
 
 use super::view::View;
 
@@ -22,6 +32,15 @@ pub enum Event<Key, Value> {
 		key: Arc<Key>,
 	},
 }
+impl<K, V> Event<K, V> {
+	/// The key of the event, whether inserted or removed
+	pub fn key(&self) -> &Arc<K> {
+		match self {
+			Self::Insert { key, .. } => key,
+			Self::Remove { key } => key,
+		}
+	}
+}
 impl<K, V> Clone for Event<K, V> {
 	fn clone(&self) -> Self {
 		match self {
@@ -46,6 +65,8 @@ pub type Shared<T> = Arc<RwLock<T>>;
 pub type Broadcaster<K, V> = Bus<Event<K, V>>;
 /// An optional [Generator]
 pub type OptGenerator<K, V> = Option<Box<Generator<K, V>>>;
+/// The entries of a view, along with a reader for the changes that follow them.
+pub type Snapshot<K, V> = (Vec<(K, V)>, BusReader<Event<K, V>>);
 pub(crate) struct Watcher<Key, Value> {
 	bus: IntMut<Option<Shared<Broadcaster<Key, Value>>>>,
 	init: IntMut<OptGenerator<Key, Value>>,
@@ -84,9 +105,10 @@ impl<K, V> Watcher<K, V> {
 	}
 	pub fn send(&self, event: Event<K, V>) {
 		if let Some(bus) = &*self.bus.lock() {
-			let mut bus = bus.write();
-			bus.broadcast(event);
+			broadcast(bus, event);
 		};
+		#[cfg(feature = "sync")]
+		pump();
 	}
 }
 
@@ -103,4 +125,156 @@ where
 	fn sync(&self) -> Arc<Synchronizer>;
 	/// Waits until all events are processed.
 	fn wait(&self);
+	/// Returns the current entries along with a reader for the changes that follow them.
+	///
+	/// The reader is taken before the entries are read, so no change is missed between both.
+	/// A change made while the entries are read may be in the entries and also come through the reader,
+	/// so changes on that boundary are delivered at least once.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Watch, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+	/// tree.insert(1u32, 10u32).unwrap();
+	/// let (snapshot, mut reader) = tree.watch_with_snapshot().unwrap();
+	/// tree.insert(2u32, 20u32).unwrap();
+	///
+	/// assert_eq!(snapshot, vec![(1, 10)]);
+	/// assert_eq!(**reader.recv().unwrap().key(), 2);
+	/// ```
+	fn watch_with_snapshot(&self) -> Result<Snapshot<Self::Key, Self::Value>> {
+		let reader = self.watch();
+		let snapshot = self.iter().collect::<Result<_>>()?;
+		Ok((snapshot, reader))
+	}
+	/// Waits until all events are processed, or until the timeout elapses.
+	/// Returns `false` on timeout, so that a stuck pipeline can be detected instead of blocking forever.
+	fn wait_timeout(&self, dur: Duration) -> bool {
+		self.sync().wait_timeout(dur)
+	}
+	/// The events sent to this view that it is yet to handle.
+	/// Please refer to [Synchronizer::lag]
+	fn lag(&self) -> u32 {
+		self.sync().lag()
+	}
+	/// Whether this view and the views it is built on have handled every event, as for a readiness check.
+	/// Please refer to [Synchronizer::is_caught_up]
+	fn is_caught_up(&self) -> bool {
+		self.sync().is_caught_up()
+	}
+	/// Returns the last error of a thread that feeds this view, if any.
+	/// Stored and loaded views also return it as an error when read.
+	fn last_error(&self) -> Option<Arc<anyhow::Error>> {
+		self.sync().last_error()
+	}
+	/// Returns the counters of the events that went through the view, with the `metrics` feature.
+	/// On a [Tree](crate::Tree) it is called as `Watch::stats(&tree)`, since [Tree::stats](crate::Tree::stats) counts its entries.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, Change, Operate, Store, Watch};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+	/// let stored = tree.map(|_, v| v * 2).store("stored").unwrap();
+	/// tree.insert(1u32, 1u32).unwrap();
+	/// stored.wait();
+	///
+	/// let stats = stored.stats();
+	/// assert_eq!((stats.received, stats.lag), (1, 0));
+	/// ```
+	#[cfg(feature = "metrics")]
+	fn stats(&self) -> crate::SyncStats {
+		self.sync().stats()
+	}
+	/// Returns a reader of the events transformed by a function.
+	/// The events are transformed in a thread, and [Events::wait] waits for it.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, Change, Watch, traits::watch::Event};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<u32, u32> = db.open_tree("tree").unwrap();
+	/// let mut events = tree.map_events(|event| match event {
+	///   Event::Insert { key, value } => Event::Insert { key, value: std::sync::Arc::new(*value * 2) },
+	///   Event::Remove { key } => Event::Remove { key },
+	/// });
+	///
+	/// tree.insert(1u32, 2u32).unwrap();
+	/// events.wait();
+	///
+	/// match events.try_recv().unwrap() {
+	///   Event::Insert { value, .. } => assert_eq!(*value, 4),
+	///   Event::Remove { .. } => unreachable!(),
+	/// }
+	/// ```
+	fn map_events<F, O>(&self, f: F) -> Events<Self::Key, O>
+	where
+		F: 'static + Fn(Event<Self::Key, Self::Value>) -> Event<Self::Key, O> + Send + Sync,
+		O: 'static + Send + Sync,
+	{
+		Events::new(self, move |event| Ok(vec![f(event)]))
+	}
+	/// Returns a reader of the events that pass a filter.
+	/// The events are filtered in a thread, and [Events::wait] waits for it.
+	fn filter_events<F>(&self, f: F) -> Events<Self::Key, Self::Value>
+	where
+		F: 'static + Fn(&Event<Self::Key, Self::Value>) -> bool + Send + Sync,
+	{
+		Events::new(self, move |event| match f(&event) {
+			true => Ok(vec![event]),
+			false => Ok(vec![]),
+		})
+	}
+}
+
+/// A reader of events derived from a [Watch] struct.
+/// You can create [Events] with [map_events](Watch::map_events) and [filter_events](Watch::filter_events).
+///
+/// It dereferences to a [BusReader], and has a synchronizer so that you can wait for the events to arrive.
+pub struct Events<Key, Value> {
+	reader: BusReader<Event<Key, Value>>,
+	sync: Arc<Synchronizer>,
+}
+
+impl<K, V> Events<K, V>
+where
+	K: 'static + Send + Sync,
+	V: 'static + Send + Sync,
+{
+	fn new<W, F>(from: &W, f: F) -> Self
+	where
+		W: Watch<Key = K>,
+		F: 'static + Fn(Event<K, W::Value>) -> Result<Vec<Event<K, V>>> + Send + Sync,
+	{
+		let sync = Arc::new(Synchronizer::from(vec![from.sync()]));
+		let bus = Arc::new(RwLock::new(Bus::new(128)));
+		// The reader is added before the thread starts, so no event is missed
+		let reader = bus.write().add_rx();
+		spawn_watcher(Arc::clone(&sync), from.watch(), bus, f);
+		Self { reader, sync }
+	}
+	pub(crate) fn from_reader(reader: BusReader<Event<K, V>>, sync: Arc<Synchronizer>) -> Self {
+		Self { reader, sync }
+	}
+	/// Waits until all events are processed.
+	pub fn wait(&self) {
+		self.sync.wait()
+	}
+	/// A synchronizer for the events.
+	pub fn sync(&self) -> Arc<Synchronizer> {
+		Arc::clone(&self.sync)
+	}
+	/// Returns the inner [BusReader]
+	pub fn into_inner(self) -> BusReader<Event<K, V>> {
+		self.reader
+	}
+}
+impl<K, V> Deref for Events<K, V> {
+	type Target = BusReader<Event<K, V>>;
+	fn deref(&self) -> &Self::Target {
+		&self.reader
+	}
+}
+impl<K, V> DerefMut for Events<K, V> {
+	fn deref_mut(&mut self) -> &mut Self::Target {
+		&mut self.reader
+	}
 }
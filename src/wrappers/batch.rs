@@ -1,21 +1,22 @@
-use anyhow::Result;
-use std::marker::PhantomData;
-
-use crate::traits::serial::Serial;
+use crate::{
+	error::Result,
+	traits::serial::{serialize_value, Serial},
+};
 
 /// A wrapper around [sled::Batch]
+///
+/// Besides the serialized batch, it keeps the staged operations in order,
+/// so that they can be replayed through views that aren't backed by a single tree.
 pub struct Batch<K, V> {
 	inner: sled::Batch,
-	k: PhantomData<K>,
-	v: PhantomData<V>,
+	ops: Vec<(K, Option<V>)>,
 }
 
 impl<K, V> Default for Batch<K, V> {
 	fn default() -> Self {
 		Self {
 			inner: Default::default(),
-			k: PhantomData,
-			v: PhantomData,
+			ops: Vec::new(),
 		}
 	}
 }
@@ -35,9 +36,10 @@ where
 	pub fn insert<IK: Into<K>, IV: Into<V>>(&mut self, key: IK, value: IV) -> Result<()> {
 		let key = key.into();
 		let value = value.into();
-		let key = Serial::serialize(&key)?;
-		let value = Serial::serialize(&value)?;
-		self.inner.insert(key, value);
+		let ser_key = Serial::serialize(&key)?;
+		let ser_value = serialize_value(&value)?;
+		self.inner.insert(ser_key, ser_value);
+		self.ops.push((key, Some(value)));
 		Ok(())
 	}
 	/// Remove a key from the batch
@@ -49,12 +51,40 @@ where
 	/// batch.remove("key");
 	pub fn remove<RK: Into<K>>(&mut self, key: RK) -> Result<()> {
 		let key = key.into();
-		let key = Serial::serialize(&key)?;
-		self.inner.remove(key);
+		let ser_key = Serial::serialize(&key)?;
+		self.inner.remove(ser_key);
+		self.ops.push((key, None));
 		Ok(())
 	}
 }
 
+impl<K, V> Batch<K, V> {
+	/// The number of staged operations, counting every insert and remove
+	/// # Examples
+	/// ```
+	/// use husky::Batch;
+	/// let mut batch: Batch<String, String> = Batch::default();
+	/// batch.insert("key", "value").unwrap();
+	/// batch.remove("key").unwrap();
+	/// assert_eq!(batch.len(), 2);
+	/// ```
+	pub fn len(&self) -> usize {
+		self.ops.len()
+	}
+	/// Checks if no operation is staged
+	pub fn is_empty(&self) -> bool {
+		self.ops.is_empty()
+	}
+	/// Iterates over the staged operations in order, with [None] values for removes
+	pub fn iter(&self) -> impl Iterator<Item = (&K, Option<&V>)> {
+		self.ops.iter().map(|(k, v)| (k, v.as_ref()))
+	}
+	/// Splits the batch into the sled batch and the staged operations, in order
+	pub(crate) fn into_parts(self) -> (sled::Batch, Vec<(K, Option<V>)>) {
+		(self.inner, self.ops)
+	}
+}
+
 impl<K, V> From<Batch<K, V>> for sled::Batch {
 	fn from(batch: Batch<K, V>) -> Self {
 		batch.inner
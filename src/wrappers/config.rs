@@ -0,0 +1,64 @@
+use std::path::Path;
+
+use crate::{database::Db, error::Result};
+
+pub use sled::Mode;
+
+/// A builder for opening a [Db], wrapping [sled::Config]
+/// # Examples
+/// ```
+/// # use husky::{Config, Mode};
+/// let db = Config::new()
+///   .temporary(true)
+///   .cache_capacity(1024 * 1024)
+///   .flush_every_ms(Some(100))
+///   .mode(Mode::LowSpace)
+///   .open()
+///   .unwrap();
+/// ```
+#[derive(Default, Debug, Clone)]
+pub struct Config {
+	inner: sled::Config,
+}
+
+impl From<sled::Config> for Config {
+	fn from(inner: sled::Config) -> Self {
+		Self { inner }
+	}
+}
+
+impl Config {
+	/// Creates a configuration with sled's defaults
+	pub fn new() -> Self {
+		Self::default()
+	}
+	/// Sets the path of the database
+	pub fn path(self, path: impl AsRef<Path>) -> Self {
+		self.inner.path(path).into()
+	}
+	/// Deletes the database once it is dropped, and doesn't write to disk unless the cache runs out
+	pub fn temporary(self, temporary: bool) -> Self {
+		self.inner.temporary(temporary).into()
+	}
+	/// Sets the size of the page cache, in bytes
+	pub fn cache_capacity(self, bytes: u64) -> Self {
+		self.inner.cache_capacity(bytes).into()
+	}
+	/// Sets how often a background thread flushes the database to disk
+	/// With [None] it is only flushed when asked, as with [Db::flush_all]
+	pub fn flush_every_ms(self, ms: Option<u64>) -> Self {
+		self.inner.flush_every_ms(ms).into()
+	}
+	/// Sets whether the database favors speed or disk space
+	pub fn mode(self, mode: Mode) -> Self {
+		self.inner.mode(mode).into()
+	}
+	/// Sets the zstd compression level of the pages, which needs the `compression` feature
+	pub fn compression_factor(self, factor: i32) -> Self {
+		self.inner.compression_factor(factor).into()
+	}
+	/// Opens the database
+	pub fn open(&self) -> Result<Db> {
+		Ok(Db::from(self.inner.open()?))
+	}
+}
@@ -1,17 +1,51 @@
-use anyhow::Result;
 use delegate::delegate;
+use sled::transaction::{ConflictableTransactionResult, TransactionResult};
 use std::{
 	collections::hash_map::DefaultHasher,
 	hash::{Hash, Hasher},
 };
 
 use crate::{
-	macros::hash,
+	config::Config,
+	error::Result,
+	macros::{bail, hash},
 	structs::single::Single,
 	traits::{load::Loaded, serial::Serial},
+	transaction::Transactional,
 	tree::Tree,
 };
 
+/// The reserved tree that maps hashed tree names to the original ones
+const NAMES_TREE: &[u8] = b"__husky_tree_names";
+
+/// The reserved tree that maps hashed store names to the trees and singles they opened
+const STORES_TREE: &[u8] = b"__husky_store_parts";
+/// Marks a part of a store as a tree, by its hashed name
+const STORE_TREE: &[u8] = &[0];
+/// Marks a part of a store as a single, by its key in the top level tree
+const STORE_SINGLE: &[u8] = &[1];
+
+/// Reads the original name from a key of the names tree
+fn original_name(key: &[u8]) -> Option<&str> {
+	// Strings are hashed as their bytes followed by 0xff
+	match key.get(8..)?.split_last() {
+		Some((0xff, name)) => std::str::from_utf8(name).ok(),
+		_ => None,
+	}
+}
+
+/// A hasher that keeps the bytes it is fed, instead of hashing them
+#[derive(Default)]
+struct NameRecorder(Vec<u8>);
+impl Hasher for NameRecorder {
+	fn finish(&self) -> u64 {
+		0
+	}
+	fn write(&mut self, bytes: &[u8]) {
+		self.0.extend_from_slice(bytes);
+	}
+}
+
 /// A wrapper around [sled::Db]
 #[derive(Clone)]
 pub struct Db {
@@ -25,6 +59,10 @@ impl From<sled::Db> for Db {
 }
 
 impl Db {
+	/// Creates a [Config] for opening a database
+	pub fn builder() -> Config {
+		Config::new()
+	}
 	/// Opens the specified tree
 	pub fn open_tree<K, V, N>(&self, name: N) -> Result<Tree<K, V>>
 	where
@@ -32,10 +70,23 @@ impl Db {
 		V: Serial,
 		N: Hash,
 	{
-		let name = hash!("tree", name);
-		let inner = self.inner.open_tree(name)?;
+		let hash = hash!("tree", name);
+		self.register_name(&hash, name)?;
+		let inner = self.inner.open_tree(hash)?;
 		Ok(Tree::new(self.clone(), inner))
 	}
+	/// Keeps the original name of a tree next to its hash, so that it can be listed
+	fn register_name<N: Hash>(&self, hash: &[u8], name: N) -> Result<()> {
+		let mut recorder = NameRecorder::default();
+		name.hash(&mut recorder);
+		// The full name is part of the key, so names with the same hash are all kept
+		let key = [hash, &recorder.0].concat();
+		let names = self.inner.open_tree(NAMES_TREE)?;
+		if !names.contains_key(&key)? {
+			names.insert(key, &[])?;
+		}
+		Ok(())
+	}
 	/// Opens a single value in the database
 	pub fn open_single<K, V>(&self, key: K) -> Result<Single<V>>
 	where
@@ -57,19 +108,201 @@ impl Db {
 	where
 		N: Hash,
 	{
-		let name = hash!("tree", name);
-		Ok(self.inner.drop_tree(name)?)
+		self.drop_hashed(&hash!("tree", name))
+	}
+	/// Drops a tree by its hashed name, along with its original name
+	fn drop_hashed(&self, hash: &[u8]) -> Result<bool> {
+		let names = self.inner.open_tree(NAMES_TREE)?;
+		for key in names.scan_prefix(hash).keys() {
+			names.remove(key?)?;
+		}
+		Ok(self.inner.drop_tree(hash)?)
+	}
+	/// Opens a tree that belongs to a stored view, keeping track of it so that [drop_store](Db::drop_store) finds it
+	pub(crate) fn open_store_tree<K, V, N>(&self, store: &N, part: &str) -> Result<Tree<K, V>>
+	where
+		K: Serial,
+		V: Serial,
+		N: Hash,
+	{
+		let name = hash!(store, part);
+		let tree = self.open_tree(name)?;
+		self.register_part(store, &hash!("tree", name), STORE_TREE)?;
+		Ok(tree)
+	}
+	/// Opens the tree named after a stored view, keeping track of it so that [drop_store](Db::drop_store) finds it
+	pub(crate) fn open_named_store_tree<K, V, N>(&self, store: &N) -> Result<Tree<K, V>>
+	where
+		K: Serial,
+		V: Serial,
+		N: Hash,
+	{
+		let tree = self.open_tree(store)?;
+		self.register_part(store, &hash!("tree", store), STORE_TREE)?;
+		Ok(tree)
+	}
+	/// Opens a single that belongs to a stored view, keeping track of it so that [drop_store](Db::drop_store) finds it
+	pub(crate) fn open_store_single<V, N>(&self, store: &N, part: &str) -> Result<Single<V>>
+	where
+		V: Serial,
+		N: Hash,
+	{
+		let key = hash!(store, part).to_vec();
+		self.register_part(store, &key.serialize()?, STORE_SINGLE)?;
+		self.open_single(key)
+	}
+	/// Keeps a part of a store next to the hash of its name
+	fn register_part<N: Hash>(&self, store: &N, part: &[u8], kind: &[u8]) -> Result<()> {
+		let key = [&hash!("store", store)[..], part].concat();
+		let stores = self.inner.open_tree(STORES_TREE)?;
+		if !stores.contains_key(&key)? {
+			stores.insert(key, kind)?;
+		}
+		Ok(())
+	}
+	/// Drops every tree and single of a stored view, returning false if nothing was stored with that name
+	///
+	/// [drop_tree](Db::drop_tree) only drops the named tree, while a stored view is kept in trees of its own.
+	/// Only views stored since their parts are tracked are found, and they shouldn't be used afterwards.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change, Operate, Store};
+	/// # let db = husky::open_temp().unwrap();
+	/// let tree: Tree<u32, String> = db.open_tree("tree").unwrap();
+	/// let index = tree.index(|_, v| vec![v.len()]).store("by_len").unwrap();
+	/// assert_eq!(db.tree_names().unwrap().len(), 3);
+	///
+	/// drop(index);
+	/// assert!(db.drop_store(&"by_len").unwrap());
+	/// assert_eq!(db.tree_names().unwrap().len(), 1);
+	/// ```
+	pub fn drop_store<N>(&self, name: &N) -> Result<bool>
+	where
+		N: Hash,
+	{
+		let stores = self.inner.open_tree(STORES_TREE)?;
+		let mut dropped = false;
+		for entry in stores.scan_prefix(hash!("store", name)) {
+			let (key, kind) = entry?;
+			let part = &key[8..];
+			if kind == STORE_SINGLE {
+				self.inner.remove(part)?;
+			} else {
+				self.drop_hashed(part)?;
+			}
+			stores.remove(key)?;
+			dropped = true;
+		}
+		Ok(dropped)
+	}
+	/// Renames a tree, returning false if there was no tree with the old name
+	///
+	/// Sled can't rename trees, so the entries are copied to the new tree in a single batch,
+	/// and the old tree is dropped. No events are sent, and trees opened with the old name
+	/// shouldn't be used afterwards. It fails if the new tree already has entries.
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// let tree: Tree<u32, String> = db.open_tree("old").unwrap();
+	/// tree.insert(1u32, "value").unwrap();
+	///
+	/// assert!(db.rename_tree(&"old", "new").unwrap());
+	/// let tree: Tree<u32, String> = db.open_tree("new").unwrap();
+	/// assert_eq!(tree.get(1u32).unwrap(), Some("value".to_string()));
+	/// assert_eq!(db.tree_names_original().unwrap(), vec!["new"]);
+	/// ```
+	pub fn rename_tree<O, N>(&self, old: &O, new: N) -> Result<bool>
+	where
+		O: Hash,
+		N: Hash,
+	{
+		let old_hash = hash!("tree", old);
+		if !self.inner.tree_names().iter().any(|n| n == &old_hash[..]) {
+			return Ok(false);
+		}
+		let new_hash = hash!("tree", new);
+		if old_hash == new_hash {
+			return Ok(true);
+		}
+		let to = self.inner.open_tree(new_hash)?;
+		if !to.is_empty() {
+			bail!("The tree to rename to already has entries");
+		}
+		let from = self.inner.open_tree(old_hash)?;
+		let mut batch = sled::Batch::default();
+		for entry in from.iter() {
+			let (key, value) = entry?;
+			batch.insert(key, value);
+		}
+		to.apply_batch(batch)?;
+		self.register_name(&new_hash, new)?;
+		self.drop_tree(old)
+	}
+	/// Runs a transaction over several trees, which may have different types
+	/// Please refer to [Transactional](crate::transaction::Transactional)
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View, Change};
+	/// # use sled::transaction::ConflictableTransactionError::Abort;
+	/// # let db = husky::open_temp().unwrap();
+	/// let pending: Tree<u32, String> = db.open_tree("pending").unwrap();
+	/// let done: Tree<String, u32> = db.open_tree("done").unwrap();
+	/// pending.insert(1u32, "task").unwrap();
+	///
+	/// db.transaction((&pending, &done), |(pending, done)| {
+	///   let task = pending.remove(1).map_err(Abort)?.unwrap();
+	///   done.insert(task, 1).map_err(Abort)?;
+	///   Ok(())
+	/// })
+	/// .unwrap();
+	///
+	/// assert_eq!(pending.get(1u32).unwrap(), None);
+	/// assert_eq!(done.get("task").unwrap(), Some(1));
+	/// ```
+	pub fn transaction<T, F, R, E>(&self, trees: T, f: F) -> TransactionResult<R, E>
+	where
+		T: Transactional<E>,
+		F: for<'a> Fn(&T::View<'a>) -> ConflictableTransactionResult<R, E>,
+	{
+		trees.transaction(f)
 	}
 	/// Lists all the hashed tree names
 	pub fn tree_names(&self) -> Result<Vec<u64>> {
 		let names = self.inner.tree_names();
 		let mut deserialized = Vec::with_capacity(names.len());
 		for name in names {
-			let name = Serial::deserialize(name.to_vec())?;
-			deserialized.push(name);
+			// Skips the default and reserved trees, which aren't hashed
+			let name: [u8; 8] = match name.as_ref().try_into() {
+				Ok(name) => name,
+				Err(_) => continue,
+			};
+			deserialized.push(u64::from_be_bytes(name));
 		}
 		Ok(deserialized)
 	}
+	/// Lists the original names of the trees opened with a string name
+	pub fn tree_names_original(&self) -> Result<Vec<String>> {
+		let names = self.inner.open_tree(NAMES_TREE)?;
+		let mut original = Vec::new();
+		for key in names.iter().keys() {
+			if let Some(name) = original_name(&key?) {
+				original.push(name.to_string());
+			}
+		}
+		Ok(original)
+	}
+	/// The original name of a hashed tree name, if it was opened with a string name
+	pub(crate) fn tree_name_original(&self, hash: &[u8]) -> Option<String> {
+		let names = self.inner.open_tree(NAMES_TREE).ok()?;
+		let mut keys = names.scan_prefix(hash).keys();
+		keys.find_map(|key| original_name(&key.ok()?).map(str::to_string))
+	}
+	/// Flushes every tree of the database to disk, returning how many bytes were written
+	/// To flush in the background instead, open the database with [Config::flush_every_ms]
+	pub fn flush_all(&self) -> Result<usize> {
+		Ok(self.inner.flush()?)
+	}
 	/// Returns the inner [sled::Db]
 	pub fn to_inner(&self) -> &sled::Db {
 		&self.inner
@@ -84,15 +317,15 @@ impl Db {
       /// Delegates to [sled::Db::was_recovered]
       pub fn was_recovered(&self) -> bool;
       /// Delegated to [sled::Db::generate_id]
-      pub fn generate_id(&self) -> Result<u64, sled::Error>;
+      pub fn generate_id(&self) -> std::result::Result<u64, sled::Error>;
       /// Delegated to [sled::Db::export]
       pub fn export(&self) -> Vec<(Vec<u8>, Vec<u8>, impl Iterator<Item = Vec<Vec<u8>>>)>;
       /// Delegated to [sled::Db::import]
       pub fn import(&self, import: Vec<(Vec<u8>, Vec<u8>, impl Iterator<Item = Vec<Vec<u8>>>)>);
       /// Delegated to [sled::Db::checksum]
-      pub fn checksum(&self) -> Result<u32, sled::Error>;
+      pub fn checksum(&self) -> std::result::Result<u32, sled::Error>;
       /// Delegated to [sled::Db::size_on_disk]
-      pub fn size_on_disk(&self) -> Result<u64, sled::Error>;
+      pub fn size_on_disk(&self) -> std::result::Result<u64, sled::Error>;
 	  }
 	}
 }
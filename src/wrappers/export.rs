@@ -0,0 +1,62 @@
+use csv::{ReaderBuilder, WriterBuilder};
+use serde::Serialize;
+use std::io::Write;
+
+use crate::{
+	error::{HuskyError, Result},
+	traits::serial::Serial,
+	tree::Tree,
+};
+
+/// The column names of a record, taken from the fields of a struct
+/// Records that aren't structs get numbered columns after a prefix, or just the prefix if there's one
+fn columns<T: Serialize>(record: &T, prefix: &str) -> Result<Vec<String>> {
+	let mut writer = WriterBuilder::new().has_headers(true).from_writer(vec![]);
+	writer.serialize(record)?;
+	let data = writer.into_inner().map_err(HuskyError::serialization)?;
+	let mut reader = ReaderBuilder::new()
+		.has_headers(false)
+		.from_reader(&data[..]);
+	let rows = reader
+		.records()
+		.collect::<std::result::Result<Vec<_>, _>>()?;
+	// A header is only written before the row for structs
+	let columns = match rows.as_slice() {
+		[header, _] => header.iter().map(String::from).collect(),
+		[row] if row.len() == 1 => vec![prefix.to_string()],
+		[row] => (0..row.len()).map(|i| format!("{}{}", prefix, i)).collect(),
+		_ => vec![prefix.to_string()],
+	};
+	Ok(columns)
+}
+
+impl<K, V> Tree<K, V>
+where
+	K: Serial + Serialize,
+	V: Serial + Serialize,
+{
+	/// Writes the entries as CSV, in key order, one entry per row
+	/// The fields of struct keys and values become columns named after them, others are named `key` and `value`
+	/// The entries are read one by one, so the tree is never loaded into memory
+	pub fn export_csv(&self, writer: impl Write) -> Result<()> {
+		let mut writer = WriterBuilder::new().has_headers(false).from_writer(writer);
+		let mut iter = self.iter();
+		let (key, value) = match iter.next() {
+			Some(res) => res?,
+			None => {
+				writer.write_record(["key", "value"])?;
+				writer.flush()?;
+				return Ok(());
+			}
+		};
+		let mut header = columns(&key, "key")?;
+		header.extend(columns(&value, "value")?);
+		writer.write_record(&header)?;
+		writer.serialize((key, value))?;
+		for res in iter {
+			writer.serialize(res?)?;
+		}
+		writer.flush()?;
+		Ok(())
+	}
+}
@@ -1,7 +1,12 @@
 /// Wrapper around [sled::Batch]
 pub mod batch;
+/// Wrapper around [sled::Config]
+pub mod config;
 /// Wrapper around [sled::Db]
 pub mod database;
+/// CSV export of trees, with the `csv` feature
+#[cfg(feature = "csv")]
+pub mod export;
 /// Wrapper around [sled::transaction::TransactionalTree]
 pub mod transaction;
 /// Wrapper around [sled::Tree]
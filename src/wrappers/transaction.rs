@@ -1,8 +1,15 @@
-use anyhow::Result;
 use delegate::delegate;
+use sled::transaction::{
+	ConflictableTransactionResult, TransactionResult, Transactional as SledTransactional,
+};
 use std::marker::PhantomData;
 
-use crate::{helpers::deserialize_option, traits::serial::Serial};
+use crate::{
+	error::Result,
+	helpers::deserialize_option,
+	traits::serial::{serialize_value, Serial},
+	tree::Tree,
+};
 
 /// Wrapper around [sled::transaction::TransactionalTree]
 pub struct TransactionalTree<'a, K, V> {
@@ -29,7 +36,7 @@ where
 	/// Inserts a new key-value pair into the tree
 	pub fn insert(&self, key: K, value: V) -> Result<Option<V>> {
 		let key = Serial::serialize(&key)?;
-		let value = Serial::serialize(&value)?;
+		let value = serialize_value(&value)?;
 		let value = self.inner.insert(key, value)?.map(|v| v.to_vec());
 		deserialize_option(value)
 	}
@@ -59,7 +66,43 @@ where
       /// Delegates to [sled::transaction::TransactionalTree::flush]
       pub fn flush(&self);
       /// Delegates to [sled::transaction::TransactionalTree::generate_id]
-      pub fn generate_id(&self) -> Result<u64, sled::Error>;
+      pub fn generate_id(&self) -> std::result::Result<u64, sled::Error>;
 	  }
 	}
 }
+
+/// Trees that can be changed together in a single transaction.
+/// Implemented for tuples of references to [Tree], which may have different types.
+pub trait Transactional<E> {
+	/// The [TransactionalTree] handles, one for each tree.
+	type View<'a>;
+	/// Runs a transaction over all the trees.
+	fn transaction<F, R>(&self, f: F) -> TransactionResult<R, E>
+	where
+		F: for<'a> Fn(&Self::View<'a>) -> ConflictableTransactionResult<R, E>;
+}
+
+macro_rules! impl_transactional {
+	( $( $i:tt $k:ident $v:ident ),+ ) => {
+		impl<'t, E, $( $k, $v ),+> Transactional<E> for ( $( &'t Tree<$k, $v>, )+ )
+		where
+			$( $k: Serial, $v: Serial, )+
+		{
+			type View<'a> = ( $( TransactionalTree<'a, $k, $v>, )+ );
+			fn transaction<F, R>(&self, f: F) -> TransactionResult<R, E>
+			where
+				F: for<'a> Fn(&Self::View<'a>) -> ConflictableTransactionResult<R, E>,
+			{
+				let trees = ( $( self.$i.to_inner(), )+ );
+				trees.transaction(|trees| f(&( $( TransactionalTree::from(&trees.$i), )+ )))
+			}
+		}
+	};
+}
+
+impl_transactional!(0 K0 V0);
+impl_transactional!(0 K0 V0, 1 K1 V1);
+impl_transactional!(0 K0 V0, 1 K1 V1, 2 K2 V2);
+impl_transactional!(0 K0 V0, 1 K1 V1, 2 K2 V2, 3 K3 V3);
+impl_transactional!(0 K0 V0, 1 K1 V1, 2 K2 V2, 3 K3 V3, 4 K4 V4);
+impl_transactional!(0 K0 V0, 1 K1 V1, 2 K2 V2, 3 K3 V3, 4 K4 V4, 5 K5 V5);
@@ -1,29 +1,54 @@
-use anyhow::Result;
 use bus::Bus;
 use delegate::delegate;
-use parking_lot::RwLock;
-use sled::IVec;
+use parking_lot::{Mutex, RwLock};
+use sled::{transaction::ConflictableTransactionError, IVec};
 use std::{
+	cell::RefCell,
+	fmt,
 	ops::{Bound, RangeBounds},
 	sync::Arc,
 };
 
+#[cfg(feature = "rkyv")]
+use crate::traits::serial::ReadArchived;
 use crate::{
 	batch::Batch,
 	database::Db,
-	helpers::{deserialize_option, deserialize_tuple, serialize_option},
-	macros::unwrap_or_return,
+	error::{HuskyError, Result},
+	helpers::{deserialize_key, deserialize_option, deserialize_tuple, serialize_option},
+	macros::{bail, unwrap_or_return},
 	structs::iter,
 	threads::Synchronizer,
 	traits::{
-		serial::Serial,
-		watch::{Event, Watcher},
+		auto_inc::AutoInc,
+		serial::{deserialize_value, serialize_value, serialize_value_into, Serial},
+		watch::{Event, Events, Watch, Watcher},
 	},
 	transaction::TransactionalTree,
 };
 
-pub(crate) type Iter<K, V> =
-	iter::Iter<sled::Iter, fn(Result<(IVec, IVec), sled::Error>) -> Result<(K, V)>, (K, V)>;
+/// The largest buffer kept around for writes, larger ones are shrunk back once used
+const MAX_BUFFER: usize = 1 << 16;
+
+thread_local! {
+	/// The buffers reused to serialize the keys and values written by this thread
+	static BUFFERS: RefCell<(Vec<u8>, Vec<u8>)> = RefCell::default();
+}
+
+pub(crate) type Iter<K, V> = iter::Iter<
+	sled::Iter,
+	fn(std::result::Result<(IVec, IVec), sled::Error>) -> Result<(K, V)>,
+	(K, V),
+>;
+
+/// A summary of the entries of a [Tree], returned by [stats](Tree::stats)
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct TreeStats {
+	/// The number of entries
+	pub len: usize,
+	/// The sum of the lengths of the stored keys and values, see [approximate_size_bytes](Tree::approximate_size_bytes)
+	pub approximate_size_bytes: u64,
+}
 
 /// Wrapper around [sled::Tree]
 pub struct Tree<K, V>
@@ -35,6 +60,8 @@ where
 	inner: sled::Tree,
 	pub(crate) watcher: Arc<Watcher<K, V>>,
 	pub(crate) sync: Arc<Synchronizer>,
+	/// Held by merges and updates until their event is sent, so the events follow the order of the writes
+	updating: Arc<Mutex<()>>,
 }
 
 impl<K, V> Clone for Tree<K, V>
@@ -48,6 +75,20 @@ where
 			inner: self.inner.clone(),
 			watcher: self.watcher.clone(),
 			sync: Arc::clone(&self.sync),
+			updating: Arc::clone(&self.updating),
+		}
+	}
+}
+impl<K, V> fmt::Debug for Tree<K, V>
+where
+	K: Serial,
+	V: Serial,
+{
+	fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+		let hash = self.inner.name();
+		match self.db.tree_name_original(&hash) {
+			Some(name) => f.debug_tuple("Tree").field(&name).finish(),
+			None => f.debug_tuple("Tree").field(&hash).finish(),
 		}
 	}
 }
@@ -70,24 +111,61 @@ where
 			inner,
 			watcher,
 			sync,
+			updating: Arc::default(),
 		}
 	}
 	/// Inserts a owned key-value pair into the tree
 	/// Please refer to [Change](crate::Change)
 	pub fn insert_owned(&self, key: K, value: V) -> Result<Option<V>> {
 		self.sync.outgoing(1);
-		let old_value = {
-			let key = Serial::serialize(&key)?;
-			let value = Serial::serialize(&value)?;
-			self.inner.insert(key, value)?
-		};
+		let old_value = BUFFERS.with(|buffers| -> Result<_> {
+			let (ser_key, ser_value) = &mut *buffers.borrow_mut();
+			ser_key.clear();
+			ser_value.clear();
+			key.serialize_into(ser_key)?;
+			serialize_value_into(&value, ser_value)?;
+			let old_value = self.inner.insert(&ser_key[..], &ser_value[..])?;
+			ser_key.shrink_to(MAX_BUFFER);
+			ser_value.shrink_to(MAX_BUFFER);
+			Ok(old_value)
+		})?;
 		let key = Arc::new(key);
 		let value = Arc::new(value);
 		self.watcher.send(Event::Insert { key, value });
 		let old_value = unwrap_or_return!(old_value);
-		let old_value = Serial::deserialize(old_value.to_vec())?;
+		let old_value = deserialize_value(old_value.to_vec())?;
 		Ok(Some(old_value))
 	}
+	/// Pushes an owned value onto the end of the tree, returning its key
+	/// The key is claimed with a compare and swap, so concurrent pushes never collide
+	/// Please refer to [Change](crate::Change)
+	pub fn push_owned(&self, value: V) -> Result<K>
+	where
+		K: AutoInc,
+	{
+		let ser_value = serialize_value(&value)?;
+		loop {
+			let key = match self.inner.last()? {
+				Some((k, _)) => match K::deserialize(k.to_vec())?.try_next() {
+					Some(key) => key,
+					None => bail!("The key sequence is exhausted"),
+				},
+				None => K::first(),
+			};
+			let ser_key = Serial::serialize(&key)?;
+			let swap = self
+				.inner
+				.compare_and_swap(ser_key, None as Option<&[u8]>, Some(ser_value.clone()))?;
+			if swap.is_ok() {
+				self.sync.outgoing(1);
+				self.watcher.send(Event::Insert {
+					key: Arc::new(key.clone()),
+					value: Arc::new(value),
+				});
+				return Ok(key);
+			}
+		}
+	}
 	/// Opens a [TransactionalTree](crate::transaction::TransactionalTree)
 	pub fn transaction<F, R, E>(&self, f: F) -> sled::transaction::TransactionResult<R, E>
 	where
@@ -99,9 +177,67 @@ where
 				f(&tree)
 			})
 	}
-	/// Applies a [Batch](crate::Batch) to the tree
-	pub fn apply_batch(&self, batch: Batch<K, V>) -> Result<(), sled::Error> {
-		self.inner.apply_batch(batch.into())
+	/// Applies a [Batch](crate::Batch) to the tree atomically
+	/// Then an event is sent for each staged operation, in order
+	pub fn apply_batch(&self, batch: Batch<K, V>) -> Result<()> {
+		let (batch, ops) = batch.into_parts();
+		self.inner.apply_batch(batch)?;
+		self.sync.outgoing(ops.len() as u32);
+		for (key, value) in ops {
+			let key = Arc::new(key);
+			match value {
+				Some(value) => self.watcher.send(Event::Insert {
+					key,
+					value: Arc::new(value),
+				}),
+				None => self.watcher.send(Event::Remove { key }),
+			}
+		}
+		Ok(())
+	}
+	/// Inserts every entry of an iterator in a single [Batch](crate::Batch)
+	/// Then an event is sent for each entry, in order
+	pub fn extend<I, IK, IV>(&self, iter: I) -> Result<()>
+	where
+		I: IntoIterator<Item = (IK, IV)>,
+		IK: Into<K>,
+		IV: Into<V>,
+	{
+		let mut batch = Batch::default();
+		for (k, v) in iter {
+			batch.insert(k, v)?;
+		}
+		self.apply_batch(batch)
+	}
+	/// Returns a reader of the events with keys in a range
+	/// The events are filtered in a thread, so other keys don't wake the reader up
+	pub fn watch_range<R>(&self, range: R) -> Events<K, V>
+	where
+		K: Ord,
+		R: 'static + RangeBounds<K> + Send + Sync,
+	{
+		self.filter_events(move |event| range.contains(&**event.key()))
+	}
+	/// Returns a reader of the events with keys that start with a prefix
+	/// The events are filtered in a thread, so other keys don't wake the reader up
+	pub fn watch_prefix(&self, prefix: impl AsRef<[u8]>) -> Events<K, V>
+	where
+		K: AsRef<[u8]>,
+	{
+		let prefix = prefix.as_ref().to_vec();
+		self.filter_events(move |event| (**event.key()).as_ref().starts_with(&prefix))
+	}
+	/// Copies every entry into another tree, in a single [Batch](crate::Batch), returning how many there were
+	/// The target keeps its other entries, and sends an event for each copied one
+	pub fn clone_to(&self, target: &Tree<K, V>) -> Result<usize> {
+		let mut batch = Batch::default();
+		for res in self.iter() {
+			let (key, value) = res?;
+			batch.insert(key, value)?;
+		}
+		let len = batch.len();
+		target.apply_batch(batch)?;
+		Ok(len)
 	}
 	/// Gets the value for a given key reference
 	/// Please refer to [View](crate::View)
@@ -111,59 +247,247 @@ where
 		let value = self.inner.get(&key)?.map(|v| v.to_vec());
 		deserialize_option(value)
 	}
+	/// Reads the archived value of a key in place, without deserializing it
+	/// The bytes are only copied if they aren't aligned, or if they are compressed
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<u32, (u32, String)> = db.open_tree("tree").unwrap();
+	/// tree.insert(1u32, (10u32, "a long name".to_string())).unwrap();
+	/// let first = tree.get_archived(&1, |archived| archived.0.value()).unwrap();
+	/// assert_eq!(first, Some(10));
+	/// ```
+	#[cfg(feature = "rkyv")]
+	pub fn get_archived<R>(&self, key: &K, f: impl FnOnce(&rkyv::Archived<V>) -> R) -> Result<Option<R>>
+	where
+		V: ReadArchived,
+	{
+		self.sync.wait();
+		let key = Serial::serialize(key)?;
+		let bytes = unwrap_or_return!(self.inner.get(&key)?);
+		#[cfg(feature = "compress_values")]
+		let bytes = crate::traits::serial::compress::decompress(bytes.to_vec())?;
+		// The archive must be as aligned as an AlignedVec
+		let aligned;
+		let bytes = match bytes.as_ptr() as usize % rkyv::AlignedVec::ALIGNMENT {
+			0 => &bytes[..],
+			_ => {
+				let mut vec = rkyv::AlignedVec::with_capacity(bytes.len());
+				vec.extend_from_slice(&bytes);
+				aligned = vec;
+				&aligned[..]
+			}
+		};
+		let archived = V::read_archived(bytes)?;
+		Ok(Some(f(archived)))
+	}
 	/// Removes a owned key
 	/// Please refer to [Change](crate::Change)
 	pub fn remove_owned(&self, key: K) -> Result<Option<V>> {
 		self.sync.outgoing(1);
 		let ser_key = Serial::serialize(&key)?;
+		let value = self.inner.remove(&ser_key)?.map(|v| v.to_vec());
 
+		// Sent once removed, like inserts, so that views reading the tree don't find the old value
 		let key = Arc::new(key);
 		self.watcher.send(Event::Remove { key });
 
-		let value = self.inner.remove(&ser_key)?.map(|v| v.to_vec());
 		deserialize_option(value)
 	}
 	/// Delegates to [sled::Tree::compare_and_swap]
-	pub fn compare_and_swap(&self, key: &K, old: Option<&V>, new: Option<&V>) -> Result<()> {
-		let key = Serial::serialize(key)?;
-		let old = serialize_option(old)?;
-		let new = serialize_option(new)?;
-		self.inner.compare_and_swap(key, old, new)??;
-		Ok(())
+	/// Returns whether the current value matched the old one and got swapped
+	/// An event is sent only if the swap succeeded
+	pub fn compare_and_swap(&self, key: &K, old: Option<&V>, new: Option<&V>) -> Result<bool> {
+		let ser_key = Serial::serialize(key)?;
+		let ser_old = serialize_option(old)?;
+		let ser_new = serialize_option(new)?;
+		if self
+			.inner
+			.compare_and_swap(ser_key, ser_old, ser_new)?
+			.is_err()
+		{
+			return Ok(false);
+		}
+		self.sync.outgoing(1);
+		let key = Arc::new(key.clone());
+		match new {
+			Some(value) => self.watcher.send(Event::Insert {
+				key,
+				value: Arc::new(value.clone()),
+			}),
+			None => self.watcher.send(Event::Remove { key }),
+		}
+		Ok(true)
+	}
+	/// Inserts a value only if the key is missing, with a compare and swap
+	/// Returns the value the key already had, or [None] if it got inserted, in which case an event is sent
+	/// Please refer to [Change](crate::Change)
+	pub fn insert_if_absent(&self, key: &K, value: &V) -> Result<Option<V>> {
+		let ser_key = Serial::serialize(key)?;
+		let ser_value = serialize_value(value)?;
+		let swapped = self
+			.inner
+			.compare_and_swap(ser_key, None as Option<&[u8]>, Some(ser_value))?;
+		if let Err(e) = swapped {
+			return deserialize_option(e.current.map(|v| v.to_vec()));
+		}
+		self.sync.outgoing(1);
+		self.watcher.send(Event::Insert {
+			key: Arc::new(key.clone()),
+			value: Arc::new(value.clone()),
+		});
+		Ok(None)
+	}
+	/// Moves the value of a key to another in a single transaction, returning it
+	/// Then a remove event is sent for the old key, and an insert event for the new one
+	/// Please refer to [Change](crate::Change)
+	pub fn swap(&self, from: &K, to: &K) -> Result<Option<V>> {
+		let ser_from = Serial::serialize(from)?;
+		let ser_to = Serial::serialize(to)?;
+		let moved: sled::transaction::TransactionResult<_, HuskyError> =
+			self.inner.transaction(|t| {
+				let bytes = unwrap_or_return!(t.remove(ser_from.as_slice())?);
+				// Read before committing, so a value that can't be read isn't moved without its events
+				let value = deserialize_value::<V>(bytes.to_vec())
+					.map_err(ConflictableTransactionError::Abort)?;
+				t.insert(ser_to.as_slice(), bytes)?;
+				Ok(Some(value))
+			});
+		let value = unwrap_or_return!(moved?);
+		self.sync.outgoing(2);
+		self.watcher.send(Event::Remove {
+			key: Arc::new(from.clone()),
+		});
+		self.watcher.send(Event::Insert {
+			key: Arc::new(to.clone()),
+			value: Arc::new(value.clone()),
+		});
+		Ok(Some(value))
+	}
+	/// Delegates to [sled::Tree::set_merge_operator]
+	/// The operator gets the key, the current value and the merged operand, and returns the new value, or [None] to remove it
+	/// If the stored bytes can't be read or the result can't be written, the current value is kept
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, View};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<String, u64> = db.open_tree("tree").unwrap();
+	/// tree.set_merge_operator(|_, old, add| Some(old.unwrap_or(0) + add));
+	/// tree.merge(&"hits".to_string(), &2).unwrap();
+	/// assert_eq!(tree.merge(&"hits".to_string(), &3).unwrap(), Some(5));
+	/// ```
+	pub fn set_merge_operator(
+		&self,
+		f: impl 'static + Fn(&K, Option<V>, V) -> Option<V> + Sync + Send,
+	) {
+		self.inner.set_merge_operator(move |key: &[u8], old: Option<&[u8]>, operand: &[u8]| {
+			let kept = old.map(|old| old.to_vec());
+			let merged = || -> Result<Option<Vec<u8>>> {
+				let key = K::deserialize(key.to_vec())?;
+				let old = old.map(|old| deserialize_value(old.to_vec())).transpose()?;
+				let operand = deserialize_value(operand.to_vec())?;
+				f(&key, old, operand).map(|new| serialize_value(&new)).transpose()
+			};
+			merged().unwrap_or(kept)
+		});
+	}
+	/// Delegates to [sled::Tree::merge]
+	/// Merges an operand into the value of a key atomically, returning the merged value
+	/// Sends an event with the merged value, and fails if no merge operator was set
+	/// Merges through this tree take turns, so views get the merged values in the order they were written
+	pub fn merge(&self, key: &K, operand: &V) -> Result<Option<V>> {
+		let ser_key = Serial::serialize(key)?;
+		let ser_operand = serialize_value(operand)?;
+		let _updating = self.updating.lock();
+		let merged = self.inner.merge(ser_key, ser_operand)?;
+		let merged: Option<V> = deserialize_option(merged.map(|v| v.to_vec()))?;
+		self.sync.outgoing(1);
+		let key = Arc::new(key.clone());
+		match &merged {
+			Some(value) => self.watcher.send(Event::Insert {
+				key,
+				value: Arc::new(value.clone()),
+			}),
+			None => self.watcher.send(Event::Remove { key }),
+		}
+		Ok(merged)
 	}
 	/// Delegates to [sled::Tree::update_and_fetch]
+	/// Sends an event with the value that got written, unless the key was and stays missing
+	/// Fails without changing the key if its value can't be read, or the new one can't be written
 	pub fn update_and_fetch(
 		&self,
 		key: &K,
-		mut f: impl FnMut(Option<V>) -> Option<V>,
+		f: impl FnMut(Option<V>) -> Option<V>,
 	) -> Result<Option<V>> {
-		let key = Serial::serialize(key)?;
-		let value = self
-			.inner
-			.update_and_fetch(key, |v| {
-				let value = v.and_then(|v| Serial::deserialize(v.into()).ok());
-				let value = f(value);
-				value.and_then(|value| Serial::serialize(&value).ok())
-			})?
-			.map(|v| v.to_vec());
-		deserialize_option(value)
+		let (_, new) = self.update(key, f)?;
+		Ok(new)
 	}
 	/// Delegates to [sled::Tree::fetch_and_update]
+	/// Sends an event with the value that got written, unless the key was and stays missing
+	/// Fails without changing the key if its value can't be read, or the new one can't be written
 	pub fn fetch_and_update(
 		&self,
 		key: &K,
-		mut f: impl FnMut(Option<V>) -> Option<V>,
+		f: impl FnMut(Option<V>) -> Option<V>,
 	) -> Result<Option<V>> {
-		let key = Serial::serialize(key)?;
-		let value = self
+		let (old, _) = self.update(key, f)?;
+		Ok(old)
+	}
+	/// Atomically updates a value, returning both the old and the new value
+	fn update(
+		&self,
+		key: &K,
+		mut f: impl FnMut(Option<V>) -> Option<V>,
+	) -> Result<(Option<V>, Option<V>)> {
+		let ser_key = Serial::serialize(key)?;
+		// Keeps the value of the last attempt, which is the one that got written
+		let mut new = None;
+		// Keeps the first error, after which the current bytes are written back unchanged
+		let mut err = None;
+		// Held until the event is sent, so a later update can't send its value first
+		let _updating = self.updating.lock();
+		let old = self
 			.inner
-			.fetch_and_update(key, |v| {
-				let value = v.and_then(|v| Serial::deserialize(v.into()).ok());
-				let value = f(value);
-				value.and_then(|value| Serial::serialize(&value).ok())
+			.fetch_and_update(ser_key, |v| {
+				let current = v.map(|v| v.to_vec());
+				if err.is_some() {
+					return current;
+				}
+				let value = match current.clone().map(deserialize_value).transpose() {
+					Ok(value) => value,
+					Err(e) => {
+						err = Some(e);
+						return current;
+					}
+				};
+				new = f(value);
+				match new.as_ref().map(serialize_value).transpose() {
+					Ok(bytes) => bytes,
+					Err(e) => {
+						err = Some(e);
+						current
+					}
+				}
 			})?
 			.map(|v| v.to_vec());
-		deserialize_option(value)
+		if let Some(e) = err {
+			return Err(e);
+		}
+		if old.is_none() && new.is_none() {
+			return Ok((None, None));
+		}
+		self.sync.outgoing(1);
+		let key = Arc::new(key.clone());
+		match &new {
+			Some(value) => self.watcher.send(Event::Insert {
+				key,
+				value: Arc::new(value.clone()),
+			}),
+			None => self.watcher.send(Event::Remove { key }),
+		}
+		Ok((deserialize_option(old)?, new))
 	}
 	/// Delegates to [sled::Tree::contains_key]
 	pub fn contains_key_ref(&self, key: &K) -> Result<bool> {
@@ -188,6 +512,33 @@ where
 				.map(|(k, v)| (k.to_vec(), v.to_vec())),
 		)
 	}
+	/// Gets the entry at a position in key order, counting from 0
+	/// Only that entry is deserialized
+	pub fn nth(&self, n: usize) -> Result<Option<(K, V)>> {
+		let mut iter = self.inner.iter();
+		for _ in 0..n {
+			if iter.next().transpose()?.is_none() {
+				return Ok(None);
+			}
+		}
+		let entry = iter.next().transpose()?;
+		deserialize_tuple(entry.map(|(k, v)| (k.to_vec(), v.to_vec())))
+	}
+	/// Gets the first `n` entries in key order, or fewer if the tree is smaller
+	pub fn first_n(&self, n: usize) -> Result<Vec<(K, V)>> {
+		self.iter().take(n).collect()
+	}
+	/// Gets the last `n` entries in key order, or fewer if the tree is smaller
+	/// The tree is read backwards, so only those entries are deserialized
+	pub fn last_n(&self, n: usize) -> Result<Vec<(K, V)>> {
+		let mut last = Vec::new();
+		for res in self.inner.iter().rev().take(n) {
+			let (k, v) = res?;
+			last.extend(deserialize_tuple(Some((k.to_vec(), v.to_vec())))?);
+		}
+		last.reverse();
+		Ok(last)
+	}
 	/// Delegates to [sled::Tree::first]
 	pub fn first(&self) -> Result<Option<(K, V)>> {
 		deserialize_tuple(self.inner.first()?.map(|(k, v)| (k.to_vec(), v.to_vec())))
@@ -196,13 +547,57 @@ where
 	pub fn last(&self) -> Result<Option<(K, V)>> {
 		deserialize_tuple(self.inner.last()?.map(|(k, v)| (k.to_vec(), v.to_vec())))
 	}
+	/// Gets the first key, without deserializing its value
+	pub fn first_key(&self) -> Result<Option<K>> {
+		deserialize_key(self.inner.first()?.map(|(k, _)| k.to_vec()))
+	}
+	/// Gets the last key, without deserializing its value
+	pub fn last_key(&self) -> Result<Option<K>> {
+		deserialize_key(self.inner.last()?.map(|(k, _)| k.to_vec()))
+	}
 	/// Delegates to [sled::Tree::pop_max]
+	/// Sends a remove event for the popped key
 	pub fn pop_max(&self) -> Result<Option<(K, V)>> {
-		deserialize_tuple(self.inner.pop_max()?.map(|(k, v)| (k.to_vec(), v.to_vec())))
+		let entry = self.inner.pop_max()?.map(|(k, v)| (k.to_vec(), v.to_vec()));
+		self.popped(deserialize_tuple(entry)?)
 	}
 	/// Delegates to [sled::Tree::pop_min]
+	/// Sends a remove event for the popped key
 	pub fn pop_min(&self) -> Result<Option<(K, V)>> {
-		deserialize_tuple(self.inner.pop_min()?.map(|(k, v)| (k.to_vec(), v.to_vec())))
+		let entry = self.inner.pop_min()?.map(|(k, v)| (k.to_vec(), v.to_vec()));
+		self.popped(deserialize_tuple(entry)?)
+	}
+	/// Removes every entry that doesn't match the predicate, returning how many were removed
+	/// An entry is only removed if it still holds the value that was checked, then its event is sent
+	pub fn retain(&self, pred: impl Fn(&K, &V) -> bool) -> Result<usize> {
+		let mut count = 0;
+		for res in self.inner.iter() {
+			let (k, v) = res?;
+			let key = K::deserialize(k.to_vec())?;
+			let value = deserialize_value(v.to_vec())?;
+			if pred(&key, &value) {
+				continue;
+			}
+			// A concurrent write wins, its value wasn't checked
+			let swapped = self
+				.inner
+				.compare_and_swap(k, Some(v), None as Option<&[u8]>)?;
+			if swapped.is_err() {
+				continue;
+			}
+			count += 1;
+			self.sync.outgoing(1);
+			self.watcher.send(Event::Remove { key: Arc::new(key) });
+		}
+		Ok(count)
+	}
+	fn popped(&self, entry: Option<(K, V)>) -> Result<Option<(K, V)>> {
+		if let Some((key, _)) = &entry {
+			self.sync.outgoing(1);
+			let key = Arc::new(key.clone());
+			self.watcher.send(Event::Remove { key });
+		}
+		Ok(entry)
 	}
 	/// Delegates to [sled::Tree::iter]
 	pub fn iter(&self) -> Iter<K, V> {
@@ -210,19 +605,51 @@ where
 	}
 	/// Returns a range over the entries in the tree.
 	pub fn range(&self, range: impl RangeBounds<K>) -> Result<Iter<K, V>> {
-		let from = match range.start_bound() {
-			Bound::Included(i) => Bound::Included(Serial::serialize(i)?),
-			Bound::Excluded(i) => Bound::Excluded(Serial::serialize(i)?),
-			Bound::Unbounded => Bound::Unbounded,
-		};
-		let to = match range.end_bound() {
-			Bound::Included(i) => Bound::Included(Serial::serialize(i)?),
-			Bound::Excluded(i) => Bound::Excluded(Serial::serialize(i)?),
-			Bound::Unbounded => Bound::Unbounded,
-		};
-		let range = self.inner.range((from, to));
+		let range = self.inner.range(serialize_range(range)?);
 		Ok(Iter::new(range, deserialize_entry))
 	}
+	/// Removes every key in a range, returning how many were removed
+	/// Sled has no range delete, so each key is removed and sends its own event
+	pub fn remove_range(&self, range: impl RangeBounds<K>) -> Result<usize> {
+		let mut count = 0;
+		for res in self.inner.range(serialize_range(range)?) {
+			let (k, _) = res?;
+			// Read before removing, so a key that can't be read stays along with the rest of the range
+			let key = Arc::new(K::deserialize(k.to_vec())?);
+			if self.inner.remove(&k)?.is_none() {
+				continue;
+			}
+			count += 1;
+			self.sync.outgoing(1);
+			self.watcher.send(Event::Remove { key });
+		}
+		Ok(count)
+	}
+	/// Sums the lengths of the stored keys and values, as they were serialized or compressed
+	/// It is O(n) in the number of entries, and doesn't count the overhead of sled itself
+	pub fn approximate_size_bytes(&self) -> Result<u64> {
+		Ok(self.stats()?.approximate_size_bytes)
+	}
+	/// Counts the entries and their approximate size in a single pass, see [approximate_size_bytes](Tree::approximate_size_bytes)
+	/// # Examples
+	/// ```
+	/// # use husky::{Tree, Change};
+	/// # let db = husky::open_temp().unwrap();
+	/// # let tree: Tree<u32, String> = db.open_tree("tree").unwrap();
+	/// tree.insert(1u32, "value").unwrap();
+	/// let stats = tree.stats().unwrap();
+	/// assert_eq!(stats.len, 1);
+	/// assert!(stats.approximate_size_bytes > 0);
+	/// ```
+	pub fn stats(&self) -> Result<TreeStats> {
+		let mut stats = TreeStats::default();
+		for res in self.inner.iter() {
+			let (k, v) = res?;
+			stats.len += 1;
+			stats.approximate_size_bytes += (k.len() + v.len()) as u64;
+		}
+		Ok(stats)
+	}
 	/// Returns the inner [sled::Tree]
 	pub fn to_inner(&self) -> &sled::Tree {
 		&self.inner
@@ -235,30 +662,90 @@ where
 	delegate! {
 	  to self.inner {
       /// Delegates to [sled::Tree::flush]
-      pub fn flush(&self) -> Result<usize, sled::Error>;
+      pub fn flush(&self) -> std::result::Result<usize, sled::Error>;
       /// Delegates to [sled::Tree::flush_async]
-      pub async fn flush_async(&self) -> Result<usize, sled::Error>;
+      pub async fn flush_async(&self) -> std::result::Result<usize, sled::Error>;
       /// Delegates to [sled::Tree::len]
       pub fn len(&self) -> usize;
       /// Delegates to [sled::Tree::is_empty]
       pub fn is_empty(&self) -> bool;
       /// Delegates to [sled::Tree::clear]
-      pub fn clear(&self) -> Result<(), sled::Error>;
+      pub fn clear(&self) -> std::result::Result<(), sled::Error>;
       /// Delegates to [sled::Tree::name]
       pub fn name(&self) -> IVec;
       /// Delegates to [sled::Tree::checksum]
-      pub fn checksum(&self) -> Result<u32, sled::Error>;
+      pub fn checksum(&self) -> std::result::Result<u32, sled::Error>;
 	  }
 	}
 }
 
-fn deserialize_entry<K, V>(r: Result<(IVec, IVec), sled::Error>) -> Result<(K, V)>
+type SerialRange = (Bound<Vec<u8>>, Bound<Vec<u8>>);
+
+fn serialize_range<K>(range: impl RangeBounds<K>) -> Result<SerialRange>
+where
+	K: Serial,
+{
+	let from = match range.start_bound() {
+		Bound::Included(i) => Bound::Included(Serial::serialize(i)?),
+		Bound::Excluded(i) => Bound::Excluded(Serial::serialize(i)?),
+		Bound::Unbounded => Bound::Unbounded,
+	};
+	let to = match range.end_bound() {
+		Bound::Included(i) => Bound::Included(Serial::serialize(i)?),
+		Bound::Excluded(i) => Bound::Excluded(Serial::serialize(i)?),
+		Bound::Unbounded => Bound::Unbounded,
+	};
+	Ok((from, to))
+}
+
+fn deserialize_entry<K, V>(r: std::result::Result<(IVec, IVec), sled::Error>) -> Result<(K, V)>
 where
 	K: Serial,
 	V: Serial,
 {
 	let (key, value) = r?;
 	let key = Serial::deserialize(key.to_vec())?;
-	let value = Serial::deserialize(value.to_vec())?;
+	let value = deserialize_value(value.to_vec())?;
 	Ok((key, value))
 }
+
+#[cfg(feature = "serde_json")]
+impl<K, V> Tree<K, V>
+where
+	K: Serial + serde::Serialize + serde::de::DeserializeOwned,
+	V: Serial + serde::Serialize + serde::de::DeserializeOwned,
+{
+	/// Writes every entry as a line of JSON, as in `{"key":...,"value":...}`, in key order
+	/// The keys and values go through serde, apart from how they are stored
+	pub fn export_json(&self, mut writer: impl std::io::Write) -> Result<()> {
+		for res in self.iter() {
+			let (key, value) = res?;
+			// Converted first, as the macro panics on values that can't be serialized
+			let key = serde_json::to_value(key)?;
+			let value = serde_json::to_value(value)?;
+			let line = serde_json::json!({ "key": key, "value": value });
+			serde_json::to_writer(&mut writer, &line)?;
+			writer.write_all(b"\n")?;
+		}
+		writer.flush()?;
+		Ok(())
+	}
+	/// Inserts the entries written by [export_json](Tree::export_json), returning how many there were
+	/// The entries are inserted in a single [Batch](crate::Batch), and an event is sent for each
+	pub fn import_json(&self, reader: impl std::io::BufRead) -> Result<usize> {
+		let mut batch = Batch::default();
+		for line in reader.lines() {
+			let line = line?;
+			if line.trim().is_empty() {
+				continue;
+			}
+			let mut entry: serde_json::Value = serde_json::from_str(&line)?;
+			let key: K = serde_json::from_value(entry["key"].take())?;
+			let value: V = serde_json::from_value(entry["value"].take())?;
+			batch.insert(key, value)?;
+		}
+		let len = batch.len();
+		self.apply_batch(batch)?;
+		Ok(len)
+	}
+}